@@ -1,3 +1,4 @@
 #!/usr/bin/env node
 const rrt = require('./rrt.node');
-rrt.execute_tests();
\ No newline at end of file
+// execute_tests() is true only if the run passed, any other outcome of the run exits with 1
+process.exitCode = rrt.execute_tests() ? 0 : 1;
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use crate::failure::FailureReason;
+
+// Occurrences of a failure reason that are logged in full, the later ones only count towards the summary
+pub const SHOWN_OCCURRENCES: usize = 3;
+
+// How an occurrence of a failure reason is logged
+#[derive(Debug, PartialEq)]
+pub enum Output {
+    Full,
+    Hint, // the first one beyond the shown occurrences, only notes that it is summarized after the run
+    Counted,
+}
+
+// The occurrences of one template
+struct Occurrences {
+    count: usize,
+    message: String, // of the first occurrence
+    varies: bool, // later messages differ from it, e.g. in the elapsed time of a timeout
+}
+
+// Counts the failure reasons of a run by their template, so a reason hundreds of tests share, e.g. because the
+// host is down, is only logged a few times
+pub struct ErrorAggregator {
+    enabled: bool,
+    templates: HashMap<String, Occurrences>,
+    order: Vec<String>, // the templates in the order they first occurred
+}
+
+// The message with every number replaced by #
+fn mask_numbers(message: &str) -> String {
+    let mut masked = String::new();
+    let mut in_number = false;
+    for character in message.chars() {
+        if character.is_ascii_digit() {
+            if !in_number {
+                masked.push('#');
+            }
+            in_number = true;
+        } else {
+            masked.push(character);
+            in_number = false;
+        }
+    }
+    return masked;
+}
+
+// The kind of the reason and its message without the numbers, reasons that only differ in them are the same
+fn template(reason: &FailureReason) -> String {
+    let kind = serde_json::to_value(reason).ok()
+        .and_then(|value| value.get("kind").and_then(|kind| kind.as_str()).map(|kind| kind.to_string()))
+        .unwrap_or_default();
+    return format!("{}: {}", kind, mask_numbers(&reason.to_string()));
+}
+
+impl ErrorAggregator {
+    pub fn new(enabled: bool) -> ErrorAggregator {
+        return ErrorAggregator { enabled, templates: HashMap::new(), order: Vec::new() };
+    }
+
+    // Whether the next occurrence of the reason is still logged in full, without counting it
+    pub fn shows(&self, reason: &FailureReason) -> bool {
+        return !self.enabled
+            || self.templates.get(&template(reason)).is_none_or(|occurrences| occurrences.count < SHOWN_OCCURRENCES);
+    }
+
+    // Counts an occurrence of the reason and tells how to log it
+    pub fn record(&mut self, reason: &FailureReason) -> Output {
+        if !self.enabled {
+            return Output::Full;
+        }
+
+        let template = template(reason);
+        let message = reason.to_string();
+        let occurrences = self.templates.entry(template.clone()).or_insert_with(|| {
+            self.order.push(template);
+            Occurrences { count: 0, message: message.clone(), varies: false }
+        });
+        occurrences.count += 1;
+        occurrences.varies |= occurrences.message != message;
+
+        return match occurrences.count {
+            count if count <= SHOWN_OCCURRENCES => Output::Full,
+            count if count == SHOWN_OCCURRENCES + 1 => Output::Hint,
+            _ => Output::Counted,
+        };
+    }
+
+    // One line per template that occurred more often than it was logged, like
+    // "... and 247 more tests failed with: transport error: connection refused"
+    pub fn summary(&self) -> Vec<String> {
+        return self.order.iter()
+            .filter_map(|template| self.templates.get(template).map(|occurrences| (template, occurrences)))
+            .filter(|(_, occurrences)| occurrences.count > SHOWN_OCCURRENCES)
+            .map(|(template, occurrences)| {
+                let more = occurrences.count - SHOWN_OCCURRENCES;
+                let message = if occurrences.varies {
+                    template.split_once(": ").map_or(template.as_str(), |(_, message)| message)
+                } else {
+                    occurrences.message.as_str()
+                };
+                format!("… and {} more {} failed with: {}", more, if more == 1 { "test" } else { "tests" }, message)
+            })
+            .collect();
+    }
+}
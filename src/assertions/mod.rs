@@ -0,0 +1,458 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+
+use crate::failure::{self, FailureReason};
+
+// A single status code (404) or a whole status class ("4xx")
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum StatusPattern {
+    Code(u16),
+    Class(String),
+}
+
+impl StatusPattern {
+    // Checks whether the pattern is a status code or a class like 2xx
+    pub fn is_valid(&self) -> bool {
+        return match self {
+            StatusPattern::Code(_) => true,
+            StatusPattern::Class(class) => {
+                let class = class.to_lowercase();
+                class.len() == 3 && (class.parse::<u16>().is_ok() || (class.ends_with("xx")
+                    && class.chars().next().is_some_and(|digit| ('1'..='5').contains(&digit))))
+            },
+        };
+    }
+
+    pub fn matches(&self, status: u16) -> bool {
+        return match self {
+            StatusPattern::Code(code) => *code == status,
+            StatusPattern::Class(class) => {
+                let class = class.to_lowercase();
+                match class.strip_suffix("xx") {
+                    Some(digit) => digit.parse::<u16>() == Ok(status / 100),
+                    None => class.parse::<u16>() == Ok(status),
+                }
+            },
+        };
+    }
+}
+
+impl fmt::Display for StatusPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            StatusPattern::Code(code) => write!(f, "{}", code),
+            StatusPattern::Class(class) => write!(f, "{}", class.to_lowercase()),
+        };
+    }
+}
+
+// Joins a list of status patterns into a readable enumeration
+pub fn describe_patterns(patterns: &[StatusPattern]) -> String {
+    return patterns.iter()
+        .map(|pattern| pattern.to_string())
+        .collect::<Vec<String>>()
+        .join(", ");
+}
+
+// Categories of transport-level errors a test can expect instead of a response
+#[derive(Debug, PartialEq)]
+pub enum ErrorCategory {
+    Timeout,
+    ConnectionRefused,
+    Dns,
+    Other,
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            ErrorCategory::Timeout => write!(f, "timeout"),
+            ErrorCategory::ConnectionRefused => write!(f, "connection_refused"),
+            ErrorCategory::Dns => write!(f, "dns"),
+            ErrorCategory::Other => write!(f, "other"),
+        };
+    }
+}
+
+pub const EXPECTED_ERROR_VALUES: [&str; 4] = ["timeout", "connection_refused", "dns", "any"];
+
+impl ErrorCategory {
+    // Checks whether the category satisfies an expect_error value
+    pub fn satisfies(&self, expected: &str) -> bool {
+        return expected == "any" || expected == self.to_string();
+    }
+}
+
+// Walks the error chain to determine the category of a transport error
+pub fn classify_error(error: &(dyn std::error::Error + 'static)) -> ErrorCategory {
+    let mut current = Some(error);
+
+    while let Some(error) = current {
+        if error.is::<failure::TimeoutError>() {
+            return ErrorCategory::Timeout;
+        }
+
+        if let Some(hyper_error) = error.downcast_ref::<hyper::Error>() {
+            if hyper_error.is_timeout() {
+                return ErrorCategory::Timeout;
+            }
+        }
+
+        if let Some(io_error) = error.downcast_ref::<std::io::Error>() {
+            match io_error.kind() {
+                std::io::ErrorKind::ConnectionRefused => return ErrorCategory::ConnectionRefused,
+                std::io::ErrorKind::TimedOut => return ErrorCategory::Timeout,
+                _ => (),
+            }
+        }
+
+        // hyper's resolver errors have no dedicated type
+        let message = error.to_string();
+        if message.starts_with("dns error") || message.contains("failed to lookup address") {
+            return ErrorCategory::Dns;
+        }
+
+        current = error.source();
+    }
+
+    return ErrorCategory::Other;
+}
+
+// Expected number of elements of a json array, either exact or within bounds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ArrayLength {
+    Exact(usize),
+    Bounds {
+        exact: Option<usize>,
+        min: Option<usize>,
+        max: Option<usize>,
+        path: Option<String>, // dotted path to the array, defaults to the body root
+    },
+}
+
+impl fmt::Display for ArrayLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            ArrayLength::Exact(length) | ArrayLength::Bounds { exact: Some(length), .. } => write!(f, "{}", length),
+            ArrayLength::Bounds { min, max, .. } => match (min, max) {
+                (Some(min), Some(max)) => write!(f, "{} to {}", min, max),
+                (Some(min), None) => write!(f, "at least {}", min),
+                (None, Some(max)) => write!(f, "at most {}", max),
+                (None, None) => write!(f, "any"),
+            },
+        };
+    }
+}
+
+// Checks the length of the array at the configured path, returns the log message
+pub fn check_array_length(json_body: Option<&serde_json::Value>, expected: &ArrayLength)
+     -> Result<String, (String, Box<FailureReason>)> {
+
+    let path = match expected {
+        ArrayLength::Bounds { path: Some(path), .. } => path.as_str(),
+        _ => "",
+    };
+    let path_name = if path.is_empty() { "body root" } else { path };
+
+    let array = match json_body.and_then(|json| crate::utils::lookup_json_path(json, path)) {
+        Some(serde_json::Value::Array(array)) => array,
+        Some(other) => return Err((format!("Expected an array at '{}', but found: {}", path_name, other),
+            Box::new(FailureReason::BodyAssertion { path: path_name.to_string(), expected: "an array".to_string(),
+                actual: failure::excerpt(&other.to_string()) }))),
+        None => return Err((format!("Expected an array at '{}', but the response body has no such json value.",
+            path_name), Box::new(FailureReason::BodyAssertion { path: path_name.to_string(),
+                expected: "an array".to_string(), actual: "no such json value".to_string() }))),
+    };
+
+    let length = array.len();
+    let matches = match expected {
+        ArrayLength::Exact(exact) | ArrayLength::Bounds { exact: Some(exact), .. } => length == *exact,
+        ArrayLength::Bounds { min, max, .. } => min.is_none_or(|min| length >= min)
+            && max.is_none_or(|max| length <= max),
+    };
+
+    let message = format!("Expected array length at '{}': {}, got {}", path_name, expected, length);
+    if matches {
+        return Ok(message + " — PASSED");
+    }
+
+    let first_element = match array.first() {
+        Some(element) => element.to_string(),
+        None => "none, the array is empty".to_string(),
+    };
+
+    return Err((format!("{} — FAILED\nFirst element: {}", message, first_element),
+     Box::new(FailureReason::BodyAssertion {
+        path: path_name.to_string(),
+        expected: format!("array length {}", expected),
+        actual: format!("array length {}", length),
+    })));
+}
+
+// The json types a field constraint can ask for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonType {
+    String,
+    Number,
+    Integer,
+    Boolean,
+    Array,
+    Object,
+    Null,
+}
+
+impl JsonType {
+    fn name(&self) -> &'static str {
+        return match self {
+            JsonType::String => "string",
+            JsonType::Number => "number",
+            JsonType::Integer => "integer",
+            JsonType::Boolean => "boolean",
+            JsonType::Array => "array",
+            JsonType::Object => "object",
+            JsonType::Null => "null",
+        };
+    }
+
+    fn matches(&self, value: &serde_json::Value) -> bool {
+        return match self {
+            JsonType::String => value.is_string(),
+            JsonType::Number => value.is_number(),
+            JsonType::Integer => value.is_i64() || value.is_u64(),
+            JsonType::Boolean => value.is_boolean(),
+            JsonType::Array => value.is_array(),
+            JsonType::Object => value.is_object(),
+            JsonType::Null => value.is_null(),
+        };
+    }
+}
+
+// What a field of an array element has to be: of a type, matching a regex or equal to a literal value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FieldConstraint {
+    Type {
+        #[serde(rename = "type")]
+        json_type: JsonType,
+    },
+    Matches { matches: String }, // regex over the text of the value, strings without their quotes
+    Literal(serde_json::Value),
+}
+
+impl FieldConstraint {
+    fn is_met_by(&self, value: Option<&serde_json::Value>) -> bool {
+        return match (self, value) {
+            (_, None) => false,
+            (FieldConstraint::Type { json_type }, Some(value)) => json_type.matches(value),
+            (FieldConstraint::Matches { matches }, Some(value)) => {
+                let text = value.as_str().map_or_else(|| value.to_string(), |text| text.to_string());
+                Regex::new(matches).is_ok_and(|pattern| pattern.is_match(&text))
+            },
+            (FieldConstraint::Literal(expected), Some(value)) => expected == value,
+        };
+    }
+}
+
+impl fmt::Display for FieldConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            FieldConstraint::Type { json_type } => write!(f, "of type {}", json_type.name()),
+            FieldConstraint::Matches { matches } => write!(f, "matching /{}/", matches),
+            FieldConstraint::Literal(expected) => write!(f, "= {}", expected),
+        };
+    }
+}
+
+// At least one element of a json array has to meet all field constraints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArrayContains {
+    pub path: Option<String>, // dotted path to the array, defaults to the body root
+    pub fields: BTreeMap<String, FieldConstraint>, // dotted paths inside the element
+}
+
+impl ArrayContains {
+    pub fn describe(&self) -> String {
+        return self.fields.iter()
+            .map(|(field, constraint)| format!("{} {}", field, constraint))
+            .collect::<Vec<String>>()
+            .join(", ");
+    }
+}
+
+// Looks for an element meeting all constraints, on failure the element meeting the most of them is shown
+pub fn check_array_contains(json_body: Option<&serde_json::Value>, expected: &ArrayContains)
+     -> Result<String, (String, Box<FailureReason>)> {
+
+    let path = expected.path.as_deref().unwrap_or_default();
+    let path_name = if path.is_empty() { "body root" } else { path };
+
+    let array = match json_body.and_then(|json| crate::utils::lookup_json_path(json, path)) {
+        Some(serde_json::Value::Array(array)) => array,
+        Some(other) => return Err((format!("Expected an array at '{}', but found: {}", path_name, other),
+            Box::new(FailureReason::BodyAssertion { path: path_name.to_string(), expected: "an array".to_string(),
+                actual: failure::excerpt(&other.to_string()) }))),
+        None => return Err((format!("Expected an array at '{}', but the response body has no such json value.",
+            path_name), Box::new(FailureReason::BodyAssertion { path: path_name.to_string(),
+                expected: "an array".to_string(), actual: "no such json value".to_string() }))),
+    };
+
+    let message = format!("Expected an element of '{}' with {}", path_name, expected.describe());
+
+    // The fields every element misses, the first element missing the fewest is the closest one
+    let mut closest: Option<(usize, Vec<&String>)> = None;
+    for (index, element) in array.iter().enumerate() {
+        let missed: Vec<&String> = expected.fields.iter()
+            .filter(|(field, constraint)| !constraint.is_met_by(crate::utils::lookup_json_path(element, field)))
+            .map(|(field, _)| field)
+            .collect();
+
+        if missed.is_empty() {
+            return Ok(format!("{}, element {} matches — PASSED", message, index));
+        }
+        if closest.as_ref().is_none_or(|(_, fewest)| missed.len() < fewest.len()) {
+            closest = Some((index, missed));
+        }
+    }
+
+    let (closest_description, actual) = match closest {
+        Some((index, missed)) => {
+            let missed = missed.iter().map(|field| field.as_str()).collect::<Vec<&str>>().join(", ");
+            let element = failure::excerpt(&array[index].to_string());
+            (format!("Closest element {} misses {}: {}", index, missed, element),
+             format!("none of {} elements, the closest one misses {}: {}", array.len(), missed, element))
+        },
+        None => ("The array is empty".to_string(), "none of 0 elements".to_string()),
+    };
+
+    return Err((format!("{}, none of the {} scanned elements matches — FAILED\n{}", message, array.len(),
+     closest_description), Box::new(FailureReason::BodyAssertion {
+        path: path_name.to_string(),
+        expected: format!("an element with {}", expected.describe()),
+        actual,
+    })));
+}
+
+// An entry of expect_headers_absent, either a header name or "Name: value" to only forbid that value
+pub fn parse_absent_header(entry: &str) -> (&str, Option<&str>) {
+    return match entry.split_once(':') {
+        Some((name, value)) => (name.trim(), Some(value.trim())),
+        None => (entry.trim(), None),
+    };
+}
+
+// Checks that the response doesnt contain the header (with the forbidden value), returns the log message
+pub fn check_absent_header(entry: &str, headers: &hyper::HeaderMap) -> (String, Option<FailureReason>) {
+    let (name, forbidden) = parse_absent_header(entry);
+    let description = match forbidden {
+        Some(value) => format!("Expected header '{}' not to be '{}'", name, value),
+        None => format!("Expected header '{}' to be absent", name),
+    };
+
+    let offending = headers.get_all(name).iter()
+        .map(|value| value.to_str().unwrap_or_default().to_string())
+        .find(|value| forbidden.is_none_or(|forbidden| value.trim().eq_ignore_ascii_case(forbidden)));
+
+    return match offending {
+        Some(value) => (format!("{}, got '{}'", description, value),
+            Some(FailureReason::HeaderPresent { header: name.to_string(), value })),
+        None => (description, None),
+    };
+}
+
+// All values of a repeated header in the order they were sent
+pub fn header_values(headers: &hyper::HeaderMap, name: &str) -> Vec<String> {
+    return headers.get_all(name).iter().map(|value| value.to_str().unwrap_or_default().to_string()).collect();
+}
+
+// The Location header has to be exactly the expected url, a test with it doesnt follow redirects
+pub fn check_location(expected: &str, headers: &hyper::HeaderMap) -> (String, Option<FailureReason>) {
+    let values = header_values(headers, "location");
+    let description = format!("Expected Location '{}'", expected);
+
+    if values.iter().any(|value| value.trim() == expected) {
+        return (description, None);
+    }
+    return (format!("{}, got {:?}", description, values), Some(FailureReason::HeaderMismatch {
+        header: "location".to_string(),
+        missing: vec![expected.to_string()],
+        actual: values,
+    }));
+}
+
+// Checks that every expected value is among the values of the header, regardless of their order. Besides whole
+// values, the comma separated parts of a value count, e.g. 'Vary: Accept, Origin' contains 'Origin'.
+pub fn check_header(name: &str, expected: &[String], headers: &hyper::HeaderMap) -> (String, Option<FailureReason>) {
+    let values = header_values(headers, name);
+    let mut candidates: Vec<&str> = values.iter().map(|value| value.trim()).collect();
+    if !name.eq_ignore_ascii_case("set-cookie") {
+        candidates.extend(values.iter().flat_map(|value| value.split(',')).map(|part| part.trim()));
+    }
+
+    let missing: Vec<&String> = expected.iter().filter(|value| !candidates.contains(&value.trim())).collect();
+    let description = format!("Expected header '{}' to contain {}", name,
+        expected.iter().map(|value| format!("'{}'", value)).collect::<Vec<String>>().join(", "));
+
+    if missing.is_empty() {
+        return (description, None);
+    }
+    return (format!("{}, got {:?}", description, values), Some(FailureReason::HeaderMismatch {
+        header: name.to_string(),
+        missing: missing.into_iter().cloned().collect(),
+        actual: values,
+    }));
+}
+
+// Characters of the body shown when the json contract fails, enough to recognize an html error page
+const CONTRACT_BODY_CHARS: usize = 200;
+
+// The type of a json value as named in the configs, an integer counts as a number
+fn json_type_of(value: &serde_json::Value) -> JsonType {
+    return match value {
+        serde_json::Value::Object(_) => JsonType::Object,
+        serde_json::Value::Array(_) => JsonType::Array,
+        serde_json::Value::String(_) => JsonType::String,
+        serde_json::Value::Number(_) => JsonType::Number,
+        serde_json::Value::Bool(_) => JsonType::Boolean,
+        serde_json::Value::Null => JsonType::Null,
+    };
+}
+
+// Checks that the body is json of the expected top level type without looking at any value. The failure shows the
+// Content-Type and the start of the body, e.g. the html error page of a proxy answering with 200.
+pub fn check_json_contract(json_body: Option<&serde_json::Value>, json_error: Option<&serde_json::Error>,
+     expected_type: Option<JsonType>, headers: &hyper::HeaderMap, body: &[u8]) -> (String, Option<FailureReason>) {
+    let expected = match expected_type {
+        Some(json_type) => format!("a json body of type {}", json_type.name()),
+        None => "a valid json body".to_string(),
+    };
+    let description = format!("Expected {}", expected);
+
+    let actual = match (json_body, json_error) {
+        (_, Some(error)) => format!("no valid json: {}", error),
+        (Some(json), None) => match expected_type {
+            Some(json_type) if !json_type.matches(json) => format!("type {}", json_type_of(json).name()),
+            _ => return (description, None),
+        },
+        (None, None) => "no body".to_string(),
+    };
+
+    let text = String::from_utf8_lossy(body);
+    let body = match text.char_indices().nth(CONTRACT_BODY_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    };
+    let content_type = headers.get(hyper::header::CONTENT_TYPE)
+        .map(|value| value.to_str().unwrap_or_default().to_string());
+    return (format!("{}, got {}", description, actual), Some(FailureReason::JsonContract {
+        expected,
+        actual,
+        content_type,
+        body,
+    }));
+}
@@ -0,0 +1,58 @@
+use serde::{Serialize, Deserialize};
+
+// The response time boundaries of a test in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimeBoundaries {
+    pub ok: u128, // faster responses are green
+    pub warn: u128, // faster responses are yellow, slower ones red
+    pub timeout: u128, // the request is cancelled once it took this long
+}
+
+impl TimeBoundaries {
+    pub const DEFAULT: TimeBoundaries = TimeBoundaries { ok: 500, warn: 1000, timeout: 10000 };
+}
+
+// The time_boundaries of the config, either a list like [500, 1000, 10000] or named like { ok: 500, warn: 1000 }.
+// Untagged enums cant buffer u128 values, so they are read as u64.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TimeBoundariesConfig {
+    List(Vec<u64>), // ok, warn and the optional timeout
+    Named { ok: u64, warn: u64, timeout: Option<u64> },
+}
+
+impl TimeBoundariesConfig {
+    // The timeout if it was given, the boundaries only set the timeout of tests without a timeout_ms
+    pub fn timeout(&self) -> Option<u64> {
+        return match self {
+            TimeBoundariesConfig::List(values) => values.get(2).copied(),
+            TimeBoundariesConfig::Named { timeout, .. } => *timeout,
+        };
+    }
+
+    // Converts both forms into boundaries, a missing timeout is the default one. Fails unless the given ones strictly increase.
+    pub fn resolve(&self) -> Result<TimeBoundaries, String> {
+        let (ok, warn, timeout) = match self {
+            TimeBoundariesConfig::List(values) => match values.as_slice() {
+                [ok, warn] => (*ok, *warn, None),
+                [ok, warn, timeout] => (*ok, *warn, Some(*timeout)),
+                _ => return Err(format!("expected two or three values, got {}", values.len())),
+            },
+            TimeBoundariesConfig::Named { ok, warn, timeout } => (*ok, *warn, *timeout),
+        };
+        let boundaries = TimeBoundaries {
+            ok: ok as u128,
+            warn: warn as u128,
+            timeout: timeout.map_or(TimeBoundaries::DEFAULT.timeout, |timeout| timeout as u128),
+        };
+
+        if boundaries.ok >= boundaries.warn {
+            return Err(format!("ok ({} ms) has to be lower than warn ({} ms)", boundaries.ok, boundaries.warn));
+        }
+        if timeout.is_some() && boundaries.warn >= boundaries.timeout {
+            return Err(format!("ok ({} ms), warn ({} ms) and timeout ({} ms) have to be strictly increasing",
+             boundaries.ok, boundaries.warn, boundaries.timeout));
+        }
+        return Ok(boundaries);
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+
+use serde::{Serialize, Deserialize};
+
+use crate::report::{TestOutcome, TestResult, TimeClass};
+
+// Limits on the run as a whole, checked after the last test. Exceeding one fails the run even if every test passed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BudgetsConfig {
+    pub max_run_duration_s: Option<u64>, // without the pauses of --step
+    pub max_failed: Option<u32>,
+    pub max_average_response_ms: Option<u128>, // over all tests that received a response
+    pub max_red: Option<u32>, // tests whose response time was classified red
+}
+
+// The evaluation of a single budget, part of the json report
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetResult {
+    pub budget: &'static str, // the name of the budget in the config
+    pub limit: u128,
+    pub actual: u128,
+    pub passed: bool,
+}
+
+impl fmt::Display for BudgetResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} is {} (limit {})", self.budget, self.actual, self.limit);
+    }
+}
+
+fn check(budget: &'static str, limit: Option<u128>, actual: u128) -> Option<BudgetResult> {
+    return limit.map(|limit| BudgetResult { budget, limit, actual, passed: actual <= limit });
+}
+
+// Evaluates every configured budget against the results of the run
+pub fn evaluate(budgets: &BudgetsConfig, results: &[TestResult], duration_ms: u128) -> Vec<BudgetResult> {
+    let failed = results.iter().filter(|result| result.outcome == TestOutcome::Failed).count() as u128;
+    let red = results.iter().filter(|result| result.time_class == Some(TimeClass::Red)).count() as u128;
+    let response_times: Vec<u128> = results.iter().filter_map(|result| result.response_time_ms).collect();
+    let average = if response_times.is_empty() {
+        0
+    } else {
+        response_times.iter().sum::<u128>() / response_times.len() as u128
+    };
+
+    // A started second counts as a whole one, so 10.2 s exceed a budget of 10
+    let duration_s = duration_ms.div_ceil(1000);
+
+    return [
+        check("max_run_duration_s", budgets.max_run_duration_s.map(|seconds| seconds as u128), duration_s),
+        check("max_failed", budgets.max_failed.map(|failed| failed as u128), failed),
+        check("max_average_response_ms", budgets.max_average_response_ms, average),
+        check("max_red", budgets.max_red.map(|red| red as u128), red),
+    ].into_iter().flatten().collect();
+}
@@ -0,0 +1,142 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::{Endpoint, dependency, interpolation, utils};
+use crate::report::{TestOutcome, TestResult};
+
+// Longer values are cut off in the summary, their full length is noted
+const MAX_VALUE_CHARS: usize = 80;
+
+// A capture as it stood at the end of the run, where it came from and who used it
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureSummary {
+    pub key: String,
+    pub value: String, // redacted for secret names and the captures sent as credentials
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<usize>, // the full length in characters if the value was cut off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub produced_by: Option<String>, // the test that stored it last, none for variables, defines and scripts
+    pub consumed_by: Vec<String>, // the tests that ran and refer to it
+}
+
+// Every capture sorted by key, the producers are the config index of the test that stored the capture last
+pub fn summarize(captures: &interpolation::Captures, producers: &HashMap<String, usize>, tests: &[Endpoint],
+     results: &[TestResult]) -> Vec<CaptureSummary> {
+    let known: HashSet<String> = captures.keys().cloned().collect();
+    let consumers: Vec<(String, Vec<String>)> = results.iter()
+        .filter(|result| result.outcome != TestOutcome::Skipped)
+        .filter_map(|result| tests.get(result.index - 1).map(|test| (result.reference(), test)))
+        .map(|(reference, test)| (reference, dependency::consumed(test, &known)))
+        .collect();
+
+    // Captures sent as a bearer token or session id are as secret as their names might not tell
+    let credentials: HashSet<&String> = tests.iter()
+        .flat_map(|test| [&test.bearer_token, &test.session_id])
+        .filter_map(|credential| credential.as_ref()?.as_ref())
+        .collect();
+
+    let mut keys: Vec<&String> = captures.keys().collect();
+    keys.sort();
+    return keys.into_iter()
+        .map(|key| {
+            let text = if utils::is_secret_name(key) || credentials.contains(key) {
+                "<redacted>".to_string()
+            } else {
+                interpolation::text(&captures[key]).to_string()
+            };
+            let length = text.chars().count();
+            let (value, length) = if length > MAX_VALUE_CHARS {
+                (text.chars().take(MAX_VALUE_CHARS).collect::<String>() + "...", Some(length))
+            } else {
+                (text, None)
+            };
+            CaptureSummary {
+                key: key.clone(),
+                value,
+                length,
+                produced_by: producers.get(key).and_then(|index| results.iter().find(|result| result.index == index + 1))
+                    .map(|result| result.reference()),
+                consumed_by: consumers.iter()
+                    .filter(|(_, names)| names.contains(key))
+                    .map(|(reference, _)| reference.clone())
+                    .collect(),
+            }
+        })
+        .collect();
+}
+
+// The summary as a table with one row per capture
+pub fn render_table(summaries: &[CaptureSummary]) -> String {
+    let header = ["Key", "Value", "Produced by", "Consumed by"];
+    let mut rows: Vec<[String; 4]> = vec![header.map(|column| column.to_string())];
+    for summary in summaries.iter() {
+        let value = match summary.length {
+            Some(length) => format!("{} ({} chars)", summary.value, length),
+            None => summary.value.clone(),
+        };
+        rows.push([
+            summary.key.clone(),
+            value,
+            summary.produced_by.clone().unwrap_or_else(|| "-".to_string()),
+            if summary.consumed_by.is_empty() { "-".to_string() } else { summary.consumed_by.join(", ") },
+        ]);
+    }
+
+    let mut widths = [0; 4];
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in rows.iter() {
+        let cells: Vec<String> = row.iter().zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        table += cells.join("  ").trim_end();
+        table += "\n";
+    }
+    return table;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(yaml: &str) -> Endpoint {
+        return serde_yaml::from_str(yaml).unwrap();
+    }
+
+    fn values(tests: &[Endpoint]) -> Vec<(String, String)> {
+        let captures: interpolation::Captures = ["access_token", "login", "sid", "user_id", "other"].iter()
+            .map(|name| (name.to_string(), serde_json::Value::String(format!("{}-value", name))))
+            .collect();
+        return summarize(&captures, &HashMap::new(), tests, &[]).into_iter()
+            .map(|summary| (summary.key, summary.value))
+            .collect();
+    }
+
+    #[test]
+    fn redacts_secret_names_and_captures_sent_as_credentials() {
+        let tests = [
+            endpoint("route: me\nmethod: get\nbearer_token: login"),
+            endpoint("route: cart\nmethod: get\nsession_id: sid"),
+            endpoint("route: public\nmethod: get\nbearer_token: none\nsession_id: null"),
+        ];
+        assert_eq!(values(&tests), vec![
+            ("access_token".to_string(), "<redacted>".to_string()),
+            ("login".to_string(), "<redacted>".to_string()),
+            ("other".to_string(), "other-value".to_string()),
+            ("sid".to_string(), "<redacted>".to_string()),
+            ("user_id".to_string(), "user_id-value".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn shows_captures_that_no_test_sends_as_a_credential() {
+        let tests = [endpoint("route: users/{{user_id}}\nmethod: get")];
+        assert_eq!(values(&tests).iter().filter(|(_, value)| value == "<redacted>").count(), 1);
+    }
+}
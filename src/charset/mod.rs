@@ -0,0 +1,167 @@
+use hyper::header::{HeaderMap, CONTENT_TYPE};
+
+// The characters of windows-1252 at 0x80 to 0x9F, the other bytes are the same as in latin1
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Charset {
+    Utf8,
+    Windows1252, // also what latin1 and us-ascii labels are read as, like browsers do
+    Utf16Le,
+    Utf16Be,
+}
+
+// What happened to a response body before the text assertions see it
+pub enum Decoded {
+    Unchanged, // utf-8 or no charset at all
+    Transcoded { charset: String, body: Vec<u8> },
+    Unknown(String), // a charset that cant be decoded, the body is read as utf-8
+}
+
+fn from_label(label: &str) -> Option<Charset> {
+    return match label.to_lowercase().as_str() {
+        "utf-8" | "utf8" | "unicode-1-1-utf-8" => Some(Charset::Utf8),
+        "windows-1252" | "cp1252" | "x-cp1252" | "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "l1"
+            | "us-ascii" | "ascii" => Some(Charset::Windows1252),
+        "utf-16le" | "utf-16" => Some(Charset::Utf16Le),
+        "utf-16be" => Some(Charset::Utf16Be),
+        _ => None,
+    };
+}
+
+// The charset parameter of the Content-Type header, without quotes
+fn header_label(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    return content_type.split(';')
+        .skip(1)
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_string());
+}
+
+// A byte order mark wins over the header and is removed from the body
+fn sniff_bom(body: &[u8]) -> Option<(Charset, &'static str, usize)> {
+    if body.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Some((Charset::Utf8, "utf-8", 3));
+    }
+    if body.starts_with(&[0xFF, 0xFE]) {
+        return Some((Charset::Utf16Le, "utf-16le", 2));
+    }
+    if body.starts_with(&[0xFE, 0xFF]) {
+        return Some((Charset::Utf16Be, "utf-16be", 2));
+    }
+    return None;
+}
+
+fn decode_utf16(body: &[u8], little_endian: bool) -> String {
+    let units: Vec<u16> = body.chunks(2)
+        .map(|pair| match (pair, little_endian) {
+            ([low, high], true) | ([high, low], false) => u16::from_le_bytes([*low, *high]),
+            _ => 0xFFFD, // an odd trailing byte
+        })
+        .collect();
+    return String::from_utf16_lossy(&units);
+}
+
+fn decode(charset: Charset, body: &[u8]) -> String {
+    return match charset {
+        Charset::Utf8 => String::from_utf8_lossy(body).into_owned(),
+        Charset::Windows1252 => body.iter()
+            .map(|&byte| match byte {
+                0x80..=0x9F => WINDOWS_1252_HIGH[(byte - 0x80) as usize],
+                _ => byte as char,
+            })
+            .collect(),
+        Charset::Utf16Le => decode_utf16(body, true),
+        Charset::Utf16Be => decode_utf16(body, false),
+    };
+}
+
+// Transcodes a buffered body to utf-8 by the byte order mark or the charset of the Content-Type header
+pub fn to_utf8(headers: &HeaderMap, body: &[u8]) -> Decoded {
+    let (charset, label, skipped) = match sniff_bom(body) {
+        Some((charset, label, skipped)) => (charset, format!("{} (byte order mark)", label), skipped),
+        None => {
+            let label = match header_label(headers) {
+                Some(label) => label,
+                None => return Decoded::Unchanged,
+            };
+            match from_label(&label) {
+                Some(Charset::Utf8) => return Decoded::Unchanged,
+                Some(charset) => (charset, label.to_lowercase(), 0),
+                None => return Decoded::Unknown(label),
+            }
+        },
+    };
+
+    // Pure ascii reads the same in every supported charset but utf-16
+    let body = &body[skipped..];
+    if skipped == 0 && charset == Charset::Windows1252 && body.is_ascii() {
+        return Decoded::Unchanged;
+    }
+
+    return Decoded::Transcoded { charset: label, body: decode(charset, body).into_bytes() };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(content_type: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, content_type.parse().unwrap());
+        return headers;
+    }
+
+    // The body as utf-8 text and the charset it was read as, None if it was left as it is
+    fn transcoded(headers: &HeaderMap, body: &[u8]) -> Option<(String, String)> {
+        return match to_utf8(headers, body) {
+            Decoded::Transcoded { charset, body } => Some((charset, String::from_utf8(body).unwrap())),
+            Decoded::Unchanged => None,
+            Decoded::Unknown(label) => panic!("unknown charset {}", label),
+        };
+    }
+
+    #[test]
+    fn decodes_latin1_umlauts_as_windows_1252() {
+        let body = b"{\"name\": \"M\xFCller \xC4\xD6\xDC\xDF \x80\"}";
+        assert_eq!(transcoded(&headers("application/json; charset=ISO-8859-1"), body),
+            Some(("iso-8859-1".to_string(), "{\"name\": \"Müller ÄÖÜß €\"}".to_string())));
+        assert_eq!(transcoded(&headers("text/plain; charset=\"windows-1252\""), b"\x93quoted\x94"),
+            Some(("windows-1252".to_string(), "\u{201C}quoted\u{201D}".to_string())));
+        assert_eq!(transcoded(&headers("text/plain; charset=latin1"), b"plain ascii"), None);
+    }
+
+    #[test]
+    fn decodes_utf16_by_its_byte_order_mark() {
+        let little_endian = [0xFF, 0xFE, b'{', 0, b'}', 0, 0xE4, 0];
+        assert_eq!(transcoded(&HeaderMap::new(), &little_endian),
+            Some(("utf-16le (byte order mark)".to_string(), "{}ä".to_string())));
+
+        // The byte order mark wins over the header
+        let big_endian = [0xFE, 0xFF, 0, b'{', 0, b'}', 0x20, 0xAC];
+        assert_eq!(transcoded(&headers("application/json; charset=utf-16le"), &big_endian),
+            Some(("utf-16be (byte order mark)".to_string(), "{}€".to_string())));
+    }
+
+    #[test]
+    fn strips_the_byte_order_mark_of_utf8() {
+        assert_eq!(transcoded(&headers("application/json; charset=utf-8"), b"\xEF\xBB\xBF{\"a\": 1}"),
+            Some(("utf-8 (byte order mark)".to_string(), "{\"a\": 1}".to_string())));
+        assert!(matches!(to_utf8(&headers("application/json; charset=UTF-8"), b"{}"), Decoded::Unchanged));
+        assert!(matches!(to_utf8(&headers("application/json"), b"{}"), Decoded::Unchanged));
+    }
+
+    #[test]
+    fn reads_unknown_charsets_as_they_are() {
+        match to_utf8(&headers("text/plain; charset=koi8-r"), b"text") {
+            Decoded::Unknown(label) => assert_eq!(label, "koi8-r"),
+            _ => panic!("koi8-r isnt supported"),
+        }
+    }
+}
@@ -1,7 +1,366 @@
+use std::path::PathBuf;
+
 #[derive(clap::Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
+   #[command(subcommand)]
+   pub command: Option<Command>,
+
    /// Path to the config file, will look for it in the executable's cwd per default
-   #[arg(short, long)]
+   #[arg(short, long, global = true)]
    pub file: Option<String>,
+
+   /// Abort the run once this many tests have failed
+   #[arg(long)]
+   pub max_failures: Option<u32>,
+
+   /// Stop starting new tests once the run took this many seconds, the remaining tests are skipped
+   #[arg(long)]
+   pub max_duration: Option<u64>,
+
+   /// Treat every test as critical, aborting the run on the first failure
+   #[arg(long)]
+   pub fail_fast: bool,
+
+   /// Ignore the critical flags of the tests, critical failures are treated as ordinary ones
+   #[arg(long)]
+   pub no_critical: bool,
+
+   /// Skip the check whether the host of the api_address accepts connections before the first test
+   #[arg(long, global = true)]
+   pub no_preflight: bool,
+
+   /// Keep running after a critical failure, only the tests depending on its captures are skipped
+   #[arg(long, conflicts_with = "fail_fast")]
+   pub continue_on_critical: bool,
+
+   /// Fail the validation on captures no test reads and on credentials naming captures no test produces
+   #[arg(long)]
+   pub strict_captures: bool,
+
+   /// Warn whenever a test overwrites a value another test captured, naming both tests
+   #[arg(long)]
+   pub warn_capture_overwrite: bool,
+
+   /// Define a variable like 'api_version=v3', overrides the variables of the config
+   #[arg(short = 'D', long = "define", value_parser = parse_define, global = true)]
+   pub defines: Vec<(String, String)>,
+
+   /// Fail the run if there are warnings, e.g. slow responses with yellow_is_warning or assertions of severity warn
+   #[arg(long)]
+   pub warnings_as_errors: bool,
+
+   /// Rewrite the snapshots of all tests with 'snapshot: true' instead of comparing against them
+   #[arg(long)]
+   pub update_snapshots: bool,
+
+   /// Print response bodies as they are, without pretty-printing json
+   #[arg(long, global = true)]
+   pub raw_body: bool,
+
+   /// Pause after every test to show the captures: Enter runs the next test, s skips it, r re-runs the previous one
+   /// and q quits. Only works in an interactive terminal.
+   #[arg(long)]
+   pub step: bool,
+
+   /// Log additional information for every test, regardless of the config
+   #[arg(long, global = true)]
+   pub verbose: bool,
+
+   /// Timeout of every request in milliseconds, overrides the third time boundary of the config and the tests
+   #[arg(long)]
+   pub timeout_ms: Option<u128>,
+
+   /// Run every test this many times, overrides the repeat setting of the tests
+   #[arg(long)]
+   pub repeat: Option<u32>,
+
+   /// Run the whole suite this many times and report which tests are flaky, only the json report is written
+   #[arg(long, value_parser = clap::value_parser!(u32).range(1..),
+    conflicts_with_all = ["step", "report_html", "metrics_file", "github"])]
+   pub runs: Option<u32>,
+
+   /// Write the log to this file, overrides the log_file and to_file settings of the config
+   #[arg(long)]
+   pub log_file: Option<PathBuf>,
+
+   /// Write a json report of the run to this file
+   #[arg(long)]
+   pub report_json: Option<PathBuf>,
+
+   /// Write a self-contained html report of the run to this file
+   #[arg(long)]
+   pub report_html: Option<PathBuf>,
+
+   /// Write the metrics of the run to this file in the OpenMetrics text format, e.g. for the textfile collector
+   #[arg(long)]
+   pub metrics_file: Option<PathBuf>,
+
+   /// Write the exchanged requests and responses of every test into this directory, overrides the transcripts setting of the config
+   #[arg(long)]
+   pub transcripts: Option<PathBuf>,
+
+   /// Keep the transcripts of earlier runs instead of removing them from the directory
+   #[arg(long)]
+   pub keep_transcripts: bool,
+
+   /// Compare every response with the one recorded by --transcripts in this directory, differences fail the test
+   #[arg(long)]
+   pub compare_with: Option<PathBuf>,
+
+   /// Persist the progress into this file after every test and continue where an earlier run with it stopped, e.g.
+   /// for suites that cant finish within the rate limit of the API
+   #[arg(long, conflicts_with = "runs")]
+   pub resume: Option<PathBuf>,
+
+   /// Write a copy of the config to this file in which every passed test expects the body and headers it got, the
+   /// values that change between runs are replaced with keywords like $timestamp
+   #[arg(long, conflicts_with_all = ["runs", "resume"])]
+   pub record_expectations: Option<PathBuf>,
+
+   /// Print every request and response head as it goes over the wire to stderr, with the request bodies as hex dumps,
+   /// secret headers stay redacted
+   #[arg(long)]
+   pub trace_http: bool,
+
+   /// Print every capture at the end of the run with the test that produced it and the tests that used it, verbose
+   /// output prints it as well
+   #[arg(long)]
+   pub show_captures: bool,
+
+   /// Log every failure reason in full, instead of summarizing the ones many tests share after their first occurrences
+   #[arg(long)]
+   pub no_aggregate_errors: bool,
+
+   /// Report failures as GitHub Actions annotations and write a job summary, enabled automatically inside Actions
+   #[arg(long, global = true)]
+   pub github: bool,
+
+   /// Only run the tests with these indices, e.g. 47, 45-50 or 1,3,7-9
+   #[arg(long, value_parser = parse_index_selection)]
+   pub index: Option<IndexSelection>,
+
+   /// Only run one of several shards of the suite, e.g. 2/4, tests sharing captures stay in the same shard
+   #[arg(long, value_parser = parse_shard)]
+   pub shard: Option<Shard>,
+
+   /// Only run the tests whose meta has this value, e.g. owner=payments-team, repeat it to require several values
+   #[arg(long = "meta-filter", value_parser = parse_meta_filter)]
+   pub meta_filters: Vec<(String, String)>,
+
+   /// Print the settings of the run after merging the command line, the config and the tests, secrets are redacted
+   #[arg(long)]
+   pub print_config: bool,
+
+   /// Print every test of the config without running them
+   #[arg(long)]
+   pub list: bool,
+
+   /// Output format of --list
+   #[arg(long, value_enum, default_value = "text", requires = "list")]
+   pub format: ListFormat,
+}
+
+fn parse_header(header: &str) -> Result<(String, String), String> {
+   return match header.split_once(':') {
+      Some((name, value)) => Ok((name.trim().to_string(), value.trim().to_string())),
+      None => Err(format!("expected a header like 'Name: value', got '{}'", header)),
+   };
+}
+
+fn parse_define(define: &str) -> Result<(String, String), String> {
+   return match define.split_once('=') {
+      Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.to_string())),
+      _ => Err(format!("expected a variable like 'name=value', got '{}'", define)),
+   };
+}
+
+fn parse_meta_filter(filter: &str) -> Result<(String, String), String> {
+   return match filter.split_once('=') {
+      Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.trim().to_string())),
+      _ => Err(format!("expected a meta filter like 'owner=payments-team', got '{}'", filter)),
+   };
+}
+
+// One based test indices selected via --index
+#[derive(Clone, Debug)]
+pub struct IndexSelection(pub Vec<usize>);
+
+fn parse_index(index: &str) -> Result<usize, String> {
+   return index.trim().parse::<usize>().map_err(|_| format!("'{}' is not a test index", index.trim()));
+}
+
+fn parse_index_selection(selection: &str) -> Result<IndexSelection, String> {
+   let mut indices = Vec::new();
+
+   for part in selection.split(',') {
+      match part.split_once('-') {
+         Some((start, end)) => {
+            let (start, end) = (parse_index(start)?, parse_index(end)?);
+            if start > end {
+               return Err(format!("the range {}-{} is empty", start, end));
+            }
+            indices.extend(start..=end);
+         },
+         None => indices.push(parse_index(part)?),
+      }
+   }
+
+   indices.sort();
+   indices.dedup();
+   return Ok(IndexSelection(indices));
+}
+
+// A one based shard out of a number of shards, selected via --shard
+#[derive(Clone, Copy, Debug)]
+pub struct Shard {
+   pub index: usize,
+   pub count: usize,
+}
+
+fn parse_shard(shard: &str) -> Result<Shard, String> {
+   let (index, count) = shard.split_once('/').ok_or(format!("expected a shard like '2/4', got '{}'", shard))?;
+   let index = index.trim().parse::<usize>().map_err(|_| format!("'{}' is not a shard", index.trim()))?;
+   let count = count.trim().parse::<usize>().map_err(|_| format!("'{}' is not a number of shards", count.trim()))?;
+   if index == 0 || index > count {
+      return Err(format!("the shard {} has to be between 1 and {}", index, count));
+   }
+   return Ok(Shard { index, count });
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub enum ListFormat {
+   Text,
+   Json,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+   /// Write a commented starter rest-test.yaml into the cwd
+   Init {
+      /// Overwrite an existing rest-test.yaml
+      #[arg(long)]
+      force: bool,
+
+      /// Base url of the API that is written into api_address
+      #[arg(long)]
+      url: Option<String>,
+   },
+
+   /// Generate a config from an existing API description
+   Import {
+      #[command(subcommand)]
+      source: ImportSource,
+   },
+
+   /// Send a single request and check its status, without a config file
+   Check {
+      /// Full url of the request
+      url: String,
+
+      /// Http method of the request
+      #[arg(long, default_value = "GET")]
+      method: String,
+
+      /// Expected status of the response
+      #[arg(long, default_value_t = 200)]
+      status: u16,
+
+      /// Timeout of the request in milliseconds
+      #[arg(long)]
+      max_time: Option<u64>,
+
+      /// Additional header like 'Accept: application/json', can be given multiple times
+      #[arg(long, value_parser = parse_header)]
+      header: Vec<(String, String)>,
+
+      /// Raw request body
+      #[arg(long)]
+      body: Option<String>,
+
+      /// Environment variable holding a bearer token that is sent with the request
+      #[arg(long)]
+      bearer_env: Option<String>,
+   },
+
+   /// Convert the config into a shell script of curl invocations or a Postman collection
+   Export {
+      /// Format of the export
+      #[arg(long, value_enum)]
+      format: ExportFormat,
+
+      /// Path of the exported file
+      #[arg(long)]
+      out: PathBuf,
+
+      /// Overwrite an existing file
+      #[arg(long)]
+      force: bool,
+   },
+
+   /// Rewrite deprecated forms of the config into their replacements, only prints the changes without --write
+   Migrate {
+      /// Write the migrated config back to its file
+      #[arg(long)]
+      write: bool,
+   },
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub enum ExportFormat {
+   Curl,
+   Postman,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ImportSource {
+   /// Generate one test per operation of an OpenAPI 3 spec (yaml or json)
+   Openapi {
+      /// Path to the spec
+      spec: PathBuf,
+
+      /// Path of the generated config
+      #[arg(long, default_value = "rest-test.yaml")]
+      out: PathBuf,
+
+      /// Overwrite an existing config
+      #[arg(long)]
+      force: bool,
+   },
+
+   /// Generate one test per request of a HAR capture, e.g. exported from the browser devtools
+   Har {
+      /// Path to the capture
+      file: PathBuf,
+
+      /// Only import requests to hosts matching this pattern, supports * wildcards
+      #[arg(long)]
+      host: Option<String>,
+
+      /// Keep the recorded Cookie headers
+      #[arg(long)]
+      keep_cookies: bool,
+
+      /// Path of the generated config
+      #[arg(long, default_value = "rest-test.yaml")]
+      out: PathBuf,
+
+      /// Overwrite an existing config
+      #[arg(long)]
+      force: bool,
+   },
+
+   /// Convert the requests of a Postman collection (v2.0 or v2.1)
+   Postman {
+      /// Path to the exported collection
+      collection: PathBuf,
+
+      /// Path of the generated config
+      #[arg(long, default_value = "rest-test.yaml")]
+      out: PathBuf,
+
+      /// Overwrite an existing config
+      #[arg(long)]
+      force: bool,
+   },
 }
@@ -0,0 +1,32 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{drift, transcript};
+
+// The request a test also sends to the compare_against host as recorded in the reports
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Comparison {
+    pub url: String,
+    pub status: Option<u16>,
+    pub response_time_ms: Option<u128>,
+    pub response_body: Option<String>, // excerpt of the body
+    pub error: Option<String>,
+}
+
+// The response of the compare_against host, kept until the expectations of the test are checked
+pub struct Secondary {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: bytes::BytesMut,
+}
+
+// Compares the response of the compare_against host with the primary one like --compare-with compares with a
+// recorded one, a missing response is the only difference
+pub fn compare(status: u16, headers: &[(String, String)], body: &[u8], secondary: &Result<Secondary, String>,
+     config: Option<&drift::DriftConfig>) -> drift::Drift {
+    let secondary = match secondary {
+        Ok(secondary) => secondary,
+        Err(error) => return drift::Drift { differences: vec![format!("no response: {}", error)], diff: String::new() },
+    };
+    let primary = transcript::Recorded { status, headers: headers.to_vec(), body: body.to_vec() };
+    return drift::compare(&primary, secondary.status, &secondary.headers, &secondary.body, config);
+}
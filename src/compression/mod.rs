@@ -0,0 +1,450 @@
+use serde::{Serialize, Deserialize};
+
+use crate::failure::FailureReason;
+
+pub const ENCODING_VALUES: [&str; 4] = ["gzip", "br", "any", "none"];
+
+// Decoded bodies larger than this are rejected, a small compressed body could expand to gigabytes
+const MAX_DECODED_BYTES: usize = 64 * 1024 * 1024;
+
+// Base lengths and extra bits of the length codes 257 to 285
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+    131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+// Base distances and extra bits of the distance codes 0 to 29
+const DISTANCE_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025,
+    1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12,
+    13, 13];
+
+// The order the lengths of the code length alphabet are stored in
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+// The size of a response body on the wire and after removing its Content-Encoding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodySize {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>, // the Content-Encoding, none if the body was sent as is
+    pub wire_bytes: usize,
+    pub decoded_bytes: Option<usize>, // unknown if the encoding cant be decoded, like br
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>, // why the body couldnt be decoded although its encoding is supported
+}
+
+impl BodySize {
+    // How many times larger the decoded body is, unknown without a decoded size or an empty body
+    pub fn ratio(&self) -> Option<f64> {
+        let decoded = self.decoded_bytes?;
+        return (self.wire_bytes > 0).then(|| decoded as f64 / self.wire_bytes as f64);
+    }
+
+    pub fn describe(&self) -> String {
+        return match (&self.encoding, self.decoded_bytes) {
+            (None, _) => format!("{} bytes, not compressed", self.wire_bytes),
+            (Some(encoding), Some(decoded)) => format!("{} bytes on the wire, {} bytes decoded ({}, ratio {:.2})",
+                self.wire_bytes, decoded, encoding, self.ratio().unwrap_or(1.0)),
+            (Some(encoding), None) => format!("{} bytes on the wire, {} cant be decoded", self.wire_bytes, encoding),
+        };
+    }
+}
+
+// Offers the server only what the test expects and what can be decoded, br only if it is asked for
+pub fn accept_encoding(expected: Option<&str>) -> &'static str {
+    return match expected {
+        Some("br") => "br",
+        Some("gzip") => "gzip",
+        _ => "gzip, deflate",
+    };
+}
+
+// The Content-Encoding of the response, identity counts as none
+pub fn content_encoding(headers: &hyper::HeaderMap) -> Option<String> {
+    let value = headers.get(hyper::header::CONTENT_ENCODING)?.to_str().ok()?.trim().to_lowercase();
+    return (!value.is_empty() && value != "identity").then_some(value);
+}
+
+// Removes the Content-Encoding of a body, none if the encoding isnt supported
+pub fn decode(encoding: &str, body: &[u8]) -> Option<Result<Vec<u8>, String>> {
+    return match encoding {
+        "gzip" | "x-gzip" => Some(gunzip(body)),
+        "deflate" => Some(inflate_zlib(body)),
+        _ => None,
+    };
+}
+
+pub fn check_encoding(expected: &str, headers: &hyper::HeaderMap) -> (String, Option<FailureReason>) {
+    let actual = content_encoding(headers);
+    let matches = match (expected, &actual) {
+        ("any", actual) => actual.is_some(),
+        ("none", actual) => actual.is_none(),
+        (expected, Some(actual)) => actual == expected,
+        (_, None) => false,
+    };
+    let message = format!("Expected the Content-Encoding {}, got {}", expected, actual.as_deref().unwrap_or("none"));
+    let failure = (!matches).then(|| FailureReason::EncodingMismatch { expected: expected.to_string(), actual });
+    return (message, failure);
+}
+
+pub fn check_ratio(minimum: f64, size: &BodySize) -> (String, Option<FailureReason>) {
+    let ratio = size.ratio();
+    let message = match ratio {
+        Some(ratio) => format!("Expected a compression ratio of at least {}, got {:.2} ({} bytes decoded, {} on the \
+            wire)", minimum, ratio, size.decoded_bytes.unwrap_or_default(), size.wire_bytes),
+        None => format!("Expected a compression ratio of at least {}, the decoded size of the {} is unknown", minimum,
+            size.encoding.as_deref().map_or("empty body".to_string(), |encoding| format!("{} body", encoding))),
+    };
+    let failure = (!ratio.is_some_and(|ratio| ratio >= minimum)).then_some(FailureReason::CompressionRatio {
+        minimum,
+        ratio,
+        wire_bytes: size.wire_bytes,
+        decoded_bytes: size.decoded_bytes,
+    });
+    return (message, failure);
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    return !crc;
+}
+
+// Every member of a gzip body, concatenated members are decoded one after the other
+fn gunzip(body: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::new();
+    let mut rest = body;
+    while !rest.is_empty() {
+        if rest.len() < 18 || rest[0] != 0x1f || rest[1] != 0x8b || rest[2] != 8 {
+            return Err("not a gzip body".to_string());
+        }
+        let flags = rest[3];
+        let mut position = 10;
+        if flags & 0x04 != 0 {
+            let extra = u16::from_le_bytes([rest[position], rest[position + 1]]) as usize;
+            position += 2 + extra;
+        }
+        for flag in [0x08, 0x10] {
+            if flags & flag != 0 {
+                let end = rest.get(position..).and_then(|text| text.iter().position(|byte| *byte == 0))
+                    .ok_or("truncated gzip header")?;
+                position += end + 1;
+            }
+        }
+        if flags & 0x02 != 0 {
+            position += 2;
+        }
+
+        let (member, used) = inflate(rest.get(position..).ok_or("truncated gzip header")?, decoded.len())?;
+        let trailer = rest.get(position + used..position + used + 8).ok_or("truncated gzip trailer")?;
+        if crc32(&member) != u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) {
+            return Err("the crc32 of the gzip trailer doesnt match".to_string());
+        }
+        if member.len() as u32 != u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]) {
+            return Err("the size of the gzip trailer doesnt match".to_string());
+        }
+        decoded.extend(member);
+        rest = &rest[position + used + 8..];
+    }
+    return Ok(decoded);
+}
+
+// deflate is meant to be zlib wrapped, some servers send the raw stream though
+fn inflate_zlib(body: &[u8]) -> Result<Vec<u8>, String> {
+    let wrapped = body.len() >= 2 && body[0] & 0x0f == 8 && (u16::from(body[0]) << 8 | u16::from(body[1])) % 31 == 0;
+    let stream = if wrapped { &body[2..] } else { body };
+    return inflate(stream, 0).map(|(decoded, _)| decoded);
+}
+
+// Reads the bits of a deflate stream, least significant first
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32, String> {
+        while self.count < count {
+            let byte = *self.data.get(self.position).ok_or("truncated deflate stream")?;
+            self.buffer |= (byte as u32) << self.count;
+            self.position += 1;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1u32 << count) - 1);
+        self.buffer >>= count;
+        self.count -= count;
+        return Ok(value);
+    }
+
+    // Stored blocks start at the next byte
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+// A canonical huffman code, the symbols sorted by the length of their code
+struct Huffman {
+    counts: [u16; 16], // codes per length
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate().filter(|(_, length)| **length != 0) {
+            symbols[offsets[*length as usize] as usize] = symbol as u16;
+            offsets[*length as usize] += 1;
+        }
+        return Huffman { counts, symbols };
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        return Err("invalid huffman code in the deflate stream".to_string());
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [8u8; 288];
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    return (Huffman::new(&lengths), Huffman::new(&[5u8; 30]));
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let literals = reader.bits(5)? as usize + 257;
+    let distances = reader.bits(5)? as usize + 1;
+    let code_lengths = reader.bits(4)? as usize + 4;
+
+    let mut lengths = [0u8; 19];
+    for index in CODE_LENGTH_ORDER.iter().take(code_lengths) {
+        lengths[*index] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&lengths);
+
+    let mut lengths = vec![0u8; literals + distances];
+    let mut index = 0;
+    while index < lengths.len() {
+        let symbol = code_length_code.decode(reader)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 if index > 0 => (lengths[index - 1], 3 + reader.bits(2)? as usize),
+            17 => (0, 3 + reader.bits(3)? as usize),
+            18 => (0, 11 + reader.bits(7)? as usize),
+            _ => return Err("invalid code lengths in the deflate stream".to_string()),
+        };
+        if index + repeat > lengths.len() {
+            return Err("invalid code lengths in the deflate stream".to_string());
+        }
+        lengths[index..index + repeat].fill(length);
+        index += repeat;
+    }
+    return Ok((Huffman::new(&lengths[..literals]), Huffman::new(&lengths[literals..])));
+}
+
+// Decodes a raw deflate stream (RFC 1951), returns the data and the bytes the stream took up. The already decoded
+// bytes of earlier gzip members count against the size limit.
+fn inflate(data: &[u8], decoded_before: usize) -> Result<(Vec<u8>, usize), String> {
+    let mut reader = BitReader { data, position: 0, buffer: 0, count: 0 };
+    let mut output: Vec<u8> = Vec::new();
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let header = data.get(reader.position..reader.position + 4).ok_or("truncated deflate stream")?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                if length != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err("invalid stored block in the deflate stream".to_string());
+                }
+                let start = reader.position + 4;
+                output.extend_from_slice(data.get(start..start + length as usize).ok_or("truncated deflate stream")?);
+                reader.position = start + length as usize;
+            },
+            kind @ (1 | 2) => {
+                let (literal_code, distance_code) = if kind == 1 { fixed_codes() } else { dynamic_codes(&mut reader)? };
+                loop {
+                    let symbol = literal_code.decode(&mut reader)? as usize;
+                    if symbol < 256 {
+                        output.push(symbol as u8);
+                        continue;
+                    }
+                    if symbol == 256 {
+                        break;
+                    }
+                    let code = symbol - 257;
+                    if code >= LENGTH_BASE.len() {
+                        return Err("invalid length code in the deflate stream".to_string());
+                    }
+                    let length = LENGTH_BASE[code] as usize + reader.bits(LENGTH_EXTRA[code] as u32)? as usize;
+                    let code = distance_code.decode(&mut reader)? as usize;
+                    if code >= DISTANCE_BASE.len() {
+                        return Err("invalid distance code in the deflate stream".to_string());
+                    }
+                    let distance = DISTANCE_BASE[code] as usize + reader.bits(DISTANCE_EXTRA[code] as u32)? as usize;
+                    if distance > output.len() {
+                        return Err("the deflate stream refers to data before its start".to_string());
+                    }
+                    let start = output.len() - distance;
+                    for offset in 0..length {
+                        output.push(output[start + offset]);
+                    }
+                    if decoded_before + output.len() > MAX_DECODED_BYTES {
+                        return Err(format!("the decoded body exceeds {} bytes", MAX_DECODED_BYTES));
+                    }
+                }
+            },
+            _ => return Err("invalid block type in the deflate stream".to_string()),
+        }
+        if decoded_before + output.len() > MAX_DECODED_BYTES {
+            return Err(format!("the decoded body exceeds {} bytes", MAX_DECODED_BYTES));
+        }
+        if last {
+            return Ok((output, reader.position));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The streams below were compressed by zlib, the text of the dynamic block is repeated so it pays off
+    const STORED: &str = "010c00f3ff73746f72656420626c6f636b";
+    const FIXED_ZLIB: &str = "78da4b4c4a4e8421858cd49c9c7c08090070120901";
+    const DYNAMIC: &str = "d5ccc90d80300c05d1567e013465bc2811d806921cd23d115d709d278da89dd415a36983c\
+        c20af8c32cc9c029cb26a062b7a51b4e97b9eb047efa1c17599d3f199e3a28934db203f38be";
+    const DYNAMIC_GZIP: &str = "1f8b08080000000002ff626f64792e74787400d5ccc90d80300c05d1567e013465bc\
+        2811d806921cd23d115d709d278da89dd415a36983cc20af8c32cc9c029cb26a062b7a51b4e97b9eb047efa1c17599d3\
+        f199e3a28934db203f38be5862d3b1f6000000";
+    // A stored member followed by a fixed one
+    const TWO_MEMBERS: &str = "1f8b0800000000000403010600f9ff666972737420fc7af11c060000001f8b0800000\
+        0000002032b4e4dcecf4b010069111fb606000000";
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        return (0..hex.len()).step_by(2).map(|index| u8::from_str_radix(&hex[index..index + 2], 16).unwrap()).collect();
+    }
+
+    fn text() -> Vec<u8> {
+        return b"deflate uses dynamic huffman codes once the symbol frequencies make them pay off, ".repeat(3);
+    }
+
+    fn block_type(stream: &[u8]) -> u8 {
+        return (stream[0] >> 1) & 3;
+    }
+
+    #[test]
+    fn computes_the_crc32_of_gzip() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn inflates_a_stored_block() {
+        let stream = bytes(STORED);
+        assert_eq!(block_type(&stream), 0);
+        assert_eq!(inflate(&stream, 0).unwrap(), (b"stored block".to_vec(), stream.len()));
+    }
+
+    #[test]
+    fn inflates_a_fixed_block_wrapped_in_zlib() {
+        let body = bytes(FIXED_ZLIB);
+        assert_eq!(block_type(&body[2..]), 1);
+        assert_eq!(decode("deflate", &body).unwrap().unwrap(), b"abcabcabcabc hello hello");
+    }
+
+    #[test]
+    fn inflates_a_raw_dynamic_block() {
+        let stream = bytes(DYNAMIC);
+        assert_eq!(block_type(&stream), 2);
+        assert_eq!(decode("deflate", &stream).unwrap().unwrap(), text());
+    }
+
+    #[test]
+    fn gunzips_a_member_with_a_file_name() {
+        let body = bytes(DYNAMIC_GZIP);
+        assert_eq!(body[3], 0x08);
+        assert_eq!(decode("gzip", &body).unwrap().unwrap(), text());
+        assert_eq!(decode("x-gzip", &body).unwrap().unwrap(), text());
+    }
+
+    #[test]
+    fn gunzips_concatenated_members() {
+        assert_eq!(decode("gzip", &bytes(TWO_MEMBERS)).unwrap().unwrap(), b"first second");
+    }
+
+    #[test]
+    fn doesnt_decode_unsupported_encodings() {
+        assert!(decode("br", &bytes(DYNAMIC)).is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_bodies() {
+        for hex in [STORED, DYNAMIC] {
+            let stream = bytes(hex);
+            for length in 0..stream.len() {
+                assert!(inflate(&stream[..length], 0).is_err(), "{} of {} bytes of {}", length, stream.len(), hex);
+            }
+        }
+        let body = bytes(DYNAMIC_GZIP);
+        for length in 1..body.len() {
+            assert!(gunzip(&body[..length]).is_err(), "{} of {} bytes", length, body.len());
+        }
+        // Cut after its first member, the body is complete
+        let body = bytes(TWO_MEMBERS);
+        for length in (1..body.len()).filter(|length| *length != 29) {
+            assert!(gunzip(&body[..length]).is_err(), "{} of {} bytes", length, body.len());
+        }
+        assert_eq!(gunzip(&body[..29]).unwrap(), b"first ");
+        // The adler32 at the end of the zlib stream isnt checked
+        let body = bytes(FIXED_ZLIB);
+        for length in 0..body.len() - 4 {
+            assert!(decode("deflate", &body[..length]).unwrap().is_err(), "{} of {} bytes", length, body.len());
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_bodies() {
+        let mut body = bytes(DYNAMIC_GZIP);
+        let crc = body.len() - 8;
+        body[crc] ^= 1;
+        assert_eq!(gunzip(&body).unwrap_err(), "the crc32 of the gzip trailer doesnt match");
+        body[crc] ^= 1;
+        let size = body.len() - 4;
+        body[size] ^= 1;
+        assert_eq!(gunzip(&body).unwrap_err(), "the size of the gzip trailer doesnt match");
+
+        assert_eq!(gunzip(&bytes(DYNAMIC)).unwrap_err(), "not a gzip body");
+        assert_eq!(inflate(&[0x07], 0).unwrap_err(), "invalid block type in the deflate stream");
+        assert_eq!(inflate(&bytes("010c00f3fe73746f72656420626c6f636b"), 0).unwrap_err(),
+            "invalid stored block in the deflate stream");
+        // A fixed block whose first symbol copies from a distance of 1
+        assert_eq!(inflate(&[0x03, 0x02, 0x00], 0).unwrap_err(), "the deflate stream refers to data before its start");
+    }
+}
@@ -0,0 +1,482 @@
+use std::borrow::Cow;
+
+use crate::interpolation::{self, Captures, Segment};
+
+// Tiny expression language for conditional test execution, e.g.
+// `exists(token) && (payments == true || region != 'eu')`
+//
+// The tokens and the cursor over them are shared with the numeric expectations and the scripts of the tests.
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    Identifier(String),
+    Literal(String),
+    Placeholder(String), // {{name}}
+    Assign,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+    Not,
+    Plus,
+    Minus,
+    Times,
+    Divide,
+    Comma,
+    OpenParen,
+    CloseParen,
+    OpenBrace,
+    CloseBrace,
+    Semicolon,
+    LineBreak,
+}
+
+#[derive(Debug)]
+pub enum Operand {
+    Capture(String),
+    Literal(String),
+}
+
+#[derive(Debug)]
+pub enum Condition {
+    Exists(String),
+    Truthy(String),
+    Equal(Operand, Operand),
+    NotEqual(Operand, Operand),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            Token::Identifier(name) => write!(f, "'{}'", name),
+            Token::Literal(value) => write!(f, "'{}'", value),
+            Token::Placeholder(name) => write!(f, "'{{{{{}}}}}'", name),
+            Token::Assign => write!(f, "'='"),
+            Token::Equal => write!(f, "'=='"),
+            Token::NotEqual => write!(f, "'!='"),
+            Token::Less => write!(f, "'<'"),
+            Token::LessEqual => write!(f, "'<='"),
+            Token::Greater => write!(f, "'>'"),
+            Token::GreaterEqual => write!(f, "'>='"),
+            Token::And => write!(f, "'&&'"),
+            Token::Or => write!(f, "'||'"),
+            Token::Not => write!(f, "'!'"),
+            Token::Plus => write!(f, "'+'"),
+            Token::Minus => write!(f, "'-'"),
+            Token::Times => write!(f, "'*'"),
+            Token::Divide => write!(f, "'/'"),
+            Token::Comma => write!(f, "','"),
+            Token::OpenParen => write!(f, "'('"),
+            Token::CloseParen => write!(f, "')'"),
+            Token::OpenBrace => write!(f, "'{{'"),
+            Token::CloseBrace => write!(f, "'}}'"),
+            Token::Semicolon => write!(f, "';'"),
+            Token::LineBreak => write!(f, "the end of the line"),
+        };
+    }
+}
+
+fn is_name_char(char: char) -> bool {
+    return char.is_alphanumeric() || char == '_' || char == '-' || char == '.';
+}
+
+// Tokens together with the line they start on. Line breaks are only tokens if they end the statements of a script,
+// and not even then inside parentheses. Errors carry the line they occurred on.
+pub fn tokenize(source: &str, line_breaks: bool) -> Result<Vec<(Token, usize)>, (usize, String)> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = source.chars().collect();
+    let mut index = 0;
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut depth = 0;
+
+    while index < chars.len() {
+        let char = chars[index];
+        let next = chars.get(index + 1).copied();
+        let position = index - line_start;
+
+        let (token, length) = match char {
+            '\n' => {
+                if line_breaks && depth == 0 {
+                    tokens.push((Token::LineBreak, line));
+                }
+                line += 1;
+                index += 1;
+                line_start = index;
+                continue;
+            },
+            ' ' | '\t' | '\r' => { index += 1; continue; },
+            '#' => {
+                index += chars[index..].iter().take_while(|c| **c != '\n').count();
+                continue;
+            },
+            '{' if next == Some('{') => {
+                let rest: String = chars[index..].iter().collect();
+                match interpolation::segments(&rest).first() {
+                    Some(Segment::Placeholder { name: "", .. }) =>
+                        return Err((line, format!("empty placeholder at position {}", position))),
+                    Some(Segment::Placeholder { name, source, .. }) =>
+                        (Token::Placeholder(name.to_string()), source.chars().count()),
+                    _ => return Err((line, format!("unterminated placeholder starting at position {}", position))),
+                }
+            },
+            ';' => (Token::Semicolon, 1),
+            '(' => { depth += 1; (Token::OpenParen, 1) },
+            ')' => { depth -= 1; (Token::CloseParen, 1) },
+            '{' => (Token::OpenBrace, 1),
+            '}' => (Token::CloseBrace, 1),
+            ',' => (Token::Comma, 1),
+            '+' => (Token::Plus, 1),
+            '-' => (Token::Minus, 1),
+            '*' => (Token::Times, 1),
+            '/' => (Token::Divide, 1),
+            '=' if next == Some('=') => (Token::Equal, 2),
+            '=' => (Token::Assign, 1),
+            '!' if next == Some('=') => (Token::NotEqual, 2),
+            '!' => (Token::Not, 1),
+            '<' if next == Some('=') => (Token::LessEqual, 2),
+            '<' => (Token::Less, 1),
+            '>' if next == Some('=') => (Token::GreaterEqual, 2),
+            '>' => (Token::Greater, 1),
+            '&' if next == Some('&') => (Token::And, 2),
+            '|' if next == Some('|') => (Token::Or, 2),
+            '\'' | '"' => {
+                let end = chars[index + 1..].iter().position(|c| *c == char)
+                    .ok_or((line, format!("unterminated string starting at position {}", position)))?;
+                let value: String = chars[index + 1..index + 1 + end].iter().collect();
+                tokens.push((Token::Literal(value), line));
+                for char in chars[index..index + end + 2].iter() {
+                    index += 1;
+                    if *char == '\n' {
+                        line += 1;
+                        line_start = index;
+                    }
+                }
+                continue;
+            },
+            _ if is_name_char(char) => {
+                // A number ends at the first character that cant be part of it, so 2-1 is a subtraction
+                let digits = chars[index..].iter().take_while(|c| c.is_ascii_digit() || **c == '.').count();
+                let length = match chars.get(index + digits) {
+                    Some(char) if digits > 0 && (!is_name_char(*char) || *char == '-') => digits,
+                    None if digits > 0 => digits,
+                    _ => chars[index..].iter().take_while(|c| is_name_char(**c)).count(),
+                };
+                let word: String = chars[index..index + length].iter().collect();
+
+                // Numbers and booleans are literals, everything else is a name
+                if word == "true" || word == "false" || word.parse::<f64>().is_ok() {
+                    (Token::Literal(word), length)
+                } else {
+                    (Token::Identifier(word), length)
+                }
+            },
+            _ => return Err((line, format!("unexpected character '{}' at position {}", char, position))),
+        };
+
+        tokens.push((token, line));
+        index += length;
+    }
+
+    return Ok(tokens);
+}
+
+// Cursor over the tokens for the recursive descent parsers, the end describes what the tokens form in the errors
+pub struct Tokens {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    end: &'static str,
+}
+
+impl Tokens {
+    pub fn new(tokens: Vec<(Token, usize)>, end: &'static str) -> Tokens {
+        return Tokens { tokens, position: 0, end };
+    }
+
+    pub fn peek(&self) -> Option<&Token> {
+        return self.peek_nth(0);
+    }
+
+    // The token n places after the upcoming one
+    pub fn peek_nth(&self, n: usize) -> Option<&Token> {
+        return self.tokens.get(self.position + n).map(|(token, _)| token);
+    }
+
+    pub fn next(&mut self) -> Option<&Token> {
+        self.position += 1;
+        return self.tokens.get(self.position - 1).map(|(token, _)| token);
+    }
+
+    // The line of the upcoming token, or of the last one at the end, only scripts span several lines
+    #[cfg(feature = "scripting")]
+    pub fn line(&self) -> usize {
+        return self.tokens.get(self.position).or(self.tokens.last()).map_or(1, |(_, line)| *line);
+    }
+
+    // The upcoming token or the end for an error, e.g. "expected ')', found the end of the expression"
+    pub fn found(&self) -> String {
+        return self.peek().map_or(self.end.to_string(), |token| token.to_string());
+    }
+
+    // Consumes the expected token, anything else stays for the error
+    pub fn expect(&mut self, expected: Token) -> Result<(), String> {
+        if self.peek() != Some(&expected) {
+            return Err(format!("expected {}, found {}", expected, self.found()));
+        }
+
+        self.next();
+        return Ok(());
+    }
+
+    // Errors if there are tokens left after the parsed ones
+    pub fn finish(&self) -> Result<(), String> {
+        return match self.peek() {
+            Some(token) => Err(format!("unexpected {} after {}", token, self.end)),
+            None => Ok(()),
+        };
+    }
+}
+
+pub fn truthy(value: &str) -> bool {
+    return !value.is_empty() && value != "false" && value != "0" && value != "null";
+}
+
+// Recursive descent parser over the token list
+struct Parser {
+    tokens: Tokens,
+}
+
+impl Parser {
+    fn parse_or(&mut self) -> Result<Condition, String> {
+        let mut condition = self.parse_and()?;
+        while self.tokens.peek() == Some(&Token::Or) {
+            self.tokens.next();
+            condition = Condition::Or(Box::new(condition), Box::new(self.parse_and()?));
+        }
+
+        return Ok(condition);
+    }
+
+    fn parse_and(&mut self) -> Result<Condition, String> {
+        let mut condition = self.parse_unary()?;
+        while self.tokens.peek() == Some(&Token::And) {
+            self.tokens.next();
+            condition = Condition::And(Box::new(condition), Box::new(self.parse_unary()?));
+        }
+
+        return Ok(condition);
+    }
+
+    fn parse_unary(&mut self) -> Result<Condition, String> {
+        if self.tokens.peek() == Some(&Token::Not) {
+            self.tokens.next();
+            return Ok(Condition::Not(Box::new(self.parse_unary()?)));
+        }
+
+        return self.parse_primary();
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, String> {
+        let operand = match (self.tokens.peek(), self.tokens.peek_nth(1)) {
+            (Some(Token::Identifier(name)), _) => Operand::Capture(name.clone()),
+            (Some(Token::Literal(value)), _) => Operand::Literal(value.clone()),
+            // A negative number like -1
+            (Some(Token::Minus), Some(Token::Literal(value))) if value.parse::<f64>().is_ok() => {
+                let operand = Operand::Literal(format!("-{}", value));
+                self.tokens.next();
+                operand
+            },
+            _ => return Err(format!("expected a capture name or a value, found {}", self.tokens.found())),
+        };
+
+        self.tokens.next();
+        return Ok(operand);
+    }
+
+    fn parse_primary(&mut self) -> Result<Condition, String> {
+        if self.tokens.peek() == Some(&Token::OpenParen) {
+            self.tokens.next();
+            let condition = self.parse_or()?;
+            self.tokens.expect(Token::CloseParen)?;
+            return Ok(condition);
+        }
+
+        // exists(name) checks whether a value has been captured
+        if let Some(Token::Identifier(name)) = self.tokens.peek() {
+            if name == "exists" && self.tokens.peek_nth(1) == Some(&Token::OpenParen) {
+                self.tokens.next();
+                self.tokens.next();
+                let name = match self.tokens.next() {
+                    Some(Token::Identifier(name)) => name.clone(),
+                    _ => return Err("exists() expects a capture name".to_string()),
+                };
+                self.tokens.expect(Token::CloseParen)?;
+                return Ok(Condition::Exists(name));
+            }
+        }
+
+        let left = self.parse_operand()?;
+        return match self.tokens.peek() {
+            Some(Token::Equal) => { self.tokens.next(); Ok(Condition::Equal(left, self.parse_operand()?)) },
+            Some(Token::NotEqual) => { self.tokens.next(); Ok(Condition::NotEqual(left, self.parse_operand()?)) },
+            _ => match left {
+                Operand::Capture(name) => Ok(Condition::Truthy(name)),
+                Operand::Literal(value) => Err(format!("expected a comparison after '{}'", value)),
+            },
+        };
+    }
+}
+
+impl Operand {
+    // Captures are compared by their text, so 'true' equals a captured boolean
+    fn resolve<'a>(&'a self, captures: &'a Captures) -> Option<Cow<'a, str>> {
+        return match self {
+            Operand::Capture(name) => captures.get(name).map(interpolation::text),
+            Operand::Literal(value) => Some(Cow::Borrowed(value)),
+        };
+    }
+}
+
+impl Condition {
+    pub fn parse(expression: &str) -> Result<Condition, String> {
+        let tokens = tokenize(expression, false).map_err(|(_, message)| message)?;
+        let mut parser = Parser { tokens: Tokens::new(tokens, "the end of the expression") };
+        let condition = parser.parse_or()?;
+        parser.tokens.finish()?;
+
+        return Ok(condition);
+    }
+
+    pub fn evaluate(&self, captures: &Captures) -> bool {
+        return match self {
+            Condition::Exists(name) => captures.contains_key(name),
+            Condition::Truthy(name) => captures.get(name).map(interpolation::text).is_some_and(|value| truthy(&value)),
+            Condition::Equal(left, right) => left.resolve(captures) == right.resolve(captures),
+            Condition::NotEqual(left, right) => left.resolve(captures) != right.resolve(captures),
+            Condition::Not(condition) => !condition.evaluate(captures),
+            Condition::And(left, right) => left.evaluate(captures) && right.evaluate(captures),
+            Condition::Or(left, right) => left.evaluate(captures) || right.evaluate(captures),
+        };
+    }
+
+    // Names of all captures the condition refers to
+    pub fn captures(&self) -> Vec<&String> {
+        return match self {
+            Condition::Exists(name) | Condition::Truthy(name) => vec![name],
+            Condition::Equal(left, right) | Condition::NotEqual(left, right) => [left, right].iter()
+                .filter_map(|operand| match operand {
+                    Operand::Capture(name) => Some(name),
+                    Operand::Literal(_) => None,
+                })
+                .collect(),
+            Condition::Not(condition) => condition.captures(),
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                let mut names = left.captures();
+                names.extend(right.captures());
+                names
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        return tokenize(source, false).unwrap().into_iter().map(|(token, _)| token).collect();
+    }
+
+    fn captures(values: &[(&str, serde_json::Value)]) -> Captures {
+        return values.iter().map(|(name, value)| (name.to_string(), value.clone())).collect();
+    }
+
+    fn holds(expression: &str, captures: &Captures) -> bool {
+        return Condition::parse(expression).unwrap().evaluate(captures);
+    }
+
+    #[test]
+    fn splits_names_values_and_operators() {
+        assert_eq!(tokens("exists(user-id) && a.b != 'x y'"), vec![
+            Token::Identifier("exists".to_string()), Token::OpenParen, Token::Identifier("user-id".to_string()),
+            Token::CloseParen, Token::And, Token::Identifier("a.b".to_string()), Token::NotEqual,
+            Token::Literal("x y".to_string()),
+        ]);
+        assert_eq!(tokens("2fa == true"), vec![
+            Token::Identifier("2fa".to_string()), Token::Equal, Token::Literal("true".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn ends_numbers_before_a_minus() {
+        assert_eq!(tokens("{{a}}-2.5*3"), vec![
+            Token::Placeholder("a".to_string()), Token::Minus, Token::Literal("2.5".to_string()), Token::Times,
+            Token::Literal("3".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn keeps_line_breaks_outside_of_parentheses() {
+        let tokens = tokenize("a = 1 # comment\nb = (1,\n2)", true).unwrap();
+        assert_eq!(tokens.iter().filter(|(token, _)| *token == Token::LineBreak).count(), 1);
+        assert_eq!(tokens.last(), Some(&(Token::CloseParen, 3)));
+        assert!(!tokenize("a\nb", false).unwrap().iter().any(|(token, _)| *token == Token::LineBreak));
+    }
+
+    #[test]
+    fn reports_the_line_and_position_of_an_error() {
+        assert_eq!(tokenize("a\nb = 'open", true).unwrap_err(),
+            (2, "unterminated string starting at position 4".to_string()));
+        assert_eq!(tokenize("a % b", false).unwrap_err(), (1, "unexpected character '%' at position 2".to_string()));
+        assert_eq!(tokenize("{{}}", false).unwrap_err(), (1, "empty placeholder at position 0".to_string()));
+        assert_eq!(tokenize("{{a", false).unwrap_err(),
+            (1, "unterminated placeholder starting at position 0".to_string()));
+    }
+
+    #[test]
+    fn evaluates_comparisons_by_their_text() {
+        let captures = captures(&[("payments", true.into()), ("count", (-1).into()), ("region", "eu".into())]);
+        assert!(holds("payments == true", &captures));
+        assert!(holds("count == -1", &captures));
+        assert!(holds("region != 'us' && !(region == \"us\")", &captures));
+        assert!(!holds("region == us", &captures));
+    }
+
+    #[test]
+    fn evaluates_exists_and_truthiness() {
+        let captures = captures(&[("token", "".into()), ("flag", "0".into()), ("id", "7".into())]);
+        assert!(holds("exists(token)", &captures));
+        assert!(!holds("token || flag", &captures));
+        assert!(holds("missing || id", &captures));
+        assert!(!holds("exists(missing)", &captures));
+    }
+
+    #[test]
+    fn binds_and_stronger_than_or() {
+        let captures = captures(&[("a", true.into())]);
+        assert!(holds("a || b && c", &captures));
+        assert!(!holds("(a || b) && c", &captures));
+    }
+
+    #[test]
+    fn lists_the_captures_it_refers_to() {
+        let condition = Condition::parse("exists(a) && (b == 'x' || !c)").unwrap();
+        assert_eq!(condition.captures(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn rejects_invalid_conditions() {
+        assert_eq!(Condition::parse("(a").unwrap_err(), "expected ')', found the end of the expression");
+        assert_eq!(Condition::parse("a b").unwrap_err(), "unexpected 'b' after the end of the expression");
+        assert_eq!(Condition::parse("'x'").unwrap_err(), "expected a comparison after 'x'");
+        assert_eq!(Condition::parse("exists('a')").unwrap_err(), "exists() expects a capture name");
+        assert_eq!(Condition::parse("a ==").unwrap_err(),
+            "expected a capture name or a value, found the end of the expression");
+    }
+}
@@ -0,0 +1,54 @@
+use serde::{Serialize, Deserialize};
+
+use crate::Endpoint;
+
+// The validators of an earlier response, usually captured, that make the request conditional
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConditionalRequest {
+    pub if_none_match: Option<String>, // an ETag
+    pub if_modified_since: Option<String>, // a Last-Modified date
+}
+
+impl ConditionalRequest {
+    // The conditional headers before interpolation, they replace headers of the same name
+    pub fn headers(&self) -> Vec<(String, String)> {
+        return [("If-None-Match", &self.if_none_match), ("If-Modified-Since", &self.if_modified_since)].into_iter()
+            .filter_map(|(name, value)| value.as_ref().map(|value| (name.to_string(), value.clone())))
+            .collect();
+    }
+}
+
+// The assertions on a response body, none of them can hold for a 304, which never has one
+pub fn body_assertions(test: &Endpoint) -> Vec<&'static str> {
+    return [
+        ("expect_body_file", test.expect_body_file.is_some()),
+        ("expect_body_exact", test.expect_body_exact.is_some()),
+        ("expect_body_equals_capture", test.expect_body_equals_capture.is_some()),
+        ("expect_array_length", test.expect_array_length.is_some()),
+        ("expect_array_contains", test.expect_array_contains.is_some()),
+        ("expect_body", test.expect_body.is_some()),
+        ("expect_valid_json", test.expect_valid_json == Some(true)),
+        ("expect_json_type", test.expect_json_type.is_some()),
+        ("require_json", test.require_json == Some(true)),
+        ("snapshot", test.snapshot == Some(true)),
+    ].into_iter().filter(|(_, set)| *set).map(|(name, _)| name).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_the_body_assertions_of_a_test() {
+        let test: Endpoint = serde_yaml::from_str("
+            route: items
+            method: GET
+            status: 304
+            expect_body: { id: $integer }
+            expect_json_type: object
+            expect_valid_json: false
+            require_json: true
+        ").unwrap();
+        assert_eq!(body_assertions(&test), ["expect_body", "expect_json_type", "require_json"]);
+    }
+}
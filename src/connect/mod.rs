@@ -0,0 +1,192 @@
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use hyper::client::connect::dns::{GaiResolver, Name};
+use hyper::client::HttpConnector;
+use hyper::service::Service;
+use hyper::Uri;
+use hyper_tls::{HttpsConnector, MaybeHttpsStream};
+use serde::{Serialize, Deserialize};
+use tokio::net::TcpStream;
+
+pub type Client = hyper::Client<TimedConnector>;
+
+// How long the phases of a request took. The first byte counts from the start of the request like curl does,
+// so it includes the connection phases, which are 0 if the request reused a pooled connection.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    pub dns_ms: u128, // 0 if the host was an ip or connect_to was given
+    pub connect_ms: u128,
+    pub tls_ms: Option<u128>, // plain http has no handshake
+    pub ttfb_ms: u128,
+    pub download_ms: u128,
+    pub reused: bool,
+}
+
+impl PhaseTimings {
+    // The phases by the names of their max_<phase>_ms assertions
+    pub fn phases(&self) -> [(&'static str, u128); 5] {
+        return [("dns", self.dns_ms), ("connect", self.connect_ms), ("tls", self.tls_ms.unwrap_or(0)),
+         ("ttfb", self.ttfb_ms), ("download", self.download_ms)];
+    }
+}
+
+// The phases of the latest connection, filled in by the connectors while they run
+#[derive(Debug, Default)]
+struct ConnectionPhases {
+    dns: Option<Duration>,
+    connect: Option<Duration>,
+    tls: Option<Duration>,
+    connected_at: Option<Instant>,
+}
+
+// Shared by the connectors of a client, so the phases can be read once the response arrived
+#[derive(Debug, Clone, Default)]
+pub struct PhaseRecorder(Arc<Mutex<ConnectionPhases>>);
+
+impl PhaseRecorder {
+    fn update(&self, update: impl FnOnce(&mut ConnectionPhases)) {
+        if let Ok(mut phases) = self.0.lock() {
+            update(&mut phases);
+        }
+    }
+
+    // Completes the phases with the ones measured around the request, no connection phases mean a pooled one was reused
+    pub fn timings(&self, ttfb: Duration, download: Duration) -> PhaseTimings {
+        let phases = self.0.lock().map(|phases| (phases.dns, phases.connect, phases.tls)).unwrap_or_default();
+        let reused = phases.1.is_none();
+        return PhaseTimings {
+            dns_ms: phases.0.unwrap_or_default().as_millis(),
+            connect_ms: phases.1.unwrap_or_default().as_millis(),
+            tls_ms: phases.2.map(|tls| tls.as_millis()),
+            ttfb_ms: ttfb.as_millis(),
+            download_ms: download.as_millis(),
+            reused,
+        };
+    }
+}
+
+// The system resolver, timed
+#[derive(Clone)]
+pub struct TimedResolver {
+    resolver: GaiResolver,
+    recorder: PhaseRecorder,
+}
+
+impl Service<Name> for TimedResolver {
+    type Response = <GaiResolver as Service<Name>>::Response;
+    type Error = <GaiResolver as Service<Name>>::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        return self.resolver.poll_ready(cx);
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let recorder = self.recorder.clone();
+        let resolving = self.resolver.call(name);
+        return Box::pin(async move {
+            let started = Instant::now();
+            let addresses = resolving.await?;
+            recorder.update(|phases| phases.dns = Some(started.elapsed()));
+            return Ok(addresses);
+        });
+    }
+}
+
+// Opens the tcp connection of a request, optionally to a fixed address instead of the host of the url.
+// The TLS handshake and the Host header still use the host of the url, since only the connection is redirected.
+#[derive(Clone)]
+pub struct Connector {
+    http: HttpConnector<TimedResolver>,
+    connect_to: Option<SocketAddr>,
+    recorder: PhaseRecorder,
+}
+
+impl Service<Uri> for Connector {
+    type Response = TcpStream;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = Result<TcpStream, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        return self.http.poll_ready(cx).map_err(|error| error.into());
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let uri = match self.connect_to {
+            Some(address) => match Uri::builder().scheme("http").authority(address.to_string()).path_and_query("/").build() {
+                Ok(uri) => uri,
+                Err(error) => return Box::pin(async move { Err(error.into()) }),
+            },
+            None => uri,
+        };
+
+        let recorder = self.recorder.clone();
+        let connecting = self.http.call(uri);
+        return Box::pin(async move {
+            let started = Instant::now();
+            let stream = connecting.await?;
+            // The resolver ran inside the connector, its time isnt part of the connect phase
+            recorder.update(|phases| {
+                phases.connect = Some(started.elapsed().saturating_sub(phases.dns.unwrap_or_default()));
+                phases.connected_at = Some(Instant::now());
+            });
+            return Ok(stream);
+        });
+    }
+}
+
+// Times the TLS handshake, which follows once the tcp connection of the inner connector is open
+#[derive(Clone)]
+pub struct TimedConnector {
+    https: HttpsConnector<Connector>,
+    recorder: PhaseRecorder,
+}
+
+impl Service<Uri> for TimedConnector {
+    type Response = MaybeHttpsStream<TcpStream>;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        return self.https.poll_ready(cx);
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let is_https = uri.scheme_str() == Some("https");
+        let recorder = self.recorder.clone();
+        let connecting = self.https.call(uri);
+        return Box::pin(async move {
+            let stream = connecting.await?;
+            if is_https {
+                recorder.update(|phases| phases.tls = phases.connected_at.map(|connected_at| connected_at.elapsed()));
+            }
+            return Ok(stream);
+        });
+    }
+}
+
+// Creates the client of a request, connect_to redirects its connection and the recorder receives its phases
+pub fn client(connect_to: Option<SocketAddr>, recorder: &PhaseRecorder) -> Client {
+    let resolver = TimedResolver { resolver: GaiResolver::new(), recorder: recorder.clone() };
+    let mut http = HttpConnector::new_with_resolver(resolver);
+    http.enforce_http(false);
+
+    let https = HttpsConnector::new_with_connector(Connector { http, connect_to, recorder: recorder.clone() });
+    return hyper::Client::builder().build(TimedConnector { https, recorder: recorder.clone() });
+}
+
+// Parses a connect_to value like 10.0.3.7:8443, [::1]:443 or a bare ip, which keeps the port of the url
+pub fn parse_target(target: &str, url: &Uri) -> Option<SocketAddr> {
+    if let Ok(address) = target.parse::<SocketAddr>() {
+        return Some(address);
+    }
+
+    let ip = target.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>().ok()?;
+    let port = url.port_u16().unwrap_or(if url.scheme_str() == Some("https") { 443 } else { 80 });
+    return Some(SocketAddr::new(ip, port));
+}
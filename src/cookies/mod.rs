@@ -0,0 +1,118 @@
+use cookie::Cookie;
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+
+use crate::failure::FailureReason;
+
+pub const SAME_SITE_VALUES: [&str; 3] = ["strict", "lax", "none"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaxAgeBounds {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+// Assertions on a single cookie set by the response, unset fields arent checked
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CookieExpectation {
+    pub present: Option<bool>, // defaults to true, false asserts that the cookie isnt set
+    pub value: Option<String>,
+    pub matches: Option<String>, // regex the value has to match
+    pub http_only: Option<bool>,
+    pub secure: Option<bool>,
+    pub same_site: Option<String>, // strict, lax or none
+    pub max_age: Option<MaxAgeBounds>, // in seconds
+}
+
+// Outcome of a single assertion on a cookie
+pub struct CookieCheck {
+    pub description: String,
+    pub failure: Option<FailureReason>,
+}
+
+// Parses every Set-Cookie header of a response, unparsable ones are reported as errors
+pub fn parse_set_cookies(headers: &hyper::HeaderMap) -> (Vec<Cookie<'static>>, Vec<String>) {
+    let mut cookies = Vec::new();
+    let mut errors = Vec::new();
+
+    for header in headers.get_all("set-cookie").iter() {
+        match Cookie::parse(header.to_str().unwrap_or_default().to_string()) {
+            Ok(cookie) => cookies.push(cookie),
+            Err(error) => errors.push(error.to_string()),
+        }
+    }
+
+    return (cookies, errors);
+}
+
+fn check(name: &str, attribute: &str, expected: String, actual: String, passed: bool) -> CookieCheck {
+    let description = format!("Expected {} of cookie '{}': {}, got {}", attribute, name, expected, actual);
+    let failure = (!passed).then(|| FailureReason::CookieMismatch {
+        name: name.to_string(),
+        attribute: attribute.to_string(),
+        expected,
+        actual,
+    });
+
+    return CookieCheck { description, failure };
+}
+
+fn flag(value: Option<bool>) -> String {
+    return if value.unwrap_or(false) { "set".to_string() } else { "not set".to_string() };
+}
+
+// Evaluates the expectations of a cookie against the cookies of the response, the last one of a name wins
+pub fn check_cookie(name: &str, expected: &CookieExpectation, cookies: &[Cookie<'static>]) -> Vec<CookieCheck> {
+    let cookie = cookies.iter().rev().find(|cookie| cookie.name() == name);
+    let present = expected.present.unwrap_or(true);
+
+    let cookie = match cookie {
+        Some(cookie) if present => cookie,
+        Some(_) => return vec![check(name, "presence", "absent".to_string(), "present".to_string(), false)],
+        None => return vec![check(name, "presence", if present { "present" } else { "absent" }.to_string(),
+            "absent".to_string(), !present)],
+    };
+
+    let mut checks = vec![check(name, "presence", "present".to_string(), "present".to_string(), true)];
+
+    if let Some(value) = &expected.value {
+        checks.push(check(name, "value", format!("'{}'", value), format!("'{}'", cookie.value()), cookie.value() == value));
+    }
+
+    // The pattern got validated beforehand
+    if let Some(Ok(pattern)) = expected.matches.as_ref().map(|pattern| Regex::new(pattern)) {
+        checks.push(check(name, "value", format!("to match '{}'", pattern), format!("'{}'", cookie.value()),
+            pattern.is_match(cookie.value())));
+    }
+
+    if let Some(http_only) = expected.http_only {
+        checks.push(check(name, "HttpOnly", flag(Some(http_only)), flag(cookie.http_only()),
+            cookie.http_only().unwrap_or(false) == http_only));
+    }
+
+    if let Some(secure) = expected.secure {
+        checks.push(check(name, "Secure", flag(Some(secure)), flag(cookie.secure()),
+            cookie.secure().unwrap_or(false) == secure));
+    }
+
+    if let Some(same_site) = &expected.same_site {
+        let actual = cookie.same_site().map_or("not set".to_string(), |same_site| same_site.to_string());
+        checks.push(check(name, "SameSite", same_site.clone(), actual.clone(), actual.eq_ignore_ascii_case(same_site)));
+    }
+
+    if let Some(bounds) = &expected.max_age {
+        let actual = cookie.max_age().map(|max_age| max_age.whole_seconds());
+        let expected = match (bounds.min, bounds.max) {
+            (Some(min), Some(max)) => format!("between {} and {}", min, max),
+            (Some(min), None) => format!("at least {}", min),
+            (None, Some(max)) => format!("at most {}", max),
+            (None, None) => "any".to_string(),
+        };
+        let passed = actual.is_some_and(|seconds| bounds.min.is_none_or(|min| seconds >= min)
+            && bounds.max.is_none_or(|max| seconds <= max));
+        checks.push(check(name, "Max-Age", expected, actual.map_or("not set".to_string(), |seconds| seconds.to_string()),
+            passed));
+    }
+
+    return checks;
+}
@@ -0,0 +1,96 @@
+use serde::{Serialize, Deserialize};
+
+use crate::failure::FailureReason;
+
+// Methods a browser sends without them being listed in Access-Control-Allow-Methods
+const SAFELISTED_METHODS: [&str; 3] = ["GET", "HEAD", "POST"];
+
+// The request a browser is about to send, the test sends its preflight instead
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorsPreflight {
+    pub origin: String,
+    pub request_method: String,
+    pub request_headers: Option<Vec<String>>,
+}
+
+// Outcome of a single CORS rule
+pub struct CorsCheck {
+    pub description: String,
+    pub failure: Option<FailureReason>,
+}
+
+impl CorsPreflight {
+    // The headers of the preflight request, the origin is given already interpolated
+    pub fn preflight_headers(&self, origin: &str) -> Vec<(String, String)> {
+        let mut headers = vec![
+            ("Origin".to_string(), origin.to_string()),
+            ("Access-Control-Request-Method".to_string(), self.request_method.to_uppercase()),
+        ];
+
+        if let Some(requested) = self.request_headers.as_ref().filter(|requested| !requested.is_empty()) {
+            headers.push(("Access-Control-Request-Headers".to_string(), requested.join(", ").to_lowercase()));
+        }
+
+        return headers;
+    }
+}
+
+fn check(rule: &str, expected: String, actual: String, passed: bool) -> CorsCheck {
+    let description = format!("Expected {} to {}, got {}", rule, expected, actual);
+    let failure = (!passed).then(|| FailureReason::CorsViolation {
+        rule: rule.to_string(),
+        expected,
+        actual,
+    });
+
+    return CorsCheck { description, failure };
+}
+
+fn header_value(headers: &hyper::HeaderMap, name: &str) -> Option<String> {
+    return headers.get(name).map(|value| value.to_str().unwrap_or_default().trim().to_string());
+}
+
+fn quoted(value: &Option<String>) -> String {
+    return value.as_ref().map_or("no header".to_string(), |value| format!("'{}'", value));
+}
+
+// Evaluates the response of a preflight against the rules a browser applies to it
+pub fn check_preflight(preflight: &CorsPreflight, origin: &str, status: u16, headers: &hyper::HeaderMap)
+     -> Vec<CorsCheck> {
+    let mut checks = vec![check("the preflight status", "be ok (2xx)".to_string(), status.to_string(),
+        (200..300).contains(&status))];
+
+    let allow_origin = header_value(headers, "access-control-allow-origin");
+    checks.push(check("Access-Control-Allow-Origin", format!("be '{}' or '*'", origin), quoted(&allow_origin),
+        allow_origin.as_ref().is_some_and(|allowed| allowed == origin || allowed == "*")));
+
+    // The allowed methods are case sensitive, a missing header only permits the safelisted ones
+    let method = preflight.request_method.to_uppercase();
+    let allow_methods = header_value(headers, "access-control-allow-methods");
+    let method_allowed = match &allow_methods {
+        Some(allowed) => allowed.split(',').map(|allowed| allowed.trim()).any(|allowed| allowed == method || allowed == "*"),
+        None => SAFELISTED_METHODS.contains(&method.as_str()),
+    };
+    checks.push(check("Access-Control-Allow-Methods", format!("allow {}", method), quoted(&allow_methods),
+        method_allowed));
+
+    // A wildcard permits every header but Authorization, which always has to be listed
+    let allow_headers = header_value(headers, "access-control-allow-headers");
+    let allowed_headers: Vec<String> = allow_headers.iter()
+        .flat_map(|allowed| allowed.split(','))
+        .map(|allowed| allowed.trim().to_lowercase())
+        .collect();
+    for requested in preflight.request_headers.iter().flatten() {
+        let requested_lower = requested.to_lowercase();
+        let header_allowed = allowed_headers.contains(&requested_lower)
+            || (requested_lower != "authorization" && allowed_headers.iter().any(|allowed| allowed == "*"));
+        let expected = if requested_lower == "authorization" {
+            format!("list '{}' explicitly", requested)
+        } else {
+            format!("allow '{}'", requested)
+        };
+        checks.push(check("Access-Control-Allow-Headers", expected, quoted(&allow_headers), header_allowed));
+    }
+
+    return checks;
+}
@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Local, SecondsFormat, TimeZone, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::{CredentialSource, LITERAL_PREFIX};
+
+// A credential as it was sent with a test, kept for the diagnostic of a rejected request
+pub struct SentCredential {
+    pub kind: &'static str, // bearer_token or session_id
+    pub value: String,
+    pub source: CredentialSource,
+    pub capture: Option<String>, // the capture that supplied it, or the one its literal is named after
+}
+
+// Where the credential of a test answered with 401 or 403 came from, purely informational
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthDiagnostic {
+    pub credential: String, // bearer_token or session_id
+    pub source: String, // captured value, test literal or global config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capture: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub produced_by: Option<String>, // the test that stored the capture
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub produced_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_s: Option<i64>, // since the capture was stored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwt_exp: Option<String>, // the decoded but unverified exp claim
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwt_expires_in_s: Option<i64>, // negative once the token expired
+    #[serde(skip)]
+    captured: bool, // sent as the value of the capture instead of its name
+    #[serde(skip)]
+    jwt: bool,
+}
+
+// The credential a test sent, a literal named like a capture hints at a producer that didnt run
+pub fn sent(kind: &'static str, local: &Option<Option<String>>, resolved: Option<&(String, CredentialSource)>,
+     produced: &HashSet<String>) -> Option<SentCredential> {
+    let (value, source) = resolved?;
+    let capture = match (source, local) {
+        (CredentialSource::Capture, Some(Some(name))) => Some(name.clone()),
+        (CredentialSource::Literal, Some(Some(name))) if !name.starts_with(LITERAL_PREFIX) && produced.contains(name) =>
+            Some(name.clone()),
+        _ => None,
+    };
+    return Some(SentCredential { kind, value: value.clone(), source: *source, capture });
+}
+
+// Decodes unpadded base64url, the encoding of the segments of a JWT
+fn decode_base64url(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for char in text.trim_end_matches('=').bytes() {
+        let value = match char {
+            b'A'..=b'Z' => char - b'A',
+            b'a'..=b'z' => char - b'a' + 26,
+            b'0'..=b'9' => char - b'0' + 52,
+            b'-' | b'+' => 62,
+            b'_' | b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    return Some(bytes);
+}
+
+// The claims of a token that looks like a JWT, three base64url segments with a json payload
+fn jwt_claims(token: &str) -> Option<serde_json::Map<String, serde_json::Value>> {
+    let segments: Vec<&str> = token.split('.').collect();
+    if segments.len() != 3 {
+        return None;
+    }
+    let header: serde_json::Value = serde_json::from_slice(&decode_base64url(segments[0])?).ok()?;
+    header.get("alg")?;
+    return match serde_json::from_slice(&decode_base64url(segments[1])?).ok()? {
+        serde_json::Value::Object(claims) => Some(claims),
+        _ => None,
+    };
+}
+
+// Describes the credential, the producer is the test that stored its capture and when
+pub fn diagnose(credential: &SentCredential, producer: Option<(String, DateTime<Local>)>, now: DateTime<Local>)
+     -> AuthDiagnostic {
+    let mut diagnostic = AuthDiagnostic {
+        credential: credential.kind.to_string(),
+        source: credential.source.to_string(),
+        capture: credential.capture.clone(),
+        produced_by: None,
+        produced_at: None,
+        age_s: None,
+        jwt_exp: None,
+        jwt_expires_in_s: None,
+        captured: credential.source == CredentialSource::Capture,
+        jwt: false,
+    };
+
+    if let Some((test, at)) = producer {
+        diagnostic.produced_by = Some(test);
+        diagnostic.produced_at = Some(at.to_rfc3339_opts(SecondsFormat::Secs, false));
+        diagnostic.age_s = Some((now - at).num_seconds());
+    }
+
+    if let Some(claims) = jwt_claims(&credential.value) {
+        diagnostic.jwt = true;
+        let exp = claims.get("exp").and_then(|exp| exp.as_i64().or_else(|| exp.as_f64().map(|exp| exp as i64)));
+        if let Some(exp) = exp.and_then(|exp| Utc.timestamp_opt(exp, 0).single()) {
+            diagnostic.jwt_exp = Some(exp.to_rfc3339_opts(SecondsFormat::Secs, true));
+            diagnostic.jwt_expires_in_s = Some((exp - now.with_timezone(&Utc)).num_seconds());
+        }
+    }
+
+    return diagnostic;
+}
+
+impl AuthDiagnostic {
+    // The lines of the console and the log file
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        let origin = match (&self.capture, &self.produced_by) {
+            (Some(capture), Some(test)) => format!("the capture '{}', stored by test {} at {}, {} s ago", capture,
+                test, self.produced_at.as_deref().unwrap_or_default(), self.age_s.unwrap_or_default()),
+            (Some(capture), None) if self.captured => format!("the capture '{}', set before the run", capture),
+            (Some(capture), None) => format!("the literal '{}', no test stored a capture of that name before this \
+                one", capture),
+            (None, _) => format!("the {}", self.source),
+        };
+        lines.push(format!("{} supplied by {}", self.credential, origin));
+
+        if self.jwt {
+            lines.push(match (&self.jwt_exp, self.jwt_expires_in_s) {
+                (Some(exp), Some(seconds)) if seconds < 0 => format!("JWT exp {}, expired {} s ago", exp, -seconds),
+                (Some(exp), Some(seconds)) => format!("JWT exp {}, valid for another {} s", exp, seconds),
+                _ => "JWT without an exp claim".to_string(),
+            });
+        }
+        return lines;
+    }
+}
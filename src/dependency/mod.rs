@@ -0,0 +1,384 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+use crate::{Config, Endpoint, condition, interpolation, test_reference};
+#[cfg(feature = "scripting")]
+use crate::script;
+
+// Collects the names of the {{placeholders}} of a text
+pub fn placeholders(text: &str, names: &mut Vec<String>) {
+    for segment in interpolation::segments(text).iter() {
+        if let interpolation::Segment::Placeholder { name, .. } = segment {
+            names.push(name.to_string());
+        }
+    }
+}
+
+// The name a capture of a test is stored as, prefixed with its capture_namespace
+pub fn capture_name(test: &Endpoint, key: &str) -> String {
+    return match &test.capture_namespace {
+        Some(namespace) => format!("{}.{}", namespace, key),
+        None => key.to_string(),
+    };
+}
+
+// Names of the captures a test stores for later tests
+pub fn produced(test: &Endpoint) -> Vec<String> {
+    let mut names: Vec<String> = test.capture.iter().flatten().map(|(key, _)| capture_name(test, key)).collect();
+    #[cfg(feature = "scripting")]
+    for (source, hook) in [(&test.pre_script, script::Hook::Pre), (&test.post_script, script::Hook::Post)] {
+        if let Some(Ok(script)) = source.as_ref().map(|source| script::Script::parse(source, hook)) {
+            names.extend(script.assigned());
+        }
+    }
+    names.sort();
+    names.dedup();
+
+    return names;
+}
+
+// Names of the captures a test refers to, credentials only count if they name a known capture
+pub fn consumed(test: &Endpoint, known: &HashSet<String>) -> Vec<String> {
+    let mut names = Vec::new();
+
+    placeholders(&test.route, &mut names);
+    for value in test.json.iter().flat_map(interpolation::json_strings) {
+        placeholders(value, &mut names);
+    }
+    for value in test.json_body.iter().flat_map(|body| body.values()) {
+        placeholders(value, &mut names);
+    }
+    if let Some(body) = &test.body {
+        placeholders(body, &mut names);
+    }
+    if let Some(template) = &test.template {
+        let mut template_names = Vec::new();
+        placeholders(template, &mut template_names);
+        names.extend(template_names.into_iter()
+            .filter(|name| !test.template_values.as_ref().is_some_and(|values| values.contains_key(name))));
+    }
+    if let (Some(expected), Some(true)) = (&test.expected_body, test.interpolate) {
+        placeholders(expected, &mut names);
+    }
+    for value in test.template_values.iter().flat_map(|values| values.values()) {
+        placeholders(value, &mut names);
+    }
+    for value in test.headers.iter().flat_map(|headers| headers.values()).flatten() {
+        placeholders(value, &mut names);
+    }
+    if let Some(preflight) = &test.cors {
+        placeholders(&preflight.origin, &mut names);
+    }
+    if let Some(message) = test.websocket.as_ref().and_then(|exchange| exchange.send.as_ref()) {
+        placeholders(message, &mut names);
+    }
+    if let Some(location) = &test.expect_location {
+        placeholders(location, &mut names);
+    }
+    for (_, value) in test.conditional.iter().flat_map(|conditional| conditional.headers()) {
+        placeholders(&value, &mut names);
+    }
+    for hop in test.expect_redirect_chain.iter().flatten() {
+        placeholders(hop, &mut names);
+    }
+    if let Some(trace_id) = &test.trace_id {
+        placeholders(trace_id, &mut names);
+    }
+    if let Some(Some(forwarded_for)) = &test.forwarded_for {
+        placeholders(forwarded_for, &mut names);
+    }
+
+    for credential in [&test.bearer_token, &test.session_id] {
+        if let Some(Some(name)) = credential {
+            if known.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    }
+
+    if let Some(Ok(condition)) = test.when.as_ref().map(|expression| condition::Condition::parse(expression)) {
+        names.extend(condition.captures().into_iter().cloned());
+    }
+    for expression in test.expect_expr.iter().flatten() {
+        placeholders(expression, &mut names);
+    }
+    if let Some(name) = &test.expect_body_equals_capture {
+        names.push(name.clone());
+    }
+
+    #[cfg(feature = "scripting")]
+    for (source, hook) in [(&test.pre_script, script::Hook::Pre), (&test.post_script, script::Hook::Post)] {
+        if let Some(Ok(script)) = source.as_ref().map(|source| script::Script::parse(source, hook)) {
+            names.extend(script.captures());
+        }
+    }
+
+    names.sort();
+    names.dedup();
+    return names;
+}
+
+// Names the pre_script of a test sets, the request of the same test already sees them
+#[cfg(feature = "scripting")]
+fn set_before_request(test: &Endpoint) -> Vec<String> {
+    return match test.pre_script.as_ref().map(|source| script::Script::parse(source, script::Hook::Pre)) {
+        Some(Ok(script)) => script.assigned(),
+        _ => Vec::new(),
+    };
+}
+
+#[cfg(not(feature = "scripting"))]
+fn set_before_request(_test: &Endpoint) -> Vec<String> {
+    return Vec::new();
+}
+
+// Names the expect_expr of a test reads from its own captures, they are stored before the expressions are evaluated
+fn set_before_expressions(test: &Endpoint) -> Vec<String> {
+    let mut names = Vec::new();
+    for expression in test.expect_expr.iter().flatten() {
+        placeholders(expression, &mut names);
+    }
+    let own = produced(test);
+    return names.into_iter().filter(|name| own.contains(name)).collect();
+}
+
+// Names of all captures produced by any test
+pub fn all_produced(tests: &[Endpoint]) -> HashSet<String> {
+    return tests.iter().flat_map(produced).collect();
+}
+
+// Captures no test and no global header reads, as the one based index of the producing test and the name. Values a
+// script sets are left out, they are often only intermediate results.
+pub fn unused_captures(config: &Config, variables: &HashSet<String>) -> Vec<(usize, String)> {
+    let mut known = all_produced(&config.tests);
+    known.extend(variables.iter().cloned());
+
+    let mut used: Vec<String> = config.tests.iter().flat_map(|test| consumed(test, &known)).collect();
+    for value in config.headers.iter().flat_map(|headers| headers.values()) {
+        placeholders(value, &mut used);
+    }
+    if let Some(forwarded_for) = &config.forwarded_for {
+        placeholders(forwarded_for, &mut used);
+    }
+
+    return config.tests.iter().enumerate()
+        .flat_map(|(index, test)| produced(test).into_iter().map(move |name| (index + 1, name)))
+        .filter(|(index, name)| !used.contains(name)
+            && config.tests[index - 1].capture.iter().flatten()
+                .any(|(key, _)| capture_name(&config.tests[index - 1], key) == *name))
+        .collect();
+}
+
+// A name like auth_token or auth.token rather than a token, which are long and mixed case or contain other characters
+fn looks_like_capture_name(value: &str) -> bool {
+    return value.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && value.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '.');
+}
+
+// Credentials of the tests that look like the name of a capture, but no test captures it nor is it a variable.
+// They are sent as literal values, as the one based index of the test, the credential and the value.
+pub fn dangling_credentials(tests: &[Endpoint], variables: &HashSet<String>) -> Vec<(usize, &'static str, String)> {
+    let produced_names = all_produced(tests);
+    let mut dangling = Vec::new();
+
+    for (index, test) in tests.iter().enumerate() {
+        for (credential, value) in [("bearer_token", &test.bearer_token), ("session_id", &test.session_id)] {
+            if let Some(Some(value)) = value {
+                if looks_like_capture_name(value) && value != "none" && !produced_names.contains(value)
+                    && !variables.contains(value) {
+                    dangling.push((index + 1, credential, value.clone()));
+                }
+            }
+        }
+    }
+    return dangling;
+}
+
+// Captures that overwrite a variable defined up front, as the one based index of the test and the name
+pub fn shadowed_variables(tests: &[Endpoint], variables: &HashSet<String>) -> Vec<(usize, String)> {
+    return tests.iter().enumerate()
+        .flat_map(|(index, test)| produced(test).into_iter().map(move |name| (index + 1, name)))
+        .filter(|(_, name)| variables.contains(name))
+        .collect();
+}
+
+// The tests to run for a subset of indices
+#[derive(Debug)]
+pub struct Selection {
+    pub indices: Vec<usize>, // zero based, in config order
+    pub pulled_in: Vec<String>, // explains which tests were added for their captures
+}
+
+// Resolves one based test indices, tests providing captures for a selected test are pulled in as well
+pub fn select(tests: &[Endpoint], variables: &HashSet<String>, requested: &[usize]) -> Result<Selection, String> {
+    if let Some(index) = requested.iter().find(|index| **index == 0 || **index > tests.len()) {
+        return Err(format!("Test index {} is out of range, the config contains {} tests.", index, tests.len()));
+    }
+
+    let graph = graph(tests, variables);
+
+    let mut selected: Vec<usize> = requested.iter().map(|index| index - 1).collect();
+    let mut pulled_in = Vec::new();
+    let mut pending = selected.clone();
+
+    // The providers are the ones whose values the test would see in a full run
+    while let Some(index) = pending.pop() {
+        for (provider, name) in graph.producers[index].iter() {
+            if selected.contains(provider) {
+                continue;
+            }
+
+            pulled_in.push(format!("Test {} is run as well, it captures '{}' for test {}.",
+             test_reference(&tests[*provider], provider + 1), name, test_reference(&tests[index], index + 1)));
+            selected.push(*provider);
+            pending.push(*provider);
+        }
+    }
+
+    selected.sort();
+    selected.dedup();
+    return Ok(Selection { indices: selected, pulled_in });
+}
+
+// Which tests a test reads captures from, the closest earlier producer of a name or else the closest later one
+#[derive(Debug)]
+pub struct Graph {
+    pub producers: Vec<Vec<(usize, String)>>, // zero based producer and the name read from it, per test
+    pub unknown: Vec<(usize, String)>, // zero based test and a name that is neither a variable nor captured by any test
+}
+
+pub fn graph(tests: &[Endpoint], variables: &HashSet<String>) -> Graph {
+    let mut known = all_produced(tests);
+    known.extend(variables.iter().cloned());
+    let produced_names: Vec<Vec<String>> = tests.iter().map(produced).collect();
+
+    let mut graph = Graph { producers: vec![Vec::new(); tests.len()], unknown: Vec::new() };
+    for (index, test) in tests.iter().enumerate() {
+        let mut own = set_before_request(test);
+        own.extend(set_before_expressions(test));
+        for name in consumed(test, &known).into_iter().filter(|name| !own.contains(name)) {
+            let produces = |producer: &usize| produced_names[*producer].contains(&name);
+
+            // A variable is available from the start, a later capture cant be the value the test reads
+            let producer = match (0..index).rev().find(produces) {
+                Some(producer) => Some(producer),
+                None if variables.contains(&name) => None,
+                None => (index + 1..tests.len()).find(produces),
+            };
+
+            match producer {
+                Some(producer) => graph.producers[index].push((producer, name)),
+                None if !variables.contains(&name) => graph.unknown.push((index, name)),
+                None => (),
+            }
+        }
+        graph.producers[index].sort();
+        graph.producers[index].dedup();
+    }
+
+    return graph;
+}
+
+// Zero based indices of all tests a test depends on, directly or through the captures of its producers
+pub fn all_producers(graph: &Graph, index: usize) -> Vec<usize> {
+    let mut producers: Vec<usize> = Vec::new();
+    let mut pending = vec![index];
+
+    while let Some(current) = pending.pop() {
+        for (producer, _) in graph.producers[current].iter() {
+            if *producer != index && !producers.contains(producer) {
+                producers.push(*producer);
+                pending.push(*producer);
+            }
+        }
+    }
+
+    producers.sort();
+    return producers;
+}
+
+// State of the search for cycles
+struct CycleSearch {
+    counter: usize,
+    order: Vec<Option<usize>>,
+    lowest: Vec<usize>,
+    stack: Vec<usize>,
+    cycles: Vec<Vec<usize>>,
+}
+
+fn strong_connect(graph: &Graph, index: usize, state: &mut CycleSearch) {
+    state.order[index] = Some(state.counter);
+    state.lowest[index] = state.counter;
+    state.counter += 1;
+    state.stack.push(index);
+
+    for (producer, _) in graph.producers[index].iter() {
+        let producer = *producer;
+        match state.order[producer] {
+            None => {
+                strong_connect(graph, producer, state);
+                state.lowest[index] = state.lowest[index].min(state.lowest[producer]);
+            },
+            Some(order) if state.stack.contains(&producer) => state.lowest[index] = state.lowest[index].min(order),
+            Some(_) => (),
+        }
+    }
+
+    if Some(state.lowest[index]) == state.order[index] {
+        let mut component = Vec::new();
+        while let Some(member) = state.stack.pop() {
+            component.push(member);
+            if member == index {
+                break;
+            }
+        }
+        if component.len() > 1 {
+            component.sort();
+            state.cycles.push(component);
+        }
+    }
+}
+
+// Groups of tests that read each others captures, so none of them can run first (Tarjan's algorithm)
+pub fn cycles(graph: &Graph) -> Vec<Vec<usize>> {
+    let count = graph.producers.len();
+    let mut state = CycleSearch { counter: 0, order: vec![None; count], lowest: vec![0; count], stack: Vec::new(),
+        cycles: Vec::new() };
+
+    for index in 0..count {
+        if state.order[index].is_none() {
+            strong_connect(graph, index, &mut state);
+        }
+    }
+
+    state.cycles.sort();
+    return state.cycles;
+}
+
+// The order to run the tests in, as close to the config as possible but every test after the ones it reads from.
+// Tests of a cycle keep their config order.
+pub fn schedule(graph: &Graph) -> Vec<usize> {
+    let count = graph.producers.len();
+    let mut waiting_for: Vec<usize> = graph.producers.iter().map(|producers| producers.len()).collect();
+    let mut consumers: Vec<Vec<usize>> = vec![Vec::new(); count];
+    for (index, producers) in graph.producers.iter().enumerate() {
+        for (producer, _) in producers.iter() {
+            consumers[*producer].push(index);
+        }
+    }
+
+    let mut ready: BinaryHeap<Reverse<usize>> = (0..count).filter(|index| waiting_for[*index] == 0).map(Reverse).collect();
+    let mut order = Vec::with_capacity(count);
+    while let Some(Reverse(index)) = ready.pop() {
+        order.push(index);
+        for consumer in consumers[index].iter() {
+            waiting_for[*consumer] -= 1;
+            if waiting_for[*consumer] == 0 {
+                ready.push(Reverse(*consumer));
+            }
+        }
+    }
+
+    order.extend((0..count).filter(|index| !order.contains(index)).collect::<Vec<usize>>());
+    return order;
+}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hyper::header::{HeaderMap, WWW_AUTHENTICATE};
+use md5::Md5;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+// Hash functions a server may demand inside its challenge
+#[derive(Debug, PartialEq)]
+enum Algorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+#[derive(Debug)]
+pub struct DigestChallenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    qop: Option<String>,
+    algorithm: Algorithm,
+    algorithm_name: String,
+}
+
+impl std::fmt::Display for DigestChallenge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "realm=\"{}\", nonce=\"{}\", qop={}, algorithm={}",
+            self.realm, self.nonce, self.qop.clone().unwrap_or("none".to_string()), self.algorithm_name);
+    }
+}
+
+// Splits the parameter list of a challenge into key-value pairs, quoted values may contain commas
+fn parse_parameters(parameters: &str) -> HashMap<String, String> {
+    let mut result = HashMap::new();
+    let mut chars = parameters.chars().peekable();
+
+    loop {
+        // Skip separators between the pairs
+        while let Some(' ') | Some(',') = chars.peek() {
+            chars.next();
+        }
+
+        let key: String = chars.by_ref().take_while(|char| *char != '=').collect();
+        if key.is_empty() {
+            break;
+        }
+
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(char) = chars.next() {
+                match char {
+                    '\\' => if let Some(escaped) = chars.next() { value.push(escaped) },
+                    '"' => break,
+                    _ => value.push(char),
+                }
+            }
+        } else {
+            value = chars.by_ref().take_while(|char| *char != ',').collect();
+        }
+
+        result.insert(key.trim().to_lowercase(), value.trim().to_string());
+    }
+
+    return result;
+}
+
+// Parses a single WWW-Authenticate header value, returns None if its not a supported digest challenge
+fn parse_challenge(header: &str) -> Option<DigestChallenge> {
+    let (scheme, parameters) = header.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("digest") {
+        return None;
+    }
+
+    let parameters = parse_parameters(parameters);
+    let algorithm_name = parameters.get("algorithm").cloned().unwrap_or("MD5".to_string());
+    let algorithm = match algorithm_name.to_uppercase().as_str() {
+        "MD5" => Algorithm::Md5,
+        "MD5-SESS" => Algorithm::Md5Sess,
+        "SHA-256" => Algorithm::Sha256,
+        "SHA-256-SESS" => Algorithm::Sha256Sess,
+        _ => return None,
+    };
+
+    // Only qop=auth is supported, auth-int would require hashing the body
+    let qop = match parameters.get("qop") {
+        Some(options) => {
+            if !options.split(',').any(|option| option.trim() == "auth") {
+                return None;
+            }
+            Some("auth".to_string())
+        },
+        None => None,
+    };
+
+    return Some(DigestChallenge {
+        realm: parameters.get("realm").cloned().unwrap_or_default(),
+        nonce: parameters.get("nonce")?.clone(),
+        opaque: parameters.get("opaque").cloned(),
+        qop,
+        algorithm,
+        algorithm_name,
+    });
+}
+
+// Looks for the first supported digest challenge in the response headers
+pub fn find_challenge(headers: &HeaderMap) -> Option<DigestChallenge> {
+    return headers.get_all(WWW_AUTHENTICATE).iter()
+        .filter_map(|value| value.to_str().ok())
+        .find_map(parse_challenge);
+}
+
+fn hash(algorithm: &Algorithm, data: String) -> String {
+    let bytes = match algorithm {
+        Algorithm::Md5 | Algorithm::Md5Sess => Md5::digest(data.as_bytes()).to_vec(),
+        Algorithm::Sha256 | Algorithm::Sha256Sess => Sha256::digest(data.as_bytes()).to_vec(),
+    };
+
+    return bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+}
+
+// Generates a client nonce, doesnt need to be cryptographically secure
+fn generate_cnonce(challenge: &DigestChallenge) -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let mut cnonce = hash(&Algorithm::Md5, format!("{}:{}", nanos, challenge.nonce));
+    cnonce.truncate(16);
+
+    return cnonce;
+}
+
+// Computes the value of the Authorization header answering the given challenge (RFC 7616)
+pub fn authorization_header(challenge: &DigestChallenge, credentials: &DigestCredentials,
+     method: &str, uri: &str) -> String {
+    return answer(challenge, credentials, method, uri, &generate_cnonce(challenge));
+}
+
+fn answer(challenge: &DigestChallenge, credentials: &DigestCredentials, method: &str, uri: &str,
+     cnonce: &str) -> String {
+
+    let algorithm = &challenge.algorithm;
+    let nonce_count = "00000001";
+
+    let mut ha1 = hash(algorithm, format!("{}:{}:{}",
+        credentials.username, challenge.realm, credentials.password));
+    if *algorithm == Algorithm::Md5Sess || *algorithm == Algorithm::Sha256Sess {
+        ha1 = hash(algorithm, format!("{}:{}:{}", ha1, challenge.nonce, cnonce));
+    }
+
+    let ha2 = hash(algorithm, format!("{}:{}", method, uri));
+
+    let response = match &challenge.qop {
+        Some(qop) => hash(algorithm, format!("{}:{}:{}:{}:{}:{}",
+            ha1, challenge.nonce, nonce_count, cnonce, qop, ha2)),
+        None => hash(algorithm, format!("{}:{}:{}", ha1, challenge.nonce, ha2)),
+    };
+
+    let mut header = format!(
+        "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+        credentials.username, challenge.realm, challenge.nonce, uri, challenge.algorithm_name, response);
+
+    if let Some(qop) = &challenge.qop {
+        header += &format!(", qop={}, nc={}, cnonce=\"{}\"", qop, nonce_count, cnonce);
+    }
+
+    if let Some(opaque) = &challenge.opaque {
+        header += &format!(", opaque=\"{}\"", opaque);
+    }
+
+    return header;
+}
+
+// The Authorization header for the log, the response hash is derived from the password
+pub fn redact(authorization: &str) -> String {
+    return match authorization.split_once("response=\"") {
+        Some((before, after)) => {
+            let rest = after.split_once('"').map_or("", |(_, rest)| rest);
+            format!("{}response=\"<redacted>\"{}", before, rest)
+        },
+        None => authorization.to_string(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example of RFC 7616 section 3.9.1
+    const CHALLENGE: &str = "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm={}, \
+        nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"";
+    const CNONCE: &str = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+    fn rfc_answer(algorithm: &str) -> String {
+        let challenge = parse_challenge(&CHALLENGE.replace("{}", algorithm)).unwrap();
+        let credentials = DigestCredentials { username: "Mufasa".to_string(), password: "Circle of Life".to_string() };
+        return answer(&challenge, &credentials, "GET", "/dir/index.html", CNONCE);
+    }
+
+    #[test]
+    fn answers_the_rfc_example_with_md5() {
+        assert_eq!(rfc_answer("MD5"), "Digest username=\"Mufasa\", realm=\"http-auth@example.org\", \
+            nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", uri=\"/dir/index.html\", algorithm=MD5, \
+            response=\"8ca523f5e9506fed4657c9700eebdbec\", qop=auth, nc=00000001, \
+            cnonce=\"f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ\", \
+            opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"");
+    }
+
+    #[test]
+    fn answers_the_rfc_example_with_sha256() {
+        assert!(rfc_answer("SHA-256")
+            .contains("response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\""));
+    }
+
+    #[test]
+    fn parses_only_supported_challenges() {
+        let challenge = parse_challenge(&CHALLENGE.replace("{}", "sha-256-sess")).unwrap();
+        assert_eq!((challenge.algorithm, challenge.qop.as_deref()), (Algorithm::Sha256Sess, Some("auth")));
+        assert!(parse_challenge(&CHALLENGE.replace("{}", "SHA-512")).is_none());
+        assert!(parse_challenge("Digest realm=\"a\", nonce=\"b\", qop=\"auth-int\"").is_none());
+        assert!(parse_challenge("Basic realm=\"a\"").is_none());
+        assert!(parse_challenge("Digest realm=\"a\"").is_none());
+    }
+
+    #[test]
+    fn redacts_the_response_hash() {
+        assert_eq!(redact(&rfc_answer("MD5")), "Digest username=\"Mufasa\", realm=\"http-auth@example.org\", \
+            nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", uri=\"/dir/index.html\", algorithm=MD5, \
+            response=\"<redacted>\", qop=auth, nc=00000001, cnonce=\"f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ\", \
+            opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"");
+    }
+}
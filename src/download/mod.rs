@@ -0,0 +1,181 @@
+use std::path::{Path, PathBuf};
+use std::fs;
+
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+
+use crate::Result;
+use crate::failure::FailureReason;
+
+// Longest file name most file systems accept
+const MAX_FILENAME_BYTES: usize = 255;
+
+// Writes the body of the response into a directory, e.g. an exported csv for later inspection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveResponse {
+    pub dir: PathBuf,
+    pub use_server_filename: Option<bool>, // the filename of the Content-Disposition, sanitized, instead of the index
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = value.get(index + 1..index + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    return String::from_utf8(decoded).ok();
+}
+
+// The parameters of a Content-Disposition value, quoted values may contain semicolons
+fn parameters(value: &str) -> Vec<(String, String)> {
+    let mut parameters = Vec::new();
+    let mut rest = value.split_once(';').map_or("", |(_, rest)| rest);
+    while let Some((name, after)) = rest.split_once('=') {
+        let after = after.trim_start();
+        let (parameter, next) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut text = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = quoted.len();
+                while let Some((position, char)) = chars.next() {
+                    match char {
+                        '\\' => text.extend(chars.next().map(|(_, escaped)| escaped)),
+                        '"' => { end = position + 1; break; },
+                        _ => text.push(char),
+                    }
+                }
+                (text, quoted[end..].split_once(';').map_or("", |(_, next)| next))
+            },
+            None => match after.split_once(';') {
+                Some((parameter, next)) => (parameter.trim().to_string(), next),
+                None => (after.trim().to_string(), ""),
+            },
+        };
+        parameters.push((name.trim().to_lowercase(), parameter));
+        rest = next;
+    }
+    return parameters;
+}
+
+// The filename the server suggests, the encoded filename* of RFC 6266 takes precedence over the plain one
+pub fn server_filename(headers: &hyper::HeaderMap) -> Option<String> {
+    let value = headers.get(hyper::header::CONTENT_DISPOSITION)?.to_str().ok()?;
+    let parameters = parameters(value);
+    let encoded = parameters.iter().find(|(name, _)| name == "filename*")
+        .and_then(|(_, value)| value.splitn(3, '\'').nth(2))
+        .and_then(percent_decode);
+    return encoded.or_else(|| parameters.into_iter().find(|(name, _)| name == "filename").map(|(_, value)| value));
+}
+
+// Reduces a filename of the server to a single harmless path component. Only the part after the last separator is
+// kept, everything but letters, digits, dots, dashes and underscores is replaced and leading dots are removed, so
+// neither ../ nor an absolute path nor a hidden file can come out of it.
+pub fn sanitize_filename(filename: &str) -> Option<String> {
+    let base = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    let mut sanitized: String = base.chars()
+        .map(|char| if char.is_alphanumeric() || ['.', '-', '_'].contains(&char) { char } else { '_' })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_string();
+    while sanitized.len() > MAX_FILENAME_BYTES {
+        sanitized.pop();
+    }
+    return (!sanitized.is_empty()).then_some(sanitized);
+}
+
+// Checks the filename of the Content-Disposition against a pattern
+pub fn check_filename(pattern: &str, headers: &hyper::HeaderMap) -> (String, Option<FailureReason>) {
+    let filename = server_filename(headers);
+    let matches = match (Regex::new(pattern), &filename) {
+        (Ok(regex), Some(filename)) => regex.is_match(filename),
+        _ => false,
+    };
+    let message = format!("Expected the Content-Disposition filename to match '{}', got {}", pattern,
+        filename.as_ref().map_or("none".to_string(), |filename| format!("'{}'", filename)));
+    let failure = (!matches).then(|| FailureReason::FilenameMismatch { pattern: pattern.to_string(), filename });
+    return (message, failure);
+}
+
+// The path the body is saved to, the base name of the test with the extension of the server's filename unless its
+// whole name is used
+pub fn target(save: &SaveResponse, base_name: &str, server_filename: Option<&str>) -> PathBuf {
+    let sanitized = server_filename.and_then(sanitize_filename);
+    let filename = match sanitized {
+        Some(sanitized) if save.use_server_filename == Some(true) => sanitized,
+        _ => {
+            let extension = sanitized.as_deref()
+                .and_then(|name| Path::new(name).extension())
+                .and_then(|extension| extension.to_str())
+                .unwrap_or("bin");
+            format!("{}.{}", base_name, extension)
+        },
+    };
+    return save.dir.join(filename);
+}
+
+pub fn save(path: &Path, body: &[u8]) -> Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(path, body)?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(content_disposition: &str) -> hyper::HeaderMap {
+        let mut headers = hyper::HeaderMap::new();
+        headers.insert(hyper::header::CONTENT_DISPOSITION, content_disposition.parse().unwrap());
+        return headers;
+    }
+
+    #[test]
+    fn keeps_only_the_last_path_component() {
+        assert_eq!(sanitize_filename("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(sanitize_filename("/etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(sanitize_filename("C:\\Windows\\system32\\evil.dll").as_deref(), Some("evil.dll"));
+        assert_eq!(sanitize_filename("..\\..\\boot.ini").as_deref(), Some("boot.ini"));
+        assert_eq!(sanitize_filename("reports/").as_deref(), None);
+    }
+
+    #[test]
+    fn removes_leading_dots_and_replaces_other_characters() {
+        assert_eq!(sanitize_filename("..").as_deref(), None);
+        assert_eq!(sanitize_filename("...").as_deref(), None);
+        assert_eq!(sanitize_filename(".bashrc").as_deref(), Some("bashrc"));
+        assert_eq!(sanitize_filename("..hidden.csv").as_deref(), Some("hidden.csv"));
+        assert_eq!(sanitize_filename("report 2024:Q1?.csv").as_deref(), Some("report_2024_Q1_.csv"));
+        assert_eq!(sanitize_filename(&"a".repeat(300)).map(|name| name.len()), Some(MAX_FILENAME_BYTES));
+    }
+
+    #[test]
+    fn prefers_the_encoded_filename() {
+        let headers_of_both = headers("attachment; filename=\"plain.csv\"; filename*=UTF-8''r%C3%A9sum%C3%A9.csv");
+        assert_eq!(server_filename(&headers_of_both).as_deref(), Some("résumé.csv"));
+        assert_eq!(server_filename(&headers("attachment; filename=\"a;b.csv\"")).as_deref(), Some("a;b.csv"));
+        assert_eq!(server_filename(&headers("inline")), None);
+    }
+
+    #[test]
+    fn sanitizes_decoded_traversals() {
+        let filename = server_filename(&headers("attachment; filename*=UTF-8''%2e%2e%2f%2e%2e%2fetc%2fpasswd"));
+        assert_eq!(filename.as_deref(), Some("../../etc/passwd"));
+        assert_eq!(filename.as_deref().and_then(sanitize_filename).as_deref(), Some("passwd"));
+
+        let save = SaveResponse { dir: PathBuf::from("downloads"), use_server_filename: Some(true) };
+        let filename = server_filename(&headers("attachment; filename*=UTF-8''%2e%2e%5c%2e%2e"));
+        assert_eq!(target(&save, "export", filename.as_deref()), PathBuf::from("downloads/export.bin"));
+        let filename = server_filename(&headers("attachment; filename=\"../../.ssh/authorized_keys\""));
+        assert_eq!(target(&save, "export", filename.as_deref()), PathBuf::from("downloads/authorized_keys"));
+    }
+}
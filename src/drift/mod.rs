@@ -0,0 +1,88 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{snapshot, transcript, utils};
+
+// Headers that change with every response, they never count as drift
+const VOLATILE_HEADERS: [&str; 9] = ["date", "content-length", "connection", "keep-alive", "transfer-encoding", "age",
+    "expires", "set-cookie", "traceparent"];
+
+// How the responses of --compare-with are compared with the recorded ones, or those of compare_against with the
+// primary ones
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DriftConfig {
+    pub headers: Option<Vec<String>>, // only these headers are compared, per default all but the volatile ones
+    pub ignore: Option<DriftIgnore>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DriftIgnore {
+    pub headers: Option<Vec<String>>,
+    pub paths: Option<Vec<String>>, // json paths masked in both bodies, * matches every key or element
+}
+
+// How a response differs from the recorded one
+pub struct Drift {
+    pub differences: Vec<String>,
+    pub diff: String, // the line diff of the normalized bodies, empty if they match
+}
+
+// The values of a header, repeated headers are joined like they would be on a single line
+fn header_value(headers: &[(String, String)], name: &str) -> Option<String> {
+    let values: Vec<&str> = headers.iter()
+        .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+        .collect();
+    return (!values.is_empty()).then(|| values.join(", "));
+}
+
+fn compared_headers(recorded: &[(String, String)], headers: &[(String, String)], config: Option<&DriftConfig>)
+     -> Vec<String> {
+    let ignored = config.and_then(|config| config.ignore.as_ref()).and_then(|ignore| ignore.headers.clone())
+        .unwrap_or_default();
+
+    let mut names: Vec<String> = match config.and_then(|config| config.headers.as_ref()) {
+        Some(selected) => selected.iter().map(|name| name.to_lowercase()).collect(),
+        None => recorded.iter().chain(headers.iter())
+            .map(|(name, _)| name.to_lowercase())
+            .filter(|name| !VOLATILE_HEADERS.contains(&name.as_str()))
+            .collect(),
+    };
+    names.sort();
+    names.dedup();
+
+    // Secret headers are redacted inside the transcripts, so there is nothing to compare
+    names.retain(|name| !utils::is_secret_name(name) && !ignored.iter().any(|ignored| ignored.eq_ignore_ascii_case(name)));
+    return names;
+}
+
+// Compares the status, the headers and the normalized body of a response with the recorded one
+pub fn compare(recorded: &transcript::Recorded, status: u16, headers: &[(String, String)], body: &[u8],
+     config: Option<&DriftConfig>) -> Drift {
+    let mut differences = Vec::new();
+    if recorded.status != status {
+        differences.push(format!("status {} became {}", recorded.status, status));
+    }
+
+    for name in compared_headers(&recorded.headers, headers, config).iter() {
+        match (header_value(&recorded.headers, name), header_value(headers, name)) {
+            (Some(before), Some(after)) if before != after =>
+                differences.push(format!("header '{}' changed from '{}' to '{}'", name, before, after)),
+            (Some(before), None) => differences.push(format!("header '{}' is gone, it was '{}'", name, before)),
+            (None, Some(after)) => differences.push(format!("header '{}' was added with '{}'", name, after)),
+            _ => (),
+        }
+    }
+
+    let ignore_paths = config.and_then(|config| config.ignore.as_ref()).and_then(|ignore| ignore.paths.clone())
+        .unwrap_or_default();
+    let before = snapshot::normalize(&recorded.body, &ignore_paths);
+    let after = snapshot::normalize(body, &ignore_paths);
+    let mut diff = String::new();
+    if before != after {
+        let (lines, changed) = snapshot::diff(&before, &after);
+        differences.push(format!("body changed in {} lines", changed));
+        diff = lines;
+    }
+
+    return Drift { differences, diff };
+}
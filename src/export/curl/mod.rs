@@ -0,0 +1,161 @@
+use crate::export::{ExportedConfig, ExportedRequest, Part, Template};
+
+// Shell variables may only consist of alphanumerics and underscores
+fn shell_variable(name: &str) -> String {
+    let mut variable: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    if variable.starts_with(|c: char| c.is_ascii_digit()) {
+        variable.insert(0, '_');
+    }
+
+    return variable;
+}
+
+// Renders a template as a double quoted shell word, references are expanded by the shell
+fn quote(template: &Template) -> String {
+    let mut quoted = String::from("\"");
+
+    for part in template.iter() {
+        match part {
+            Part::Text(text) => for c in text.chars() {
+                if matches!(c, '"' | '\\' | '$' | '`') {
+                    quoted.push('\\');
+                }
+                quoted.push(c);
+            },
+            Part::Variable(name) | Part::Secret(name) => quoted += &format!("${{{}}}", shell_variable(name)),
+        }
+    }
+
+    quoted.push('"');
+    return quoted;
+}
+
+fn quote_text(text: &str) -> String {
+    return quote(&vec![Part::Text(text.to_string())]);
+}
+
+fn single_quote(text: &str) -> String {
+    return format!("'{}'", text.replace('\'', "'\\''"));
+}
+
+fn concat(mut first: Template, second: &Template) -> Template {
+    first.extend(second.iter().cloned());
+    return first;
+}
+
+// Shell statement that stores a value of the last response, mirroring the runners capture sources
+fn capture_statement(name: &str, source: &str) -> String {
+    let variable = shell_variable(name);
+
+    return match source {
+        "$status" => format!("{}=\"$status\"", variable),
+        "$body" => format!("{}=$(cat \"$RESPONSE_BODY\")", variable),
+        "$time_ms" => format!("{}=\"$time_ms\"", variable),
+        _ => if let Some(header) = source.strip_prefix("$header:") {
+            format!(
+                "{}=$(grep -i {} \"$RESPONSE_HEADERS\" | head -n 1 | cut -d ':' -f 2- | sed 's/^ *//' | tr -d '\\r')",
+                variable, single_quote(&format!("^{}:", header)))
+        } else if let Some(cookie) = source.strip_prefix("$cookie:") {
+            // The cookie jar is in the Netscape format, the name is the sixth and the value the seventh column
+            format!("{}=$(awk -F '\\t' {} \"$COOKIE_JAR\")", variable,
+                single_quote(&format!("$6 == \"{}\" {{ value = $7 }} END {{ print value }}", cookie)))
+        } else {
+            format!("{}=$(jq -r {} \"$RESPONSE_BODY\")", variable,
+                single_quote(&format!(".[{}]", serde_json::Value::from(source))))
+        },
+    };
+}
+
+fn render_request(index: usize, request: &ExportedRequest, output: &mut String) {
+    *output += &format!("\n# {}: {}\n", index, request.description);
+    for note in request.notes.iter() {
+        *output += &format!("# {}\n", note);
+    }
+
+    let mut arguments = vec![
+        "-s -o \"$RESPONSE_BODY\" -D \"$RESPONSE_HEADERS\" -b \"$COOKIE_JAR\" -c \"$COOKIE_JAR\"".to_string(),
+        "-w '%{http_code} %{time_total}'".to_string(),
+        format!("-X {}", request.method),
+        quote(&concat(vec![Part::Variable("API_ADDRESS".to_string())], &request.route)),
+    ];
+
+    if let Some(token) = &request.bearer_token {
+        let header = concat(vec![Part::Text("Authorization: Bearer ".to_string())], token);
+        arguments.push(format!("-H {}", quote(&header)));
+    }
+
+    for (name, value) in request.headers.iter() {
+        let header = concat(vec![Part::Text(format!("{}: ", name))], value);
+        arguments.push(format!("-H {}", quote(&header)));
+    }
+
+    if let Some(session_id) = &request.session_id {
+        arguments.push(format!("--cookie {}", quote(&concat(vec![Part::Text("session_id=".to_string())], session_id))));
+    }
+
+    if let Some((username, password)) = &request.digest_auth {
+        let credentials = concat(vec![Part::Text(format!("{}:", username))], password);
+        arguments.push(format!("--digest -u {}", quote(&credentials)));
+    }
+
+    if let Some(body) = &request.body {
+        arguments.push(format!("--data-raw {}", quote(body)));
+    }
+
+    *output += &format!("response=$(curl {})\n", arguments.join(" \\\n  "));
+    *output += "status=${response%% *}\n";
+    if request.captures.iter().any(|(_, source)| source == "$time_ms") {
+        *output += "time_ms=$(echo \"${response#* }\" | awk '{ printf \"%d\", $1 * 1000 }')\n";
+    }
+
+    if let Some(status) = request.status {
+        *output += &format!("if [ \"$status\" = \"{}\" ]; then\n", status);
+        *output += &format!("  echo {}\n", quote_text(&format!("PASSED {}: {}", index, request.description)));
+        *output += "else\n";
+        *output += &format!("  echo {}\n", quote(&vec![
+            Part::Text(format!("FAILED {}: {}, expected status {}, got ",
+                index, request.description.trim_end_matches('.'), status)),
+            Part::Variable("status".to_string()),
+        ]));
+        *output += "  FAILED=$((FAILED + 1))\nfi\n";
+    }
+
+    for (name, source) in request.captures.iter() {
+        *output += &capture_statement(name, source);
+        *output += "\n";
+    }
+}
+
+// Renders the config as a POSIX shell script of curl invocations
+pub fn render(config: &ExportedConfig) -> String {
+    let mut output = String::from("#!/bin/sh\n");
+    output += &format!("# Exported from {} by rust-rest-test, requires curl and jq for json captures.\n", config.name);
+
+    if !config.secrets.is_empty() {
+        output += "# Secrets are read from these environment variables, set them before running the script:\n";
+        for secret in config.secrets.iter() {
+            output += &format!("#   {}\n", secret);
+        }
+    }
+
+    output += "set -u\n\n";
+    output += &format!("API_ADDRESS={}\n", quote_text(&config.api_address));
+    for (name, value) in config.variables.iter() {
+        output += &format!("{}={}\n", shell_variable(name), quote(value));
+    }
+
+    output += "\nRESPONSE_BODY=$(mktemp)\nRESPONSE_HEADERS=$(mktemp)\nCOOKIE_JAR=$(mktemp)\n";
+    output += "trap 'rm -f \"$RESPONSE_BODY\" \"$RESPONSE_HEADERS\" \"$COOKIE_JAR\"' EXIT\n";
+    output += "FAILED=0\n";
+
+    for (index, request) in config.requests.iter().enumerate() {
+        render_request(index + 1, request, &mut output);
+    }
+
+    output += &format!("\necho \"$FAILED out of {} tests failed.\"\n", config.requests.len());
+    output += "[ \"$FAILED\" -eq 0 ]\n";
+    return output;
+}
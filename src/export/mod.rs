@@ -0,0 +1,315 @@
+use std::collections::{HashMap, HashSet};
+use std::{fs, path::Path};
+
+use crate::{Config, Endpoint, LITERAL_PREFIX, Result, assertions, construct_body, dependency, interpolation, redirect,
+    resolve_headers, utils};
+
+pub mod curl;
+pub mod postman;
+
+// A piece of an exported value, captures and secrets are referenced by name instead of being resolved
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part {
+    Text(String),
+    Variable(String), // a captured value or config variable
+    Secret(String), // an environment variable that has to be set before running the export
+}
+
+pub type Template = Vec<Part>;
+
+// A test converted into a format independent request description
+#[derive(Debug, Default)]
+pub struct ExportedRequest {
+    pub description: String,
+    pub method: String,
+    pub route: Template,
+    pub headers: Vec<(String, Template)>,
+    pub bearer_token: Option<Template>,
+    pub session_id: Option<Template>,
+    pub digest_auth: Option<(String, Template)>, // username and password
+    pub body: Option<Template>,
+    pub status: Option<u16>,
+    pub captures: Vec<(String, String)>, // capture name and source, e.g. $status or a json key
+    pub notes: Vec<String>, // parts of the test that cant be expressed by the export format
+}
+
+#[derive(Debug, Default)]
+pub struct ExportedConfig {
+    pub name: String,
+    pub api_address: String,
+    pub variables: Vec<(String, Template)>,
+    pub secrets: Vec<String>,
+    pub requests: Vec<ExportedRequest>,
+}
+
+impl ExportedRequest {
+    // Names of all captures and variables the request refers to
+    fn references(&self) -> Vec<&String> {
+        let mut templates = vec![&self.route];
+        templates.extend(self.headers.iter().map(|(_, value)| value));
+        templates.extend(self.bearer_token.iter().chain(self.session_id.iter()).chain(self.body.iter()));
+
+        return templates.into_iter().flatten()
+            .filter_map(|part| match part {
+                Part::Variable(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+    }
+}
+
+// Hands out one environment variable per distinct secret value
+#[derive(Default)]
+struct Secrets {
+    known: Vec<(String, String, String)>, // base name, environment variable and the secret it replaces
+}
+
+impl Secrets {
+    fn reference(&mut self, base_name: &str, value: &str) -> Template {
+        let base_name = environment_name(base_name);
+
+        if let Some((_, name, _)) = self.known.iter().find(|(base, _, known)| *base == base_name && known == value) {
+            return vec![Part::Secret(name.clone())];
+        }
+
+        let mut name = base_name.clone();
+        let mut counter = 1;
+        while self.known.iter().any(|(_, known_name, _)| *known_name == name) {
+            counter += 1;
+            name = format!("{}_{}", base_name, counter);
+        }
+
+        self.known.push((base_name, name.clone(), value.to_string()));
+        return vec![Part::Secret(name)];
+    }
+}
+
+fn environment_name(name: &str) -> String {
+    return name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+}
+
+// Splits a value into literal text and {{name}} references
+pub fn template(text: &str) -> Template {
+    return interpolation::segments(text).iter()
+        .map(|segment| match segment {
+            interpolation::Segment::Text(text) => Part::Text(text.to_string()),
+            interpolation::Segment::Placeholder { name, .. } => Part::Variable(name.to_string()),
+        })
+        .collect();
+}
+
+// Only literal parts can leak a secret, references are exported as they are
+fn secret_template(base_name: &str, text: &str, secrets: &mut Secrets) -> Template {
+    let parsed = template(text);
+    if parsed.iter().all(|part| matches!(part, Part::Variable(_))) {
+        return parsed;
+    }
+
+    return secrets.reference(base_name, text);
+}
+
+// Mirrors the runners credential precedence, known capture names stay references
+fn credential(local: &Option<Option<String>>, global: &Option<String>, base_name: &str,
+     known_captures: &HashSet<String>, secrets: &mut Secrets) -> Option<Template> {
+    return match local {
+        Some(Some(value)) if value.to_lowercase() == "none" => None,
+        Some(Some(value)) if value.starts_with(LITERAL_PREFIX) =>
+            Some(secret_template(base_name, value.trim_start_matches(LITERAL_PREFIX), secrets)),
+        Some(Some(value)) if known_captures.contains(value) => Some(vec![Part::Variable(value.clone())]),
+        Some(Some(value)) => Some(secret_template(base_name, value, secrets)),
+        Some(None) => None,
+        None => global.as_ref().map(|value| secret_template(base_name, value, secrets)),
+    };
+}
+
+fn convert_test(test: &Endpoint, config: &Config, known_captures: &HashSet<String>, secrets: &mut Secrets)
+     -> ExportedRequest {
+    let description = match &test.it {
+        Some(description) => description.clone(),
+        None => crate::generate_description(test),
+    };
+
+    let mut request = ExportedRequest {
+        description,
+        method: test.method.to_uppercase(),
+        route: template(&test.route),
+        status: test.status,
+        bearer_token: credential(&test.bearer_token, &config.bearer_token, "bearer_token", known_captures, secrets),
+        session_id: credential(&test.session_id, &config.session_id, "session_id", known_captures, secrets),
+        ..Default::default()
+    };
+
+    let mut headers: Vec<(String, String)> = resolve_headers(&test.headers, &config.headers).into_iter().collect();
+    if let Some(preflight) = &test.cors {
+        headers.extend(preflight.preflight_headers(&preflight.origin));
+    }
+    for (name, value) in test.conditional.iter().flat_map(|conditional| conditional.headers()) {
+        headers.retain(|(key, _)| !key.eq_ignore_ascii_case(&name));
+        headers.push((name, value));
+    }
+    headers.sort();
+    for (name, value) in headers.into_iter() {
+        let value = if utils::is_secret_name(&name) { secret_template(&name, &value, secrets) } else { template(&value) };
+        request.headers.push((name, value));
+    }
+
+    if let Some(credentials) = &test.digest_auth {
+        request.digest_auth = Some((credentials.username.clone(),
+            secret_template("digest_password", &credentials.password, secrets)));
+    }
+
+    // Unknown placeholders are left untouched, so they survive as references
+    let body = construct_body(test, &HashMap::new());
+    if !body.is_empty() {
+        let has_content_type = request.headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+        if !has_content_type {
+            let content_type = match serde_json::from_str::<serde_json::Value>(&body) {
+                Ok(_) => "application/json",
+                Err(_) if test.body.is_none() => "application/json",
+                Err(_) => "text/plain",
+            };
+            request.headers.push(("Content-Type".to_string(), vec![Part::Text(content_type.to_string())]));
+        }
+        request.body = Some(template(&body));
+    }
+
+    if let Some(capture) = &test.capture {
+        request.captures = capture.iter().map(|(key, source)| (dependency::capture_name(test, key), source.clone())).collect();
+        request.captures.sort();
+    }
+
+    if let Some(scheme) = &test.scheme {
+        request.notes.push(format!("TODO: is sent via {} instead of the scheme of the api_address", scheme));
+    }
+    if let Some(location) = &test.expect_location {
+        request.notes.push(format!("TODO: expects the Location {}", location));
+    }
+    if let Some(pattern) = &test.expect_content_disposition_filename {
+        request.notes.push(format!("TODO: expects a Content-Disposition filename matching {}", pattern));
+    }
+    if let Some(save) = &test.save_response {
+        request.notes.push(format!("TODO: saves the response into {}", save.dir.display()));
+    }
+    if redirect::follows(test) {
+        request.notes.push(format!("TODO: follows at most {} redirects{}",
+         test.max_redirects.unwrap_or(redirect::DEFAULT_MAX_REDIRECTS),
+         test.expect_redirect_chain.as_ref().map_or(String::new(), |chain| format!(" through {}", chain.join(" -> ")))));
+    }
+    if let Some(not_status) = &test.expect_not_status {
+        request.notes.push(format!("TODO: expects a status not in {}", assertions::describe_patterns(not_status.as_slice())));
+    }
+    if let Some(expected) = &test.expect_error {
+        request.notes.push(format!("TODO: expects the request to fail with a {} error", expected));
+    }
+    if let Some(condition) = &test.when {
+        request.notes.push(format!("TODO: only runs when {}", condition));
+    }
+    for expression in test.expect_expr.iter().flatten() {
+        request.notes.push(format!("TODO: expects {}", expression));
+    }
+    for (field, script) in [("pre_script", &test.pre_script), ("post_script", &test.post_script)] {
+        if script.is_some() {
+            request.notes.push(format!("TODO: the {} was not exported, values it sets are missing", field));
+        }
+    }
+    if test.expect_array_length.is_some() {
+        request.notes.push("TODO: the expect_array_length assertion was not exported".to_string());
+    }
+    if let Some(expected_cookies) = &test.expect_cookies {
+        let mut names: Vec<&String> = expected_cookies.keys().collect();
+        names.sort();
+        request.notes.push(format!("TODO: the expect_cookies assertions on {} were not exported",
+            names.iter().map(|name| name.as_str()).collect::<Vec<&str>>().join(", ")));
+    }
+    if let Some(preflight) = &test.cors {
+        request.notes.push(format!("TODO: the CORS assertions for {} requests from {} were not exported",
+            preflight.request_method.to_uppercase(), preflight.origin));
+    }
+    if test.snapshot == Some(true) {
+        request.notes.push("TODO: the snapshot comparison was not exported".to_string());
+    }
+    if let Some(check) = &test.pagination_check {
+        request.notes.push(format!("TODO: the pagination check with pages of {} elements was not exported",
+            check.page_size));
+    }
+    if test.idempotency_check == Some(true) {
+        request.notes.push("TODO: the idempotency check, a replay with the same Idempotency-Key, was not exported"
+            .to_string());
+    }
+    if let Some(body_file) = &test.expect_body_file {
+        request.notes.push(format!("TODO: expects the body to match {}", body_file.display()));
+    }
+    if let Some(Some(absent)) = &test.expect_headers_absent {
+        request.notes.push(format!("TODO: expects the headers to be absent: {}", absent.join(", ")));
+    }
+    if let Some(trace_id) = &test.trace_id {
+        request.notes.push(format!("TODO: continues the trace {}, no trace header was exported", trace_id));
+    }
+    if let Some(target) = &test.connect_to {
+        request.notes.push(format!("TODO: the connection went to {} instead of the host of the url", target));
+    }
+    if test.websocket.is_some() {
+        request.notes.push("TODO: the websocket handshake and message exchange were not exported".to_string());
+    }
+    if test.sse == Some(true) {
+        request.notes.push("TODO: reads an event stream, the export waits for the whole body instead".to_string());
+    }
+    if let Some(capture) = &test.expect_body_equals_capture {
+        request.notes.push(format!("TODO: expects the body to equal the capture {}", capture));
+    }
+
+    return request;
+}
+
+// Converts the parsed config into requests, resolving nothing that is only known at runtime
+pub fn export_config(config: &Config, name: &str) -> ExportedConfig {
+    let mut secrets = Secrets::default();
+    let mut exported = ExportedConfig {
+        name: name.to_string(),
+        api_address: config.api_address.clone(),
+        ..Default::default()
+    };
+
+    let mut known_captures: HashSet<String> = HashSet::new();
+
+    if let Some(variables) = &config.variables {
+        let mut variables: Vec<(&String, &String)> = variables.iter().collect();
+        variables.sort();
+
+        for (key, value) in variables.into_iter() {
+            let value = if utils::is_secret_name(key) { secrets.reference(key, value) } else { template(value) };
+            exported.variables.push((key.clone(), value));
+            known_captures.insert(key.clone());
+        }
+    }
+
+    for test in config.tests.iter() {
+        let mut request = convert_test(test, config, &known_captures, &mut secrets);
+
+        let mut unknown: Vec<String> = request.references().into_iter()
+            .filter(|name| !known_captures.contains(*name))
+            .map(|name| format!("TODO: {{{{{}}}}} is neither captured by an earlier test nor a variable", name))
+            .collect();
+        unknown.sort();
+        unknown.dedup();
+        request.notes.extend(unknown);
+
+        exported.requests.push(request);
+
+        known_captures.extend(test.capture.iter().flatten().map(|(key, _)| dependency::capture_name(test, key)));
+    }
+
+    exported.secrets = secrets.known.into_iter().map(|(_, name, _)| name).collect();
+    return exported;
+}
+
+pub fn write_export(content: &str, out: &Path, force: bool) -> Result<()> {
+    if out.exists() && !force {
+        return Err(format!("{} already exists, use --force to overwrite it.", out.display()).into());
+    }
+
+    fs::write(out, content)?;
+    return Ok(());
+}
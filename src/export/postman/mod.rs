@@ -0,0 +1,115 @@
+use serde_json::{Value, json};
+
+use crate::export::{ExportedConfig, ExportedRequest, Part, Template};
+
+const SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+// Postman references both variables and environment values as {{name}}
+fn render_template(template: &Template) -> String {
+    return template.iter()
+        .map(|part| match part {
+            Part::Text(text) => text.clone(),
+            Part::Variable(name) | Part::Secret(name) => format!("{{{{{}}}}}", name),
+        })
+        .collect();
+}
+
+// Javascript expression reading a value of the response, mirroring the runners capture sources
+fn capture_expression(source: &str) -> String {
+    return match source {
+        "$status" => "String(pm.response.code)".to_string(),
+        "$body" => "pm.response.text()".to_string(),
+        "$time_ms" => "String(pm.response.responseTime)".to_string(),
+        _ => if let Some(header) = source.strip_prefix("$header:") {
+            format!("pm.response.headers.get({})", Value::from(header))
+        } else if let Some(cookie) = source.strip_prefix("$cookie:") {
+            format!("pm.cookies.get({})", Value::from(cookie))
+        } else {
+            format!("asText(pm.response.json()[{}])", Value::from(source))
+        },
+    };
+}
+
+fn test_script(request: &ExportedRequest) -> Vec<String> {
+    let mut lines: Vec<String> = request.notes.iter().map(|note| format!("// {}", note)).collect();
+
+    if let Some(status) = request.status {
+        lines.push(format!("pm.test(\"status is {}\", function () {{ pm.response.to.have.status({}); }});",
+            status, status));
+    }
+
+    if !request.captures.is_empty() {
+        // Captured strings are stored without their quotes, everything else in its json notation
+        lines.push("const asText = value => typeof value === \"string\" ? value : JSON.stringify(value);".to_string());
+    }
+
+    for (name, source) in request.captures.iter() {
+        lines.push(format!("pm.collectionVariables.set({}, {});", Value::from(name.as_str()), capture_expression(source)));
+    }
+
+    return lines;
+}
+
+fn render_request(request: &ExportedRequest) -> Value {
+    let mut headers: Vec<Value> = request.headers.iter()
+        .map(|(name, value)| json!({ "key": name, "value": render_template(value) }))
+        .collect();
+
+    if let Some(session_id) = &request.session_id {
+        headers.push(json!({ "key": "Cookie", "value": format!("session_id={}", render_template(session_id)) }));
+    }
+
+    let mut exported = json!({
+        "method": request.method,
+        "header": headers,
+        "url": { "raw": format!("{{{{api_address}}}}{}", render_template(&request.route)) },
+    });
+
+    if let Some(token) = &request.bearer_token {
+        exported["auth"] = json!({
+            "type": "bearer",
+            "bearer": [{ "key": "token", "value": render_template(token), "type": "string" }],
+        });
+    } else if let Some((username, password)) = &request.digest_auth {
+        exported["auth"] = json!({
+            "type": "digest",
+            "digest": [
+                { "key": "username", "value": username, "type": "string" },
+                { "key": "password", "value": render_template(password), "type": "string" },
+            ],
+        });
+    }
+
+    if let Some(body) = &request.body {
+        exported["body"] = json!({ "mode": "raw", "raw": render_template(body) });
+    }
+
+    let mut item = json!({ "name": request.description, "request": exported });
+
+    let script = test_script(request);
+    if !script.is_empty() {
+        item["event"] = json!([{ "listen": "test", "script": { "type": "text/javascript", "exec": script } }]);
+    }
+
+    return item;
+}
+
+// Renders the config as a Postman collection (v2.1)
+pub fn render(config: &ExportedConfig) -> String {
+    let mut description = format!("Exported from {} by rust-rest-test.", config.name);
+    if !config.secrets.is_empty() {
+        description += &format!(" Define the secrets {} in your Postman environment.", config.secrets.join(", "));
+    }
+
+    let mut variables = vec![json!({ "key": "api_address", "value": config.api_address })];
+    variables.extend(config.variables.iter()
+        .map(|(name, value)| json!({ "key": name, "value": render_template(value) })));
+
+    let collection = json!({
+        "info": { "name": config.name, "description": description, "schema": SCHEMA },
+        "variable": variables,
+        "item": config.requests.iter().map(render_request).collect::<Vec<Value>>(),
+    });
+
+    return serde_json::to_string_pretty(&collection).unwrap_or_default() + "\n";
+}
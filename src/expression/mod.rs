@@ -0,0 +1,298 @@
+use std::fmt;
+
+use crate::condition::{self, Token, Tokens};
+use crate::interpolation::{self, Captures};
+
+// Numeric expectations over the captures, e.g. `{{balance_after}} == {{balance_before}} - 100`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug)]
+enum Term {
+    Capture(String),
+    Number(f64),
+    Negate(Box<Term>),
+    Binary(Box<Term>, Operator, Box<Term>),
+}
+
+// Two arithmetic terms and how they have to compare
+#[derive(Debug)]
+pub struct Expectation {
+    left: Term,
+    comparison: Comparison,
+    right: Term,
+}
+
+// Why an expectation couldnt be evaluated, e.g. a capture that is no number
+#[derive(Debug)]
+pub struct EvaluationError {
+    pub message: String,
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", match self {
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+        });
+    }
+}
+
+// Recursive descent parser over the tokens of the conditions, * and / bind stronger than + and -
+struct Parser {
+    tokens: Tokens,
+}
+
+impl Parser {
+    fn parse_sum(&mut self) -> Result<Term, String> {
+        let mut term = self.parse_product()?;
+        loop {
+            let operator = match self.tokens.peek() {
+                Some(Token::Plus) => Operator::Add,
+                Some(Token::Minus) => Operator::Subtract,
+                _ => return Ok(term),
+            };
+            self.tokens.next();
+            term = Term::Binary(Box::new(term), operator, Box::new(self.parse_product()?));
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<Term, String> {
+        let mut term = self.parse_unary()?;
+        loop {
+            let operator = match self.tokens.peek() {
+                Some(Token::Times) => Operator::Multiply,
+                Some(Token::Divide) => Operator::Divide,
+                _ => return Ok(term),
+            };
+            self.tokens.next();
+            term = Term::Binary(Box::new(term), operator, Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Term, String> {
+        if self.tokens.peek() == Some(&Token::Minus) {
+            self.tokens.next();
+            return Ok(Term::Negate(Box::new(self.parse_unary()?)));
+        }
+
+        let term = match self.tokens.peek() {
+            Some(Token::Placeholder(name)) => Term::Capture(name.clone()),
+            Some(Token::Literal(value)) => match value.parse::<f64>() {
+                Ok(number) => Term::Number(number),
+                Err(_) => return Err(format!("expected a number, found '{}'", value)),
+            },
+            Some(Token::OpenParen) => {
+                self.tokens.next();
+                let term = self.parse_sum()?;
+                self.tokens.expect(Token::CloseParen)?;
+                return Ok(term);
+            },
+            Some(Token::Identifier(name)) => return Err(format!("unexpected '{}', captures are written as {{{{{}}}}}",
+                name, name)),
+            _ => return Err(format!("expected a {{{{capture}}}}, a number or '(', found {}", self.tokens.found())),
+        };
+
+        self.tokens.next();
+        return Ok(term);
+    }
+
+    fn parse_comparison(&mut self) -> Result<Comparison, String> {
+        let comparison = match self.tokens.peek() {
+            Some(Token::Equal) => Comparison::Equal,
+            Some(Token::NotEqual) => Comparison::NotEqual,
+            Some(Token::Less) => Comparison::Less,
+            Some(Token::LessEqual) => Comparison::LessOrEqual,
+            Some(Token::Greater) => Comparison::Greater,
+            Some(Token::GreaterEqual) => Comparison::GreaterOrEqual,
+            _ => return Err(format!("expected a comparison like '==' or '<', found {}", self.tokens.found())),
+        };
+
+        self.tokens.next();
+        return Ok(comparison);
+    }
+}
+
+impl Term {
+    fn evaluate(&self, captures: &Captures) -> Result<f64, String> {
+        return match self {
+            Term::Number(number) => Ok(*number),
+            Term::Capture(name) => match captures.get(name) {
+                Some(serde_json::Value::Number(number)) => number.as_f64().ok_or(format!("'{}' is no number", name)),
+                Some(value) => interpolation::text(value).trim().parse::<f64>()
+                    .map_err(|_| format!("'{}' is no number", name)),
+                None => Err(format!("'{}' has not been captured", name)),
+            },
+            Term::Negate(term) => Ok(-term.evaluate(captures)?),
+            Term::Binary(left, operator, right) => {
+                let (left, right) = (left.evaluate(captures)?, right.evaluate(captures)?);
+                match operator {
+                    Operator::Add => Ok(left + right),
+                    Operator::Subtract => Ok(left - right),
+                    Operator::Multiply => Ok(left * right),
+                    Operator::Divide if right == 0.0 => Err("division by zero".to_string()),
+                    Operator::Divide => Ok(left / right),
+                }
+            },
+        };
+    }
+
+    fn captures<'a>(&'a self, names: &mut Vec<&'a String>) {
+        match self {
+            Term::Capture(name) => {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            },
+            Term::Number(_) => (),
+            Term::Negate(term) => term.captures(names),
+            Term::Binary(left, _, right) => {
+                left.captures(names);
+                right.captures(names);
+            },
+        }
+    }
+}
+
+// Decimal fractions like 0.1 + 0.2 arent exact in binary, so equality allows for the rounding
+fn equals(left: f64, right: f64) -> bool {
+    return (left - right).abs() <= 1e-9 * left.abs().max(right.abs()).max(1.0);
+}
+
+impl Expectation {
+    pub fn parse(expression: &str) -> Result<Expectation, String> {
+        let tokens = condition::tokenize(expression, false).map_err(|(_, message)| message)?;
+        let mut parser = Parser { tokens: Tokens::new(tokens, "the end of the expression") };
+        let left = parser.parse_sum()?;
+        let comparison = parser.parse_comparison()?;
+        let right = parser.parse_sum()?;
+        parser.tokens.finish()?;
+
+        return Ok(Expectation { left, comparison, right });
+    }
+
+    // Names of all captures the expectation refers to, in the order they appear
+    pub fn captures(&self) -> Vec<&String> {
+        let mut names = Vec::new();
+        self.left.captures(&mut names);
+        self.right.captures(&mut names);
+        return names;
+    }
+
+    // The raw values of the captures, e.g. balance_before = '250', for the output of a failure
+    pub fn describe_values(&self, captures: &Captures) -> String {
+        return self.captures().iter()
+            .map(|name| match captures.get(*name) {
+                Some(value) => format!("{} = '{}'", name, interpolation::text(value)),
+                None => format!("{} is missing", name),
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+    }
+
+    // Whether the comparison holds and both sides as they were computed
+    pub fn evaluate(&self, captures: &Captures) -> Result<(bool, f64, f64), EvaluationError> {
+        let sides = self.left.evaluate(captures).and_then(|left| Ok((left, self.right.evaluate(captures)?)));
+        let (left, right) = sides.map_err(|message| EvaluationError { message })?;
+
+        let holds = match self.comparison {
+            Comparison::Equal => equals(left, right),
+            Comparison::NotEqual => !equals(left, right),
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right || equals(left, right),
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right || equals(left, right),
+        };
+        return Ok((holds, left, right));
+    }
+
+    pub fn comparison(&self) -> Comparison {
+        return self.comparison;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captures(values: &[(&str, serde_json::Value)]) -> Captures {
+        return values.iter().map(|(name, value)| (name.to_string(), value.clone())).collect();
+    }
+
+    fn evaluate(expression: &str, captures: &Captures) -> (bool, f64, f64) {
+        return Expectation::parse(expression).unwrap().evaluate(captures).unwrap();
+    }
+
+    #[test]
+    fn computes_both_sides() {
+        let captures = captures(&[("before", 250.into()), ("after", "150".into())]);
+        assert_eq!(evaluate("{{after}} == {{before}} - 100", &captures), (true, 150.0, 150.0));
+        assert_eq!(evaluate("{{after}}-{{before}}*2 < -(1)", &captures), (true, -350.0, -1.0));
+        assert_eq!(evaluate("({{after}} + 50) / 2 >= {{ before | raw }} - 150", &captures), (true, 100.0, 100.0));
+    }
+
+    #[test]
+    fn binds_times_stronger_than_minus() {
+        let captures = Captures::new();
+        assert_eq!(evaluate("10 - 2 * 3 - 1 == 3", &captures), (true, 3.0, 3.0));
+        assert_eq!(evaluate("8 / 4 / 2 != 4", &captures), (true, 1.0, 4.0));
+    }
+
+    #[test]
+    fn allows_for_the_rounding_of_decimal_fractions() {
+        assert!(evaluate("0.1 + 0.2 == 0.3", &Captures::new()).0);
+        assert!(evaluate("0.1 + 0.2 <= 0.3", &Captures::new()).0);
+    }
+
+    #[test]
+    fn lists_and_describes_the_captures() {
+        let expectation = Expectation::parse("{{a}} + {{b}} > {{a}} * {{c}}").unwrap();
+        assert_eq!(expectation.captures(), vec!["a", "b", "c"]);
+        assert_eq!(expectation.describe_values(&captures(&[("a", 1.into()), ("b", "x".into())])),
+            "a = '1', b = 'x', c is missing");
+        assert_eq!(expectation.comparison(), Comparison::Greater);
+    }
+
+    #[test]
+    fn reports_values_that_cant_be_computed() {
+        let error = |expression: &str, captures: &Captures| {
+            return Expectation::parse(expression).unwrap().evaluate(captures).unwrap_err().message;
+        };
+        let captures = captures(&[("a", "x".into()), ("zero", 0.into())]);
+        assert_eq!(error("{{a}} == 1", &captures), "'a' is no number");
+        assert_eq!(error("{{b}} == 1", &captures), "'b' has not been captured");
+        assert_eq!(error("1 / {{zero}} == 1", &captures), "division by zero");
+    }
+
+    #[test]
+    fn rejects_invalid_expectations() {
+        let error = |expression: &str| Expectation::parse(expression).unwrap_err();
+        assert_eq!(error("{{a}} + 1"), "expected a comparison like '==' or '<', found the end of the expression");
+        assert_eq!(error("balance == 1"), "unexpected 'balance', captures are written as {{balance}}");
+        assert_eq!(error("({{a}} == 1"), "expected ')', found '=='");
+        assert_eq!(error("{{a}} == 1 1"), "unexpected '1' after the end of the expression");
+        assert_eq!(error("{{a}} == true"), "expected a number, found 'true'");
+        assert_eq!(error("{{a}} == {{b"), "unterminated placeholder starting at position 9");
+        assert_eq!(error("{{}} == 1"), "empty placeholder at position 0");
+        assert_eq!(error("{{a}} == 1 +"), "expected a {{capture}}, a number or '(', found the end of the expression");
+    }
+}
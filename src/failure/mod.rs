@@ -0,0 +1,218 @@
+use std::fmt;
+
+use serde::{Serialize, Deserialize};
+
+use crate::assertions::{self, ErrorCategory};
+use crate::{middleware, redirect};
+
+// Values quoted in a failure reason are cut off after this many characters
+const VALUE_EXCERPT_LENGTH: usize = 200;
+
+// Why a test failed, a test may fail for several reasons at once
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FailureReason {
+    StatusMismatch { expected: u16, actual: u16 },
+    StatusExcluded { excluded: String, actual: u16 }, // the patterns of expect_not_status
+    Timeout {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        elapsed_ms: Option<u128>, // unknown if the system gave up on the connection
+        limit_ms: u128,
+    },
+    TooSlow { response_time_ms: u128, boundary_ms: u128, time_class: String, level: String }, // fail_on was reached
+    PhaseTooSlow { phase: String, took_ms: u128, limit_ms: u128 }, // a max_<phase>_ms was exceeded
+    TooFast { response_time_ms: u128, limit_ms: u128 }, // quicker than min_time_ms
+    Transport { error: String },
+    InvalidUrl { url: String, error: String },
+    BodyAssertion { path: String, expected: String, actual: String },
+    CaptureMissing { key: String },
+    CookieMismatch { name: String, attribute: String, expected: String, actual: String },
+    HeaderPresent { header: String, value: String }, // a header of expect_headers_absent was sent
+    HeaderMismatch { header: String, missing: Vec<String>, actual: Vec<String> }, // values of expect_headers are missing
+    CorsViolation { rule: String, expected: String, actual: String }, // a rule of the cors preflight
+    EventsMissing { expected: u32, received: u32, end: String }, // the event stream ended too early
+    HandshakeRejected { status: u16, reason: String }, // the server didnt upgrade to a websocket
+    MessageTimeout { limit_ms: u128 }, // the websocket stayed silent
+    SocketClosed, // the websocket got closed before a message arrived
+    NotIdempotent { difference: String }, // the replay of an idempotency check got a different response
+    PaginationViolation { step: String, violation: String }, // a derived request of a pagination_check
+    SnapshotMismatch { file: String, changed_lines: usize, diff: String },
+    BodyMismatch { file: String, changed_lines: usize, diff: String }, // the body differs from the expect_body_file
+    BodyNotExact { source: String, offset: usize, context: String }, // the bytes differ from the exact expectation
+    SnapshotError { file: String, error: String }, // the snapshot couldnt be read or written
+    ResponseDrift { file: String, differences: Vec<String>, diff: String }, // the response differs from the recorded one
+    ResponseMismatch { url: String, differences: Vec<String>, diff: String }, // compare_against answered otherwise
+    HookFailed { hook: String, error: String }, // a middleware of a library user rejected the request or response
+    ErrorMismatch { expected: String, actual: String }, // expect_error got a different error category
+    UnexpectedResponse { expected_error: String, status: u16 }, // expect_error got a response instead
+    InvalidJson { error: String, line: usize, column: usize, excerpt: String }, // require_json got another body
+    JsonContract { expected: String, actual: String, content_type: Option<String>, body: String }, // expect_valid_json
+    RedirectLoop { chain: Vec<String> }, // a url of the followed redirects repeated
+    TooManyRedirects { limit: u32, chain: Vec<String> }, // more redirects than max_redirects
+    RedirectChainMismatch { expected: Vec<String>, actual: Vec<String> }, // the hops differ from expect_redirect_chain
+    FilenameMismatch { pattern: String, filename: Option<String> }, // the Content-Disposition filename
+    #[cfg_attr(not(feature = "scripting"), allow(dead_code))]
+    ScriptFailed { script: String, line: usize, message: String }, // a pre_script or post_script called fail() or broke
+    ExpressionFailed { expression: String, message: String }, // an expect_expr was false or couldnt be evaluated
+    EncodingMismatch { expected: String, actual: Option<String> }, // the Content-Encoding differs from expect_encoding
+    CompressionRatio { minimum: f64, ratio: Option<f64>, wire_bytes: usize, decoded_bytes: Option<usize> },
+    BodyNotDecodable { encoding: String, error: String }, // the body doesnt match its Content-Encoding
+}
+
+// A request the runner cancelled because it reached its timeout_ms
+#[derive(Debug)]
+pub struct TimeoutError {
+    pub elapsed_ms: u128,
+    pub limit_ms: u128,
+}
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "timed out after {} ms (limit {} ms)", self.elapsed_ms, self.limit_ms);
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+impl fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            FailureReason::StatusMismatch { expected, actual } =>
+                write!(f, "expected status {}, got {}", expected, actual),
+            FailureReason::StatusExcluded { excluded, actual } =>
+                write!(f, "status {} is one of the excluded {}", actual, excluded),
+            FailureReason::Timeout { elapsed_ms: Some(elapsed_ms), limit_ms } =>
+                write!(f, "timed out after {} ms (limit {} ms)", elapsed_ms, limit_ms),
+            FailureReason::Timeout { elapsed_ms: None, limit_ms } => write!(f, "timed out (limit {} ms)", limit_ms),
+            FailureReason::TooSlow { response_time_ms, boundary_ms, time_class, level } =>
+                write!(f, "took {} ms and crossed the {} ms boundary into {}, set by the {} time_boundaries",
+                 response_time_ms, boundary_ms, time_class, level),
+            FailureReason::PhaseTooSlow { phase, took_ms, limit_ms } =>
+                write!(f, "the {} phase took {} ms, more than the {} ms allowed", phase, took_ms, limit_ms),
+            FailureReason::TooFast { response_time_ms, limit_ms } =>
+                write!(f, "took {} ms, less than the minimum of {} ms, a stub or cache may have answered instead of \
+                 the real endpoint", response_time_ms, limit_ms),
+            FailureReason::Transport { error } => write!(f, "transport error: {}", error),
+            FailureReason::InvalidUrl { url, error } => write!(f, "invalid url '{}': {}", url, error),
+            FailureReason::BodyAssertion { path, expected, actual } =>
+                write!(f, "body at '{}': expected {}, got {}", path, expected, actual),
+            FailureReason::CaptureMissing { key } => write!(f, "capture '{}' is missing", key),
+            FailureReason::CookieMismatch { name, attribute, expected, actual } =>
+                write!(f, "cookie '{}': expected {} {}, got {}", name, attribute, expected, actual),
+            FailureReason::HeaderPresent { header, value } =>
+                write!(f, "forbidden header '{}' is present with '{}'", header, value),
+            FailureReason::HeaderMismatch { header, missing, actual } =>
+                write!(f, "header '{}' lacks {:?}, got {:?}", header, missing, actual),
+            FailureReason::CorsViolation { rule, expected, actual } =>
+                write!(f, "CORS rule violated, {} has to {} but got {}", rule, expected, actual),
+            FailureReason::EventsMissing { expected, received, end } =>
+                write!(f, "expected {} events, received {} before {}", expected, received, end),
+            FailureReason::HandshakeRejected { status, reason } =>
+                write!(f, "websocket handshake rejected (status {}): {}", status, reason),
+            FailureReason::MessageTimeout { limit_ms } => write!(f, "no websocket message within {} ms", limit_ms),
+            FailureReason::SocketClosed => write!(f, "the websocket got closed before a message arrived"),
+            FailureReason::NotIdempotent { difference } =>
+                write!(f, "replaying the request with the same Idempotency-Key changed the response: {}", difference),
+            FailureReason::PaginationViolation { step, violation } =>
+                write!(f, "pagination contract violated by {}: {}", step, violation),
+            FailureReason::SnapshotMismatch { file, changed_lines, .. } =>
+                write!(f, "body differs from the snapshot {} in {} lines", file, changed_lines),
+            FailureReason::BodyMismatch { file, changed_lines, .. } =>
+                write!(f, "body differs from {} in {} lines", file, changed_lines),
+            FailureReason::BodyNotExact { source, offset, .. } =>
+                write!(f, "body differs from {} at byte {}", source, offset),
+            FailureReason::SnapshotError { file, error } => write!(f, "snapshot {}: {}", file, error),
+            FailureReason::ResponseDrift { file, differences, .. } =>
+                write!(f, "response drifted from {}: {}", file, differences.join(", ")),
+            FailureReason::ResponseMismatch { url, differences, .. } =>
+                write!(f, "response of {} differs: {}", url, differences.join(", ")),
+            FailureReason::HookFailed { hook, error } => write!(f, "{} hook failed: {}", hook, error),
+            FailureReason::ErrorMismatch { expected, actual } =>
+                write!(f, "expected a {} error, observed a {} error", expected, actual),
+            FailureReason::UnexpectedResponse { expected_error, status } =>
+                write!(f, "expected a {} error, but received a response with status {}", expected_error, status),
+            FailureReason::InvalidJson { error, excerpt, .. } =>
+                write!(f, "body is not valid json: {}, near '{}'", error, excerpt),
+            FailureReason::JsonContract { expected, actual, content_type, body } =>
+                write!(f, "expected {}, got {} (Content-Type {}): '{}'", expected, actual,
+                    content_type.as_deref().unwrap_or("missing"), body.replace('\n', " ")),
+            FailureReason::RedirectLoop { chain } => write!(f, "redirect loop: {}", chain.join(" -> ")),
+            FailureReason::TooManyRedirects { limit, chain } =>
+                write!(f, "more than {} redirects: {}", limit, chain.join(" -> ")),
+            FailureReason::RedirectChainMismatch { expected, actual } =>
+                write!(f, "expected the redirect chain {:?}, got {:?}", expected, actual),
+            FailureReason::FilenameMismatch { pattern, filename: Some(filename) } =>
+                write!(f, "expected a Content-Disposition filename matching '{}', got '{}'", pattern, filename),
+            FailureReason::FilenameMismatch { pattern, filename: None } =>
+                write!(f, "expected a Content-Disposition filename matching '{}', got none", pattern),
+            FailureReason::ScriptFailed { script, line, message } =>
+                write!(f, "{} failed at line {}: {}", script, line, message),
+            FailureReason::ExpressionFailed { expression, message } =>
+                write!(f, "expected {}: {}", expression, message),
+            FailureReason::EncodingMismatch { expected, actual } =>
+                write!(f, "expected the Content-Encoding {}, got {}", expected, actual.as_deref().unwrap_or("none")),
+            FailureReason::CompressionRatio { minimum, ratio: Some(ratio), wire_bytes, decoded_bytes } =>
+                write!(f, "compression ratio {:.2} is below {} ({} bytes on the wire, {} decoded)", ratio, minimum,
+                 wire_bytes, decoded_bytes.unwrap_or_default()),
+            FailureReason::CompressionRatio { minimum, ratio: None, wire_bytes, .. } =>
+                write!(f, "compression ratio unknown, expected at least {} ({} bytes on the wire, the decoded size is \
+                 unknown)", minimum, wire_bytes),
+            FailureReason::BodyNotDecodable { encoding, error } =>
+                write!(f, "the {} body cant be decoded: {}", encoding, error),
+        };
+    }
+}
+
+// The reason of a request that didnt get a response
+pub fn from_transport_error(error: &(dyn std::error::Error + 'static), limit_ms: u128) -> FailureReason {
+    if let Some(hook_error) = error.downcast_ref::<middleware::HookError>() {
+        return FailureReason::HookFailed { hook: hook_error.hook.to_string(), error: hook_error.error.clone() };
+    }
+
+    if let Some(redirect_error) = error.downcast_ref::<redirect::RedirectError>() {
+        let chain = redirect_error.chain.clone();
+        return match redirect_error.limit {
+            Some(limit) => FailureReason::TooManyRedirects { limit, chain },
+            None => FailureReason::RedirectLoop { chain },
+        };
+    }
+
+    if let Some(timeout) = error.downcast_ref::<TimeoutError>() {
+        return FailureReason::Timeout { elapsed_ms: Some(timeout.elapsed_ms), limit_ms: timeout.limit_ms };
+    }
+
+    if assertions::classify_error(error) == ErrorCategory::Timeout {
+        return FailureReason::Timeout { elapsed_ms: None, limit_ms };
+    }
+
+    return FailureReason::Transport { error: error.to_string() };
+}
+
+// Characters shown on each side of the position of a json syntax error
+const JSON_ERROR_CONTEXT: usize = 30;
+
+// A body that failed to parse as json, with the part of the body around the error
+pub fn invalid_json(error: &serde_json::Error, body: &str) -> FailureReason {
+    let line = body.lines().nth(error.line().saturating_sub(1)).unwrap_or_default();
+    let position = error.column().saturating_sub(1);
+    let excerpt: String = line.chars()
+        .skip(position.saturating_sub(JSON_ERROR_CONTEXT))
+        .take(2 * JSON_ERROR_CONTEXT)
+        .collect();
+
+    return FailureReason::InvalidJson {
+        error: error.to_string(),
+        line: error.line(),
+        column: error.column(),
+        excerpt,
+    };
+}
+
+// Shortens a value of the response so a failure reason stays on a single readable line
+pub fn excerpt(value: &str) -> String {
+    let value = value.replace('\n', " ");
+    return match value.char_indices().nth(VALUE_EXCERPT_LENGTH) {
+        Some((end, _)) => format!("{}...", &value[..end]),
+        None => value,
+    };
+}
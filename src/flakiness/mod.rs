@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+use std::{fs, path::Path};
+
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::report::{TestOutcome, TestRun};
+use crate::{Config, Middleware, Result, RunOptions, RunOutcome, run_suite};
+
+// How a single test fared across all runs
+#[derive(Debug, Serialize)]
+pub struct TestFlakiness {
+    pub index: usize, // one based position inside the config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub description: String,
+    pub passed: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    pub failure_reasons: Vec<String>, // every distinct reason, in the order they were first seen
+    pub min_response_time_ms: Option<u128>,
+    pub mean_response_time_ms: Option<u128>,
+    pub max_response_time_ms: Option<u128>,
+    pub flaky: bool, // failed in some runs, but passed in others
+}
+
+// The report of --runs, all runs and what they have in common
+#[derive(Debug, Serialize)]
+pub struct MultiRun {
+    pub runs: u32,
+    pub flaky: usize,
+    pub tests: Vec<TestFlakiness>,
+    pub results: Vec<TestRun>, // every run as it would have been reported on its own
+}
+
+// Groups the results of every test across the runs, repetitions of a test count as runs of it
+pub fn aggregate(runs: &[TestRun]) -> Vec<TestFlakiness> {
+    let mut tests: BTreeMap<usize, TestFlakiness> = BTreeMap::new();
+    let mut response_times: BTreeMap<usize, Vec<u128>> = BTreeMap::new();
+
+    for result in runs.iter().flat_map(|run| run.tests.iter()) {
+        let test = tests.entry(result.index).or_insert_with(|| TestFlakiness {
+            index: result.index,
+            name: result.name.clone(),
+            description: result.description.clone(),
+            passed: 0,
+            failed: 0,
+            skipped: 0,
+            failure_reasons: Vec::new(),
+            min_response_time_ms: None,
+            mean_response_time_ms: None,
+            max_response_time_ms: None,
+            flaky: false,
+        });
+
+        match result.outcome {
+            TestOutcome::Passed | TestOutcome::XPass => test.passed += 1,
+            TestOutcome::Failed | TestOutcome::XFail => test.failed += 1,
+            TestOutcome::Skipped => test.skipped += 1,
+        }
+        for reason in result.failures.iter().map(|reason| reason.to_string()) {
+            if !test.failure_reasons.contains(&reason) {
+                test.failure_reasons.push(reason);
+            }
+        }
+        if let Some(time) = result.response_time_ms {
+            response_times.entry(result.index).or_default().push(time);
+        }
+    }
+
+    for (index, test) in tests.iter_mut() {
+        if let Some(times) = response_times.get(index) {
+            test.min_response_time_ms = times.iter().min().copied();
+            test.max_response_time_ms = times.iter().max().copied();
+            test.mean_response_time_ms = Some(times.iter().sum::<u128>() / times.len() as u128);
+        }
+        test.flaky = test.passed > 0 && test.failed > 0;
+    }
+
+    return tests.into_values().collect();
+}
+
+fn format_time(time: Option<u128>) -> String {
+    return time.map_or("-".to_string(), |time| time.to_string());
+}
+
+// The aggregate table of the console, flaky tests stand out
+pub fn table(tests: &[TestFlakiness], runs: u32) -> String {
+    let mut output = format!("\nResults of {} runs:\n", runs);
+    output += &format!("{:>5}  {:>6}  {:>6}  {:>7}  {:>20}  {}\n", "Test", "Passed", "Failed", "Skipped",
+     "min/mean/max ms", "Description");
+
+    for test in tests.iter() {
+        let times = format!("{}/{}/{}", format_time(test.min_response_time_ms), format_time(test.mean_response_time_ms),
+         format_time(test.max_response_time_ms));
+        let line = format!("{:>5}  {:>6}  {:>6}  {:>7}  {:>20}  {}", test.index, test.passed, test.failed, test.skipped,
+         times, test.description);
+
+        output += &if test.flaky {
+            format!("{} {}\n", line.yellow().bold(), "FLAKY".yellow().bold())
+        } else if test.failed > 0 {
+            format!("{}\n", line.red())
+        } else {
+            format!("{}\n", line)
+        };
+
+        if test.flaky || test.failed > 0 {
+            for reason in test.failure_reasons.iter() {
+                output += &format!("{:>7}- {}\n", "", reason);
+            }
+        }
+    }
+
+    let flaky = tests.iter().filter(|test| test.flaky).count();
+    output += &if flaky > 0 {
+        format!("{}\n", format!("{} of {} tests are flaky.", flaky, tests.len()).yellow().bold())
+    } else {
+        format!("No flaky tests in {} runs.\n", runs)
+    };
+    return output;
+}
+
+pub fn write_json(report: &MultiRun, path: &Path) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(report)?)?;
+    return Ok(());
+}
+
+// Runs the whole suite several times, the captures start over with every run and a critical failure only ends its
+// own run. Only the json report is written, it contains every run.
+pub async fn run_repeatedly(config: &Config, options: &RunOptions, middleware: &[Box<dyn Middleware>]) -> RunOutcome {
+    let runs = options.runs.unwrap_or(1);
+    let mut results: Vec<TestRun> = Vec::new();
+    let mut outcomes: Vec<RunOutcome> = Vec::new();
+
+    for run in 1..=runs {
+        let (outcome, test_run) = run_suite(config, options, middleware, Some((run, runs))).await;
+        let stop = matches!(outcome, RunOutcome::ConfigError | RunOutcome::Interrupted | RunOutcome::Offline);
+        results.extend(test_run);
+        outcomes.push(outcome);
+        if stop {
+            break;
+        }
+    }
+
+    let tests = aggregate(&results);
+    print!("{}", table(&tests, results.len() as u32));
+
+    if let Some(path) = &options.report_json {
+        let report = MultiRun {
+            runs: results.len() as u32,
+            flaky: tests.iter().filter(|test| test.flaky).count(),
+            tests,
+            results,
+        };
+        match write_json(&report, path) {
+            Ok(()) => println!("Wrote the json report to {}", path.display()),
+            Err(error) => println!("Error while writing the json report: {}", error),
+        }
+    }
+
+    if config.metrics.is_some() || config.notify.is_some() {
+        println!("The metrics and notifications are only sent for a single run, not with --runs.");
+    }
+
+    // The last outcome tells why the runs stopped early, otherwise any failed run fails them all
+    return match outcomes.pop() {
+        Some(outcome @ (RunOutcome::ConfigError | RunOutcome::Interrupted | RunOutcome::Offline)) => outcome,
+        Some(RunOutcome::Passed) if outcomes.iter().all(|outcome| *outcome == RunOutcome::Passed) => RunOutcome::Passed,
+        Some(_) => RunOutcome::Failed,
+        None => RunOutcome::ConfigError,
+    };
+}
@@ -0,0 +1,24 @@
+use std::net::IpAddr;
+
+// The headers a gateway adds to the requests it forwards
+pub const HEADER_NAMES: [&str; 3] = ["X-Forwarded-For", "X-Forwarded-Proto", "X-Real-IP"];
+
+// The headers of a request forwarded for the address or chain of addresses, the first one is the client
+pub fn headers(forwarded_for: &str, scheme: &str) -> [(String, String); 3] {
+    let chain: Vec<&str> = forwarded_for.split(',').map(|address| address.trim()).collect();
+    return [
+        (HEADER_NAMES[0].to_string(), chain.join(", ")),
+        (HEADER_NAMES[1].to_string(), scheme.to_string()),
+        (HEADER_NAMES[2].to_string(), chain[0].to_string()),
+    ];
+}
+
+// Every address of the chain has to be an ip, the ones that are interpolated are only known during the run
+pub fn validate(forwarded_for: &str) -> Result<(), String> {
+    for address in forwarded_for.split(',').map(|address| address.trim()) {
+        if !address.contains("{{") && address.parse::<IpAddr>().is_err() {
+            return Err(format!("'{}' is no ip address", address));
+        }
+    }
+    return Ok(());
+}
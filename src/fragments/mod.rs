@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+
+use crate::Endpoint;
+use crate::boundaries::TimeBoundariesConfig;
+
+// Keys of the defaults that apply to every test instead of naming a fragment
+pub const TEST_DEFAULTS: [&str; 4] = ["method", "status", "verbose", "time_boundaries"];
+
+// The fields every test gets unless it sets them itself
+#[derive(Debug, Default, Deserialize)]
+pub struct TestDefaults {
+    method: Option<String>,
+    status: Option<u16>,
+    verbose: Option<bool>,
+    time_boundaries: Option<TimeBoundariesConfig>,
+}
+
+// Merges the fragment into the test, nested maps like headers are merged key by key and the test wins otherwise
+fn merge(test: &mut Value, fragment: &Value) {
+    let (Value::Mapping(test), Value::Mapping(fragment)) = (test, fragment) else {
+        return;
+    };
+
+    for (key, value) in fragment.iter() {
+        match test.get_mut(key) {
+            Some(existing @ Value::Mapping(_)) => merge(existing, value),
+            Some(_) => (),
+            None => {
+                test.insert(key.clone(), value.clone());
+            },
+        }
+    }
+}
+
+// Names listed in the extends of a test, a single name is accepted as well
+fn extended_names(test: &Value) -> Vec<String> {
+    return match test.get("extends") {
+        Some(Value::String(name)) => vec![name.clone()],
+        Some(Value::Sequence(names)) => names.iter().filter_map(|name| name.as_str().map(|name| name.to_string())).collect(),
+        _ => Vec::new(),
+    };
+}
+
+// Merges the named fragments of the defaults section into the tests that extend them, the later fragments of a
+// test win over the earlier ones. Returns whether any test extends a fragment, unknown names are errors.
+pub fn apply(document: &mut Value) -> Result<bool, Vec<String>> {
+    let fragments = match document.get("defaults") {
+        Some(Value::Mapping(fragments)) => fragments.clone(),
+        _ => Mapping::new(),
+    };
+    let tests = match document.get_mut("tests") {
+        Some(Value::Sequence(tests)) => tests,
+        _ => return Ok(false),
+    };
+
+    let mut errors = Vec::new();
+    let mut extended = false;
+    for (index, test) in tests.iter_mut().enumerate() {
+        // Merging only fills the gaps, so the fragment that has to win goes first
+        for name in extended_names(test).iter().rev() {
+            extended = true;
+            if TEST_DEFAULTS.contains(&name.as_str()) {
+                errors.push(format!("Test {} extends '{}', which the defaults apply to every test instead.", index + 1,
+                 name));
+                continue;
+            }
+            match fragments.get(name.as_str()) {
+                Some(fragment @ Value::Mapping(_)) => merge(test, fragment),
+                Some(_) => errors.push(format!("Test {} extends '{}', which is no map of test fields.", index + 1, name)),
+                None => errors.push(format!("Test {} extends the unknown fragment '{}', the defaults define: {}.",
+                 index + 1, name, describe_names(&fragments))),
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    return Ok(extended);
+}
+
+fn describe_names(fragments: &Mapping) -> String {
+    let names: Vec<&str> = fragments.keys()
+        .filter_map(|name| name.as_str())
+        .filter(|name| !TEST_DEFAULTS.contains(name))
+        .collect();
+    if names.is_empty() {
+        return "none".to_string();
+    }
+    return names.join(", ");
+}
+
+// The fields of the defaults section that apply to every test
+pub fn test_defaults(defaults: &Option<HashMap<String, Value>>) -> Result<TestDefaults, String> {
+    let fields: Mapping = defaults.iter().flatten()
+        .filter(|(key, _)| TEST_DEFAULTS.contains(&key.as_str()))
+        .map(|(key, value)| (Value::from(key.as_str()), value.clone()))
+        .collect();
+    return serde_yaml::from_value(Value::Mapping(fields))
+        .map_err(|error| format!("The defaults are invalid: {}", error));
+}
+
+// Fills the fields a test leaves out. The method is left to a method_matrix, and the status only goes to tests that
+// dont expect anything else, like an excluded status, an error, a preflight or a websocket.
+pub fn apply_defaults(tests: &mut [Endpoint], defaults: &TestDefaults) {
+    for test in tests.iter_mut() {
+        if let (true, None, Some(method)) = (test.method.is_empty(), &test.method_matrix, &defaults.method) {
+            test.method = method.clone();
+        }
+        let expects_other = test.expect_not_status.is_some() || test.expect_error.is_some() || test.cors.is_some()
+            || test.websocket.is_some();
+        if test.status.is_none() && !expects_other {
+            test.status = defaults.status;
+        }
+        if test.verbose.is_none() {
+            test.verbose = defaults.verbose;
+        }
+        if test.time_boundaries.is_none() {
+            test.time_boundaries = defaults.time_boundaries.clone();
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::io::Write;
+use std::{env, fs, path::Path};
+
+use crate::Result;
+use crate::report::{TestOutcome, TestRun};
+
+// Whether the run happens inside a GitHub Actions job
+pub fn is_github_actions() -> bool {
+    return env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true");
+}
+
+// Finds the (one based) line of every entry of the top level tests list, block style lists only
+pub fn locate_tests(content: &str) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut in_tests = false;
+    let mut item_indentation: Option<usize> = None;
+
+    for (number, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let indentation = line.len() - trimmed.len();
+
+        if indentation == 0 && !trimmed.starts_with('-') {
+            in_tests = trimmed.strip_prefix("tests:").is_some_and(|rest| rest.trim().is_empty());
+            continue;
+        }
+
+        if in_tests && (trimmed == "-" || trimmed.starts_with("- ")) {
+            let expected = *item_indentation.get_or_insert(indentation);
+            if indentation == expected {
+                lines.push(number + 1);
+            }
+        }
+    }
+
+    return lines;
+}
+
+// Values of workflow commands have to encode line breaks
+fn escape_data(text: &str) -> String {
+    return text.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A");
+}
+
+fn escape_property(text: &str) -> String {
+    return escape_data(text).replace(':', "%3A").replace(',', "%2C");
+}
+
+// Prints an error annotation for every failed test
+pub fn annotate_failures(run: &TestRun, config_file: Option<&Path>, test_lines: &[usize]) {
+    for test in run.tests.iter().filter(|test| test.outcome == TestOutcome::Failed) {
+        let reason = test.describe_failures();
+
+        // Tests without a config file, e.g. of the check subcommand, are annotated without a location
+        let mut properties = Vec::new();
+        if let Some(config_file) = config_file {
+            properties.push(format!("file={}", escape_property(&config_file.display().to_string())));
+            if let Some(line) = test_lines.get(test.index - 1) {
+                properties.push(format!("line={}", line));
+            }
+        }
+
+        println!("::error {}::{}", properties.join(","),
+         escape_data(&format!("Test {} failed: {}", test.reference(), if reason.is_empty() { &test.description } else { &reason })));
+    }
+}
+
+fn table_cell(text: &str) -> String {
+    return text.replace('|', "\\|").replace('\n', " ");
+}
+
+// Appends a markdown summary of the run to the file named by GITHUB_STEP_SUMMARY
+pub fn write_step_summary(run: &TestRun) -> Result<()> {
+    let path = match env::var("GITHUB_STEP_SUMMARY") {
+        Ok(path) if !path.is_empty() => path,
+        _ => return Ok(()),
+    };
+
+    let shard = run.shard.as_ref().map(|shard| format!(" in shard {}", shard)).unwrap_or_default();
+    let mut summary = format!("### rust-rest-test: {} out of {} tests passed{}\n\n", run.passed, run.total, shard);
+    summary += &format!("{} failed, {} skipped, {} xfail, {} xpass, took {} ms against `{}` (run id `{}`).\n\n",
+        run.failed, run.skipped, run.xfailed, run.xpassed, run.duration_ms, run.api_address, run.run_id);
+    summary += &format!("Response times: 🟢 {} green, 🟡 {} yellow, 🔴 {} red, {} warnings.\n\n",
+        run.green, run.yellow, run.red, run.warnings);
+
+    if let Some(reason) = &run.aborted {
+        summary += &format!("**{}**\n\n", reason);
+    }
+
+    summary += "| # | Result | Test | Method | Url | Status | Time |\n|---|---|---|---|---|---|---|\n";
+    for test in run.tests.iter() {
+        let result = match test.outcome {
+            TestOutcome::Passed => "✅ passed",
+            TestOutcome::Failed => "❌ failed",
+            TestOutcome::Skipped => "⏭️ skipped",
+            TestOutcome::XFail => "🐞 xfail",
+            TestOutcome::XPass => "❗ xpass",
+        };
+
+        summary += &format!("| {} | {} | {} | {} | {} | {} | {} |\n", table_cell(&test.reference()), result, table_cell(&test.description),
+            test.method, table_cell(&test.url), test.status.map(|status| status.to_string()).unwrap_or_default(),
+            test.response_time_ms.map(|time| format!("{} ms", time)).unwrap_or_default());
+    }
+
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(summary.as_bytes())?;
+    return Ok(());
+}
@@ -0,0 +1,70 @@
+use crate::{Endpoint, connect, interpolation, utils};
+
+// What happens to a test whose allowed_hosts dont cover the hosts it would be sent to
+pub const ENFORCE_VALUES: [&str; 2] = ["skip", "strict"];
+
+// The host of a base url, an ipv6 address without its brackets
+pub fn host(address: &str) -> Option<String> {
+    let uri = address.parse::<hyper::Uri>().ok()?;
+    return uri.host().map(|host| host.trim_start_matches('[').trim_end_matches(']').to_string());
+}
+
+// The first of the hosts no pattern allows
+pub fn disallowed<'a>(patterns: &[String], hosts: &'a [String]) -> Option<&'a String> {
+    return hosts.iter().find(|host| !patterns.iter().any(|pattern| utils::matches_wildcard(host, pattern)));
+}
+
+// The hosts a test reaches: those of the base urls, the address it connects to instead and the hosts of the absolute
+// urls its redirect chain is expected to lead through. Hops with captures that arent known yet are left out.
+pub fn targets(test: &Endpoint, base_hosts: &[String], captures: &interpolation::Captures) -> Vec<String> {
+    let mut hosts = base_hosts.to_vec();
+    if let Some(target) = &test.connect_to {
+        hosts.push(connect::parse_target(target, &hyper::Uri::default())
+            .map_or_else(|| target.clone(), |address| address.ip().to_string()));
+    }
+    for hop in test.expect_redirect_chain.iter().flatten().filter(|hop| !hop.starts_with('/')) {
+        if let Some(host) = host(&interpolation::interpolate(hop, captures)) {
+            hosts.push(host);
+        }
+    }
+    return hosts;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(yaml: &str) -> Endpoint {
+        return serde_yaml::from_str(yaml).unwrap();
+    }
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        return patterns.iter().map(|pattern| pattern.to_string()).collect();
+    }
+
+    #[test]
+    fn finds_the_first_host_no_pattern_allows() {
+        let hosts = patterns(&["API.staging.example.com", "127.0.0.1", "example.com"]);
+        assert_eq!(disallowed(&patterns(&["*.staging.example.com", "127.0.0.*"]), &hosts), Some(&hosts[2]));
+        assert_eq!(disallowed(&patterns(&["*.staging.example.com", "127.0.0.1", "example.com"]), &hosts), None);
+    }
+
+    #[test]
+    fn takes_the_host_of_a_base_url() {
+        assert_eq!(host("https://api.example.com:8443/v1").as_deref(), Some("api.example.com"));
+        assert_eq!(host("http://[::1]:8080").as_deref(), Some("::1"));
+    }
+
+    #[test]
+    fn adds_the_connect_to_address_and_the_redirect_hosts() {
+        let base = patterns(&["api.example.com"]);
+        let test = endpoint("route: login\nmethod: get\nconnect_to: 10.0.3.7:8443\nexpect_redirect_chain: \
+            [/sso, 'https://{{idp}}/authorize', 'https://{{unknown}}/x', 'http://[::1]:9000/callback']");
+        let captures = [("idp".to_string(), serde_json::Value::from("idp.example.com"))].into_iter().collect();
+        assert_eq!(targets(&test, &base, &captures),
+            patterns(&["api.example.com", "10.0.3.7", "idp.example.com", "::1"]));
+
+        let test = endpoint("route: login\nmethod: get\nconnect_to: '::1'");
+        assert_eq!(targets(&test, &base, &captures), patterns(&["api.example.com", "::1"]));
+    }
+}
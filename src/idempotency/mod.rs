@@ -0,0 +1,132 @@
+use serde::{Serialize, Deserialize};
+
+use crate::failure::{self, FailureReason};
+use crate::utils::random_u64;
+
+pub const HEADER_NAME: &str = "Idempotency-Key";
+pub const COMPARE_VALUES: [&str; 2] = ["bytes", "json"];
+
+// Differences beyond this many json paths are only counted
+const MAX_LISTED_DIFFERENCES: usize = 5;
+
+// The second request of an idempotency check as recorded in the reports
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub idempotency_key: String,
+    pub status: Option<u16>,
+    pub response_time_ms: Option<u128>,
+    pub response_body: Option<String>, // excerpt of the body
+    pub error: Option<String>,
+}
+
+// Outcome of a single comparison of the two responses
+pub struct ReplayCheck {
+    pub description: String,
+    pub failure: Option<FailureReason>,
+}
+
+// A random key in the format of a version 4 uuid
+pub fn generate_key() -> String {
+    let (high, low) = (random_u64(), random_u64());
+    return format!("{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}", high >> 32, (high >> 16) & 0xffff, high & 0xfff,
+        ((low >> 48) & 0x3fff) | 0x8000, low & 0xffff_ffff_ffff);
+}
+
+fn check(description: String, difference: Option<String>) -> ReplayCheck {
+    let failure = difference.map(|difference| FailureReason::NotIdempotent { difference });
+    return ReplayCheck { description, failure };
+}
+
+// Describes where two bodies start to differ, None if they are identical
+fn byte_difference(first: &[u8], second: &[u8]) -> Option<String> {
+    if first == second {
+        return None;
+    }
+
+    let offset = first.iter().zip(second.iter()).position(|(a, b)| a != b).unwrap_or(first.len().min(second.len()));
+    let start = offset.saturating_sub(20);
+    let around = |body: &[u8]| if body.len() > start {
+        format!("'{}'", failure::excerpt(&String::from_utf8_lossy(&body[start..])))
+    } else {
+        "the end of the body".to_string()
+    };
+
+    return Some(format!("the bodies differ from byte {} on ({} and {} bytes), first: {}, replay: {}",
+        offset, first.len(), second.len(), around(first), around(second)));
+}
+
+fn collect_json_differences(first: &serde_json::Value, second: &serde_json::Value, path: String,
+     differences: &mut Vec<String>) {
+    match (first, second) {
+        (serde_json::Value::Object(first_map), serde_json::Value::Object(second_map)) => {
+            let mut keys: Vec<&String> = first_map.keys().chain(second_map.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                match (first_map.get(key), second_map.get(key)) {
+                    (Some(first), Some(second)) => collect_json_differences(first, second, child, differences),
+                    (Some(_), None) => differences.push(format!("'{}' is missing in the replay", child)),
+                    (None, Some(_)) => differences.push(format!("'{}' is only in the replay", child)),
+                    (None, None) => (),
+                }
+            }
+        },
+        (serde_json::Value::Array(first_items), serde_json::Value::Array(second_items))
+         if first_items.len() == second_items.len() => {
+            for (index, (first, second)) in first_items.iter().zip(second_items.iter()).enumerate() {
+                let child = if path.is_empty() { index.to_string() } else { format!("{}.{}", path, index) };
+                collect_json_differences(first, second, child, differences);
+            }
+        },
+        _ if first != second => differences.push(format!("'{}' is {} but {} in the replay",
+            if path.is_empty() { "$body" } else { path.as_str() }, failure::excerpt(&first.to_string()),
+            failure::excerpt(&second.to_string()))),
+        _ => (),
+    }
+}
+
+// Lists the json paths whose values differ, key order and whitespace are ignored
+fn json_difference(first: &[u8], second: &[u8]) -> Option<String> {
+    let (first_json, second_json) = match (serde_json::from_slice::<serde_json::Value>(first),
+        serde_json::from_slice::<serde_json::Value>(second)) {
+        (Ok(first_json), Ok(second_json)) => (first_json, second_json),
+        _ => return byte_difference(first, second).map(|difference| format!("not both bodies are json, {}", difference)),
+    };
+
+    let mut differences = Vec::new();
+    collect_json_differences(&first_json, &second_json, String::new(), &mut differences);
+    if differences.is_empty() {
+        return None;
+    }
+
+    let hidden = differences.len().saturating_sub(MAX_LISTED_DIFFERENCES);
+    differences.truncate(MAX_LISTED_DIFFERENCES);
+    if hidden > 0 {
+        differences.push(format!("{} more", hidden));
+    }
+    return Some(format!("the bodies differ: {}", differences.join(", ")));
+}
+
+// Compares the replayed response with the first one
+pub fn check_replay(first_status: u16, first_body: &[u8], replay: &Replay, replay_body: &[u8], compare_json: bool)
+     -> Vec<ReplayCheck> {
+    let replay_status = match (replay.status, &replay.error) {
+        (Some(status), _) => status,
+        (None, error) => return vec![check("Expected the replay to get a response".to_string(),
+            Some(format!("the replay failed: {}", error.as_deref().unwrap_or("no response"))))],
+    };
+
+    let mut checks = vec![check(format!("Expected the replay to get the same status {}, got {}", first_status, replay_status),
+        (first_status != replay_status).then(|| format!("the status changed from {} to {}", first_status, replay_status)))];
+
+    if compare_json {
+        checks.push(check("Expected the replay body to be structurally equal json".to_string(),
+            json_difference(first_body, replay_body)));
+    } else {
+        checks.push(check("Expected the replay body to be byte-identical".to_string(),
+            byte_difference(first_body, replay_body)));
+    }
+
+    return checks;
+}
@@ -0,0 +1,139 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{Result, utils};
+use crate::import::{GeneratedConfig, GeneratedTest, common_base, read_document};
+
+// Headers that only concern a single connection or are set by the client itself
+const DROPPED_HEADERS: [&str; 12] = ["connection", "keep-alive", "proxy-authenticate", "proxy-authorization",
+    "te", "trailer", "transfer-encoding", "upgrade", "host", "content-length", "accept-encoding", "priority"];
+
+const COOKIE_HEADERS: [&str; 2] = ["cookie", "set-cookie"];
+
+// The parts of a recorded entry that end up in the config
+struct RecordedRequest {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+    status: u16,
+    time: f64,
+    recorded_count: usize,
+}
+
+fn host_of(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = without_scheme.split(['/', '?', '#']).next().unwrap_or_default();
+
+    return authority.rsplit_once(':').map_or(authority, |(host, _)| host);
+}
+
+fn body_hash(body: &Option<String>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+
+    return hasher.finish();
+}
+
+fn convert_entry(entry: &Value, keep_cookies: bool) -> Option<RecordedRequest> {
+    let request = &entry["request"];
+
+    let headers = request["headers"].as_array().into_iter().flatten()
+        .filter_map(|header| Some((header["name"].as_str()?.to_string(), header["value"].as_str()?.to_string())))
+        .filter(|(name, _)| {
+            let name = name.to_lowercase();
+            // HTTP/2 pseudo headers like :authority are part of the request line
+            !name.starts_with(':') && !DROPPED_HEADERS.contains(&name.as_str())
+                && (keep_cookies || !COOKIE_HEADERS.contains(&name.as_str()))
+        })
+        .collect();
+
+    let body = request["postData"]["text"].as_str()
+        .filter(|text| !text.is_empty())
+        .map(|text| text.to_string());
+
+    return Some(RecordedRequest {
+        method: request["method"].as_str()?.to_string(),
+        url: request["url"].as_str()?.to_string(),
+        headers,
+        body,
+        status: entry["response"]["status"].as_u64().unwrap_or_default() as u16,
+        time: entry["time"].as_f64().unwrap_or_default(),
+        recorded_count: 1,
+    });
+}
+
+// Generates one test per recorded request, identical requests are merged
+pub fn import(har_path: &Path, host_pattern: Option<&String>, keep_cookies: bool) -> Result<GeneratedConfig> {
+    let har = read_document(har_path)?;
+
+    let mut requests: Vec<RecordedRequest> = Vec::new();
+    for entry in har["log"]["entries"].as_array().into_iter().flatten() {
+        let recorded = match convert_entry(entry, keep_cookies) {
+            Some(recorded) => recorded,
+            None => continue,
+        };
+
+        if let Some(pattern) = host_pattern {
+            if !utils::matches_wildcard(host_of(&recorded.url), pattern) {
+                continue;
+            }
+        }
+
+        // De-duplicate by method, url and body
+        let duplicate = requests.iter_mut().find(|existing| existing.method == recorded.method
+            && existing.url == recorded.url && body_hash(&existing.body) == body_hash(&recorded.body));
+
+        match duplicate {
+            Some(existing) => existing.recorded_count += 1,
+            None => requests.push(recorded),
+        }
+    }
+
+    if requests.is_empty() {
+        return Err("The capture doesnt contain any (matching) requests.".into());
+    }
+
+    let mut config = GeneratedConfig {
+        header_comments: vec![
+            format!("Generated from the HAR capture {}.", har_path.display()),
+            "The recorded statuses are used as expectations, lines marked with TODO need manual attention.".to_string(),
+        ],
+        ..Default::default()
+    };
+
+    let urls: Vec<String> = requests.iter().map(|request| request.url.clone()).collect();
+    match common_base(&urls) {
+        Some(base) => config.api_address = base,
+        None => config.api_address_comment = Some("TODO: the requests share no common base url".to_string()),
+    }
+
+    for request in requests.into_iter() {
+        let mut test = GeneratedTest {
+            route: request.url.strip_prefix(&config.api_address).unwrap_or(&request.url).to_string(),
+            method: request.method,
+            status: request.status,
+            headers: request.headers,
+            body: request.body,
+            ..Default::default()
+        };
+
+        test.comments.push(format!("Observed response time: {:.0} ms", request.time));
+        if request.recorded_count > 1 {
+            test.comments.push(format!("Recorded {} times", request.recorded_count));
+        }
+
+        // Requests that were blocked or cancelled by the browser have no status
+        if test.status == 0 {
+            test.status = 200;
+            test.comments.push("TODO: the request got no response while recording, verify the expected status".to_string());
+        }
+
+        config.tests.push(test);
+    }
+
+    return Ok(config);
+}
@@ -0,0 +1,177 @@
+use std::{fs, path::Path};
+
+use crate::{Config, Result, RunOptions, validation};
+
+pub mod openapi;
+pub mod postman;
+pub mod har;
+
+// A test generated by one of the importers, rendered by hand so comments can be attached
+#[derive(Debug, Default)]
+pub struct GeneratedTest {
+    pub comments: Vec<String>, // written above the test, e.g. TODOs that need manual attention
+    pub it: Option<String>,
+    pub route: String,
+    pub method: String,
+    pub status: u16,
+    pub tags: Vec<String>,
+    pub bearer_token: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub json: Option<serde_json::Value>, // written as flow yaml, which json is
+    pub body: Option<String>,
+    pub trailing_comments: Vec<String>, // written below the test, e.g. scripts that couldnt be converted
+}
+
+#[derive(Debug, Default)]
+pub struct GeneratedConfig {
+    pub header_comments: Vec<String>,
+    pub api_address: String,
+    pub api_address_comment: Option<String>,
+    pub variables: Vec<(String, String)>,
+    pub tests: Vec<GeneratedTest>,
+}
+
+// Renders a string as a single line yaml scalar, quoting it where necessary
+pub fn yaml_scalar(value: &str) -> String {
+    // Block scalars would need to know their indentation, json strings are valid yaml too
+    if value.contains('\n') {
+        return serde_json::Value::from(value).to_string();
+    }
+
+    return match serde_yaml::to_string(value) {
+        Ok(rendered) => rendered.trim_end().to_string(),
+        Err(_) => format!("{:?}", value),
+    };
+}
+
+fn render_map(entries: &[(String, String)], indentation: &str) -> String {
+    return entries.iter()
+        .map(|(key, value)| format!("{}{}: {}\n", indentation, yaml_scalar(key), yaml_scalar(value)))
+        .collect();
+}
+
+impl GeneratedTest {
+    fn render(&self) -> String {
+        let mut output = String::new();
+
+        for comment in self.comments.iter() {
+            output += &format!("  # {}\n", comment);
+        }
+
+        let mut first_line = true;
+        let mut push_field = |field: String| {
+            output += if first_line { "  - " } else { "    " };
+            output += &field;
+            first_line = false;
+        };
+
+        if let Some(description) = &self.it {
+            push_field(format!("it: {}\n", yaml_scalar(description)));
+        }
+        push_field(format!("route: {}\n", yaml_scalar(&self.route)));
+        push_field(format!("method: {}\n", self.method.to_uppercase()));
+        push_field(format!("status: {}\n", self.status));
+
+        if !self.tags.is_empty() {
+            let tags: Vec<String> = self.tags.iter().map(|tag| yaml_scalar(tag)).collect();
+            push_field(format!("tags: [{}]\n", tags.join(", ")));
+        }
+
+        if let Some(token) = &self.bearer_token {
+            push_field(format!("bearer_token: {}\n", yaml_scalar(token)));
+        }
+
+        if !self.headers.is_empty() {
+            push_field(format!("headers:\n{}", render_map(&self.headers, "      ")));
+        }
+
+        if let Some(json) = &self.json {
+            push_field(format!("json: {}\n", json));
+        }
+
+        if let Some(body) = &self.body {
+            push_field(format!("body: {}\n", yaml_scalar(body)));
+        }
+
+        for comment in self.trailing_comments.iter() {
+            output += &format!("    # {}\n", comment);
+        }
+
+        return output;
+    }
+}
+
+impl GeneratedConfig {
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        for comment in self.header_comments.iter() {
+            output += &format!("# {}\n", comment);
+        }
+        if !self.header_comments.is_empty() {
+            output += "\n";
+        }
+
+        output += &format!("api_address: {}", yaml_scalar(&self.api_address));
+        if let Some(comment) = &self.api_address_comment {
+            output += &format!(" # {}", comment);
+        }
+        output += "\n";
+
+        if !self.variables.is_empty() {
+            output += &format!("\nvariables:\n{}", render_map(&self.variables, "  "));
+        }
+
+        output += "\ntests:\n";
+
+        for test in self.tests.iter() {
+            output += &test.render();
+        }
+
+        return output;
+    }
+}
+
+// Validates the generated config against the runner and writes it to the given path
+pub fn write_generated_config(config: &GeneratedConfig, out: &Path, force: bool) -> Result<()> {
+    if out.exists() && !force {
+        return Err(format!("{} already exists, use --force to overwrite it.", out.display()).into());
+    }
+
+    let content = config.render();
+
+    let parsed: Config = serde_yaml::from_str(&content)?;
+    if let Err(errors) = validation::validate_config(&parsed, &RunOptions::default()) {
+        return Err(format!("Generated config is invalid: {}", errors.join(" ")).into());
+    }
+
+    fs::write(out, content)?;
+    return Ok(());
+}
+
+// Finds the longest common prefix of all urls that ends on a path separator behind the host
+pub fn common_base(urls: &[String]) -> Option<String> {
+    let first = urls.first()?.as_bytes();
+    let mut prefix_length = first.len();
+
+    for url in urls.iter().skip(1) {
+        prefix_length = first.iter().zip(url.as_bytes().iter())
+            .take(prefix_length)
+            .take_while(|(a, b)| a == b)
+            .count();
+    }
+
+    let prefix = &first[..prefix_length];
+    let host_start = prefix.windows(3).position(|window| window == b"://").map_or(0, |index| index + 3);
+    let base_end = prefix[host_start..].iter().rposition(|byte| *byte == b'/')? + host_start + 1;
+
+    return Some(String::from_utf8_lossy(&first[..base_end]).to_string());
+}
+
+// Reads a json or yaml document
+pub fn read_document(path: &Path) -> Result<serde_json::Value> {
+    let content = fs::read_to_string(path)?;
+    let document: serde_yaml::Value = serde_yaml::from_str(&content)?;
+
+    return Ok(serde_json::to_value(document)?);
+}
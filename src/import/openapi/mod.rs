@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::Result;
+use crate::import::{GeneratedConfig, GeneratedTest, read_document};
+
+const METHODS: [&str; 7] = ["get", "post", "put", "patch", "delete", "options", "head"];
+
+// Converts openapi path templates like /users/{id} into routes like users/{{id}}
+fn convert_path(path: &str) -> (String, Vec<String>) {
+    let mut route = String::new();
+    let mut parameters = Vec::new();
+    let mut rest = path.trim_start_matches('/');
+
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        let name = &rest[start + 1..end];
+        route += &rest[..start];
+        route += &format!("{{{{{}}}}}", name);
+        parameters.push(name.to_string());
+        rest = &rest[end + 1..];
+    }
+
+    route += rest;
+    return (route, parameters);
+}
+
+// Picks the first documented 2xx response code
+fn expected_status(operation: &Value) -> Option<u16> {
+    return operation["responses"].as_object()?
+        .keys()
+        .filter_map(|code| code.parse::<u16>().ok())
+        .find(|code| (200..300).contains(code));
+}
+
+// Looks for an example of the json request body
+fn body_example(operation: &Value) -> Option<&Value> {
+    let content = &operation["requestBody"]["content"];
+    let media = content.as_object()?
+        .iter()
+        .find(|(media_type, _)| media_type.contains("json"))
+        .map(|(_, media)| media)?;
+
+    if !media["example"].is_null() {
+        return Some(&media["example"]);
+    }
+
+    if let Some(examples) = media["examples"].as_object() {
+        if let Some((_, example)) = examples.iter().next() {
+            return Some(&example["value"]);
+        }
+    }
+
+    if !media["schema"]["example"].is_null() {
+        return Some(&media["schema"]["example"]);
+    }
+
+    return None;
+}
+
+// The documented example of a path parameter, the operation's own parameters take precedence over the path's
+fn parameter_example(name: &str, item: &Value, operation: &Value) -> Option<String> {
+    let parameter = [&operation["parameters"], &item["parameters"]].into_iter()
+        .filter_map(|parameters| parameters.as_array())
+        .flatten()
+        .find(|parameter| parameter["in"] == "path" && parameter["name"] == name)?;
+
+    let example = [&parameter["example"], &parameter["schema"]["example"]].into_iter()
+        .find(|example| !example.is_null());
+    return match example {
+        Some(Value::String(example)) => Some(example.clone()),
+        Some(example) => Some(example.to_string()),
+        None => None,
+    };
+}
+
+fn convert_operation(path: &str, method: &str, operation: &Value) -> GeneratedTest {
+    let (route, parameters) = convert_path(path);
+    let mut test = GeneratedTest {
+        route,
+        method: method.to_string(),
+        ..Default::default()
+    };
+
+    test.it = operation["summary"].as_str()
+        .or(operation["operationId"].as_str())
+        .map(|summary| summary.to_string());
+
+    for parameter in parameters.iter() {
+        test.comments.push(format!("TODO: capture the path parameter {{{{{}}}}} or fill in its variable", parameter));
+    }
+
+    match expected_status(operation) {
+        Some(status) => test.status = status,
+        None => {
+            test.status = 200;
+            test.comments.push("TODO: no 2xx response is documented, verify the expected status".to_string());
+        },
+    }
+
+    if !operation["requestBody"].is_null() {
+        match body_example(operation) {
+            Some(example) => test.json = Some(example.clone()),
+            None => test.comments.push("TODO: the operation takes a request body but has no json example".to_string()),
+        }
+    }
+
+    if !operation["security"].is_null() {
+        test.comments.push("TODO: the operation requires authentication, set a bearer_token".to_string());
+    }
+
+    return test;
+}
+
+// Generates one test per operation of an OpenAPI 3 document
+pub fn import(spec_path: &Path) -> Result<GeneratedConfig> {
+    let spec = read_document(spec_path)?;
+
+    let paths = match spec["paths"].as_object() {
+        Some(paths) => paths,
+        None => return Err("The spec doesnt contain any paths.".into()),
+    };
+
+    let mut config = GeneratedConfig {
+        header_comments: vec![
+            format!("Generated from the OpenAPI spec {}.", spec_path.display()),
+            "Lines marked with TODO need manual attention.".to_string(),
+        ],
+        ..Default::default()
+    };
+
+    match spec["servers"][0]["url"].as_str() {
+        Some(url) if url.starts_with("http") => {
+            config.api_address = if url.ends_with('/') { url.to_string() } else { url.to_string() + "/" };
+        },
+        _ => {
+            config.api_address = crate::init::DEFAULT_API_ADDRESS.to_string();
+            config.api_address_comment = Some("TODO: the spec defines no absolute server url".to_string());
+        },
+    }
+
+    for (path, item) in paths.iter() {
+        for method in METHODS.iter() {
+            if let Some(operation) = item.get(*method) {
+                config.tests.push(convert_operation(path, method, operation));
+
+                // Every path parameter needs a value for the config to run, placeholders stand in for undocumented ones
+                for parameter in convert_path(path).1 {
+                    if !config.variables.iter().any(|(name, _)| *name == parameter) {
+                        let value = parameter_example(&parameter, item, operation)
+                            .unwrap_or_else(|| format!("todo-{}", parameter));
+                        config.variables.push((parameter, value));
+                    }
+                }
+            }
+        }
+    }
+
+    return Ok(config);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_path_templates_into_routes() {
+        assert_eq!(convert_path("/orders/{orderId}/items/{item}"),
+            ("orders/{{orderId}}/items/{{item}}".to_string(), vec!["orderId".to_string(), "item".to_string()]));
+        assert_eq!(convert_path("/health"), ("health".to_string(), Vec::new()));
+    }
+
+    #[test]
+    fn takes_the_example_of_a_path_parameter() {
+        let item = serde_json::json!({"parameters": [{"name": "id", "in": "path", "schema": {"example": 7}}]});
+        let operation = serde_json::json!({"parameters": [
+            {"name": "id", "in": "query", "example": "query"},
+            {"name": "slug", "in": "path", "example": "first-post"},
+        ]});
+        assert_eq!(parameter_example("id", &item, &operation).as_deref(), Some("7"));
+        assert_eq!(parameter_example("slug", &item, &operation).as_deref(), Some("first-post"));
+        assert_eq!(parameter_example("other", &item, &operation), None);
+    }
+}
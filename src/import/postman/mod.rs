@@ -0,0 +1,284 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{Result, dependency, runid};
+use crate::import::{GeneratedConfig, GeneratedTest, common_base, read_document};
+
+// A request of the collection together with the folders it is nested in
+struct CollectedRequest<'a> {
+    folders: Vec<String>,
+    name: Option<String>,
+    item: &'a Value,
+}
+
+// Walks the (nested) folder structure of the collection
+fn collect_requests<'a>(items: &'a Value, folders: &[String], requests: &mut Vec<CollectedRequest<'a>>) {
+    for item in items.as_array().into_iter().flatten() {
+        let name = item["name"].as_str().map(|name| name.to_string());
+
+        if item["item"].is_array() {
+            let mut nested_folders = folders.to_vec();
+            nested_folders.extend(name);
+            collect_requests(&item["item"], &nested_folders, requests);
+        } else if !item["request"].is_null() {
+            requests.push(CollectedRequest { folders: folders.to_vec(), name, item });
+        }
+    }
+}
+
+// Postman urls are either plain strings or objects with a raw representation
+fn raw_url(request: &Value) -> String {
+    let url = match &request["url"] {
+        Value::String(url) => url.clone(),
+        url => url["raw"].as_str().unwrap_or_default().to_string(),
+    };
+
+    // Path variables like :id become placeholders
+    return url.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) if !name.is_empty() => format!("{{{{{}}}}}", name),
+            _ => segment.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("/");
+}
+
+// The value the collection documents for a path variable like :id
+fn path_variable(request: &Value, name: &str) -> Option<String> {
+    let variable = request["url"]["variable"].as_array()?.iter().find(|variable| variable["key"] == name)?;
+    return match &variable["value"] {
+        Value::String(value) if !value.is_empty() => Some(value.clone()),
+        Value::String(_) | Value::Null => None,
+        value => Some(value.to_string()),
+    };
+}
+
+// The scheme and host of an absolute url like https://api.example.com:8443/
+fn origin(url: &str) -> Option<String> {
+    let uri = url.parse::<hyper::Uri>().ok()?;
+    return Some(format!("{}://{}/", uri.scheme_str()?, uri.authority()?));
+}
+
+// Replaces {{variable}} references with their values, used where the runner doesnt interpolate
+fn resolve_variables(text: &str, variables: &[(String, String)]) -> String {
+    let mut resolved = text.to_string();
+    for (key, value) in variables.iter() {
+        resolved = resolved.replace(&format!("{{{{{}}}}}", key), value);
+    }
+
+    return resolved;
+}
+
+// Extracts the expected status from assertions like pm.response.to.have.status(201)
+fn status_from_script(lines: &[String]) -> Option<u16> {
+    for line in lines.iter() {
+        if let Some(start) = line.find("to.have.status(") {
+            let rest = &line[start + "to.have.status(".len()..];
+            let end = rest.find(')')?;
+            if let Ok(status) = rest[..end].trim().parse::<u16>() {
+                return Some(status);
+            }
+        }
+    }
+
+    return None;
+}
+
+fn script_lines(event: &Value) -> Vec<String> {
+    return match &event["script"]["exec"] {
+        Value::Array(lines) => lines.iter().filter_map(|line| line.as_str()).map(|line| line.to_string()).collect(),
+        Value::String(script) => script.lines().map(|line| line.to_string()).collect(),
+        _ => Vec::new(),
+    };
+}
+
+fn convert_request(collected: &CollectedRequest, base: &str, variables: &[(String, String)]) -> GeneratedTest {
+    let request = &collected.item["request"];
+    let url = raw_url(request);
+    let resolved_url = resolve_variables(&url, variables);
+
+    // Keep variable references inside the route, they are interpolated at runtime
+    let route = url.strip_prefix(base).or(resolved_url.strip_prefix(base));
+    let mut test = GeneratedTest {
+        it: collected.name.clone(),
+        route: route.unwrap_or(&resolved_url).to_string(),
+        method: request["method"].as_str().unwrap_or("GET").to_string(),
+        tags: collected.folders.clone(),
+        ..Default::default()
+    };
+
+    if route.is_none() {
+        test.comments.push(format!("TODO: {} isnt below the api_address, move it to a config of its own", resolved_url));
+    }
+
+    for header in request["header"].as_array().into_iter().flatten() {
+        if header["disabled"].as_bool() == Some(true) {
+            continue;
+        }
+
+        if let (Some(key), Some(value)) = (header["key"].as_str(), header["value"].as_str()) {
+            test.headers.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    // Bearer token auth maps onto bearer_token, a single variable reference becomes a capture reference
+    let auth = &request["auth"];
+    match auth["type"].as_str() {
+        Some("bearer") => {
+            let token = auth["bearer"].as_array().into_iter().flatten()
+                .find(|entry| entry["key"] == "token")
+                .and_then(|entry| entry["value"].as_str())
+                .unwrap_or_default();
+
+            let reference = token.strip_prefix("{{").and_then(|token| token.strip_suffix("}}"));
+            test.bearer_token = Some(reference.unwrap_or(token).to_string());
+        },
+        Some("noauth") | None => (),
+        Some(other) => test.comments.push(format!("TODO: '{}' authentication was not converted", other)),
+    }
+
+    let body = &request["body"];
+    match body["mode"].as_str() {
+        Some("raw") => {
+            let raw = body["raw"].as_str().unwrap_or_default();
+            if !raw.is_empty() {
+                test.body = Some(raw.to_string());
+            }
+        },
+        Some(mode @ ("urlencoded" | "formdata")) => {
+            test.comments.push(format!("TODO: {} body was not converted: {}", mode, body[mode]));
+        },
+        _ => (),
+    }
+
+    // Scripts cant be converted, only the status assertion is picked up
+    for event in collected.item["event"].as_array().into_iter().flatten() {
+        let lines = script_lines(event);
+        if lines.iter().all(|line| line.trim().is_empty()) {
+            continue;
+        }
+
+        if event["listen"] == "test" {
+            if let Some(status) = status_from_script(&lines) {
+                test.status = status;
+            }
+        }
+
+        test.trailing_comments.push(format!("Postman {} script (not converted):",
+            event["listen"].as_str().unwrap_or("unknown")));
+        test.trailing_comments.extend(lines.iter().map(|line| format!("  {}", line)));
+    }
+
+    if test.status == 0 {
+        test.status = 200;
+        test.comments.push("TODO: the collection doesnt assert a status, verify the expected status".to_string());
+    }
+
+    return test;
+}
+
+// Converts the requests of a Postman collection (v2.0 or v2.1) into tests
+pub fn import(collection_path: &Path) -> Result<GeneratedConfig> {
+    let collection = read_document(collection_path)?;
+
+    let mut requests = Vec::new();
+    collect_requests(&collection["item"], &[], &mut requests);
+    if requests.is_empty() {
+        return Err("The collection doesnt contain any requests.".into());
+    }
+
+    let variables: Vec<(String, String)> = collection["variable"].as_array().into_iter().flatten()
+        .filter_map(|variable| Some((
+            variable["key"].as_str()?.to_string(),
+            match &variable["value"] {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            },
+        )))
+        .collect();
+
+    let collection_name = collection["info"]["name"].as_str().unwrap_or("collection");
+    let mut config = GeneratedConfig {
+        header_comments: vec![
+            format!("Generated from the Postman collection '{}' ({}).", collection_name, collection_path.display()),
+            "Folders are mapped to tags, lines marked with TODO need manual attention.".to_string(),
+        ],
+        variables: variables.clone(),
+        ..Default::default()
+    };
+
+    // The api address isnt interpolated, so variables inside the base url are resolved right away
+    let urls: Vec<String> = requests.iter()
+        .map(|request| resolve_variables(&raw_url(&request.item["request"]), &variables))
+        .collect();
+
+    // Without a common base the first origin stands in, the requests to other hosts are marked
+    match common_base(&urls) {
+        Some(base) => config.api_address = base,
+        None => {
+            let origin = urls.first().and_then(|url| origin(url));
+            config.api_address = origin.unwrap_or_else(|| crate::init::DEFAULT_API_ADDRESS.to_string());
+            config.api_address_comment = Some("TODO: the requests share no common base url, verify this one"
+                .to_string());
+        },
+    }
+
+    for request in requests.iter() {
+        let test = convert_request(request, &config.api_address, &variables);
+
+        // Every placeholder needs a value for the config to run, Postman environments arent part of the collection
+        let mut names = Vec::new();
+        dependency::placeholders(&test.route, &mut names);
+        for (_, value) in test.headers.iter() {
+            dependency::placeholders(value, &mut names);
+        }
+        if let Some(body) = &test.body {
+            dependency::placeholders(body, &mut names);
+        }
+        for name in names {
+            let defined = config.variables.iter().any(|(variable, _)| *variable == name);
+            if !defined && !runid::NAMES.contains(&name.as_str()) {
+                let value = path_variable(&request.item["request"], &name).unwrap_or_else(|| format!("todo-{}", name));
+                config.variables.push((name, value));
+            }
+        }
+
+        config.tests.push(test);
+    }
+
+    return Ok(config);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_the_origin_of_an_absolute_url() {
+        assert_eq!(origin("https://api.example.com:8443/v1/users?page=2").as_deref(),
+            Some("https://api.example.com:8443/"));
+        assert_eq!(origin("http://localhost").as_deref(), Some("http://localhost/"));
+        assert_eq!(origin("{{base}}/users"), None);
+        assert_eq!(origin("users/1"), None);
+    }
+
+    #[test]
+    fn turns_path_variables_into_placeholders() {
+        let request = serde_json::json!({"url": {"raw": "{{base}}/users/:id/posts/:post"}});
+        assert_eq!(raw_url(&request), "{{base}}/users/{{id}}/posts/{{post}}");
+    }
+
+    #[test]
+    fn takes_the_value_of_a_path_variable() {
+        let request = serde_json::json!({"url": {"raw": "{{base}}/users/:id/posts/:post/:page", "variable": [
+            {"key": "id", "value": "7"},
+            {"key": "post", "value": ""},
+            {"key": "page", "value": 2},
+        ]}});
+        assert_eq!(path_variable(&request, "id").as_deref(), Some("7"));
+        assert_eq!(path_variable(&request, "post"), None);
+        assert_eq!(path_variable(&request, "page").as_deref(), Some("2"));
+        assert_eq!(path_variable(&request, "other"), None);
+    }
+}
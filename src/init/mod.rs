@@ -0,0 +1,69 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::{Config, Result, RunOptions, import, validation};
+
+pub const DEFAULT_API_ADDRESS: &str = "http://localhost:8080/";
+
+const STARTER_CONFIG: &str = r#"# Rust Rest Test config, see https://github.com/HlustikP/rust-rest-test for all options.
+
+# The uri of the API, the only mandatory global setting
+api_address: {{api_address}}
+
+# Whether to log additional information like response headers and bodies
+verbose: false
+
+# Response times below ok are considered fast (green), below warn slow (yellow) and above that too slow (red).
+# The timeout is the time after which a test counts as failed.
+time_boundaries: { ok: 500, warn: 1000, timeout: 10000 }
+
+tests:
+  - it: logs in and receives a token
+    route: login
+    method: POST
+    status: 200
+    json:
+      username: alice
+      password: secret123
+    # Stores the value of the "token" key of the json response as "auth_token"
+    capture:
+      auth_token: token
+    # All further tests depend on the login, so cancel the run if it fails
+    critical: true
+
+  - it: fetches the profile using the captured token
+    route: profile
+    method: GET
+    status: 200
+    # Sends the captured value via "Authorization: Bearer <token>"
+    bearer_token: auth_token
+"#;
+
+// Renders the starter config, routes are appended to the address so it needs a trailing slash. The address is
+// quoted where yaml needs it, e.g. for a # or ": " inside of it.
+pub fn render_starter_config(api_address: &str) -> String {
+    let mut address = api_address.to_string();
+    if !address.ends_with('/') {
+        address.push('/');
+    }
+
+    return STARTER_CONFIG.replace("{{api_address}}", &import::yaml_scalar(&address));
+}
+
+// Writes a commented starter config into the given directory
+pub fn write_starter_config(directory: &Path, api_address: &str, force: bool) -> Result<PathBuf> {
+    let file_path = directory.join("rest-test.yaml");
+    if file_path.exists() && !force {
+        return Err(format!("{} already exists, use --force to overwrite it.", file_path.display()).into());
+    }
+
+    let content = render_starter_config(api_address);
+
+    // The starter config must be accepted by the runner itself
+    let config: Config = serde_yaml::from_str(&content)?;
+    if let Err(errors) = validation::validate_config(&config, &RunOptions::default()) {
+        return Err(format!("Generated config is invalid: {}", errors.join(" ")).into());
+    }
+
+    fs::write(&file_path, content)?;
+    return Ok(file_path);
+}
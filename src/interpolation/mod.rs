@@ -0,0 +1,270 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+// Captured values keep their json type, so a number captured from a body is sent as a number again
+pub type Captures = HashMap<String, serde_json::Value>;
+
+// A piece of text that is either taken literally or refers to a captured value
+#[derive(Debug, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    // raw values are inserted into a route without encoding them, string ones into a json body as a string, the
+    // source is the placeholder as it was written
+    Placeholder { name: &'a str, raw: bool, string: bool, source: &'a str },
+}
+
+// Besides the unreserved characters, these may appear unencoded in a path segment or a query value
+const PATH_SEGMENT_CHARACTERS: &str = "!$&'()*+,;=:@";
+const QUERY_VALUE_CHARACTERS: &str = "!$'()*,;:@/?";
+
+// Parses the inside of a placeholder, 'name | raw' opts out of the url encoding and 'name | string' out of the typed
+// value of a json body
+fn placeholder<'a>(inner: &'a str, source: &'a str) -> Segment<'a> {
+    return match inner.split_once('|').map(|(name, filter)| (name.trim(), filter.trim())) {
+        Some((name, "raw")) => Segment::Placeholder { name, raw: true, string: false, source },
+        Some((name, "string")) => Segment::Placeholder { name, raw: false, string: true, source },
+        _ => Segment::Placeholder { name: inner.trim(), raw: false, string: false, source },
+    };
+}
+
+// Splits a text into literal parts and {{name}} placeholders, an unterminated {{ is kept as text
+pub fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let after_start = &rest[start + 2..];
+        let end = match after_start.find("}}") {
+            Some(end) => end,
+            None => break,
+        };
+
+        if start > 0 {
+            segments.push(Segment::Text(&rest[..start]));
+        }
+        segments.push(placeholder(&after_start[..end], &rest[start..start + end + 4]));
+
+        rest = &after_start[end + 2..];
+    }
+
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+
+    return segments;
+}
+
+// A captured value as it is inserted into a text: strings without their quotes, numbers, booleans, null and
+// structures in their json notation
+pub fn text(value: &serde_json::Value) -> Cow<'_, str> {
+    return match value {
+        serde_json::Value::String(text) => Cow::Borrowed(text),
+        _ => Cow::Owned(value.to_string()),
+    };
+}
+
+// Replaces {{name}} placeholders with captured values, unknown names are left untouched
+pub fn interpolate(text: &str, captures: &Captures) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for segment in segments(text).iter() {
+        match segment {
+            Segment::Text(text) => result += text,
+            Segment::Placeholder { name, source, .. } => match captures.get(*name) {
+                Some(value) => result += &self::text(value),
+                None => result += source,
+            },
+        }
+    }
+
+    return result;
+}
+
+// Interpolates every string of a json value, keys and other values are kept as they are. A string that is nothing
+// but a single placeholder takes the captured value with its type, e.g. "{{count}}" becomes 42 and not "42", unless
+// it is marked as "{{count | string}}".
+pub fn interpolate_json(value: &serde_json::Value, captures: &Captures) -> serde_json::Value {
+    return match value {
+        serde_json::Value::String(text) => match segments(text).as_slice() {
+            [Segment::Placeholder { name, string: false, .. }] if captures.contains_key(*name) =>
+                captures[*name].clone(),
+            _ => serde_json::Value::String(interpolate(text, captures)),
+        },
+        serde_json::Value::Array(items) => items.iter().map(|item| interpolate_json(item, captures)).collect(),
+        serde_json::Value::Object(map) => serde_json::Value::Object(map.iter()
+            .map(|(key, value)| (key.clone(), interpolate_json(value, captures)))
+            .collect()),
+        _ => value.clone(),
+    };
+}
+
+// The strings of a json value at any depth, the ones that can hold placeholders
+pub fn json_strings(value: &serde_json::Value) -> Vec<&String> {
+    return match value {
+        serde_json::Value::String(text) => vec![text],
+        serde_json::Value::Array(items) => items.iter().flat_map(json_strings).collect(),
+        serde_json::Value::Object(map) => map.values().flat_map(json_strings).collect(),
+        _ => Vec::new(),
+    };
+}
+
+// Percent-encodes every byte that isnt unreserved or one of the allowed characters
+fn percent_encode(value: &str, allowed: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) || allowed.as_bytes().contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded += &format!("%{:02X}", byte);
+        }
+    }
+
+    return encoded;
+}
+
+// Interpolates a route, values inside the path are encoded as a single path segment and values after the first
+// literal '?' as query values. Placeholders marked with '| raw' and unknown names are inserted as they are.
+pub fn interpolate_route(route: &str, captures: &Captures) -> String {
+    let mut result = String::with_capacity(route.len());
+    let mut in_query = false;
+
+    for segment in segments(route).iter() {
+        match segment {
+            Segment::Text(text) => {
+                in_query = in_query || text.contains('?') || text.contains('#');
+                result += text;
+            },
+            Segment::Placeholder { name, raw, source, .. } => match captures.get(*name).map(text) {
+                Some(value) if *raw => result += &value,
+                Some(value) if in_query => result += &percent_encode(&value, QUERY_VALUE_CHARACTERS),
+                Some(value) => result += &percent_encode(&value, PATH_SEGMENT_CHARACTERS),
+                None => result += source,
+            },
+        }
+    }
+
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captures(values: &[(&str, serde_json::Value)]) -> Captures {
+        return values.iter().map(|(name, value)| (name.to_string(), value.clone())).collect();
+    }
+
+    #[test]
+    fn encodes_a_path_value_as_a_single_segment() {
+        let captures = captures(&[("id", "a/b?c#d%e".into())]);
+        assert_eq!(interpolate_route("users/{{id}}/posts", &captures), "users/a%2Fb%3Fc%23d%25e/posts");
+    }
+
+    #[test]
+    fn encodes_multibyte_characters_as_utf8_bytes() {
+        let captures = captures(&[("name", "Zoë 東".into())]);
+        assert_eq!(interpolate_route("users/{{name}}", &captures), "users/Zo%C3%AB%20%E6%9D%B1");
+        assert_eq!(interpolate_route("search?q={{name}}", &captures), "search?q=Zo%C3%AB%20%E6%9D%B1");
+    }
+
+    #[test]
+    fn keeps_the_characters_a_path_segment_allows() {
+        let captures = captures(&[("key", "a:b@c;d=e".into())]);
+        assert_eq!(interpolate_route("keys/{{key}}", &captures), "keys/a:b@c;d=e");
+    }
+
+    #[test]
+    fn encodes_values_after_the_question_mark_as_query_values() {
+        let captures = captures(&[("next", "/a?b=c&d#e%f".into())]);
+        assert_eq!(interpolate_route("login?next={{next}}&lang=en", &captures),
+            "login?next=/a?b%3Dc%26d%23e%25f&lang=en");
+    }
+
+    #[test]
+    fn encodes_values_after_a_fragment_like_query_values() {
+        let captures = captures(&[("part", "a b".into())]);
+        assert_eq!(interpolate_route("docs#{{part}}", &captures), "docs#a%20b");
+    }
+
+    #[test]
+    fn inserts_raw_values_as_they_are() {
+        let captures = captures(&[("path", "a/b?c=d#e%20".into())]);
+        assert_eq!(interpolate_route("files/{{path | raw}}", &captures), "files/a/b?c=d#e%20");
+        assert_eq!(interpolate_route("files/{{path|raw}}", &captures), "files/a/b?c=d#e%20");
+    }
+
+    #[test]
+    fn leaves_unknown_names_literal() {
+        let captures = captures(&[("id", "7".into())]);
+        assert_eq!(interpolate_route("users/{{missing}}/{{id}}", &captures), "users/{{missing}}/7");
+        assert_eq!(interpolate_route("users/{{ missing | raw }}", &captures), "users/{{ missing | raw }}");
+        assert_eq!(interpolate("id {{missing}}", &captures), "id {{missing}}");
+    }
+
+    #[test]
+    fn keeps_an_unterminated_placeholder_as_text() {
+        let captures = captures(&[("id", "7".into())]);
+        assert_eq!(interpolate_route("users/{{id}}/{{id", &captures), "users/7/{{id");
+    }
+
+    fn typed() -> Captures {
+        return captures(&[("count", 42.into()), ("price", 9.5.into()), ("active", false.into()),
+            ("deleted", serde_json::Value::Null), ("quote", "say \"hi\" it's".into())]);
+    }
+
+    #[test]
+    fn interpolates_typed_values_into_a_route_by_their_text() {
+        let captures = typed();
+        assert_eq!(interpolate_route("items/{{count}}?price={{price}}", &captures), "items/42?price=9.5");
+        assert_eq!(interpolate_route("users?active={{active}}&deleted={{deleted}}", &captures),
+            "users?active=false&deleted=null");
+        assert_eq!(interpolate_route("notes/{{quote}}", &captures), "notes/say%20%22hi%22%20it's");
+        assert_eq!(interpolate_route("notes?q={{quote}}", &captures), "notes?q=say%20%22hi%22%20it's");
+    }
+
+    #[test]
+    fn interpolates_typed_values_into_a_text_by_their_text() {
+        let captures = typed();
+        assert_eq!(interpolate("{{count}} {{price}} {{active}} {{deleted}} {{quote}}", &captures),
+            "42 9.5 false null say \"hi\" it's");
+        assert_eq!(text(&captures["quote"]), "say \"hi\" it's");
+        assert_eq!(text(&captures["deleted"]), "null");
+    }
+
+    #[test]
+    fn keeps_the_type_of_a_whole_placeholder_in_a_json_body() {
+        let body = serde_json::json!({
+            "count": "{{count}}", "price": "{{ price }}", "active": "{{active}}", "deleted": "{{deleted}}",
+            "quote": "{{quote}}", "items": ["{{count}}", {"flag": "{{active}}"}],
+        });
+        assert_eq!(interpolate_json(&body, &typed()), serde_json::json!({
+            "count": 42, "price": 9.5, "active": false, "deleted": null, "quote": "say \"hi\" it's",
+            "items": [42, {"flag": false}],
+        }));
+    }
+
+    #[test]
+    fn inserts_typed_values_inside_a_json_string_as_text() {
+        let body = serde_json::json!({"summary": "{{count}} at {{price}}, {{active}}/{{deleted}}: {{quote}}"});
+        let interpolated = interpolate_json(&body, &typed());
+        assert_eq!(interpolated, serde_json::json!({"summary": "42 at 9.5, false/null: say \"hi\" it's"}));
+        assert_eq!(serde_json::to_string(&interpolated).unwrap(),
+            r#"{"summary":"42 at 9.5, false/null: say \"hi\" it's"}"#);
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders_in_a_json_body() {
+        let body = serde_json::json!({"id": "{{missing}}", "count": 1});
+        assert_eq!(interpolate_json(&body, &typed()), body);
+    }
+
+    #[test]
+    fn sends_marked_placeholders_of_a_json_body_as_strings() {
+        let body = serde_json::json!({
+            "count": "{{count | string}}", "active": "{{ active|string }}", "raw": "{{count | raw}}",
+        });
+        assert_eq!(interpolate_json(&body, &typed()), serde_json::json!({"count": "42", "active": "false", "raw": 42}));
+        assert_eq!(interpolate("id {{count | string}}", &typed()), "id 42");
+    }
+}
@@ -1,12 +1,16 @@
-use std::collections::HashMap;
+#![allow(clippy::needless_return)]
+
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{io::Write, fs, path};
 use std::time::{Instant, Duration};
 
 use hyper::http::HeaderValue;
+use hyper::header::HeaderName;
 use serde::{Serialize, Deserialize};
-use hyper::{body::HttpBody as _, client};
-use hyper_tls::HttpsConnector;
+use hyper::body::HttpBody as _;
 use strum_macros::EnumIter;
 use strum::IntoEnumIterator;
 use colored::*;
@@ -19,6 +23,66 @@ use tokio::runtime::Runtime;
 
 mod utils;
 mod cli;
+mod digest;
+mod connect;
+mod middleware;
+mod cors;
+mod sse;
+mod websocket;
+mod idempotency;
+mod pagination;
+mod migrate;
+mod snapshot;
+mod transcript;
+mod matrix;
+mod fragments;
+mod trace;
+mod assertions;
+mod failure;
+mod cookies;
+mod retry;
+mod interpolation;
+mod condition;
+mod expression;
+mod validation;
+mod init;
+mod import;
+mod export;
+mod boundaries;
+mod dependency;
+mod resolve;
+mod shard;
+mod step;
+mod drift;
+mod charset;
+mod listing;
+mod report;
+mod github;
+mod notify;
+mod metrics;
+mod redirect;
+mod budget;
+mod flakiness;
+mod conditional;
+mod preflight;
+mod resume;
+mod record;
+mod wire;
+mod compression;
+mod capture;
+mod comparison;
+mod aggregate;
+mod forwarded;
+mod hosts;
+mod runid;
+mod severity;
+mod download;
+mod credential;
+mod ratelimit;
+#[cfg(feature = "scripting")]
+mod script;
+
+pub use middleware::{Middleware, TestContext};
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
@@ -34,32 +98,232 @@ enum HttpMethod {
     head,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 struct Endpoint {
+    name: Option<String>, // short unique name, used instead of the index in the output
+    extends: Option<utils::OneOrMany<String>>, // fragments of the defaults, merged in when the config is loaded
     it: Option<String>,
     critical: Option<bool>,
     route: String,
-    method: String,
-    status: u16,
-    json_body: Option<HashMap<String, String>>,
-    time_boundaries: Option<[u128; 3]>, // (green), yellow, red, timeout
+    #[serde(default)]
+    method: String, // defaults to OPTIONS for cors and GET for websocket tests
+    method_matrix: Option<matrix::MethodMatrix>, // expanded into one test per method when the config is loaded
+    status: Option<u16>,
+    expect_not_status: Option<utils::OneOrMany<assertions::StatusPattern>>,
+    expect_error: Option<String>, // timeout, connection_refused, dns or any
+    expect_array_length: Option<assertions::ArrayLength>,
+    require_json: Option<bool>, // a body that isnt json fails the test, defaults to true if json is captured or asserted
+    expect_valid_json: Option<bool>, // the body has to be json, the failure shows its Content-Type and start
+    expect_json_type: Option<assertions::JsonType>, // e.g. object or array at the top level, implies expect_valid_json
+    expect_array_contains: Option<assertions::ArrayContains>, // an element of a json array meeting all constraints
+    expect_body_equals_capture: Option<String>,
+    expect_expr: Option<Vec<String>>, // numeric comparisons over the captures, checked after the test stored its own
+    expect_body_file: Option<PathBuf>, // the expected body, relative to the config
+    compare: Option<String>, // how the expect_body_file is compared, json (the default), text or exact
+    interpolate: Option<bool>, // fills the placeholders of the expect_body_file with the captures
+    #[serde(skip)]
+    expected_body: Option<String>, // content of the expect_body_file, read when the config is loaded
+    expect_body_exact: Option<String>, // the body has to consist of exactly these bytes
+    expect_body: Option<serde_json::Value>, // the json body, keywords like $string or $timestamp match any such value
+    expect_cookies: Option<HashMap<String, cookies::CookieExpectation>>, // assertions on the Set-Cookie headers
+    #[serde(default, deserialize_with = "utils::deserialize_nullable")]
+    expect_headers_absent: Option<Option<Vec<String>>>, // added to the global list, null drops the global one
+    expect_headers: Option<HashMap<String, utils::OneOrMany<String>>>, // values every header has to contain
+    expect_location: Option<String>, // the exact Location header of a redirect, supports interpolation
+    follow_redirects: Option<bool>, // defaults to true if max_redirects or expect_redirect_chain is set
+    max_redirects: Option<u32>, // more redirects fail the test, defaults to 5
+    expect_redirect_chain: Option<Vec<String>>, // the urls the redirects lead through, supports interpolation
+    expect_content_disposition_filename: Option<String>, // pattern the filename of an attachment has to match
+    expect_encoding: Option<String>, // gzip, br, any or none, the Content-Encoding the response has to have
+    min_compression_ratio: Option<f64>, // the decoded body has to be at least this many times larger than on the wire
+    save_response: Option<download::SaveResponse>, // writes the body into a directory
+    cors: Option<cors::CorsPreflight>, // sends a preflight for the described request and checks the allow headers
+    conditional: Option<conditional::ConditionalRequest>, // sets If-None-Match and If-Modified-Since
+    sse: Option<bool>, // reads the response as a text/event-stream instead of waiting for the whole body
+    expect_events: Option<u32>, // sse only, stops reading once this many events arrived
+    max_wait_ms: Option<u64>, // sse only, stops reading after this long, defaults to the timeout
+    trace_id: Option<String>, // continues this trace instead of starting a new one, supports interpolation
+    scheme: Option<String>, // http or https, replaces the scheme of the api_address for this test
+    transfer_encoding: Option<String>, // chunked or length, how the body is framed, hyper decides per default
+    connect_to: Option<String>, // ip or ip:port to connect to instead, the url still decides the SNI and Host header
+    allowed_hosts: Option<Vec<String>>, // host patterns like *.staging.example.com, the test only runs against these
+    websocket: Option<websocket::WebSocketExchange>, // upgrades the route to a websocket and waits for a message
+    idempotency_check: Option<bool>, // sends the request a second time with the same Idempotency-Key and compares
+    idempotency_compare: Option<String>, // bytes (the default) or json
+    pagination_check: Option<pagination::PaginationCheck>, // requests the first pages and an oversized one and checks them
+    snapshot: Option<bool>, // compares the body with the file recorded by an earlier run
+    ignore_paths: Option<Vec<String>>, // json paths masked in the snapshot and the expect_body_file, * matches every key or element
+    when: Option<String>, // condition over the captures, the test is skipped if it evaluates to false
+    skip: Option<String>, // reason the test is always skipped for, e.g. a ticket, shown in the output and reports
+    xfail: Option<String>, // ticket of the known bug the test is expected to fail because of, it doesnt fail the run
+    pre_script: Option<String>, // sets captures before the request is built, needs the scripting feature
+    post_script: Option<String>, // checks the response and sets captures, needs the scripting feature
+    json: Option<serde_json::Value>, // request body with nested values, the keys are sent sorted
+    json_body: Option<BTreeMap<String, String>>, // deprecated flat form of json, every value is sent as a string
+    body: Option<String>, // raw request body, alternative to json
+    body_template: Option<PathBuf>, // file with the body, relative to the config, alternative to body and json
+    template_values: Option<HashMap<String, String>>, // fill the body_template, take precedence over the captures
+    #[serde(skip)]
+    template: Option<String>, // content of the body_template, read when the config is loaded
+    tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "utils::deserialize_text_map")]
+    meta: Option<HashMap<String, String>>, // free-form annotations like a requirement id, only carried into the reports
+    time_boundaries: Option<boundaries::TimeBoundariesConfig>, // ok, warn and timeout
+    timeout_ms: Option<u64>, // cancels the request, defaults to the timeout of the time_boundaries
+    fail_on: Option<String>, // yellow or red, responses of this time class or slower fail the test
+    min_time_ms: Option<u128>, // quicker responses fail the test, they hint at a stub or cache answering
+    max_dns_ms: Option<u64>, // limits of the single phases of the request
+    max_connect_ms: Option<u64>,
+    max_tls_ms: Option<u64>,
+    max_ttfb_ms: Option<u64>,
+    max_download_ms: Option<u64>,
+    severity: Option<severity::Severity>, // warn or error for all assertions, or per assertion like expect_headers: warn
     capture: Option<HashMap<String, String>>,
-    bearer_token: Option<String>,
-    session_id: Option<String>,
+    capture_namespace: Option<String>, // the captures are stored as <namespace>.<name>
+    capture_separator: Option<String>, // joins the values of a repeated header captured via $header:<name>[*]
+    #[serde(default, deserialize_with = "utils::deserialize_nullable")]
+    bearer_token: Option<Option<String>>, // capture key or literal token, null/none suppresses the global one
+    #[serde(default, deserialize_with = "utils::deserialize_nullable")]
+    session_id: Option<Option<String>>,
+    headers: Option<HashMap<String, Option<String>>>, // null values remove a global header
+    #[serde(default, deserialize_with = "utils::deserialize_nullable")]
+    forwarded_for: Option<Option<String>>, // client ip or chain of ips a gateway forwards, null drops the global one
+    digest_auth: Option<digest::DigestCredentials>,
     auto_description: Option<bool>,
     verbose: Option<bool>,
-    repeat: Option<u32>,
+    repeat: Option<u32>, // sends the request this many times, each repetition counts as a test
+    retries: Option<u32>, // additional attempts of a failed request, only on the failures given by retry_on
+    retry_on: Option<Vec<String>>, // timeout, connection, status_mismatch or a status class, defaults to the first two
+    retry_delay_ms: Option<u64>,
     parallel: Option<u32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 struct Config {
     api_address: String,
     verbose: Option<bool>,
+    prefix_test_output: Option<bool>, // puts the name or index of the test in front of each of its log lines
     tests: Vec<Endpoint>,
-    time_boundaries: Option<[u128; 3]>, // (green), yellow, red, timeout
+    allow_empty: Option<bool>, // an empty test list is an error unless this is set, usually it is a mistake
+    // Named fragments of test fields the tests can extend, except method, status, verbose and time_boundaries, which
+    // apply to every test that leaves them out
+    defaults: Option<HashMap<String, serde_yaml::Value>>,
+    time_boundaries: Option<boundaries::TimeBoundariesConfig>, // ok, warn and timeout
+    timeout_ms: Option<u64>, // of the tests without their own one
     caption_path: Option<Vec<String>>,
     to_file: Option<PathBuf>,
+    log_file: Option<PathBuf>, // exact path of the log file, takes precedence over to_file
+    append: Option<bool>, // append runs to the log_file instead of overwriting it
+    log_level: Option<String>, // error, info or debug, what the log file records regardless of verbose
+    bearer_token: Option<String>,
+    session_id: Option<String>,
+    headers: Option<HashMap<String, String>>,
+    forwarded_for: Option<String>, // client ip of every test, e.g. to keep the keys of a rate limiter stable
+    expect_headers_absent: Option<Vec<String>>, // header names or "Name: value" no response may contain
+    max_failures: Option<u32>,
+    yellow_is_warning: Option<bool>, // responses slower than the first time boundary count as warnings
+    max_duration_s: Option<u64>, // time budget of the whole run, tests that would start later are skipped
+    max_printed_body_bytes: Option<usize>, // verbose body output is cut off after this many bytes
+    suppress_failure_bodies: Option<bool>, // the body of a failed test is only printed with verbose output
+    transcripts: Option<PathBuf>, // directory that receives the exchanged requests and responses of every test
+    drift: Option<drift::DriftConfig>, // what --compare-with compares with the recorded responses
+    allowed_hosts: Option<Vec<String>>, // of the tests without their own ones
+    enforce_allowed_hosts: Option<String>, // skip (the default) skips a test on another host, strict aborts the run
+    compare_against: Option<String>, // second base url every test is also sent to, the responses are compared
+    comparison: Option<drift::DriftConfig>, // what is compared with the responses of compare_against
+    comparison_severity: Option<severity::Level>, // differences fail the test unless they are warnings
+    strict_xfail: Option<bool>, // a test marked xfail that passes fails the run, e.g. to notice fixed bugs
+    variables: Option<HashMap<String, String>>, // seed the captures before the first test
+    notify: Option<notify::NotifyConfig>, // webhook that receives a summary after the run
+    metrics: Option<metrics::MetricsConfig>, // pushgateway that receives the metrics of the run
+    budgets: Option<budget::BudgetsConfig>, // limits on the whole run, checked after the last test
+    tracing: Option<trace::TracingConfig>, // sends a trace id with every test
+    rate_limit: Option<ratelimit::RateLimitConfig>, // requests per second across all tests
+    #[serde(skip)]
+    source: Option<PathBuf>, // the file the config was read from
+    #[serde(skip)]
+    test_lines: Vec<usize>, // line of every test inside the source, used for annotations
+    #[serde(skip)]
+    deprecations: Vec<migrate::Change>, // deprecated forms inside the source, the run prints a hint for each
+    #[serde(skip)]
+    warnings: Vec<String>, // findings of the validation that dont stop the run, like captures nobody reads
+}
+
+// Run settings that are not part of the config file, usually supplied via the command line
+#[derive(Debug, Default)]
+pub struct RunOptions {
+    pub max_failures: Option<u32>,
+    pub max_duration_s: Option<u64>,
+    pub fail_fast: bool,
+    pub no_critical: bool,
+    pub no_preflight: bool, // skip the connection check of the api_address before the first test
+    pub strict_captures: bool, // unused captures and dangling credentials fail the validation
+    pub warn_capture_overwrite: bool, // warn when a test overwrites the capture of another test
+    pub continue_on_critical: bool, // a critical failure only skips the tests depending on it
+    pub indices: Option<Vec<usize>>, // one based, only these tests and their capture providers are run
+    pub shard: Option<(usize, usize)>, // one based shard and the number of shards, only its tests are run
+    pub meta_filters: Vec<(String, String)>, // only the tests whose meta has all of these values are run
+    pub defines: Vec<(String, String)>, // variables of the command line, they beat the ones of the config
+    pub verbose: bool,
+    pub timeout_ms: Option<u128>,
+    pub repeat: Option<u32>,
+    pub runs: Option<u32>, // runs the whole suite this many times to find flaky tests
+    pub log_file: Option<PathBuf>,
+    pub report_json: Option<PathBuf>,
+    pub report_html: Option<PathBuf>,
+    pub metrics_file: Option<PathBuf>,
+    pub github: bool,
+    pub warnings_as_errors: bool,
+    pub update_snapshots: bool,
+    pub raw_body: bool, // print bodies as they are instead of pretty-printing json
+    pub step: bool, // pause after every test until the user continues
+    pub transcripts: Option<PathBuf>,
+    pub keep_transcripts: bool, // keep the transcripts of earlier runs inside the directory
+    pub compare_with: Option<PathBuf>, // transcripts of an earlier run the responses are compared with
+    pub handle_interrupts: bool, // finish the current test and write the reports on Ctrl-C
+    pub resume: Option<PathBuf>, // state file that carries the progress across invocations
+    pub record_expectations: Option<PathBuf>, // copy of the config with the expectations observed during the run
+    pub trace_http: bool, // print the request and response heads and the request body of every exchange to stderr
+    pub show_captures: bool, // print every capture with its producer and consumers after the run
+    pub no_aggregate_errors: bool, // log every failure reason in full, even if many tests share it
+}
+
+// Overall result of a test run
+#[derive(Debug, PartialEq)]
+pub enum RunOutcome {
+    Passed,
+    Failed,
+    Aborted,
+    ConfigError,
+    Interrupted, // stopped by Ctrl-C
+    Truncated, // the time budget of the run was exceeded before all tests ran
+    Offline, // the host of the api_address didnt accept a connection before the first test
+}
+
+impl RunOutcome {
+    // The process exit code representing the outcome
+    pub fn exit_code(&self) -> i32 {
+        return match self {
+            RunOutcome::Passed => 0,
+            RunOutcome::Failed => 1,
+            RunOutcome::Aborted => 2,
+            RunOutcome::ConfigError => 3,
+            RunOutcome::Truncated => 5,
+            RunOutcome::Offline => 6,
+            RunOutcome::Interrupted => 130, // the convention for SIGINT
+        };
+    }
+}
+
+// Origin of a credential that gets attached to a request
+#[derive(strum_macros::Display, Clone, Copy, PartialEq)]
+enum CredentialSource {
+    #[strum(serialize = "captured value")]
+    Capture,
+    #[strum(serialize = "test literal")]
+    Literal,
+    #[strum(serialize = "global config")]
+    Global,
 }
 
 struct TestRequest<'a> {
@@ -68,28 +332,86 @@ struct TestRequest<'a> {
     verbose: bool,
     timeout: u128,
     body: String,
+    transfer_encoding: Option<String>,
+    max_redirects: Option<u32>, // redirects are only followed if this is set
+    redirect_chain: &'a mut Vec<String>, // the urls the followed redirects lead to
     response_time: &'a mut u128,
     buffer: &'a mut bytes::BytesMut,
+    wire_body: &'a mut Option<bytes::Bytes>, // the body as it was received if the buffer holds it transcoded to utf-8
+    body_size: &'a mut Option<compression::BodySize>, // the buffer holds the body without its Content-Encoding
     bearer_token: Option<String>,
+    session_id: Option<String>,
+    headers: BTreeMap<String, String>,
+    digest_auth: Option<&'a digest::DigestCredentials>,
     cookie_jar: &'a CookieJar,
+    sse: Option<sse::SseOptions>,
+    event_stream: &'a mut Option<sse::EventStream>,
+    websocket: Option<websocket::WebSocketRequest>,
+    websocket_outcome: &'a mut Option<websocket::Outcome>,
+    raw_body: bool,
+    max_printed_body_bytes: usize,
+    connect_to: Option<std::net::SocketAddr>,
+    sent_headers: &'a mut hyper::HeaderMap, // the headers of the last request that was sent
+    phase_timings: &'a mut Option<connect::PhaseTimings>,
+    middleware: &'a [Box<dyn Middleware>],
+    rate_limiter: Option<&'a ratelimit::RateLimiter>,
+    trace_http: bool, // prints every exchange as it goes over the wire to stderr
+    context: TestContext,
     //iterations: u32,
     //parallel: bool,
 }
 
-// Get the number of files matching a certain pattern inside a directory
-fn get_file_iteration(directory: path::PathBuf, pattern: &String) -> Result<usize> {
-    // Get all files in directory matching the pattern
-    return Ok(fs::read_dir(directory)?
-        .into_iter()
-        .filter(|file| file.is_ok())
-        .map(|file| file.unwrap().path()) // safe unwrap call inside Ok
+// The response of a test, put aside while the same request sends further ones like a replay or the pages
+struct SavedResponse {
+    body: bytes::BytesMut,
+    response_time: u128,
+    phase_timings: Option<connect::PhaseTimings>,
+    wire_body: Option<bytes::Bytes>,
+    body_size: Option<compression::BodySize>,
+    redirect_chain: Vec<String>,
+}
+
+impl TestRequest<'_> {
+    // Takes the response of the test out of the request, so the request can be sent again
+    fn save_response(&mut self) -> SavedResponse {
+        return SavedResponse {
+            body: self.buffer.split(),
+            response_time: *self.response_time,
+            phase_timings: *self.phase_timings,
+            wire_body: self.wire_body.take(),
+            body_size: self.body_size.take(),
+            redirect_chain: self.redirect_chain.clone(),
+        };
+    }
+
+    // Makes the saved response the one of the test again
+    fn restore_response(&mut self, saved: SavedResponse) {
+        *self.buffer = saved.body;
+        *self.response_time = saved.response_time;
+        *self.phase_timings = saved.phase_timings;
+        *self.wire_body = saved.wire_body;
+        *self.body_size = saved.body_size;
+        *self.redirect_chain = saved.redirect_chain;
+    }
+}
+
+// Get the iteration following the highest one of the files named <prefix><iteration>.log inside a directory
+fn get_file_iteration(directory: &path::Path, prefix: &str) -> Result<usize> {
+    let highest = fs::read_dir(directory)?
+        .filter_map(|file| file.ok())
+        .map(|file| file.path())
         .filter(|file| file.is_file())
-        .filter(|file| file.to_str().unwrap_or_default().contains(pattern))
-        .count());
+        .filter_map(|file| file.file_name()?.to_str()?
+            .strip_prefix(prefix)?
+            .strip_suffix(".log")?
+            .parse::<usize>().ok())
+        .max();
+
+    return Ok(highest.map_or(0, |iteration| iteration + 1));
 }
 
-// Creates the name of the logfile based on the current time
-fn construct_logfile_name(directory: PathBuf) -> Result<String> {
+// Creates the path of the logfile inside the directory based on the current time
+fn construct_logfile_name(directory: &path::Path) -> Result<PathBuf> {
     // rrt-YEAR-MONTH-DAY-ITERATOR.log
     // rrt-23-07-01-00.log
     // rrt-23-07-01-01.log etc
@@ -107,31 +429,29 @@ fn construct_logfile_name(directory: PathBuf) -> Result<String> {
     let iterations = get_file_iteration(directory, &date_filename)?;
 
     // Prepend 0 on single digit itertions counts
-    let iteration_string = if utils::get_num_digits(iterations, 10usize) < 10 {
+    let iteration_string = if utils::get_num_digits(iterations, 10usize) < 2 {
         "0".to_string() + &iterations.to_string()
     } else {
         iterations.to_string()
     };
 
-    return Ok(date_filename + &iteration_string + ".log");
+    return Ok(directory.join(date_filename + &iteration_string + ".log"));
 }
 
 // Handler for post-tests logfile creation
-fn write_logfile(log_buffer: Option<String>, directory: PathBuf) {
+fn write_logfile(log_buffer: Option<String>, destination: resolve::LogDestination) {
     if log_buffer.is_some() {
-        let filename = construct_logfile_name(directory);
-        let filename_ref;
-
-        let file_path = path::Path::new(match filename{
-            Ok(name) => {
-                filename_ref = name;
-                &filename_ref
+        let (file_path, append) = match destination {
+            resolve::LogDestination::Directory(directory) => match construct_logfile_name(&directory) {
+                Ok(path) => (path, false),
+                Err(error) => {
+                    println!("Error while retrieving path to logfile: {}", error);
+                    return;
+                }
             },
-            Err(error) => {
-                println!("Error while retrieving path to logfile: {}", error);
-                return;
-            }
-        });
+            resolve::LogDestination::File { path, append } => (path, append),
+        };
+        let file_path = file_path.as_path();
 
         let display = file_path.display();
 
@@ -140,8 +460,15 @@ fn write_logfile(log_buffer: Option<String>, directory: PathBuf) {
             None => "Log Buffer got corrupted.".to_string(),
         };
 
+        // Runs appended to an existing log are separated by a line with the start time
+        let mut separator = String::new();
+        if append && file_path.metadata().is_ok_and(|metadata| metadata.len() > 0) {
+            separator = format!("\n---------- {} ----------\n\n", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"));
+        }
+
         // Open a file in write-only mode, creates file if nonexistant
-        let mut file = match fs::File::create(file_path) {
+        let file = fs::OpenOptions::new().write(true).create(true).append(append).truncate(!append).open(file_path);
+        let mut file = match file {
             Err(error) => panic!("Couldn't create {}: {}", display, error),
             Ok(file) => file,
         };
@@ -157,28 +484,153 @@ fn write_logfile(log_buffer: Option<String>, directory: PathBuf) {
         };
 
         // Write log buffer to the file
-        match file.write_all(&stripped_buffer) {
+        match file.write_all(separator.as_bytes()).and_then(|_| file.write_all(&stripped_buffer)) {
             Err(error) => panic!("Couldn't write to {}: {}", display, error),
             Ok(_) => println!("Successfully wrote to {}", display),
         }
     }
 }
 
+// Collects the log for the log file, the prefix marks the lines of the running test
+#[derive(Default)]
+struct LogBuffer {
+    text: Option<String>, // only Some if a log destination is specified
+    prefix: Option<String>,
+    level: resolve::LogLevel, // entries above it are only printed
+}
+
+// Puts the prefix in front of every line that isnt empty
+fn prefix_lines(text: &str, prefix: &str) -> String {
+    return text.split('\n')
+        .map(|line| if line.is_empty() { String::new() } else { format!("{}{}", prefix, line) })
+        .collect::<Vec<String>>()
+        .join("\n");
+}
+
+// Prints the entry if print is true, the log file records it if the log level includes it, no matter the console
+fn write_log(level: resolve::LogLevel, formatted_string: String, print: bool, log_buffer: &mut LogBuffer /*IN-OUT*/) {
+    let formatted_string = match &log_buffer.prefix {
+        Some(prefix) => prefix_lines(&formatted_string, prefix),
+        None => formatted_string,
+    };
+
+    printif!(print, "{}", formatted_string);
+    if let Some(buffer) = &mut log_buffer.text {
+        if level <= log_buffer.level {
+            *buffer += &formatted_string;
+        }
+    };
+}
+
 // Logging handler, prints formatted_string if print_condition is true
-fn log(formatted_string: String, print_condition: Option<bool>, log_buffer: &mut Option<String> /*IN-OUT*/) {
+fn log(formatted_string: String, print_condition: Option<bool>, log_buffer: &mut LogBuffer /*IN-OUT*/) {
     if let Some(condition) = print_condition {
-        printif!(condition, "{}", formatted_string);
-        if let Some(buffer) = log_buffer { 
-            if condition {
-                *buffer += &formatted_string;
-            }
-        };
+        write_log(resolve::LogLevel::Info, formatted_string, condition, log_buffer);
     };
 }
 
+// Details only printed in verbose mode, the log file keeps them at the debug level
+fn log_debug(formatted_string: String, verbose: bool, log_buffer: &mut LogBuffer /*IN-OUT*/) {
+    write_log(resolve::LogLevel::Debug, formatted_string, verbose, log_buffer);
+}
+
+// Errors are always printed and always part of the log file
+fn log_error(formatted_string: String, log_buffer: &mut LogBuffer /*IN-OUT*/) {
+    write_log(resolve::LogLevel::Error, formatted_string, true, log_buffer);
+}
+
 // Generates a generic test case description
-fn generate_description(status: u16, method: String, route: String) -> String {
-    return format!("gets a Status {} when sending a {} request to the {} route.", status, method, route);
+fn generate_description(test: &Endpoint) -> String {
+    if test.websocket.is_some() {
+        return format!("upgrades the {} route to a websocket and receives a message.", test.route);
+    }
+
+    if let Some(preflight) = &test.cors {
+        return format!("allows {} requests from {} to the {} route in a CORS preflight.",
+         preflight.request_method.to_uppercase(), preflight.origin, test.route);
+    }
+
+    if let Some(expected) = &test.expect_error {
+        let category = if expected == "any" { "transport".to_string() } else { expected.replace('_', " ") };
+        return format!("fails with a {} error when sending a {} request to the {} route.",
+         category, test.method, test.route);
+    }
+
+    return format!("gets a Status {} when sending a {} request to the {} route.",
+     describe_expected_status(test), test.method, test.route);
+}
+
+// Prints the warnings of the validation to stderr, so the config or the test list on stdout stays parseable
+fn print_warnings(config: &Config) {
+    for warning in config.warnings.iter() {
+        eprintln!("{}", format!("Warning: {}", warning).yellow());
+    }
+}
+
+// Refers to a test inside messages by its one based index and its name, e.g. "3" or "3 (login)"
+fn test_reference(test: &Endpoint, index: usize) -> String {
+    return match &test.name {
+        Some(name) => format!("{} ({})", index, name),
+        None => index.to_string(),
+    };
+}
+
+// Whether the meta of a test has every one of the values, without filters every test matches
+fn matches_meta(test: &Endpoint, filters: &[(String, String)]) -> bool {
+    return filters.iter().all(|(key, value)| test.meta.as_ref().and_then(|meta| meta.get(key)) == Some(value));
+}
+
+// Describes the status expectations of a test, e.g. "200" or "not in 2xx, 301"
+fn describe_expected_status(test: &Endpoint) -> String {
+    let mut description = test.status.map(|status| status.to_string()).unwrap_or_default();
+
+    if let Some(not_status) = &test.expect_not_status {
+        if !description.is_empty() {
+            description += " and ";
+        }
+        description += &format!("not in {}", assertions::describe_patterns(not_status.as_slice()));
+    }
+
+    return description;
+}
+
+// Marks the credential of a test as a literal value, so it is never mistaken for the name of a capture
+const LITERAL_PREFIX: &str = "$literal:";
+
+// Resolves a credential with the precedence: explicit literal > capture reference > test literal > global default.
+// An explicit null or 'none' on the test suppresses the global default.
+fn resolve_credential(local: &Option<Option<String>>, global: &Option<String>,
+     captures: &interpolation::Captures) -> Option<(String, CredentialSource)> {
+    return match local {
+        Some(Some(value)) if value.to_lowercase() == "none" => None,
+        Some(Some(value)) => match (value.strip_prefix(LITERAL_PREFIX), captures.get(value)) {
+            (Some(literal), _) => Some((literal.to_string(), CredentialSource::Literal)),
+            (None, Some(captured)) => Some((interpolation::text(captured).into_owned(), CredentialSource::Capture)),
+            (None, None) => Some((value.clone(), CredentialSource::Literal)),
+        },
+        Some(None) => None,
+        None => global.clone().map(|value| (value, CredentialSource::Global)),
+    };
+}
+
+// Merges the global default headers with the test's own, where a null test value removes the header.
+// Sorted by name, so the headers are sent and logged in the same order on every run.
+fn resolve_headers(local: &Option<HashMap<String, Option<String>>>,
+     global: &Option<HashMap<String, String>>) -> BTreeMap<String, String> {
+    let mut headers: BTreeMap<String, String> = global.iter().flatten()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+
+    if let Some(local_headers) = local {
+        for (key, value) in local_headers.iter() {
+            match value {
+                Some(value) => headers.insert(key.clone(), value.clone()),
+                None => headers.remove(key),
+            };
+        }
+    }
+
+    return headers;
 }
 
 // Reads in the config file
@@ -197,388 +649,2895 @@ pub fn get_config_file() -> path::PathBuf {
     }
 }
 
-// Checks if a given method matched one of HttpMethod
-fn validate_http_method(method: &String) -> Option<HttpMethod> {
-    return HttpMethod::iter().find(|http_method|
-         http_method.to_string() == method.to_string().to_lowercase());
+// Reads, parses and validates the config file, returns the error messages on failure
+fn load_config(config_file: &path::Path, options: &RunOptions) -> std::result::Result<Config, Vec<String>> {
+    let content = fs::read_to_string(config_file)
+        .map_err(|error| vec![format!("Error while trying to open config file: {}", error)])?;
+
+    let mut document: serde_yaml::Value = serde_yaml::from_str(&content)
+        .map_err(|error| vec![format!("Error while parsing config file: {}", error)])?;
+
+    // Parsing the text again keeps the line numbers of the errors, which the merged document doesnt have
+    let extended = fragments::apply(&mut document).map_err(|errors| errors.iter()
+        .map(|error| format!("Error while validating config file: {}", error))
+        .collect::<Vec<String>>())?;
+    let parsed = if extended { serde_yaml::from_value(document) } else { serde_yaml::from_str(&content) };
+    let mut config: Config = parsed
+        .map_err(|error| vec![format!("Error while parsing config file: {}", error)])?;
+
+    // A cors test always sends a preflight and a websocket handshake is a GET, so their method can be left out
+    for test in config.tests.iter_mut().filter(|test| test.method.is_empty()) {
+        if test.cors.is_some() {
+            test.method = "OPTIONS".to_string();
+        } else if test.websocket.is_some() {
+            test.method = "GET".to_string();
+        }
+    }
+
+    // The defaults of the config only fill what the test and its special cases above leave out
+    let test_defaults = fragments::test_defaults(&config.defaults)
+        .map_err(|error| vec![format!("Error while parsing config file: {}", error)])?;
+    fragments::apply_defaults(&mut config.tests, &test_defaults);
+
+    // Body templates and expected bodies are read up front, so they can be validated before any request is sent
+    let config_directory = config_file.parent().unwrap_or(path::Path::new("."));
+    let mut file_errors = Vec::new();
+    for (index, test) in config.tests.iter_mut().enumerate() {
+        if let Some(template_file) = &test.body_template {
+            match fs::read_to_string(config_directory.join(template_file)) {
+                Ok(template) => test.template = Some(template),
+                Err(error) => file_errors.push(format!("Error while reading the body_template '{}' of test {}: {}",
+                 template_file.display(), index + 1, error)),
+            }
+        }
+        if let Some(body_file) = &test.expect_body_file {
+            match fs::read_to_string(config_directory.join(body_file)) {
+                Ok(expected) => test.expected_body = Some(expected),
+                Err(error) => file_errors.push(format!("Error while reading the expect_body_file '{}' of test {}: {}",
+                 body_file.display(), index + 1, error)),
+            }
+        }
+    }
+    if !file_errors.is_empty() {
+        return Err(file_errors);
+    }
+
+    config.source = Some(config_file.to_path_buf());
+    config.test_lines = github::locate_tests(&content);
+
+    // Everything after the expansion, like the selection, the counter and the reports, sees the expanded tests
+    let (tests, test_lines) = matrix::expand(std::mem::take(&mut config.tests), &config.test_lines)
+        .map_err(|errors| errors.iter()
+            .map(|error| format!("Error while validating config file: {}", error))
+            .collect::<Vec<String>>())?;
+    config.tests = tests;
+    config.test_lines = test_lines;
+
+    config.warnings = validation::validate_config(&config, options).map_err(|errors| errors.iter()
+        .map(|error| format!("Error while validating config file: {}", error))
+        .collect::<Vec<String>>())?;
+
+    let variables: HashSet<String> = config.variables.iter().flat_map(|variables| variables.keys().cloned()).collect();
+    config.deprecations = migrate::plan(&content, &config, &variables);
+
+    return Ok(config);
 }
 
-// Parse the response body as long as its not empty and (probably) a json
-fn parse_json_response(response_buffer: bytes::BytesMut, captures: &mut HashMap<String, String>,
-     test: &Endpoint, log_buffer: &mut Option<String>) {
+// Constructs the request body, either as json from json or json_body, the raw body as it is or the filled body template
+fn construct_body(test: &Endpoint, captures: &interpolation::Captures) -> String {
+    if let Some(raw_body) = &test.body {
+        return interpolation::interpolate(raw_body, captures);
+    }
 
-    if !response_buffer.is_empty() && response_buffer[0] == b'{' {
-        let json_body: serde_json::Value = match serde_json::from_str(&String::from_utf8_lossy(&response_buffer)) {
-                Ok(value) => value,
-                Err(error) => {
-                    log(format!("Error while parsing response body as json: {}\n", error),
-                        Some(true), log_buffer);
-                    return;
-                },
-        };
-    
-        // Capture desired values from the response body
-        match &test.capture {
-            Some(capture) => {
-                for (key, value) in capture.iter() {
-                    let captured_value = &json_body[value];
-                    if !captured_value.is_null() {
-                        let mut string_captured = json_body[value].to_string();
-
-                        // Remove Double Quotes
-                        string_captured.pop();
-                        if !string_captured.is_empty() {
-                            string_captured.remove(0);
-                        }
+    if let Some(json) = &test.json {
+        return interpolation::interpolate_json(json, captures).to_string();
+    }
 
-                        captures.insert(key.to_string(), string_captured);
-                    } else {
-                        println!("Error: Cannot capture nonexistent value '{}'", value.bold());
-                    }
-                }
-            },
-            None => (),
+    if let Some(template) = &test.template {
+        let mut values = captures.clone();
+        for (name, value) in test.template_values.iter().flatten() {
+            values.insert(name.clone(), serde_json::Value::from(interpolation::interpolate(value, captures)));
         }
+        return interpolation::interpolate(template, &values);
+    }
+
+    let value_map = match &test.json_body {
+        Some(value_map) => value_map,
+        None => return String::new(),
+    };
+
+    let mut body: String = String::from("{");
+    for (key, val) in value_map.iter() {
+        let val = interpolation::interpolate(val, captures);
+        body += &format!("{}:{},", serde_json::Value::from(key.as_str()), serde_json::Value::from(val));
+    }
+
+    // json doesnt allow a comma after the last key-value pair
+    if body.ends_with(',') {
+        body.pop();
     }
+
+    body += "}";
+    return body;
 }
 
-// Sends the request and returns the awaited response
-async fn send_request(test_request: &mut TestRequest<'_>, 
-     client: hyper::Client<HttpsConnector<client::HttpConnector>>, request: hyper::Request<hyper::Body>)
-     -> Result<hyper::Response<hyper::Body>> {
+// Maps local http methods to the ones used by hyper
+fn to_hyper_method(method: &HttpMethod) -> hyper::Method {
+    return match method {
+        HttpMethod::get => hyper::Method::GET,
+        HttpMethod::post => hyper::Method::POST,
+        HttpMethod::put => hyper::Method::PUT,
+        HttpMethod::patch => hyper::Method::PATCH,
+        HttpMethod::delete => hyper::Method::DELETE,
+        HttpMethod::head => hyper::Method::HEAD,
+        HttpMethod::options => hyper::Method::OPTIONS,
+    };
+}
 
-    let future_response = client.request(request);
+// Executes a subcommand or --list if one was given, returns its exit code
+pub async fn run_subcommand() -> Option<i32> {
+    let args = cli::Args::parse();
 
-    let now = Instant::now();
+    return match args.command {
+        Some(cli::Command::Init { force, url }) => {
+            let api_address = url.unwrap_or(init::DEFAULT_API_ADDRESS.to_string());
+            match init::write_starter_config(&utils::get_cwd(), &api_address, force) {
+                Ok(path) => {
+                    println!("Created {}", path.display());
+                    Some(0)
+                },
+                Err(error) => {
+                    println!("Error while creating the starter config: {}", error);
+                    Some(1)
+                },
+            }
+        },
+        Some(cli::Command::Import { source }) => {
+            let (generated, out, force) = match source {
+                cli::ImportSource::Openapi { spec, out, force } => (import::openapi::import(&spec), out, force),
+                cli::ImportSource::Postman { collection, out, force } =>
+                    (import::postman::import(&collection), out, force),
+                cli::ImportSource::Har { file, host, keep_cookies, out, force } =>
+                    (import::har::import(&file, host.as_ref(), keep_cookies), out, force),
+            };
 
-    let response = match tokio::time::timeout(Duration::from_millis(test_request.timeout.try_into().unwrap()),
-        future_response).await {
-        Ok(result) => match result {
-            Ok(res) => res,
-            Err(e) => return Err(Box::new(e)),
+            match generated.and_then(|config| {
+                import::write_generated_config(&config, &out, force)?;
+                Ok(config.tests.len())
+            }) {
+                Ok(test_count) => {
+                    println!("Wrote {} tests to {}", test_count, out.display());
+                    Some(0)
+                },
+                Err(error) => {
+                    println!("Error while importing: {}", error);
+                    Some(1)
+                },
+            }
         },
-        Err(_) => return Err("Request timed out.".into()),
-    };
+        Some(cli::Command::Export { format, out, force }) => {
+            let config_file = get_config_file();
+            let config = match load_config(&config_file, &get_run_options()) {
+                Ok(config) => config,
+                Err(errors) => {
+                    for error in errors.iter() {
+                        println!("{}", error);
+                    }
+                    return Some(RunOutcome::ConfigError.exit_code());
+                },
+            };
 
-    *test_request.response_time = now.elapsed().as_millis();
+            let name = config_file.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let exported = export::export_config(&config, &name);
+            let content = match format {
+                cli::ExportFormat::Curl => export::curl::render(&exported),
+                cli::ExportFormat::Postman => export::postman::render(&exported),
+            };
 
-    return Ok(response);
-}
+            match export::write_export(&content, &out, force) {
+                Ok(()) => {
+                    println!("Exported {} tests to {}", exported.requests.len(), out.display());
+                    Some(0)
+                },
+                Err(error) => {
+                    println!("Error while exporting: {}", error);
+                    Some(1)
+                },
+            }
+        },
+        Some(cli::Command::Migrate { write }) => {
+            let config_file = get_config_file();
+            let config = match load_config(&config_file, &get_run_options()) {
+                Ok(config) => config,
+                Err(errors) => {
+                    for error in errors.iter() {
+                        println!("{}", error);
+                    }
+                    return Some(RunOutcome::ConfigError.exit_code());
+                },
+            };
+            let content = match fs::read_to_string(&config_file) {
+                Ok(content) => content,
+                Err(error) => {
+                    println!("Error while trying to open config file: {}", error);
+                    return Some(RunOutcome::ConfigError.exit_code());
+                },
+            };
 
-// Builds a new request and sends it to the target
-async fn fetch_url(test_request: &mut TestRequest<'_>, log_buffer: &mut Option<String> /*IN-OUT*/)
-     -> Result<hyper::Response<hyper::Body>> {
-     
-    // TLS implementation to enable https requests
-    let https = HttpsConnector::new();
+            // Variables defined on the command line make credentials of the same name refer to them, too
+            let variables: HashSet<String> = config.variables.iter().flat_map(|variables| variables.keys().cloned())
+                .chain(args.defines.iter().map(|(name, _)| name.clone()))
+                .collect();
+            let changes = migrate::plan(&content, &config, &variables);
+            if changes.is_empty() {
+                println!("{} uses no deprecated forms.", config_file.display());
+                return Some(0);
+            }
+            if !write {
+                print!("{}", migrate::diff(&changes));
+                println!("{} changes, run with --write to apply them.", changes.len());
+                return Some(0);
+            }
 
-    // map local http methods to the ones used by hyper
-    let mut req_builder = hyper::Request::builder()
-        .method(match test_request.method {
-            HttpMethod::get => hyper::Method::GET,
-            HttpMethod::post => hyper::Method::POST,
-            HttpMethod::put => hyper::Method::PUT,
-            HttpMethod::patch => hyper::Method::PATCH,
-            HttpMethod::delete => hyper::Method::DELETE,
-            HttpMethod::head => hyper::Method::HEAD,
-            HttpMethod::options => hyper::Method::OPTIONS,
-        })
-        .uri(test_request.url);
+            match migrate::write_migrated(&config_file, &migrate::apply(&content, &changes)) {
+                Ok(()) => {
+                    print!("{}", migrate::summary(&changes));
+                    println!("Migrated {} with {} changes.", config_file.display(), changes.len());
+                    Some(0)
+                },
+                Err(error) => {
+                    println!("Error while writing the migrated config: {}", error);
+                    Some(1)
+                },
+            }
+        },
+        Some(cli::Command::Check { url, method, status, max_time, header, body, bearer_env }) => {
+            let bearer_token = match bearer_env.map(|variable| std::env::var(&variable).map_err(|_| variable)) {
+                Some(Ok(token)) => Some(Some(token)),
+                Some(Err(variable)) => {
+                    println!("Error while reading the bearer token: the environment variable {} is not set", variable);
+                    return Some(RunOutcome::ConfigError.exit_code());
+                },
+                None => None,
+            };
 
-    match &test_request.bearer_token {
-        Some(token) => {
-            let mut composed_token = String::from("");
-            composed_token += "Bearer ";
-            log(format!("Bearer Token: {}\n", token), Some(test_request.verbose), log_buffer);
-            composed_token += &token.clone();
+            // A single test against the given url, so all the assertions of a config apply
+            let test = Endpoint {
+                it: Some(format!("responds with status {} to {} {}", status, method.to_uppercase(), url)),
+                route: String::new(),
+                method,
+                status: Some(status),
+                body,
+                headers: (!header.is_empty()).then(|| header.into_iter().map(|(key, value)| (key, Some(value))).collect()),
+                bearer_token,
+                time_boundaries: max_time.map(|max_time| boundaries::TimeBoundariesConfig::Named {
+                    ok: (max_time / 2).min(500),
+                    warn: (max_time * 3 / 4).min(1000),
+                    timeout: Some(max_time),
+                }),
+                ..Default::default()
+            };
+            let config = Config { api_address: url, tests: vec![test], ..Default::default() };
 
-            if let Some(map) = req_builder.headers_mut() {
-                log(format!("Composed token: {}\n",
-                 composed_token), Some(test_request.verbose), log_buffer);
-                map.insert("Authorization", composed_token.parse::<HeaderValue>()?);
+            let options = get_run_options();
+            if let Err(errors) = validation::validate_config(&config, &options) {
+                for error in errors.iter() {
+                    println!("Error while validating the check: {}", error);
+                }
+                return Some(RunOutcome::ConfigError.exit_code());
+            }
+
+            Some(run_tests(config, options, &[]).await.exit_code())
+        },
+        None if args.print_config => {
+            let options = get_run_options();
+            let config = match load_config(&get_config_file(), &options) {
+                Ok(config) => config,
+                Err(errors) => {
+                    for error in errors.iter() {
+                        println!("{}", error);
+                    }
+                    return Some(RunOutcome::ConfigError.exit_code());
+                },
+            };
+            print_warnings(&config);
+
+            match serde_yaml::to_string(&resolve::resolve(&config, &options)) {
+                Ok(yaml) => print!("{}", yaml),
+                Err(error) => {
+                    println!("Error while serializing the resolved config: {}", error);
+                    return Some(1);
+                },
+            }
+            Some(0)
+        },
+        None if args.list => {
+            let options = get_run_options();
+            let config = match load_config(&get_config_file(), &options) {
+                Ok(config) => config,
+                Err(errors) => {
+                    for error in errors.iter() {
+                        println!("{}", error);
+                    }
+                    return Some(RunOutcome::ConfigError.exit_code());
+                },
             };
+            print_warnings(&config);
+
+            let mut tests = listing::list_tests(&config);
+            tests.retain(|test| matches_meta(&config.tests[test.index - 1], &args.meta_filters));
+            if let Some(shard) = args.shard {
+                // The same names as for a run, otherwise a test reading a define would be scheduled differently
+                let variables = validation::variable_names(&config, &options.defines);
+                let tests_of_shard = shard::tests_of_shard(&dependency::graph(&config.tests, &variables), shard.index,
+                 shard.count);
+                tests.retain(|test| tests_of_shard.contains(&(test.index - 1)));
+            }
+            match args.format {
+                cli::ListFormat::Text => print!("{}", listing::render_table(&tests)),
+                cli::ListFormat::Json => match serde_json::to_string_pretty(&tests) {
+                    Ok(json) => println!("{}", json),
+                    Err(error) => {
+                        println!("Error while serializing the test list: {}", error);
+                        return Some(1);
+                    },
+                },
+            }
+            Some(0)
         },
-        None => (),
+        None => None,
     };
+}
 
-    // Add acquired cookies to the request
-    for cookie in test_request.cookie_jar.iter() {
-        if let Some(map) = req_builder.headers_mut() {
-            if map.contains_key("Cookie") {
-                let existing_value = match map.get("Cookie"){
-                    Some(value) => {
-                        value.to_str().unwrap_or_default()
-                    },
-                    None => ""
-                };
+// Reads in the run options from the command line
+pub fn get_run_options() -> RunOptions {
+    let args = cli::Args::parse();
 
-                map.insert("Cookie", format!("{};{}",
-                    existing_value, cookie.to_string()).parse::<HeaderValue>()?);
-            } else {
-                map.insert("Cookie", cookie.to_string().parse::<HeaderValue>()?);
-            }
-        }
+    return RunOptions {
+        max_failures: args.max_failures,
+        max_duration_s: args.max_duration,
+        fail_fast: args.fail_fast,
+        no_critical: args.no_critical,
+        no_preflight: args.no_preflight,
+        strict_captures: args.strict_captures,
+        warn_capture_overwrite: args.warn_capture_overwrite,
+        continue_on_critical: args.continue_on_critical,
+        indices: args.index.map(|selection| selection.0),
+        shard: args.shard.map(|shard| (shard.index, shard.count)),
+        meta_filters: args.meta_filters,
+        defines: args.defines,
+        verbose: args.verbose,
+        timeout_ms: args.timeout_ms,
+        repeat: args.repeat,
+        runs: args.runs,
+        log_file: args.log_file,
+        report_json: args.report_json,
+        report_html: args.report_html,
+        metrics_file: args.metrics_file,
+        github: args.github || github::is_github_actions(),
+        warnings_as_errors: args.warnings_as_errors,
+        update_snapshots: args.update_snapshots,
+        raw_body: args.raw_body,
+        step: args.step,
+        transcripts: args.transcripts,
+        keep_transcripts: args.keep_transcripts,
+        compare_with: args.compare_with,
+        handle_interrupts: true,
+        resume: args.resume,
+        record_expectations: args.record_expectations,
+        trace_http: args.trace_http,
+        show_captures: args.show_captures,
+        no_aggregate_errors: args.no_aggregate_errors,
+    };
+}
+
+// Writes the copy of the config with the expectations of --record-expectations and lists the tests without them
+fn record_expectations(rest_test_config: &Config, observations: &BTreeMap<usize, record::Observation>,
+ results: &[report::TestResult], path: &path::Path, log_buffer: &mut LogBuffer) {
+    let content = match rest_test_config.source.as_ref().map(fs::read_to_string) {
+        Some(Ok(content)) => content,
+        Some(Err(error)) => {
+            log_error(format!("Error while reading the config to record the expectations: {}\n", error), log_buffer);
+            return;
+        },
+        None => {
+            log_error("Error while recording the expectations: the config wasnt read from a file\n".to_string(),
+             log_buffer);
+            return;
+        },
+    };
+
+    let recording = record::record(&content, rest_test_config, observations, results);
+    if let Err(error) = record::write(path, &recording.content) {
+        log_error(format!("Error while writing the recorded expectations to {}: {}\n", path.display(), error),
+         log_buffer);
+        return;
     }
+    log(format!("\nWrote the expectations of {} tests to {}.", recording.generated, path.display()), Some(true),
+     log_buffer);
+    for note in recording.notes.iter() {
+        log(format!("  {}", note).yellow().to_string(), Some(true), log_buffer);
+    }
+}
 
-    if test_request.body.len() > 0 {
-        if let Some(map) = req_builder.headers_mut() {
-            map.insert("Content-Type", HeaderValue::from_static("application/json"));
+// Logs an assertion with its verdict and records it, a failure fails the test unless its severity is warn
+fn record_assertion(message: String, failure: Option<failure::FailureReason>, level: severity::Level,
+     result: &mut report::TestResult, test_passed: &mut bool, log_buffer: &mut LogBuffer /*IN-OUT*/) {
+    log(format!("{} — {}\n", message, severity::verdict(failure.is_none(), level)), Some(true), log_buffer);
+    result.assert(message, failure.is_none());
+    if let Some(reason) = failure {
+        if result.violate(level, reason) {
+            *test_passed = false;
         }
     }
+}
 
-    let req = req_builder.body(hyper::Body::from(test_request.body.clone()))?;
-    let client = hyper::Client::builder().build(https);
+// Compares the body byte by byte, e.g. for canonical json that gets signed
+fn check_exact_body(source: &str, expected: &[u8], body: &[u8], level: severity::Level, result: &mut report::TestResult,
+     test_passed: &mut bool, log_buffer: &mut LogBuffer /*IN-OUT*/) {
+    match snapshot::compare_exact(expected, body) {
+        None => {
+            log(format!("Expected the exact bytes of {} — PASSED\n", source), Some(true), log_buffer);
+            result.assert(format!("Expected the exact bytes of {}", source), true);
+        },
+        Some((offset, context)) => {
+            log(format!("Expected the exact bytes of {} — {}, first difference at byte {}\n{}\n", source,
+             severity::verdict(false, level), offset, context), Some(true), log_buffer);
+            result.assert(format!("Expected the exact bytes of {}", source), false);
+            let reason = failure::FailureReason::BodyNotExact { source: source.to_string(), offset, context };
+            if result.violate(level, reason) {
+                *test_passed = false;
+            }
+        },
+    }
+}
 
-    let possible_response = send_request(test_request, client, req);
+// Logs why a test failed, right before its verdict. Reasons that many tests share are only counted after a few
+// occurrences.
+fn log_failure_reasons(result: &report::TestResult, errors: &mut aggregate::ErrorAggregator,
+     log_buffer: &mut LogBuffer /*IN-OUT*/) {
+    let mut logged = false;
+    for reason in result.failures.iter() {
+        match errors.record(reason) {
+            aggregate::Output::Full => {
+                log_error(format!("{} {}\n", "Failure reason:".red(), reason), log_buffer);
+                logged = true;
+            },
+            aggregate::Output::Hint => log_error(format!("{} the same as in {} tests before, from now on it is only \
+             summarized after the run\n", "Failure reason:".red(), aggregate::SHOWN_OCCURRENCES), log_buffer),
+            aggregate::Output::Counted => (),
+        }
+    }
+    if let (Some(trace_id), true) = (&result.trace_id, logged) {
+        log_error(format!("{} {}\n", "Trace id:".red(), trace_id), log_buffer);
+    }
+}
 
-    let mut response = match possible_response.await{
-        Ok(res) => res,
-        Err(error) => return Err(error),
+// Sets the outcome of a test that ran and logs its verdict. A test marked xfail is expected to fail because of a
+// known bug, its failure is an XFAIL and a pass is an XPASS, which only fails the run with strict_xfail.
+fn conclude(result: &mut report::TestResult, passed: bool, strict_xfail: bool,
+     log_buffer: &mut LogBuffer /*IN-OUT*/) -> report::TestOutcome {
+    let verdict = match (&result.xfail, passed) {
+        (None, true) => {
+            result.outcome = report::TestOutcome::Passed;
+            "TEST PASSED".green().bold()
+        },
+        (None, false) => {
+            result.outcome = report::TestOutcome::Failed;
+            "TEST FAILED".red().bold()
+        },
+        (Some(ticket), false) => {
+            result.outcome = report::TestOutcome::XFail;
+            format!("TEST XFAIL (expected to fail: {})", ticket).magenta().bold()
+        },
+        (Some(ticket), true) => {
+            result.outcome = report::TestOutcome::XPass;
+            let verdict = format!("TEST XPASS (expected to fail: {}, the bug might be fixed)", ticket);
+            if strict_xfail { verdict.red().bold() } else { verdict.yellow().bold() }
+        },
     };
+    log(format!("{}\n\n", verdict), Some(true), log_buffer);
+    return result.outcome;
+}
 
-    log(format!("Response Status: {}\n", response.status()), Some(true), log_buffer);
+// Writes the progress of --resume, a state that cant be written doesnt stop the run
+fn checkpoint_resume(state: &mut resume::ResumeState, path: &path::Path, completed: &[(usize, u32, u32)],
+     captures: &interpolation::Captures, results: &mut [report::TestResult], session: u32,
+     log_buffer: &mut LogBuffer /*IN-OUT*/) {
+    state.update(completed.iter().map(|(config_index, repetition, _)| (config_index + 1, *repetition)), captures,
+     results, session);
+    if let Err(error) = resume::save(path, state, results) {
+        log_error(format!("Error while writing the resume state {}: {}\n", path.display(), error), log_buffer);
+    }
+}
 
-    // stream body data into buffer
-    while let Some(next) = response.data().await {
-        test_request.buffer.put(next?);
+// Lets the running test finish on the first Ctrl-C, quits immediately on the second
+async fn watch_interrupts(interrupted: Arc<AtomicBool>) {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
     }
 
-    log(format!("Response Header: {:#?}\n", response.headers()),
-     Some(test_request.verbose), log_buffer);
+    interrupted.store(true, Ordering::SeqCst);
+    println!("{}", "\nInterrupted, finishing the current test. Press Ctrl-C again to quit immediately.".yellow());
 
-    if !test_request.buffer.is_empty() && test_request.verbose {
-        log("Response Body: ".to_string(), Some(true), log_buffer);
-        log(String::from_utf8_lossy(test_request.buffer).to_string() + "\n",
-         Some(true), log_buffer);
+    if tokio::signal::ctrl_c().await.is_ok() {
+        std::process::exit(RunOutcome::Interrupted.exit_code());
     }
+}
 
-    return Ok(response);
+// Determines whether a failed test aborts the run, returns the reason if it does
+fn check_abort(is_critical: bool, tests_failed: u32, max_failures: Option<u32>, fail_fast: bool)
+     -> Option<String> {
+    if fail_fast {
+        return Some("Test failed while running with --fail-fast, cancelling all further tests.".to_string());
+    }
+
+    if is_critical {
+        return Some("Test marked as 'critical' failed, cancelling all further tests.".to_string());
+    }
+
+    return match max_failures {
+        Some(max) if tests_failed >= max => Some(format!(
+            "Reached the maximum of {} failed tests, cancelling all further tests.", max)),
+        _ => None,
+    };
 }
 
-// Core unit test function that parses the config and executes the defined tests
-pub async fn execute_tests(config_file: path::PathBuf) {
-    // Open and read config file
-    let test_config_file = match fs::File::open(config_file) {
-        Ok(file) => file,
-        Err(error) => {
-            println!("Error while trying to open config file: {}", error);
-            return;
-        }
+// Replaces the scheme of a composed url, e.g. to request the http variant of an https api
+fn swap_scheme(url: &str, scheme: &str) -> String {
+    return match url.split_once("://") {
+        Some((_, rest)) => format!("{}://{}", scheme, rest),
+        None => format!("{}://{}", scheme, url),
     };
-    
-    // Parse config yaml file
-    let rest_test_config: Config = match serde_yaml::from_reader(test_config_file) {
-        Ok(config) => config,
-        Err(error) => {
-            println!("Error while parsing config file: {}", error);
-            return;
+}
+
+// Checks if a given method matched one of HttpMethod
+fn validate_http_method(method: &String) -> Option<HttpMethod> {
+    return HttpMethod::iter().find(|http_method|
+         http_method.to_string() == method.to_string().to_lowercase());
+}
+
+// Parse the response body as long as its not empty and (probably) a json object or array, a required json body is
+// parsed no matter what it looks like
+fn parse_json_response(response_buffer: &bytes::BytesMut, required: bool)
+     -> std::result::Result<Option<serde_json::Value>, serde_json::Error> {
+
+    let first_char = response_buffer.iter().find(|byte| !byte.is_ascii_whitespace());
+    if first_char != Some(&b'{') && first_char != Some(&b'[') && !required {
+        return Ok(None);
+    }
+
+    return serde_json::from_str(&String::from_utf8_lossy(response_buffer)).map(Some);
+}
+
+// Captures of json paths and the json assertions dont work without a json body
+fn requires_json(test: &Endpoint) -> bool {
+    let captures_json = test.capture.iter().flatten().any(|(_, source)| !source.starts_with('$'));
+    let compares_json = test.expect_body_file.is_some() && test.compare.as_deref().unwrap_or("json") == "json";
+    return captures_json || compares_json || test.expect_array_length.is_some() || test.expect_array_contains.is_some()
+        || test.expect_body.is_some();
+}
+
+// The parts of a response values can be captured from
+struct CaptureSource<'a> {
+    json_body: Option<&'a serde_json::Value>,
+    body: &'a bytes::BytesMut,
+    status: u16,
+    headers: &'a hyper::HeaderMap,
+    cookies: &'a [Cookie<'static>],
+    events: &'a [sse::Event],
+    response_time: u128,
+}
+
+// Looks up a header capture like "ETag", "Set-Cookie[1]" (zero based) or "Vary[*]", which joins all values
+fn capture_header(source: &str, headers: &hyper::HeaderMap, separator: Option<&str>) -> Option<String> {
+    let (name, index) = match source.strip_suffix(']').and_then(|source| source.split_once('[')) {
+        Some((name, index)) => (name, Some(index)),
+        None => (source, None),
+    };
+
+    let values = assertions::header_values(headers, name);
+    return match index {
+        None => values.into_iter().next(),
+        Some("*") if !values.is_empty() => Some(values.join(separator.unwrap_or(", "))),
+        Some(index) => values.into_iter().nth(index.parse::<usize>().ok()?),
+    };
+}
+
+// Runs the pre_script of a test against the request as it would be sent without it, the captures it sets are used
+// to build the actual request. The script got parsed during the validation.
+#[cfg(feature = "scripting")]
+fn run_pre_script(test: &Endpoint, api_address: &str, global_headers: &Option<HashMap<String, String>>,
+ captures: &mut interpolation::Captures) -> Option<failure::FailureReason> {
+    let script = script::Script::parse(test.pre_script.as_ref()?, script::Hook::Pre).ok()?;
+
+    let route = interpolation::interpolate_route(&test.route, captures);
+    let url = match &test.scheme {
+        Some(scheme) => swap_scheme(&(api_address.to_owned() + &route), scheme),
+        None => api_address.to_owned() + &route,
+    };
+    let headers: Vec<(String, String)> = resolve_headers(&test.headers, global_headers).into_iter()
+        .map(|(name, value)| (name, interpolation::interpolate(&value, captures)))
+        .collect();
+    let context = script::request_context(&test.method, &url, &headers, &construct_body(test, captures));
+
+    return script.run(captures, &context).err().map(|error| failure::FailureReason::ScriptFailed {
+        script: "pre_script".to_string(),
+        line: error.line,
+        message: error.message,
+    });
+}
+
+// Runs the post_script of a test after the captures, it sees the request as it was reported
+#[cfg(feature = "scripting")]
+fn run_post_script(test: &Endpoint, captures: &mut interpolation::Captures, result: &report::TestResult,
+ headers: &hyper::HeaderMap, body: &[u8]) -> Option<failure::FailureReason> {
+    let script = script::Script::parse(test.post_script.as_ref()?, script::Hook::Post).ok()?;
+
+    let mut context = script::request_context(&result.method, &result.url, &result.request_headers,
+     result.request_body.as_deref().unwrap_or_default());
+    script::add_response(&mut context, result.status.unwrap_or_default(), headers, body,
+     result.response_time_ms.unwrap_or_default());
+
+    return script.run(captures, &context).err().map(|error| failure::FailureReason::ScriptFailed {
+        script: "post_script".to_string(),
+        line: error.line,
+        message: error.message,
+    });
+}
+
+// Capture desired values from the response. Besides json keys, the special sources "$body", "$status",
+// "$header:<name>", "$cookie:<name>", "$event:<index>[.<path>]" and "$time_ms" are supported. Json values keep their
+// type, including null. The body, headers and cookies are stored as strings, the status and time as numbers and
+// headers with their first value. Repeated headers can be indexed like "$header:Set-Cookie[1]" or joined via
+// "$header:Vary[*]".
+// Returns the names that got stored, including a capture_namespace.
+fn capture_values(test: &Endpoint, captures: &mut interpolation::Captures, source: &CaptureSource<'_>) -> Vec<String> {
+    let mut stored = Vec::new();
+    let CaptureSource { json_body, body, status, headers, cookies, events, response_time } = *source;
+
+    if let Some(capture) = &test.capture {
+        for (key, value) in capture.iter() {
+            let special_value = match value.as_str() {
+                "$body" => Some(serde_json::Value::from(String::from_utf8_lossy(body))),
+                "$status" => Some(serde_json::Value::from(status)),
+                "$time_ms" => Some(serde_json::Value::from(response_time as u64)),
+                _ => if let Some(header_source) = value.strip_prefix("$header:") {
+                    match capture_header(header_source, headers, test.capture_separator.as_deref()) {
+                        Some(header) => Some(serde_json::Value::from(header)),
+                        None => {
+                            println!("Error: Cannot capture nonexistent header '{}'", header_source.bold());
+                            continue;
+                        },
+                    }
+                } else if let Some(name) = value.strip_prefix("$cookie:") {
+                    match cookies.iter().rev().find(|cookie| cookie.name() == name) {
+                        Some(cookie) => Some(serde_json::Value::from(cookie.value())),
+                        None => {
+                            println!("Error: Cannot capture nonexistent cookie '{}'", name.bold());
+                            continue;
+                        },
+                    }
+                } else if let Some(event_source) = value.strip_prefix("$event:") {
+                    match sse::capture_event(events, event_source) {
+                        Some(event_value) => Some(event_value),
+                        None => {
+                            println!("Error: Cannot capture nonexistent event value '{}'", event_source.bold());
+                            continue;
+                        },
+                    }
+                } else {
+                    None
+                },
+            };
+
+            if let Some(special_value) = special_value {
+                let name = dependency::capture_name(test, key);
+                captures.insert(name.clone(), special_value);
+                stored.push(name);
+                continue;
+            }
+
+            // A key holding null is captured as null, only a missing key is an error
+            if let Some(captured_value) = json_body.and_then(|json| json.get(value.as_str())) {
+                let name = dependency::capture_name(test, key);
+                captures.insert(name.clone(), captured_value.clone());
+                stored.push(name);
+            } else {
+                println!("Error: Cannot capture nonexistent value '{}'", value.bold());
+            }
+        }
+    }
+
+    return stored;
+}
+
+// Waits for a token of the rate limit, it is called before the timer of a request starts
+async fn throttle(test_request: &TestRequest<'_>, log_buffer: &mut LogBuffer /*IN-OUT*/) {
+    if let Some(limiter) = test_request.rate_limiter {
+        let delay = limiter.acquire().await;
+        if !delay.is_zero() {
+            log_debug(format!("Delayed {} ms by the rate limit\n", delay.as_millis()), test_request.verbose, log_buffer);
+        }
+    }
+}
+
+// Sends the request and returns the awaited response
+async fn send_request(test_request: &mut TestRequest<'_>, 
+     client: connect::Client, request: hyper::Request<hyper::Body>)
+     -> Result<hyper::Response<hyper::Body>> {
+
+    let mut request = request;
+    middleware::before_request(test_request.middleware, &mut request, &test_request.context)?;
+    *test_request.sent_headers = request.headers().clone();
+    if test_request.trace_http {
+        request = trace_request(request).await?;
+    }
+
+    let future_response = client.request(request);
+
+    let now = Instant::now();
+
+    let response = match tokio::time::timeout(Duration::from_millis(test_request.timeout.try_into().unwrap()),
+        future_response).await {
+        Ok(result) => match result {
+            Ok(res) => res,
+            Err(e) => return Err(Box::new(e)),
+        },
+        Err(_) => return Err(Box::new(failure::TimeoutError { elapsed_ms: now.elapsed().as_millis(),
+         limit_ms: test_request.timeout })),
+    };
+
+    *test_request.response_time = now.elapsed().as_millis();
+
+    if test_request.trace_http {
+        let (parts, body) = response.into_parts();
+        eprint!("{}", wire::response(&parts));
+        return Ok(hyper::Response::from_parts(parts, body));
+    }
+    return Ok(response);
+}
+
+// Prints the request for --trace-http, the body is read for it and put back with the same framing
+async fn trace_request(request: hyper::Request<hyper::Body>) -> Result<hyper::Request<hyper::Body>> {
+    let (parts, body) = request.into_parts();
+    let body = hyper::body::to_bytes(body).await?;
+    eprint!("{}", wire::request(&parts, &body));
+
+    let chunked = parts.headers.get(hyper::header::TRANSFER_ENCODING).is_some_and(|value| value == "chunked");
+    let body = if chunked {
+        let (mut sender, chunked_body) = hyper::Body::channel();
+        if !body.is_empty() {
+            sender.try_send_data(body).map_err(|_| "the chunked body could not be queued")?;
+        }
+        chunked_body
+    } else {
+        hyper::Body::from(body)
+    };
+    return Ok(hyper::Request::from_parts(parts, body));
+}
+
+// Assembles a request from the test request data, authorization takes precedence over the bearer token
+fn build_request(test_request: &TestRequest<'_>, authorization: Option<&String>,
+     log_buffer: &mut LogBuffer /*IN-OUT*/) -> Result<hyper::Request<hyper::Body>> {
+
+    let mut req_builder = hyper::Request::builder()
+        .method(to_hyper_method(test_request.method))
+        .uri(test_request.url);
+
+    // Add static headers, these may be overwritten by the more specific options below
+    for (key, value) in test_request.headers.iter() {
+        if let Some(map) = req_builder.headers_mut() {
+            map.insert(HeaderName::from_bytes(key.as_bytes())?, value.parse::<HeaderValue>()?);
+        }
+    }
+
+    if let Some(value) = authorization {
+        if let Some(map) = req_builder.headers_mut() {
+            map.insert("Authorization", value.parse::<HeaderValue>()?);
+        };
+    } else if let Some(token) = &test_request.bearer_token {
+        let composed_token = format!("Bearer {}", token);
+        // The log file keeps the debug entries, so the token itself is never written
+        log_debug("Authorization: Bearer <redacted>\n".to_string(), test_request.verbose, log_buffer);
+
+        if let Some(map) = req_builder.headers_mut() {
+            map.insert("Authorization", composed_token.parse::<HeaderValue>()?);
+        };
+    };
+
+    // Add acquired cookies to the request
+    for cookie in test_request.cookie_jar.iter().map(|cookie| cookie.to_string())
+        .chain(test_request.session_id.iter().map(|id| Cookie::new("session_id", id.clone()).to_string())) {
+        if let Some(map) = req_builder.headers_mut() {
+            if map.contains_key("Cookie") {
+                let existing_value = match map.get("Cookie"){
+                    Some(value) => {
+                        value.to_str().unwrap_or_default()
+                    },
+                    None => ""
+                };
+
+                map.insert("Cookie", format!("{};{}",
+                    existing_value, cookie).parse::<HeaderValue>()?);
+            } else {
+                map.insert("Cookie", cookie.parse::<HeaderValue>()?);
+            }
+        }
+    }
+
+    // Guess the content type unless its set explicitly
+    if !test_request.body.is_empty() {
+        if let Some(map) = req_builder.headers_mut() {
+            if !map.contains_key("Content-Type") {
+                let content_type = match serde_json::from_str::<serde_json::Value>(&test_request.body) {
+                    Ok(_) => "application/json",
+                    Err(_) => "text/plain",
+                };
+                map.insert("Content-Type", HeaderValue::from_static(content_type));
+            }
+        }
+    }
+
+    // A body of unknown length is sent chunked, one of known length gets a Content-Length unless its empty
+    let body = match test_request.transfer_encoding.as_deref() {
+        Some("chunked") => {
+            let (mut sender, body) = hyper::Body::channel();
+            if !test_request.body.is_empty() {
+                sender.try_send_data(bytes::Bytes::from(test_request.body.clone()))
+                    .map_err(|_| "the chunked body could not be queued")?;
+            }
+            if let Some(map) = req_builder.headers_mut() {
+                map.remove(hyper::header::CONTENT_LENGTH);
+                map.insert(hyper::header::TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+            }
+            log_debug("Transfer-Encoding: chunked\n".to_string(), test_request.verbose, log_buffer);
+            body
+        },
+        Some(_) => {
+            if let Some(map) = req_builder.headers_mut() {
+                map.remove(hyper::header::TRANSFER_ENCODING);
+                map.insert(hyper::header::CONTENT_LENGTH, HeaderValue::from(test_request.body.len()));
+            }
+            log_debug(format!("Content-Length: {}\n", test_request.body.len()), test_request.verbose, log_buffer);
+            hyper::Body::from(test_request.body.clone())
+        },
+        None => hyper::Body::from(test_request.body.clone()),
+    };
+
+    return Ok(req_builder.body(body)?);
+}
+
+// Answers a digest challenge of a 401 response by resending the request with the computed credentials
+async fn answer_digest_challenge(test_request: &mut TestRequest<'_>,
+     client: connect::Client, response: hyper::Response<hyper::Body>,
+     credentials: &digest::DigestCredentials, log_buffer: &mut LogBuffer /*IN-OUT*/)
+     -> Result<hyper::Response<hyper::Body>> {
+
+    if response.status() != hyper::StatusCode::UNAUTHORIZED {
+        return Ok(response);
+    }
+
+    let challenge = match digest::find_challenge(response.headers()) {
+        Some(challenge) => challenge,
+        None => {
+            log("No supported digest challenge found in the 401 response.\n".to_string(),
+             Some(true), log_buffer);
+            return Ok(response);
+        },
+    };
+
+    log_debug(format!("Digest challenge: {}\n", challenge), test_request.verbose, log_buffer);
+
+    let uri = match test_request.url.path_and_query() {
+        Some(path) => path.as_str().to_string(),
+        None => "/".to_string(),
+    };
+
+    let authorization = digest::authorization_header(&challenge, credentials,
+        to_hyper_method(test_request.method).as_str(), &uri);
+    log_debug(format!("Digest response: {}\n", digest::redact(&authorization)), test_request.verbose, log_buffer);
+
+    // Both round trips count towards the response time of the test
+    let challenge_time = *test_request.response_time;
+    let request = build_request(test_request, Some(&authorization), log_buffer)?;
+    throttle(test_request, log_buffer).await;
+    let response = send_request(test_request, client, request).await?;
+    *test_request.response_time += challenge_time;
+
+    return Ok(response);
+}
+
+// One line with the phases of a request, a reused connection has none of its own
+fn describe_timings(timings: &connect::PhaseTimings) -> String {
+    let connection = match timings.tls_ms {
+        _ if timings.reused => "connection reused".to_string(),
+        Some(tls) => format!("DNS {} ms, connect {} ms, TLS {} ms", timings.dns_ms, timings.connect_ms, tls),
+        None => format!("DNS {} ms, connect {} ms", timings.dns_ms, timings.connect_ms),
+    };
+    return format!("Timings: {}, first byte {} ms, download {} ms", connection, timings.ttfb_ms, timings.download_ms);
+}
+
+// Follows the Location of redirect responses until a response isnt one, fails on a loop or too many redirects.
+// Credentials are only sent to the host of the test.
+async fn follow_redirects(test_request: &mut TestRequest<'_>, client: connect::Client,
+     mut response: hyper::Response<hyper::Body>, max_redirects: u32, log_buffer: &mut LogBuffer /*IN-OUT*/)
+     -> Result<hyper::Response<hyper::Body>> {
+    let mut current = test_request.url.clone();
+    let mut method = to_hyper_method(test_request.method);
+    let mut keep_body = true;
+    let mut visited = vec![current.to_string()];
+    test_request.redirect_chain.clear();
+
+    while response.status().is_redirection() {
+        let location = match response.headers().get(hyper::header::LOCATION).and_then(|value| value.to_str().ok()) {
+            Some(location) => location,
+            None => break, // e.g. a 304
+        };
+        let next = redirect::resolve(location, &current)?;
+        log_debug(format!("Redirected with {} to {}\n", response.status(), next), test_request.verbose, log_buffer);
+
+        test_request.redirect_chain.push(next.to_string());
+        visited.push(next.to_string());
+        if visited[..visited.len() - 1].contains(&next.to_string()) {
+            return Err(Box::new(redirect::RedirectError { chain: visited, limit: None }));
+        }
+        if test_request.redirect_chain.len() > max_redirects as usize {
+            return Err(Box::new(redirect::RedirectError { chain: visited, limit: Some(max_redirects) }));
+        }
+
+        let (next_method, carries_body) = redirect::next_method(response.status(), &method);
+        method = next_method;
+        keep_body = keep_body && carries_body;
+
+        // Reading the body of the redirect frees its connection for the next hop
+        hyper::body::to_bytes(response.into_body()).await?;
+
+        let mut request = build_request(test_request, None, log_buffer)?;
+        *request.uri_mut() = next.clone();
+        *request.method_mut() = method.clone();
+        if !keep_body {
+            *request.body_mut() = hyper::Body::empty();
+            for header in [hyper::header::CONTENT_TYPE, hyper::header::CONTENT_LENGTH, hyper::header::TRANSFER_ENCODING] {
+                request.headers_mut().remove(header);
+            }
+        }
+        if next.authority() != test_request.url.authority() {
+            request.headers_mut().remove(hyper::header::AUTHORIZATION);
+            request.headers_mut().remove(hyper::header::COOKIE);
+        }
+
+        throttle(test_request, log_buffer).await;
+        response = send_request(test_request, client.clone(), request).await?;
+        current = next;
+    }
+
+    return Ok(response);
+}
+
+// Builds a new request and sends it to the target
+async fn fetch_url(test_request: &mut TestRequest<'_>, log_buffer: &mut LogBuffer /*IN-OUT*/)
+     -> Result<hyper::Response<hyper::Body>> {
+     
+    // TLS implementation to enable https requests, connect_to only changes where the connection goes
+    let recorder = connect::PhaseRecorder::default();
+    let client = connect::client(test_request.connect_to, &recorder);
+
+    let req = build_request(test_request, None, log_buffer)?;
+    throttle(test_request, log_buffer).await;
+    let started = Instant::now();
+
+    let possible_response = send_request(test_request, client.clone(), req);
+
+    let mut response = match possible_response.await{
+        Ok(res) => res,
+        Err(error) => return Err(error),
+    };
+
+    if let (Some(address), Some(host)) = (test_request.connect_to, test_request.url.host()) {
+        let sni = if test_request.url.scheme_str() == Some("https") { format!(", SNI {}", host) } else { String::new() };
+        log_debug(format!("Connected to {}{}, Host {}\n", address, sni, host), test_request.verbose, log_buffer);
+    }
+
+    if let Some(credentials) = test_request.digest_auth {
+        response = answer_digest_challenge(test_request, client.clone(), response, credentials, log_buffer).await?;
+    }
+
+    if let Some(max_redirects) = test_request.max_redirects {
+        response = follow_redirects(test_request, client, response, max_redirects, log_buffer).await?;
+    }
+
+    log(format!("Response Status: {}\n", response.status()), Some(true), log_buffer);
+    let ttfb = started.elapsed();
+
+    // A websocket only has a body if the server refused the upgrade
+    let mut upgraded = false;
+    if let Some(websocket) = test_request.websocket.clone() {
+        let outcome = match websocket::verify_handshake(&response, &websocket.key) {
+            Ok(()) => websocket::exchange(&mut response, &websocket).await?,
+            Err(reason) => {
+                log(format!("Response Header: {:#?}\n", response.headers()), Some(!test_request.verbose), log_buffer);
+                websocket::Outcome::Rejected(reason)
+            },
+        };
+
+        if let websocket::Outcome::Message(message) = &outcome {
+            log_debug(format!("Websocket message: {}\n", message), test_request.verbose, log_buffer);
+            test_request.buffer.put(message.as_bytes());
+        }
+        upgraded = !matches!(outcome, websocket::Outcome::Rejected(_));
+        *test_request.websocket_outcome = Some(outcome);
+    }
+
+    // stream body data into buffer, an event stream only until the test has seen enough of it
+    match test_request.sse {
+        _ if upgraded => (),
+        Some(options) => {
+            let stream = sse::read_events(&mut response, test_request.buffer, options).await?;
+            for (index, event) in stream.events.iter().enumerate() {
+                log_debug(format!("Event {}: {} {}\n", index, event.event, event.data), test_request.verbose,
+                 log_buffer);
+            }
+            *test_request.event_stream = Some(stream);
+        },
+        None => while let Some(next) = response.data().await {
+            test_request.buffer.put(next?);
+        },
+    }
+
+    let timings = recorder.timings(ttfb, started.elapsed().saturating_sub(ttfb));
+    log_debug(format!("{}\n", describe_timings(&timings)), test_request.verbose, log_buffer);
+    *test_request.phase_timings = Some(timings);
+
+    log_debug(format!("Response Header: {:#?}\n", response.headers()),
+     test_request.verbose, log_buffer);
+
+    // The assertions see the decoded body, the sizes are kept for the report
+    *test_request.body_size = None;
+    if !upgraded && test_request.sse.is_none() {
+        let encoding = compression::content_encoding(response.headers());
+        let wire_bytes = test_request.buffer.len();
+        let decoded = encoding.as_deref().and_then(|encoding| compression::decode(encoding, test_request.buffer));
+        let mut size = compression::BodySize { encoding, wire_bytes, decoded_bytes: None, error: None };
+        match decoded {
+            Some(Ok(body)) => {
+                size.decoded_bytes = Some(body.len());
+                test_request.buffer.clear();
+                test_request.buffer.put(body.as_slice());
+            },
+            Some(Err(error)) => size.error = Some(error),
+            None if size.encoding.is_none() => size.decoded_bytes = Some(wire_bytes),
+            None => (),
+        }
+        if size.encoding.is_some() {
+            log(format!("Response body: {}\n", size.describe()), Some(true), log_buffer);
+        }
+        *test_request.body_size = Some(size);
+    }
+
+    middleware::after_response(test_request.middleware, &response, test_request.buffer, &test_request.context)?;
+
+    // The text assertions and captures work on utf-8, an event stream or websocket message already is
+    *test_request.wire_body = None;
+    if !upgraded && test_request.sse.is_none() {
+        match charset::to_utf8(response.headers(), test_request.buffer) {
+            charset::Decoded::Unchanged => (),
+            charset::Decoded::Transcoded { charset, body } => {
+                log_debug(format!("Response charset: {}, transcoded to UTF-8\n", charset), test_request.verbose,
+                 log_buffer);
+                *test_request.wire_body = Some(test_request.buffer.split().freeze());
+                test_request.buffer.put(body.as_slice());
+            },
+            charset::Decoded::Unknown(label) => log(format!("{}\n",
+             format!("Warning: Unknown response charset '{}', the body is read as UTF-8", label).yellow()),
+             Some(true), log_buffer),
+        }
+    }
+
+    if !test_request.buffer.is_empty() {
+        log_debug("Response Body: ".to_string(), test_request.verbose, log_buffer);
+        log_debug(utils::printable_body(test_request.buffer, test_request.raw_body, test_request.max_printed_body_bytes)
+         + "\n", test_request.verbose, log_buffer);
+    }
+
+    return Ok(response);
+}
+
+// Core unit test function that parses the config and executes the defined tests, the middleware hooks into every request
+pub async fn execute_tests(config_file: path::PathBuf, options: RunOptions, middleware: &[Box<dyn Middleware>])
+     -> RunOutcome {
+    let rest_test_config = match load_config(&config_file, &options) {
+        Ok(config) => config,
+        Err(errors) => {
+            for error in errors.iter() {
+                println!("{}", error);
+            }
+            return RunOutcome::ConfigError;
+        }
+    };
+
+    return run_tests(rest_test_config, options, middleware).await;
+}
+
+// Runs the tests of an already validated config once, the run is one of several with --runs.
+// The reports are up to the caller, there is no run if the config turned out to be invalid.
+async fn run_suite(rest_test_config: &Config, options: &RunOptions, middleware: &[Box<dyn Middleware>],
+     run: Option<(u32, u32)>) -> (RunOutcome, Option<report::TestRun>) {
+    // Everything below works with the merged settings of the command line, the config and the tests
+    let resolved = resolve::resolve(rest_test_config, options);
+    let mut log_destination = resolved.log_destination.clone();
+
+    // Later runs are added to the log file of the first one
+    if let (Some(resolve::LogDestination::File { append, .. }), Some((run_number, _))) = (&mut log_destination, run) {
+        *append = *append || run_number > 1;
+    }
+
+    // Set buffer to Some if a log destination is specified, the log file starts with the settings of the run
+    let mut log_buffer = LogBuffer { level: resolved.log_level, ..Default::default() };
+    if log_destination.is_some() { 
+        log_buffer.text = Some(resolve::summary(&resolved));
+    };
+    let mut errors = aggregate::ErrorAggregator::new(!options.no_aggregate_errors);
+
+    if let Some((run_number, runs)) = run {
+        log(format!("{}\n", format!("Run {}/{}", run_number, runs).bold().bright_blue()), Some(true), &mut log_buffer);
+    }
+
+    // The target address
+    let api_address = &rest_test_config.api_address;
+
+    // Every test runs after the tests whose captures it reads, the validation made sure they can be scheduled
+    let variable_names = validation::variable_names(rest_test_config, &options.defines);
+    let graph = dependency::graph(&rest_test_config.tests, &variable_names);
+
+    for warning in rest_test_config.warnings.iter() {
+        log(format!("{}\n", format!("Warning: {}", warning).yellow()), Some(true), &mut log_buffer);
+    }
+    for change in rest_test_config.deprecations.iter() {
+        log(format!("{}\n", format!("Hint: {}", change.hint()).yellow()), Some(true), &mut log_buffer);
+    }
+
+    // Restrict the run to the selected tests, the meta filters narrow down the requested ones
+    let requested: Option<Vec<usize>> = if options.meta_filters.is_empty() {
+        options.indices.clone()
+    } else {
+        let candidates: Vec<usize> = options.indices.clone().unwrap_or_else(|| (1..=rest_test_config.tests.len()).collect());
+        // Indices out of range are kept, the selection reports them
+        let matching: Vec<usize> = candidates.into_iter()
+            .filter(|index| index.checked_sub(1).and_then(|index| rest_test_config.tests.get(index))
+                .is_none_or(|test| matches_meta(test, &options.meta_filters)))
+            .collect();
+        if matching.is_empty() {
+            log_error(format!("Error while selecting tests: No test matches the meta filter {}.\n", options.meta_filters
+             .iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<String>>().join(", ")),
+             &mut log_buffer);
+            return (RunOutcome::ConfigError, None);
+        }
+        Some(matching)
+    };
+    let mut selected: Vec<usize> = match &requested {
+        Some(indices) => {
+            match dependency::select(&rest_test_config.tests, &variable_names, indices) {
+                Ok(selection) => {
+                    for message in selection.pulled_in.iter() {
+                        log(format!("{}\n", message), Some(true), &mut log_buffer);
+                    }
+                    selection.indices
+                },
+                Err(error) => {
+                    log_error(format!("Error while selecting tests: {}\n", error), &mut log_buffer);
+                    return (RunOutcome::ConfigError, None);
+                },
+            }
+        },
+        None => (0..rest_test_config.tests.len()).collect(),
+    };
+
+    // A shard only runs its part of the selection, the tests of a capture chain always share a shard
+    if let Some((shard, count)) = options.shard {
+        let tests_of_shard = shard::tests_of_shard(&graph, shard, count);
+        selected.retain(|index| tests_of_shard.contains(index));
+        log(format!("Shard {}/{} runs the tests {}.\n", shard, count, if selected.is_empty() { "-".to_string() } else {
+         selected.iter().map(|index| (index + 1).to_string()).collect::<Vec<String>>().join(", ") }),
+         Some(true), &mut log_buffer);
+    }
+
+    let order = dependency::schedule(&graph);
+    let is_reordered = order.windows(2).any(|pair| pair[0] > pair[1]);
+    let selected: Vec<usize> = order.into_iter().filter(|index| selected.contains(index)).collect();
+    for (index, producers) in graph.producers.iter().enumerate().filter(|(index, _)| selected.contains(index)) {
+        for (producer, name) in producers.iter().filter(|(producer, _)| producer > &index) {
+            log(format!("Test {} runs after test {}, it reads the capture '{}'.\n",
+             test_reference(&rest_test_config.tests[index], index + 1),
+             test_reference(&rest_test_config.tests[*producer], producer + 1), name), Some(true), &mut log_buffer);
+        }
+    }
+
+    // Repeated tests are scheduled once per repetition, each of them counts as a test of its own
+    let mut schedule: Vec<(usize, u32, u32)> = Vec::new(); // config index, repetition, total repetitions
+    for config_index in selected.iter() {
+        let repeat = resolved.tests[*config_index].repeat;
+        schedule.extend((1..=repeat).map(|repetition| (*config_index, repetition, repeat)));
+    }
+
+    let mut test_count = schedule.len();
+    let shows_config_index = selected.len() != rest_test_config.tests.len() || is_reordered;
+    let mut test_index = 0;
+    let mut tests_passed = 0;
+    let mut tests_failed = 0;
+    let mut tests_skipped = 0;
+    let mut tests_dependency_skipped = 0;
+    let mut tests_not_run = 0;
+    let mut tests_host_skipped = 0;
+    let mut tests_xfailed = 0;
+    let mut tests_xpassed = 0;
+    let strict_xfail = rest_test_config.strict_xfail.unwrap_or(false);
+
+    let max_failures = resolved.max_failures;
+    let yellow_is_warning = resolved.yellow_is_warning;
+    let max_printed_body_bytes = resolved.max_printed_body_bytes;
+    let suppress_failure_bodies = resolved.suppress_failure_bodies;
+    let max_duration = resolved.max_duration_s.map(Duration::from_secs);
+    let trace_header = resolved.trace_header;
+    let mut abort_reason: Option<String> = None;
+
+    if !resolved.overrides.is_empty() {
+        log(format!("Command line overrides: {}\n", resolved.overrides.join(", ")), Some(true), &mut log_buffer);
+    }
+
+    // Container for user-defined captured values
+    let mut captures: interpolation::Captures = Default::default();
+    // config index of the test that stored a capture and when
+    let mut capture_producers: HashMap<String, (usize, chrono::DateTime<chrono::Local>)> = HashMap::new();
+    let produced_captures = dependency::all_produced(&rest_test_config.tests);
+
+    // Config-defined variables are available to interpolation from the start, as strings like the --define ones
+    let text_value = |(name, value): (&String, &String)| (name.clone(), serde_json::Value::from(value.as_str()));
+    captures.extend(rest_test_config.variables.iter().flatten().map(text_value));
+    captures.extend(options.defines.iter().map(|(name, value)| text_value((name, value))));
+
+    // An earlier session already ran some of the tests, their captures are restored and their results reported again
+    let mut resume_state = match &options.resume {
+        Some(path) => match resume::load(path, &resume::config_hash(rest_test_config)) {
+            Ok(state) => Some(state),
+            Err(error) => {
+                log_error(format!("Error while resuming: {}\n", error), &mut log_buffer);
+                return (RunOutcome::ConfigError, None);
+            },
+        },
+        None => None,
+    };
+    let mut resumed_results: Vec<report::TestResult> = Vec::new();
+    let mut session = 0;
+    if let (Some(state), Some(path)) = (&mut resume_state, &options.resume) {
+        session = state.start_session(chrono::Local::now().to_rfc3339());
+        schedule.retain(|(config_index, repetition, _)| !state.is_completed(config_index + 1, *repetition));
+        test_count = schedule.len();
+        captures.extend(state.captures.iter().map(|(name, value)| (name.clone(), value.clone())));
+        captures.extend(options.defines.iter().map(|(name, value)| text_value((name, value))));
+        resumed_results = std::mem::take(&mut state.results);
+
+        for result in resumed_results.iter() {
+            let dependency_failed = result.skip_reason.as_deref()
+                .is_some_and(|reason| reason.starts_with("critical dependency failed"));
+            match result.outcome {
+                report::TestOutcome::Passed => tests_passed += 1,
+                report::TestOutcome::Failed => tests_failed += 1,
+                report::TestOutcome::Skipped if dependency_failed => tests_dependency_skipped += 1,
+                report::TestOutcome::Skipped => tests_skipped += 1,
+                report::TestOutcome::XFail => tests_xfailed += 1,
+                report::TestOutcome::XPass => tests_xpassed += 1,
+            }
+        }
+        if session == 1 {
+            log(format!("Recording the progress into {}.\n", path.display()), Some(true), &mut log_buffer);
+        } else {
+            log(format!("Resuming {} as session {}: {} tests ran in earlier sessions, {} are left.\n", path.display(),
+             session, resumed_results.len(), schedule.len()), Some(true), &mut log_buffer);
+        }
+    }
+    // The identifiers of the run, a variable or define of the same name or an earlier session pins them
+    let mut run_identity = runid::RunIdentity::generate();
+    run_identity.pin(&captures);
+    captures.extend(run_identity.variables());
+    log(format!("Run id: {}, run timestamp: {}\n", run_identity.run_id, run_identity.run_timestamp), Some(true),
+     &mut log_buffer);
+
+    let resumed_count = resumed_results.len();
+    let mut checkpointed = 0; // tests of the schedule whose results the resume state holds
+
+    // A capture silently replacing a variable is easily overlooked
+    for (index, name) in dependency::shadowed_variables(&rest_test_config.tests, &variable_names).iter() {
+        log(format!("{}\n", format!("Warning: The capture '{}' of test {} shadows the variable of the same name.",
+         name, index).yellow()), Some(true), &mut log_buffer);
+    }
+
+    // Storage for captured cookies
+    let mut cookie_jar = CookieJar::new();
+
+    // A single bucket for the whole run, a repetition or retry waits like any other request
+    let rate_limiter = rest_test_config.rate_limit.as_ref().map(ratelimit::RateLimiter::new);
+
+    // Snapshots are stored next to the config
+    let snapshot_directory = rest_test_config.source.as_ref()
+        .and_then(|source| source.parent())
+        .map_or(utils::get_cwd(), |directory| directory.to_path_buf())
+        .join(snapshot::DIRECTORY_NAME);
+
+    // A directory that cant be prepared doesnt stop the run, there are just no transcripts
+    let mut transcript_directory = resolved.transcripts.clone();
+
+    // Writing the transcripts would replace the recording before its responses were compared
+    if options.compare_with.is_some() && options.compare_with == transcript_directory {
+        log_error("Error while validating config file: The transcripts would be written into the directory of \
+         --compare-with.\n".to_string(), &mut log_buffer);
+        return (RunOutcome::ConfigError, None);
+    }
+    if let Some(directory) = &transcript_directory {
+        if let Err(error) = transcript::prepare(directory, options.keep_transcripts) {
+            log_error(format!("Error while preparing the transcripts directory {}: {}\n", directory.display(), error),
+             &mut log_buffer);
+            transcript_directory = None;
+        }
+    }
+
+    // Dont wait for the timeouts of every test if the API cant be reached at all. Tests with a connect_to never
+    // connect to the host of the api_address, so their address is probed instead.
+    if !options.no_preflight && !schedule.is_empty() {
+        let api_uri = api_address.parse::<hyper::Uri>().ok();
+        let mut pinned: Vec<std::net::SocketAddr> = Vec::new();
+        let mut probes_host = false;
+        for (config_index, _, _) in schedule.iter() {
+            let target = rest_test_config.tests[*config_index].connect_to.as_deref().zip(api_uri.as_ref())
+                .and_then(|(target, uri)| connect::parse_target(target, uri));
+            match target {
+                Some(address) if !pinned.contains(&address) => pinned.push(address),
+                Some(_) => (),
+                None => probes_host = true,
+            }
+        }
+
+        let mut probes = Vec::new();
+        if probes_host {
+            probes.push(preflight::check(api_address).await);
+        }
+        for address in pinned {
+            probes.push(preflight::check_address(address).await);
+        }
+        for probe in probes {
+            match probe {
+                Ok(reachable) => log(format!("Preflight: {}\n", reachable), Some(true), &mut log_buffer),
+                Err(error) => {
+                    log_error(format!("{} {}, no test was run. Pass --no-preflight to skip this check.\n",
+                     "Preflight failed:".red().bold(), error), &mut log_buffer);
+                    if let Some(destination) = log_destination {
+                        write_logfile(log_buffer.text, destination);
+                    }
+                    return (RunOutcome::Offline, None);
+                },
+            }
+        }
+    }
+
+    // Results of the individual tests for the reports
+    let started_at = chrono::Local::now();
+    let run_start = Instant::now();
+    let mut results: Vec<report::TestResult> = resumed_results;
+
+    // What the tests responded with, --record-expectations turns it into expectations after the run
+    let mut observations: BTreeMap<usize, record::Observation> = BTreeMap::new();
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let mut was_interrupted = false;
+    let mut budget_exceeded = false;
+    let mut paused = Duration::ZERO;
+    let interrupt_watcher = options.handle_interrupts.then(|| tokio::spawn(watch_interrupts(interrupted.clone())));
+
+    // Step mode needs someone at the terminal to answer
+    let step_mode = options.step && step::is_interactive();
+    if options.step && !step_mode {
+        log(format!("{}\n", "Warning: --step needs an interactive terminal, running without pauses.".yellow()),
+         Some(true), &mut log_buffer);
+    }
+
+    // The hosts every test is sent to, unparsable addresses are matched as they are
+    let base_hosts: Vec<String> = [Some(api_address), rest_test_config.compare_against.as_ref()].into_iter().flatten()
+        .map(|address| hosts::host(address).unwrap_or_else(|| address.clone()))
+        .collect();
+    let allowed_hosts = |config_index: usize| rest_test_config.tests[config_index].allowed_hosts.as_ref()
+        .or(rest_test_config.allowed_hosts.as_ref());
+
+    // A strict guard stops the run before the first request if any test would reach a host it doesnt allow
+    if rest_test_config.enforce_allowed_hosts.as_deref() == Some("strict") {
+        let violation = schedule[test_index..].iter().find_map(|(config_index, _, _)| {
+            let test = &rest_test_config.tests[*config_index];
+            let target_hosts = hosts::targets(test, &base_hosts, &captures);
+            allowed_hosts(*config_index).and_then(|patterns| hosts::disallowed(patterns, &target_hosts).cloned())
+                .map(|host| (test_reference(test, config_index + 1), host))
+        });
+        if let Some((test, host)) = violation {
+            abort_reason = Some(format!("Test {} doesnt allow the host {}, cancelling all tests \
+             (enforce_allowed_hosts: strict).", test, host));
+        }
+    }
+
+    while abort_reason.is_none() && test_index < schedule.len() {
+        if let (Some(state), Some(path)) = (&mut resume_state, &options.resume) {
+            checkpoint_resume(state, path, &schedule[checkpointed..test_index], &captures, &mut results, session,
+             &mut log_buffer);
+            checkpointed = test_index;
+        }
+
+        // The pauses between the tests dont count towards the budget
+        if step_mode && test_index > 0 {
+            let paused_at = Instant::now();
+            let action = step::pause(&captures).await;
+            paused += paused_at.elapsed();
+
+            match action {
+                step::StepAction::Continue => (),
+                step::StepAction::Quit => {
+                    abort_reason = Some("Quit in step mode, cancelling all further tests.".to_string());
+                    break;
+                },
+                step::StepAction::Rerun => {
+                    schedule.insert(test_index, schedule[test_index - 1]);
+                    test_count += 1;
+                },
+                step::StepAction::Skip => {
+                    let config_index = schedule[test_index].0;
+                    let test = &rest_test_config.tests[config_index];
+                    let mut result = report::TestResult::new(config_index + 1,
+                     test.it.clone().unwrap_or_else(|| generate_description(test)), &test.method, &test.route,
+                     api_address.to_owned() + &test.route);
+                    result.name = test.name.clone();
+                    result.meta = test.meta.iter().flatten().map(|(key, value)| (key.clone(), value.clone())).collect();
+                    result.outcome = report::TestOutcome::Skipped;
+                    result.skip_reason = Some("skipped in step mode".to_string());
+                    log(format!("{}", format!("Test {}/{} skipped in step mode\n\n", test_index + 1, test_count)
+                     .yellow().bold()), Some(true), &mut log_buffer);
+                    results.push(result);
+                    tests_not_run += 1;
+                    test_index += 1;
+                    continue;
+                },
+            }
+        }
+
+        let (config_index, repetition, repeat) = schedule[test_index];
+
+        if interrupted.load(Ordering::SeqCst) {
+            was_interrupted = true;
+            abort_reason = Some("Run interrupted, cancelling all further tests.".to_string());
+            break;
+        }
+
+        // A running request is never cut short by the budget, it has its own timeout
+        if let Some(budget) = max_duration {
+            if run_start.elapsed().saturating_sub(paused) >= budget {
+                budget_exceeded = true;
+                abort_reason = Some(format!("Run time budget of {} s exceeded, cancelling all further tests.",
+                 budget.as_secs()));
+                break;
+            }
+        }
+
+        let test = &rest_test_config.tests[config_index];
+        let mut response_time: u128 = 0;
+        test_index += 1;
+
+        // Keeps interleaved or grepped logs attributable to their test
+        let settings = &resolved.tests[config_index];
+        if resolved.prefix_test_output {
+            log_buffer.prefix = Some(format!("[{}] ", test.name.clone().unwrap_or(format!("#{}", config_index + 1))));
+        }
+
+        let verbose = settings.verbose;
+        let is_critical = settings.critical;
+        let aborts_on_failure = is_critical && !options.continue_on_critical;
+        let time_boundaries = settings.time_boundaries;
+        let timeout_ms = settings.timeout_ms;
+        let boundaries_level = settings.boundaries_level;
+
+        // Print current test index
+        let mut details = Vec::new();
+        if shows_config_index {
+            details.push(format!("#{}", config_index + 1));
+        }
+        if repeat > 1 {
+            details.push(format!("repetition {}/{}", repetition, repeat));
+        }
+
+        let position = if details.is_empty() {
+            format!("Test {}/{}\n", test_index, test_count)
+        } else {
+            format!("Test {}/{} ({})\n", test_index, test_count, details.join(", "))
+        };
+        log(position.bold().bright_blue().to_string(), Some(true), &mut log_buffer);
+
+        // Print test description if available
+        match &test.it {
+            Some(description) => log(format!("{}\n", description.clone().yellow()),
+             Some(true), &mut log_buffer),
+            None => {
+                match test.auto_description {
+                    Some(condition) => { if condition {
+                        log(format!("{}\n", generate_description(test)), Some(true), &mut log_buffer);
+                    } },
+                    None => log(format!("{}\n", generate_description(test)), Some(true), &mut log_buffer),
+                }
+            },
+        };
+
+        let mut result = report::TestResult::new(config_index + 1,
+         test.it.clone().unwrap_or_else(|| generate_description(test)), &test.method, &test.route, api_address.to_owned() + &test.route);
+        result.name = test.name.clone();
+        result.meta = test.meta.iter().flatten().map(|(key, value)| (key.clone(), value.clone())).collect();
+        result.xfail = test.xfail.clone();
+
+        // Skipped on purpose, e.g. until the ticket of the reason is resolved
+        if let Some(reason) = &test.skip {
+            log(format!("Skipped: {}\n", reason), Some(true), &mut log_buffer);
+            log(format!("{}", "TEST SKIPPED\n\n".yellow().bold()), Some(true), &mut log_buffer);
+            tests_skipped += 1;
+
+            result.outcome = report::TestOutcome::Skipped;
+            result.skip_reason = Some(reason.clone());
+            results.push(result);
+            continue;
+        }
+
+        // A test reading the captures of a failed critical test would only fail as well
+        if options.continue_on_critical {
+            let failed_dependency = dependency::all_producers(&graph, config_index).into_iter()
+                .find(|producer| resolved.tests[*producer].critical && results.iter()
+                    .any(|earlier| earlier.index == producer + 1 && earlier.outcome == report::TestOutcome::Failed));
+            if let Some(producer) = failed_dependency {
+                let reason = format!("critical dependency failed: test {}",
+                 test_reference(&rest_test_config.tests[producer], producer + 1));
+                log(format!("Skipped, the {}\n", reason), Some(true), &mut log_buffer);
+                log(format!("{}", "TEST SKIPPED\n\n".yellow().bold()), Some(true), &mut log_buffer);
+                tests_dependency_skipped += 1;
+
+                result.outcome = report::TestOutcome::Skipped;
+                result.skip_reason = Some(reason);
+                results.push(result);
+                continue;
+            }
+        }
+
+        // Skip the test if its condition isnt met, the expression got validated beforehand
+        if let Some(expression) = &test.when {
+            if let Ok(condition) = condition::Condition::parse(expression) {
+                if !condition.evaluate(&captures) {
+                    let values: Vec<String> = condition.captures().iter()
+                        .map(|name| format!("{} = {}", name,
+                            captures.get(*name).map_or("<none>".into(), interpolation::text)))
+                        .collect();
+                    let reason = format!("Condition '{}' evaluated to false ({})", expression, values.join(", "));
+                    log(format!("{}\n", reason), Some(true), &mut log_buffer);
+                    log(format!("{}", "TEST SKIPPED\n\n".yellow().bold()), Some(true), &mut log_buffer);
+                    tests_skipped += 1;
+
+                    result.outcome = report::TestOutcome::Skipped;
+                    result.skip_reason = Some(reason);
+                    results.push(result);
+                    continue;
+                }
+            }
+        }
+
+        // Keeps e.g. destructive tests away from production, whatever the api_address resolved to
+        let target_hosts = hosts::targets(test, &base_hosts, &captures);
+        if let Some((patterns, host)) = allowed_hosts(config_index)
+            .and_then(|patterns| hosts::disallowed(patterns, &target_hosts).map(|host| (patterns, host))) {
+            let reason = format!("the host {} is not one of the allowed_hosts ({})", host, patterns.join(", "));
+            log(format!("{}\n", format!("Warning: Skipped, {}.", reason).yellow().bold()), Some(true), &mut log_buffer);
+            log(format!("{}", "TEST SKIPPED\n\n".yellow().bold()), Some(true), &mut log_buffer);
+            tests_host_skipped += 1;
+
+            result.outcome = report::TestOutcome::Skipped;
+            result.skip_reason = Some(reason);
+            results.push(result);
+            continue;
+        }
+
+        // A failing pre_script fails the test before anything is sent
+        #[cfg(feature = "scripting")]
+        if let Some(reason) = run_pre_script(test, api_address, &rest_test_config.headers, &mut captures) {
+            result.fail(reason);
+
+            log_failure_reasons(&result, &mut errors, &mut log_buffer);
+            let outcome = conclude(&mut result, false, strict_xfail, &mut log_buffer);
+            results.push(result);
+            if outcome == report::TestOutcome::XFail {
+                tests_xfailed += 1;
+                continue;
+            }
+
+            tests_failed += 1;
+            abort_reason = check_abort(aborts_on_failure, tests_failed, max_failures, options.fail_fast);
+            if abort_reason.is_some() {
+                break;
+            }
+            continue;
+        }
+
+        // Check if the http method is valid
+        let method = match validate_http_method(&test.method) {
+            Some(value) => value,
+            None => panic!("Unknown or unsupported method {}", test.method),
+        };
+
+        // Construct the api url
+        let route = &interpolation::interpolate_route(&test.route, &captures);
+        let url = match &test.scheme {
+            Some(scheme) => swap_scheme(&(api_address.to_owned() + route), scheme),
+            None => api_address.to_owned() + route,
+        };
+        result.url = url.clone();
+        log_debug(format!("Request URL: {}\n", url), verbose, &mut log_buffer);
+        let url = match url.parse::<hyper::Uri>() {
+            Ok(url) => url,
+            Err(error) => {
+                result.error = Some(format!("Invalid url '{}': {}", url, error));
+                result.fail(failure::FailureReason::InvalidUrl { url: url.clone(), error: error.to_string() });
+
+                log_failure_reasons(&result, &mut errors, &mut log_buffer);
+                let outcome = conclude(&mut result, false, strict_xfail, &mut log_buffer);
+                results.push(result);
+                if outcome == report::TestOutcome::XFail {
+                    tests_xfailed += 1;
+                    continue;
+                }
+
+                tests_failed += 1;
+                abort_reason = check_abort(aborts_on_failure, tests_failed, max_failures, options.fail_fast);
+                if abort_reason.is_some() {
+                    break;
+                }
+                continue;
+            },
+        };
+
+        let body = construct_body(test, &captures);
+
+        // Create buffer for the response body
+        let mut buffer = bytes::BytesMut::with_capacity(512);
+        let mut event_stream: Option<sse::EventStream> = None;
+        let mut websocket_outcome: Option<websocket::Outcome> = None;
+        let mut sent_headers = hyper::HeaderMap::new();
+        let mut phase_timings: Option<connect::PhaseTimings> = None;
+        let mut wire_body: Option<bytes::Bytes> = None;
+        let mut body_size: Option<compression::BodySize> = None;
+        let mut redirect_chain: Vec<String> = Vec::new();
+
+        // Resolve credentials and static headers against the global defaults
+        let bearer_token = resolve_credential(&test.bearer_token,
+         &rest_test_config.bearer_token, &captures);
+        let session_id = resolve_credential(&test.session_id,
+         &rest_test_config.session_id, &captures);
+        let mut headers = resolve_headers(&test.headers, &rest_test_config.headers);
+        let cors_origin = test.cors.as_ref().map(|preflight| interpolation::interpolate(&preflight.origin, &captures));
+        if let (Some(preflight), Some(origin)) = (&test.cors, &cors_origin) {
+            headers.extend(preflight.preflight_headers(origin));
+        }
+        for (name, value) in test.conditional.iter().flat_map(|conditional| conditional.headers()) {
+            headers.retain(|key, _| !key.eq_ignore_ascii_case(&name));
+            headers.insert(name, value);
+        }
+        if test.sse == Some(true) && !headers.keys().any(|key| key.eq_ignore_ascii_case("accept")) {
+            headers.insert("Accept".to_string(), "text/event-stream".to_string());
+        }
+        // The headers of a gateway forwarding the request, unless the test or the config set them on their own
+        let forwarded_for = match &test.forwarded_for {
+            Some(local) => local.as_ref(),
+            None => rest_test_config.forwarded_for.as_ref(),
+        };
+        let scheme = url.scheme_str().unwrap_or("http");
+        for (name, value) in forwarded_for.iter().flat_map(|ip| forwarded::headers(ip, scheme)) {
+            if !headers.keys().any(|key| key.eq_ignore_ascii_case(&name)) {
+                headers.insert(name, value);
+            }
+        }
+        // Gives the server the chance to compress, unless the test negotiates on its own
+        let asserts_compression = test.expect_encoding.is_some() || test.min_compression_ratio.is_some();
+        if asserts_compression && !headers.keys().any(|key| key.eq_ignore_ascii_case("accept-encoding")) {
+            headers.insert("Accept-Encoding".to_string(),
+             compression::accept_encoding(test.expect_encoding.as_deref()).to_string());
+        }
+        // The key of an idempotency check is generated unless the test sets it
+        let idempotency_key = (test.idempotency_check == Some(true)).then(|| {
+            match headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(idempotency::HEADER_NAME)) {
+                Some((_, value)) => interpolation::interpolate(value, &captures),
+                None => {
+                    let key = idempotency::generate_key();
+                    headers.insert(idempotency::HEADER_NAME.to_string(), key.clone());
+                    key
+                },
+            }
+        });
+        if let Some(key) = &idempotency_key {
+            log(format!("Idempotency-Key: {}\n", key), Some(true), &mut log_buffer);
+        }
+
+        // Every test starts a new trace unless it continues an earlier one or sets the header itself
+        if let Some(header) = trace_header.filter(|header| !headers.keys().any(|key| key.eq_ignore_ascii_case(header.name()))) {
+            let reused = test.trace_id.as_ref().map(|id| interpolation::interpolate(id, &captures));
+            let context = match reused.as_deref().map(|id| trace::resume(header, id)) {
+                Some(Ok(context)) => context,
+                Some(Err(error)) => {
+                    log(format!("{}\n", format!("Warning: Cannot continue the trace, {}, starting a new one.", error)
+                     .yellow()), Some(true), &mut log_buffer);
+                    trace::start(header)
+                },
+                None => trace::start(header),
+            };
+            log(format!("{}: {}\n", context.header, context.value), Some(true), &mut log_buffer);
+            headers.insert(context.header.to_string(), context.value);
+            result.trace_id = Some(context.id);
+        }
+
+        let websocket_request = test.websocket.as_ref().map(|exchange| websocket::WebSocketRequest::new(
+            exchange.send.as_ref().map(|message| interpolation::interpolate(message, &captures)),
+            Duration::from_millis(exchange.timeout_ms.unwrap_or(timeout_ms as u64))));
+        if let Some(websocket_request) = &websocket_request {
+            headers.extend(websocket_request.upgrade_headers());
+        }
+        let headers: BTreeMap<String, String> = headers.into_iter()
+            .map(|(key, value)| (key, interpolation::interpolate(&value, &captures)))
+            .collect();
+
+        for (name, value) in headers.iter()
+            .filter(|(name, _)| forwarded::HEADER_NAMES.iter().any(|forwarded| forwarded.eq_ignore_ascii_case(name))) {
+            log_debug(format!("{}: {}\n", name, value), verbose, &mut log_buffer);
+        }
+
+        // Credentials are not part of the reports
+        result.request_headers = headers.iter()
+            .map(|(key, value)| (key.clone(), if utils::is_secret_name(key) { "<redacted>".to_string() } else { value.clone() }))
+            .collect();
+        result.request_headers.sort();
+        if bearer_token.is_some() {
+            result.request_headers.push(("Authorization".to_string(), "Bearer <redacted>".to_string()));
+        }
+        if session_id.is_some() {
+            result.request_headers.push(("Cookie".to_string(), "session_id=<redacted>".to_string()));
+        }
+        result.request_body = (!body.is_empty()).then(|| utils::redact_body(&body));
+        if let Some(body) = &result.request_body {
+            log_debug(format!("Request Body: \n{}\n", utils::printable_body(body.as_bytes(), options.raw_body,
+             max_printed_body_bytes)), verbose, &mut log_buffer);
+        }
+
+        if let Some((_, source)) = &bearer_token {
+            log_debug(format!("Bearer token supplied by: {}\n", source), verbose, &mut log_buffer);
+        }
+
+        if let Some((_, source)) = &session_id {
+            log_debug(format!("Session id supplied by: {}\n", source), verbose, &mut log_buffer);
+        }
+
+        // Kept for the diagnostic of a 401 or 403, the credentials themselves move into the request
+        let sent_credentials: Vec<credential::SentCredential> = [
+            credential::sent("bearer_token", &test.bearer_token, bearer_token.as_ref(), &produced_captures),
+            credential::sent("session_id", &test.session_id, session_id.as_ref(), &produced_captures),
+        ].into_iter().flatten().collect();
+
+        // The derived requests of a pagination check only change the limit and offset of the url
+        let page_requests = test.pagination_check.as_ref()
+            .map_or(Vec::new(), |check| pagination::requests(check, &url));
+
+        // The request to the compare_against host only differs in the base url
+        let secondary_address = rest_test_config.compare_against.as_ref()
+            .filter(|_| test.sse != Some(true) && test.websocket.is_none())
+            .map(|compare_against| match &test.scheme {
+                Some(scheme) => swap_scheme(&(compare_against.to_owned() + route), scheme),
+                None => compare_against.to_owned() + route,
+            });
+        let secondary_url = secondary_address.as_ref().map(|address| address.parse::<hyper::Uri>()
+            .map_err(|error| format!("invalid url '{}': {}", address, error)));
+
+        // Construct request data struct
+        let mut test_request = TestRequest {
+            url: &url,
+            method: &method,
+            verbose,
+            timeout: timeout_ms,
+            body,
+            transfer_encoding: test.transfer_encoding.clone(),
+            max_redirects: redirect::follows(test).then(|| test.max_redirects.unwrap_or(redirect::DEFAULT_MAX_REDIRECTS)),
+            redirect_chain: &mut redirect_chain,
+            response_time: &mut response_time,
+            buffer: &mut buffer,
+            wire_body: &mut wire_body,
+            body_size: &mut body_size,
+            bearer_token: bearer_token.map(|(token, _)| token),
+            session_id: session_id.map(|(id, _)| id),
+            headers,
+            digest_auth: test.digest_auth.as_ref(),
+            cookie_jar: &cookie_jar,
+            sse: (test.sse == Some(true)).then(|| sse::SseOptions {
+                expected_events: test.expect_events,
+                max_wait: Duration::from_millis(test.max_wait_ms.unwrap_or(timeout_ms as u64)),
+            }),
+            event_stream: &mut event_stream,
+            websocket: websocket_request.clone(),
+            websocket_outcome: &mut websocket_outcome,
+            raw_body: options.raw_body,
+            max_printed_body_bytes,
+            connect_to: test.connect_to.as_deref().and_then(|target| connect::parse_target(target, &url)),
+            sent_headers: &mut sent_headers,
+            phase_timings: &mut phase_timings,
+            middleware,
+            rate_limiter: rate_limiter.as_ref(),
+            trace_http: options.trace_http,
+            context: TestContext {
+                index: config_index + 1,
+                description: result.description.clone(),
+                method: result.method.clone(),
+                url: url.to_string(),
+                repetition,
+            },
+        };
+
+        // Send the request and get the response, failed attempts are repeated if they qualify for a retry
+        let retries = test.retries.unwrap_or(0);
+        let retry_on: Vec<retry::RetryCondition> = match &test.retry_on {
+            Some(values) => values.iter().filter_map(|value| retry::RetryCondition::parse(value).ok()).collect(),
+            None => retry::DEFAULT_RETRY_ON.to_vec(),
+        };
+
+        let mut attempt = 1;
+        let fetched = loop {
+            let fetched = fetch_url(&mut test_request, &mut log_buffer).await;
+            if retries == 0 {
+                break fetched;
+            }
+
+            let failure = match &fetched {
+                Ok(response) => retry::status_failure(test, response.status().as_u16()),
+                Err(error) => Some(failure::from_transport_error(error.as_ref(), timeout_ms)),
+            };
+            let qualifies = attempt <= retries
+                && failure.as_ref().is_some_and(|reason| retry_on.iter().any(|condition| condition.matches(reason)));
+
+            result.attempts.push(report::Attempt {
+                attempt,
+                status: fetched.as_ref().ok().map(|response| response.status().as_u16()),
+                response_time_ms: fetched.as_ref().ok().map(|_| *test_request.response_time),
+                failure: failure.clone(),
+                retried: qualifies,
+            });
+
+            let reason = match &failure {
+                Some(reason) => reason,
+                None => break fetched,
+            };
+
+            let categories = retry::categories(reason);
+            let detected = if categories.is_empty() { "none".to_string() } else { categories.join(", ") };
+            let verdict = if qualifies {
+                "retrying".to_string()
+            } else if attempt > retries {
+                "no retries left".to_string()
+            } else {
+                format!("doesnt qualify for a retry (retry_on: {})",
+                 retry_on.iter().map(|condition| condition.to_string()).collect::<Vec<String>>().join(", "))
+            };
+            log(format!("Attempt {}/{} failed: {} (category: {}), {}\n", attempt, retries + 1, reason, detected,
+             verdict).yellow().to_string(), Some(true), &mut log_buffer);
+
+            if !qualifies {
+                break fetched;
+            }
+
+            if let Some(delay) = test.retry_delay_ms {
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+            }
+            test_request.buffer.clear();
+            attempt += 1;
+        };
+
+        // The request as it was sent, hyper adds the Host header on its own
+        let transcript_name = transcript::base_name(config_index + 1, repetition, repeat);
+        let request_line = format!("{} {} HTTP/1.1", result.method,
+         url.path_and_query().map_or("/", |path| path.as_str()));
+        let mut request_headers: Vec<(String, String)> = url.authority()
+            .filter(|_| !test_request.sent_headers.contains_key(hyper::header::HOST))
+            .map(|authority| ("host".to_string(), authority.to_string()))
+            .into_iter()
+            .collect();
+        request_headers.extend(test_request.sent_headers.iter()
+            .map(|(name, value)| (name.to_string(), value.to_str().unwrap_or_default().to_string())));
+
+        // The replay is sent with the same headers, the first response remains the one of the test
+        let mut replay_body = bytes::BytesMut::new();
+        if let (Some(key), Ok(_)) = (&idempotency_key, &fetched) {
+            let first_response = test_request.save_response();
+
+            log(format!("Replaying the request with the Idempotency-Key {}\n", key), Some(true), &mut log_buffer);
+            let replayed = fetch_url(&mut test_request, &mut log_buffer).await;
+            replay_body = test_request.buffer.split();
+            let replay_time = *test_request.response_time;
+            test_request.restore_response(first_response);
+
+            result.replay = Some(idempotency::Replay {
+                idempotency_key: key.clone(),
+                status: replayed.as_ref().ok().map(|response| response.status().as_u16()),
+                response_time_ms: replayed.as_ref().ok().map(|_| replay_time),
+                response_body: report::body_excerpt(&replay_body),
+                error: replayed.as_ref().err().map(|error| error.to_string()),
+            });
+        }
+
+        // The pages are requested after the test, its own response remains the one of the test
+        if let (Some(check), Ok(_)) = (&test.pagination_check, &fetched) {
+            let first_response = test_request.save_response();
+
+            for request in page_requests.iter() {
+                log(format!("Pagination check, requesting {}: {}\n", request.step, request.url), Some(true),
+                 &mut log_buffer);
+                test_request.url = &request.url;
+                test_request.context.url = request.url.to_string();
+                test_request.redirect_chain.clear();
+
+                let page = fetch_url(&mut test_request, &mut log_buffer).await;
+                let page_body = test_request.buffer.split();
+                let step = pagination::record(check, request, page.as_ref().ok().map(|response| response.status().as_u16()),
+                 page.as_ref().ok().map(|_| *test_request.response_time), &page_body,
+                 page.as_ref().err().map(|error| error.to_string()));
+                log_debug(format!("Pagination check, {}: {} elements, {}\n", step.step,
+                 step.items.map_or("no".to_string(), |items| items.to_string()),
+                 step.status.map_or("no response".to_string(), |status| format!("status {}", status))),
+                 verbose, &mut log_buffer);
+                result.pagination.push(step);
+            }
+
+            test_request.url = &url;
+            test_request.context.url = url.to_string();
+            test_request.restore_response(first_response);
+        }
+
+        // The compare_against host gets the same request, the response of the api_address remains the one of the test
+        let mut secondary: Option<std::result::Result<comparison::Secondary, String>> = None;
+        if let (Some(secondary_url), Ok(_)) = (&secondary_url, &fetched) {
+            secondary = Some(match secondary_url {
+                Ok(secondary_url) => {
+                    let first_response = test_request.save_response();
+                    let connect_to = test_request.connect_to.take(); // pins the connection to the primary host
+
+                    log(format!("Sending the request to {} for comparison\n", secondary_url), Some(true),
+                     &mut log_buffer);
+                    test_request.url = secondary_url;
+                    test_request.context.url = secondary_url.to_string();
+                    test_request.redirect_chain.clear();
+
+                    let compared = fetch_url(&mut test_request, &mut log_buffer).await;
+                    let secondary_body = test_request.buffer.split();
+                    result.comparison = Some(comparison::Comparison {
+                        url: secondary_url.to_string(),
+                        status: compared.as_ref().ok().map(|response| response.status().as_u16()),
+                        response_time_ms: compared.as_ref().ok().map(|_| *test_request.response_time),
+                        response_body: report::body_excerpt(&secondary_body),
+                        error: compared.as_ref().err().map(|error| error.to_string()),
+                    });
+
+                    test_request.url = &url;
+                    test_request.context.url = url.to_string();
+                    test_request.connect_to = connect_to;
+                    test_request.restore_response(first_response);
+
+                    compared.map(|response| comparison::Secondary {
+                        status: response.status().as_u16(),
+                        headers: response.headers().iter()
+                            .map(|(name, value)| (name.to_string(), value.to_str().unwrap_or_default().to_string()))
+                            .collect(),
+                        body: secondary_body,
+                    }).map_err(|error| error.to_string())
+                },
+                Err(error) => Err(error.clone()),
+            });
+        }
+
+        result.redirect_chain = test_request.redirect_chain.clone();
+
+        let response = match fetched {
+            Ok(res) => res,
+            Err(error) => { 
+                if errors.shows(&failure::from_transport_error(error.as_ref(), timeout_ms)) {
+                    log_error(format!("Error while sending request: {}\n", error), &mut log_buffer);
+                }
+                result.error = Some(error.to_string());
+
+                if let Some(directory) = &transcript_directory {
+                    let request = transcript::Message { start_line: request_line, headers: request_headers,
+                     body: result.request_body.as_deref().unwrap_or_default().as_bytes() };
+                    if let Err(error) = transcript::record(directory, &transcript_name, &request, None) {
+                        log_error(format!("Error while writing the transcript: {}\n", error), &mut log_buffer);
+                    }
+                }
+                let category = assertions::classify_error(error.as_ref());
+
+                // The test might expect the request to fail
+                if let Some(expected) = &test.expect_error {
+                    log(format!("Expected error: {}, observed error category: {}\n", expected, category),
+                     Some(true), &mut log_buffer);
+                    result.assert(format!("Expected a {} error, observed a {} error", expected, category),
+                     category.satisfies(expected));
+
+                    if category.satisfies(expected) {
+                        match conclude(&mut result, true, strict_xfail, &mut log_buffer) {
+                            report::TestOutcome::XPass => tests_xpassed += 1,
+                            _ => tests_passed += 1,
+                        }
+                        results.push(result);
+                        continue;
+                    }
+
+                    result.fail(failure::FailureReason::ErrorMismatch {
+                        expected: expected.to_string(),
+                        actual: category.to_string(),
+                    });
+                } else {
+                    result.fail(failure::from_transport_error(error.as_ref(), timeout_ms));
+                }
+                log_failure_reasons(&result, &mut errors, &mut log_buffer);
+                let outcome = conclude(&mut result, false, strict_xfail, &mut log_buffer);
+
+                results.push(result);
+                if outcome == report::TestOutcome::XFail {
+                    tests_xfailed += 1;
+                    continue;
+                }
+                tests_failed += 1;
+                abort_reason = check_abort(aborts_on_failure, tests_failed, max_failures, options.fail_fast);
+                if abort_reason.is_some() {
+                    break;
+                }
+                continue
+            },
+        };
+
+        let response_status = response.status();
+
+        result.status = Some(response_status.as_u16());
+        result.response_time_ms = Some(response_time);
+        result.timings = phase_timings;
+        result.response_body = report::body_excerpt(&buffer);
+        result.body_size = body_size.clone().filter(|size| size.encoding.is_some() || asserts_compression);
+        let received_body = wire_body.as_deref().unwrap_or(&buffer); // exact comparisons and recordings keep the charset
+        // Like the request headers, e.g. the session of a Set-Cookie is not part of the reports
+        result.response_headers = response.headers().iter()
+            .map(|(name, value)| (name.to_string(), if utils::is_secret_name(name.as_str()) {
+                "<redacted>".to_string()
+            } else {
+                value.to_str().unwrap_or_default().to_string()
+            }))
+            .collect();
+
+        if let Some(directory) = &transcript_directory {
+            let request = transcript::Message { start_line: request_line, headers: request_headers,
+             body: result.request_body.as_deref().unwrap_or_default().as_bytes() };
+            let response = transcript::Message { start_line: format!("{:?} {}", response.version(), response_status),
+             headers: result.response_headers.clone(), body: received_body };
+            if let Err(error) = transcript::record(directory, &transcript_name, &request, Some(&response)) {
+                log_error(format!("Error while writing the transcript: {}\n", error), &mut log_buffer);
+            }
+        }
+
+        // The body is saved as it was received, whatever the outcome of the test
+        if let Some(save) = &test.save_response {
+            let server_filename = download::server_filename(response.headers());
+            let usable = server_filename.as_deref().and_then(download::sanitize_filename).is_some();
+            if save.use_server_filename == Some(true) && !usable {
+                log(format!("{}\n", "Warning: The response has no usable Content-Disposition filename, it is saved \
+                 under the index of the test.".yellow()), Some(true), &mut log_buffer);
+            }
+            let path = download::target(save, &transcript_name, server_filename.as_deref());
+            match download::save(&path, received_body) {
+                Ok(()) => {
+                    log(format!("Saved the response to {}\n", path.display()), Some(true), &mut log_buffer);
+                    result.saved_file = Some(path.display().to_string());
+                },
+                Err(error) => log_error(format!("Error while saving the response to {}: {}\n", path.display(), error),
+                 &mut log_buffer),
+            }
+        }
+
+        // Add every cookie of the response to the cookie jar
+        let (response_cookies, cookie_errors) = cookies::parse_set_cookies(response.headers());
+        for error in cookie_errors.iter() {
+            log_error(format!("Error while parsing cookie: {}\n", error), &mut log_buffer);
+        }
+        for cookie in response_cookies.iter() {
+            let (cookie_name, cookie_value) = cookie.name_value();
+            cookie_jar.add(Cookie::new(cookie_name.to_owned(), cookie_value.to_owned()));
+        }
+
+        let json_contract = test.expect_valid_json == Some(true) || test.expect_json_type.is_some();
+        let require_json = json_contract || test.require_json.unwrap_or_else(|| requires_json(test));
+        let (json_body, json_error) = match parse_json_response(&buffer, require_json) {
+            Ok(json_body) => (json_body, None),
+            Err(error) => {
+                if !require_json {
+                    log_error(format!("Error while parsing response body as json: {}\n", error), &mut log_buffer);
+                }
+                (None, Some(error))
+            },
+        };
+        // The body is compared with the capture as earlier tests left it, not with what this test stores under the key
+        let compared_capture = test.expect_body_equals_capture.as_ref().and_then(|key| captures.get(key).cloned());
+        let stored = capture_values(test, &mut captures, &CaptureSource {
+            json_body: json_body.as_ref(),
+            body: &buffer,
+            status: response_status.as_u16(),
+            headers: response.headers(),
+            cookies: &response_cookies,
+            events: event_stream.as_ref().map_or(&[], |stream| &stream.events),
+            response_time,
+        });
+        for name in stored.into_iter() {
+            match capture_producers.insert(name.clone(), (config_index, chrono::Local::now())).map(|(index, _)| index) {
+                Some(previous) if previous != config_index && options.warn_capture_overwrite => {
+                    log(format!("{}\n", format!("Warning: Test {} overwrote the capture '{}' of test {}.",
+                     test_reference(test, config_index + 1), name,
+                     test_reference(&rest_test_config.tests[previous], previous + 1)).yellow()), Some(true), &mut log_buffer);
+                },
+                _ => (),
+            }
+        }
+        #[cfg(feature = "scripting")]
+        let script_failure = run_post_script(test, &mut captures, &result, response.headers(), &buffer);
+        #[cfg(not(feature = "scripting"))]
+        let script_failure: Option<failure::FailureReason> = None;
+
+        let response_time_output = format!("Response time: {} ms", response_time);
+
+        // Evaluate the response time
+        let time_class = report::TimeClass::classify(response_time, &time_boundaries);
+        result.time_class = Some(time_class);
+        result.time_boundaries = Some(time_boundaries);
+        match time_class {
+            report::TimeClass::Green => log(format!("{}\n", response_time_output.green()),
+             Some(true), &mut log_buffer),
+            report::TimeClass::Yellow => log(format!("{}\n", response_time_output.yellow()),
+             Some(true), &mut log_buffer),
+            report::TimeClass::Red => log(format!("{}\n", response_time_output.red()),
+             Some(true), &mut log_buffer),
         }
-    };
 
-    // Set buffer to Some if a destination directory is specified
-    let mut log_buffer: Option<String> = None;
-    if rest_test_config.to_file.is_some() { 
-        log_buffer = Some(Default::default());
-    };
+        if let Some(replay_time) = result.replay.as_ref().and_then(|replay| replay.response_time_ms) {
+            log(format!("Response time of the replay: {} ms\n", replay_time), Some(true), &mut log_buffer);
+        }
+        if let Some(comparison) = result.comparison.as_ref().filter(|comparison| comparison.response_time_ms.is_some()) {
+            log(format!("Response time of {}: {} ms\n", comparison.url, comparison.response_time_ms.unwrap_or_default()),
+             Some(true), &mut log_buffer);
+        }
 
-    // The target address
-    let api_address = &rest_test_config.api_address;
+        if yellow_is_warning && time_class != report::TimeClass::Green {
+            log(format!("{}\n", format!("Warning: The response time exceeds the first time boundary of {} ms.",
+             time_boundaries.ok).yellow()), Some(true), &mut log_buffer);
+        }
+ 
+        // Check expectations
+        let level = |assertion| severity::level(test.severity.as_ref(), assertion);
+        let mut test_passed = true;
+        if let Some(status) = test.status {
+            log(format!("Expected Status: {}\n", status),
+             Some(true), &mut log_buffer);
+            let status_matches = response_status == status;
+            result.assert(format!("Expected status {}, got {}", status, response_status.as_u16()), status_matches);
+            if !status_matches && result.violate(level("status"),
+             failure::FailureReason::StatusMismatch { expected: status, actual: response_status.as_u16() }) {
+                test_passed = false;
+            }
+        }
 
-    // Get verbose value, default to false
-    let global_verbose = rest_test_config.verbose.unwrap_or(false);
+        // The captures and json assertions of the test would only see an empty body, the contract replaces this check
+        if let (Some(error), true, false) = (&json_error, require_json, json_contract) {
+            let reason = failure::invalid_json(error, &String::from_utf8_lossy(&buffer));
+            record_assertion("Expected a json body".to_string(), Some(reason), level("require_json"), &mut result,
+             &mut test_passed, &mut log_buffer);
+        }
 
-    let test_count = rest_test_config.tests.len();
-    let mut test_index = 0;
-    let mut tests_passed = 0;
+        if json_contract {
+            let (message, failure) = assertions::check_json_contract(json_body.as_ref(), json_error.as_ref(),
+             test.expect_json_type, response.headers(), &buffer);
+            let contract = if test.expect_json_type.is_some() { "expect_json_type" } else { "expect_valid_json" };
+            record_assertion(message, failure, level(contract), &mut result, &mut test_passed, &mut log_buffer);
+        }
 
-    // Get boundaries, set to default values if not found
-    let mut time_boundaries = rest_test_config.time_boundaries.unwrap_or([500, 1000, 10000]);
+        if let Some(reason) = script_failure {
+            record_assertion("post_script".to_string(), Some(reason), level("post_script"), &mut result,
+             &mut test_passed, &mut log_buffer);
+        }
 
-    // Container for user-defined captured values
-    let mut captures: HashMap<String, String> = Default::default();
+        // The expressions see the captures of this test, too
+        for source in test.expect_expr.iter().flatten() {
+            let expectation = match expression::Expectation::parse(source) {
+                Ok(expectation) => expectation,
+                Err(_) => continue,
+            };
+            let values = expectation.describe_values(&captures);
+            let failure = match expectation.evaluate(&captures) {
+                Ok((true, _, _)) => None,
+                Ok((false, left, right)) => Some(format!("{} {} {} is false ({})", left, expectation.comparison(), right,
+                 values)),
+                Err(error) => Some(format!("{} ({})", error.message, values)),
+            };
+            let failure = failure.map(|message| failure::FailureReason::ExpressionFailed { expression: source.clone(),
+             message });
+            record_assertion(format!("Expected {}", source), failure, level("expect_expr"), &mut result,
+             &mut test_passed, &mut log_buffer);
+        }
 
-    // Storage for captured cookies
-    let mut cookie_jar = CookieJar::new();
+        // Slow responses only fail tests that ask for it
+        if let Some(fail_on) = test.fail_on.as_deref() {
+            let (limit_class, boundary) = if fail_on == "yellow" {
+                (report::TimeClass::Yellow, time_boundaries.ok)
+            } else {
+                (report::TimeClass::Red, time_boundaries.warn)
+            };
+            let too_slow = time_class >= limit_class;
+            // The boundary that was actually crossed, a red response crossed the second one
+            let crossed = if time_class == report::TimeClass::Red { time_boundaries.warn } else { time_boundaries.ok };
+            let failure = too_slow.then(|| failure::FailureReason::TooSlow { response_time_ms: response_time,
+             boundary_ms: crossed, time_class: time_class.name().to_string(), level: boundaries_level.to_string() });
+            record_assertion(format!("Expected response time below the {} boundary of {} ms, took {} ms", fail_on,
+             boundary, response_time), failure, level("fail_on"), &mut result, &mut test_passed, &mut log_buffer);
+        }
 
-    for test in rest_test_config.tests.iter() {
-        let mut response_time: u128 = 0;
-        test_index += 1;
+        // An endpoint doing real work cant answer this quickly, every repetition is checked on its own
+        if let Some(min_time) = test.min_time_ms {
+            let failure = (response_time < min_time)
+                .then_some(failure::FailureReason::TooFast { response_time_ms: response_time, limit_ms: min_time });
+            record_assertion(format!("Expected a response time of at least {} ms, took {} ms", min_time, response_time),
+             failure, level("min_time_ms"), &mut result, &mut test_passed, &mut log_buffer);
+        }
 
-        // Local verbosity is of higher precedence
-        let verbose = match test.verbose {
-            Some(condition) => condition,
-            None => global_verbose,
-        };
+        // A reused connection had no connection phases, so their limits always hold
+        let phase_limits = [(test.max_dns_ms, "max_dns_ms"), (test.max_connect_ms, "max_connect_ms"),
+         (test.max_tls_ms, "max_tls_ms"), (test.max_ttfb_ms, "max_ttfb_ms"), (test.max_download_ms, "max_download_ms")];
+        if let Some(timings) = &phase_timings {
+            for ((phase, took), (limit, field)) in timings.phases().into_iter().zip(phase_limits) {
+                let limit = match limit {
+                    Some(limit) => limit as u128,
+                    None => continue,
+                };
+                let failure = (took > limit).then(|| failure::FailureReason::PhaseTooSlow {
+                    phase: phase.to_string(),
+                    took_ms: took,
+                    limit_ms: limit,
+                });
+                record_assertion(format!("Expected the {} phase to take at most {} ms, took {} ms", phase, limit, took),
+                 failure, level(field), &mut result, &mut test_passed, &mut log_buffer);
+            }
+        }
 
-        // Determine criticalness, default to false
-        let is_critical = test.critical.unwrap_or(false);
+        if let Some(not_status) = &test.expect_not_status {
+            let excluded = not_status.as_slice().iter()
+                .any(|pattern| pattern.matches(response_status.as_u16()));
+            let patterns = assertions::describe_patterns(not_status.as_slice());
+            let message = format!("Expected status NOT in {}, got {}", patterns, response_status.as_u16());
+            let failure = excluded.then(|| failure::FailureReason::StatusExcluded {
+                excluded: patterns,
+                actual: response_status.as_u16(),
+            });
+            record_assertion(message, failure, level("expect_not_status"), &mut result, &mut test_passed,
+             &mut log_buffer);
+        }
 
-        // Overwrite time boundaries if there is a local definition
-        time_boundaries = match test.time_boundaries {
-            Some(value) => value,
-            None => time_boundaries,
-        };
+        if let Some(expected) = &test.expect_array_length {
+            match assertions::check_array_length(json_body.as_ref(), expected) {
+                Ok(message) => {
+                    log(format!("{}\n", message), Some(true), &mut log_buffer);
+                    result.assert(message, true);
+                },
+                Err((message, reason)) => {
+                    let verdict = severity::verdict(false, level("expect_array_length"));
+                    log(format!("{}\n", message.replacen("FAILED", &verdict, 1)),
+                     Some(true), &mut log_buffer);
+                    result.assert(message, false);
+                    if result.violate(level("expect_array_length"), *reason) {
+                        test_passed = false;
+                    }
+                },
+            };
+        }
 
-        // Print current test index
-        log(format!("Test {}/{}\n", test_index, test_count).bold().bright_blue().to_string(),
-         Some(true), &mut log_buffer);
+        if let Some(expected) = &test.expect_array_contains {
+            match assertions::check_array_contains(json_body.as_ref(), expected) {
+                Ok(message) => {
+                    log(format!("{}\n", message), Some(true), &mut log_buffer);
+                    result.assert(message, true);
+                },
+                Err((message, reason)) => {
+                    let verdict = severity::verdict(false, level("expect_array_contains"));
+                    log(format!("{}\n", message.replacen("FAILED", &verdict, 1)),
+                     Some(true), &mut log_buffer);
+                    result.assert(message, false);
+                    if result.violate(level("expect_array_contains"), *reason) {
+                        test_passed = false;
+                    }
+                },
+            };
+        }
 
-        // Print test description if available
-        match &test.it {
-            Some(description) => log(format!("{}\n", description.clone().yellow()),
-             Some(true), &mut log_buffer),
-            None => {
-                match test.auto_description {
-                    Some(condition) => { if condition {
-                        log(generate_description(test.status,
-                         test.method.clone(), test.route.clone()),
-                    Some(true), &mut log_buffer);
-                    } },
-                    None => log(generate_description(test.status,
-                         test.method.clone(), test.route.clone()),
-                    Some(true), &mut log_buffer),
+        // Listed once, so a reviewer sees what the body comparisons leave out
+        let ignore_paths = test.ignore_paths.clone().unwrap_or_default();
+        if !ignore_paths.is_empty() {
+            log_debug(format!("Ignored paths: {}\n", ignore_paths.join(", ")), verbose, &mut log_buffer);
+        }
+
+        if let Some(expected) = &test.expect_body {
+            let (message, failures) = record::check_body(expected, json_body.as_ref(), &ignore_paths);
+            log(format!("{} — {}\n", message, severity::verdict(failures.is_empty(), level("expect_body"))),
+             Some(true), &mut log_buffer);
+            result.assert(message, failures.is_empty());
+            for reason in failures {
+                if result.violate(level("expect_body"), reason) {
+                    test_passed = false;
                 }
-            },
-        };
+            }
+        }
 
-        // Check if the http method is valid
-        let method = match validate_http_method(&test.method) {
-            Some(value) => value,
-            None => panic!("Unknown or unsupported method {}", test.method),
-        };
+        if let Some(expected_cookies) = &test.expect_cookies {
+            let mut names: Vec<&String> = expected_cookies.keys().collect();
+            names.sort();
 
-        // Construct the api url
-        let route = &test.route;
-        let url = api_address.to_owned() + route;
-        let url = url.parse::<hyper::Uri>().unwrap();
+            for name in names {
+                for check in cookies::check_cookie(name, &expected_cookies[name], &response_cookies) {
+                    record_assertion(check.description, check.failure, level("expect_cookies"), &mut result,
+                     &mut test_passed, &mut log_buffer);
+                }
+            }
+        }
 
-        // Construct json body for the request
-        let mut body: String = String::from("{");
-        match &test.json_body{
-            Some(value_map) => for (key, val) in value_map.iter() {
-                    body += &format!("\"{}\":\"{}\",", key, val);
-                },
-            None => body = String::from(""),
-        };
+        let mut expected_headers: Vec<(&String, &utils::OneOrMany<String>)> = test.expect_headers.iter().flatten().collect();
+        expected_headers.sort_by_key(|(name, _)| name.to_lowercase());
+        for (name, expected) in expected_headers {
+            let (message, failure) = assertions::check_header(name, expected.as_slice(), response.headers());
+            record_assertion(message, failure, level("expect_headers"), &mut result, &mut test_passed, &mut log_buffer);
+        }
 
-        // json doesnt allow a comma after the last key-value pair
-        if body.ends_with(',') {
-            body.pop();
+        if let Some(expected) = &test.expect_location {
+            let expected = interpolation::interpolate(expected, &captures);
+            let (message, failure) = assertions::check_location(&expected, response.headers());
+            record_assertion(message, failure, level("expect_location"), &mut result, &mut test_passed, &mut log_buffer);
         }
-        
-        if body.len() > 0 {
-            body += "}";
+
+        if let Some(pattern) = &test.expect_content_disposition_filename {
+            let (message, failure) = download::check_filename(pattern, response.headers());
+            record_assertion(message, failure, level("expect_content_disposition_filename"), &mut result,
+             &mut test_passed, &mut log_buffer);
         }
 
-        // Create buffer for the response body
-        let mut buffer = bytes::BytesMut::with_capacity(512);
+        if let Some(error) = body_size.as_ref().and_then(|size| size.error.clone()) {
+            let encoding = body_size.as_ref().and_then(|size| size.encoding.clone()).unwrap_or_default();
+            log(format!("Expected a decodable {} body — FAILED, {}\n", encoding, error), Some(true), &mut log_buffer);
+            result.assert(format!("Expected a decodable {} body", encoding), false);
+            result.fail(failure::FailureReason::BodyNotDecodable { encoding, error });
+            test_passed = false;
+        }
 
-        log(format!("Capture Key: {}", test.bearer_token.clone().unwrap_or_default()),
-         Some(verbose), &mut log_buffer);
+        if let Some(expected) = &test.expect_encoding {
+            let (message, failure) = compression::check_encoding(expected, response.headers());
+            record_assertion(message, failure, level("expect_encoding"), &mut result, &mut test_passed, &mut log_buffer);
+        }
 
-        // Construct request data struct
-        let mut test_request = TestRequest {
-            url: &url,
-            method: &method,
-            verbose,
-            timeout: time_boundaries[2],
-            body,
-            response_time: &mut response_time,
-            buffer: &mut buffer,
-            bearer_token: captures.get(&test.bearer_token.clone().unwrap_or_default()).cloned(),
-            cookie_jar: &cookie_jar
+        if let (Some(minimum), Some(size)) = (test.min_compression_ratio, &body_size) {
+            let (message, failure) = compression::check_ratio(minimum, size);
+            record_assertion(message, failure, level("min_compression_ratio"), &mut result, &mut test_passed,
+             &mut log_buffer);
+        }
+
+        if let Some(expected) = &test.expect_redirect_chain {
+            let expected: Vec<String> = expected.iter().map(|hop| interpolation::interpolate(hop, &captures)).collect();
+            let (message, failure) = redirect::check_chain(&expected, &redirect_chain);
+            record_assertion(message, failure, level("expect_redirect_chain"), &mut result, &mut test_passed,
+             &mut log_buffer);
+        }
+
+        // The global list applies to every test that doesnt opt out via null
+        let mut absent_headers: Vec<&String> = match &test.expect_headers_absent {
+            Some(None) => Vec::new(),
+            Some(Some(local)) => rest_test_config.expect_headers_absent.iter().flatten().chain(local.iter()).collect(),
+            None => rest_test_config.expect_headers_absent.iter().flatten().collect(),
         };
+        absent_headers.sort();
+        absent_headers.dedup();
+        for entry in absent_headers {
+            let (message, failure) = assertions::check_absent_header(entry, response.headers());
+            record_assertion(message, failure, level("expect_headers_absent"), &mut result, &mut test_passed,
+             &mut log_buffer);
+        }
 
-        // Send the request and get the response
-        let response = match fetch_url(&mut test_request, &mut log_buffer).await {
-            Ok(res) => res,
-            Err(error) => { 
-                log(format!("Error while sending request: {}\n", error),
-                 Some(true), &mut log_buffer);
-                if is_critical {
-                    log("Test marked as 'critical' failed, cancelling all further tests.\n".to_string(),
-                    Some(true), &mut log_buffer);
-                    return;
+        if let (Some(preflight), Some(origin)) = (&test.cors, &cors_origin) {
+            for check in cors::check_preflight(preflight, origin, response_status.as_u16(), response.headers()) {
+                record_assertion(check.description, check.failure, level("cors"), &mut result, &mut test_passed,
+                 &mut log_buffer);
+            }
+        }
+
+        if let (Some(exchange), Some(outcome), Some(websocket_request)) =
+             (&test.websocket, &websocket_outcome, &websocket_request) {
+            for check in websocket::check_exchange(exchange, outcome, response_status.as_u16(),
+             websocket_request.wait.as_millis()) {
+                record_assertion(check.description, check.failure, level("websocket"), &mut result, &mut test_passed,
+                 &mut log_buffer);
+            }
+        }
+
+        let replay_checks = result.replay.as_ref().map(|replay| idempotency::check_replay(response_status.as_u16(),
+         &buffer, replay, &replay_body, test.idempotency_compare.as_deref() == Some("json")));
+        for check in replay_checks.into_iter().flatten() {
+            record_assertion(check.description, check.failure, level("idempotency_check"), &mut result, &mut test_passed,
+             &mut log_buffer);
+        }
+
+        let pagination_outcomes = test.pagination_check.as_ref()
+            .map(|check| pagination::check_steps(check, &result.pagination));
+        for outcome in pagination_outcomes.into_iter().flatten() {
+            record_assertion(outcome.description, outcome.failure, level("pagination_check"), &mut result,
+             &mut test_passed, &mut log_buffer);
+        }
+
+        if let Some(stream) = &event_stream {
+            let (message, failure) = sse::check_event_count(stream, test.expect_events);
+            let verdict = match (test.expect_events, &failure) {
+                (None, _) => "",
+                (Some(_), None) => " — PASSED",
+                (Some(_), Some(_)) => " — FAILED",
+            };
+            let verdict = verdict.replacen("FAILED", &severity::verdict(false, level("expect_events")), 1);
+            log(format!("{}{}\n", message, verdict), Some(true), &mut log_buffer);
+            if test.expect_events.is_some() {
+                result.assert(message, failure.is_none());
+            }
+            if let Some(reason) = failure {
+                if result.violate(level("expect_events"), reason) {
+                    test_passed = false;
                 }
-                continue
-            },
-        };
+            }
+        }
 
-        let response_status = response.status();
+        if let Some(key) = &test.expect_body_equals_capture {
+            let failure = match &compared_capture {
+                None => Some(failure::FailureReason::CaptureMissing { key: key.clone() }),
+                Some(captured) if interpolation::text(captured).as_bytes() != &buffer[..] => {
+                    Some(failure::FailureReason::BodyAssertion {
+                        path: "$body".to_string(),
+                        expected: format!("the capture '{}' ({})", key,
+                            failure::excerpt(&utils::compact_body(interpolation::text(captured).as_bytes()))),
+                        actual: failure::excerpt(&utils::compact_body(&buffer)),
+                    })
+                },
+                Some(_) => None,
+            };
+            record_assertion(format!("Expected body to equal capture '{}'", key), failure,
+             level("expect_body_equals_capture"), &mut result, &mut test_passed, &mut log_buffer);
+        }
+
+        if let (Some(body_file), Some(expected)) = (&test.expect_body_file, &test.expected_body) {
+            let file = body_file.display().to_string();
+            let expected = if test.interpolate == Some(true) {
+                interpolation::interpolate(expected, &captures)
+            } else {
+                expected.clone()
+            };
+
+            if test.compare.as_deref() == Some("exact") {
+                check_exact_body(&file, expected.as_bytes(), received_body, level("expect_body_file"), &mut result,
+                 &mut test_passed, &mut log_buffer);
+            } else {
+                let as_text = test.compare.as_deref() == Some("text");
+                match snapshot::compare_expected(&expected, &buffer, as_text, &ignore_paths) {
+                    None => {
+                        log(format!("Expected body to match {} — PASSED\n", file), Some(true), &mut log_buffer);
+                        result.assert(format!("Expected body to match {}", file), true);
+                    },
+                    Some((diff, changed)) => {
+                        log(format!("Expected body to match {} — {}\n{}\n", file,
+                         severity::verdict(false, level("expect_body_file")), diff), Some(true), &mut log_buffer);
+                        result.assert(format!("Expected body to match {}", file), false);
+                        if result.violate(level("expect_body_file"),
+                         failure::FailureReason::BodyMismatch { file, changed_lines: changed, diff }) {
+                            test_passed = false;
+                        }
+                    },
+                }
+            }
+        }
 
-        parse_json_response(buffer, &mut captures, test, &mut log_buffer);
+        if let Some(expected) = &test.expect_body_exact {
+            check_exact_body("expect_body_exact", expected.as_bytes(), received_body, level("expect_body_exact"),
+             &mut result, &mut test_passed, &mut log_buffer);
+        }
 
-        // If "set-cookie" header exists, add the cookie to the cookie jar
-        let cookie_entry = response.headers().get("set-cookie");
-        if let Some(cookie_value) = cookie_entry {
-            match Cookie::parse(cookie_value.to_str().unwrap_or("")) {
-                Ok(value) => {
-                    let cookie_data = value.name_value();
-                    let cookie_name = cookie_data.0.to_owned();
-                    let cookie_value = cookie_data.1.to_owned();
-                    cookie_jar.add(Cookie::new(cookie_name, cookie_value));
+        if test.snapshot == Some(true) {
+            let path = snapshot::snapshot_path(&snapshot_directory, config_index + 1, test);
+            let file = path.display().to_string();
+
+            match snapshot::check_snapshot(&path, &buffer, &ignore_paths, options.update_snapshots) {
+                Ok(snapshot::SnapshotOutcome::Recorded) => log(format!("{}\n", format!("Recorded the snapshot {}", file)
+                 .yellow()), Some(true), &mut log_buffer),
+                Ok(snapshot::SnapshotOutcome::Updated) => log(format!("{}\n", format!("Updated the snapshot {}", file)
+                 .yellow()), Some(true), &mut log_buffer),
+                Ok(snapshot::SnapshotOutcome::Matched) => {
+                    log(format!("Expected body to match the snapshot {} — PASSED\n", file), Some(true), &mut log_buffer);
+                    result.assert(format!("Expected body to match the snapshot {}", file), true);
+                },
+                Ok(snapshot::SnapshotOutcome::Mismatch { diff, changed }) => {
+                    log(format!("Expected body to match the snapshot {} — {}\n{}\n", file,
+                     severity::verdict(false, level("snapshot")), diff), Some(true), &mut log_buffer);
+                    result.assert(format!("Expected body to match the snapshot {}", file), false);
+                    if result.violate(level("snapshot"),
+                     failure::FailureReason::SnapshotMismatch { file, changed_lines: changed, diff }) {
+                        test_passed = false;
+                    }
                 },
                 Err(error) => {
-                    log(
-                        format!("Error while parsing cookie: {}\n", error),
-                        Some(true),
-                        &mut log_buffer,
-                    );
+                    log_error(format!("Error while checking the snapshot {}: {}\n", file, error), &mut log_buffer);
+                    result.fail(failure::FailureReason::SnapshotError { file, error: error.to_string() });
+                    test_passed = false;
+                },
+            }
+        }
+
+        // Drift is reported even if the expectations of the test still pass
+        if let Some(directory) = &options.compare_with {
+            let file = directory.join(format!("{}-response.http", transcript_name)).display().to_string();
+            match transcript::read_response(directory, &transcript_name) {
+                Ok(Some(recorded)) => {
+                    let drift = drift::compare(&recorded, response_status.as_u16(), &result.response_headers,
+                     received_body, rest_test_config.drift.as_ref());
+                    if drift.differences.is_empty() {
+                        log(format!("Expected response to match {} — PASSED\n", file), Some(true), &mut log_buffer);
+                        result.assert(format!("Expected response to match {}", file), true);
+                    } else {
+                        let details = drift.differences.iter().chain(Some(&drift.diff).filter(|diff| !diff.is_empty()))
+                            .map(|line| line.as_str()).collect::<Vec<&str>>().join("\n");
+                        log(format!("Expected response to match {} — FAILED\n{}\n", file, details),
+                         Some(true), &mut log_buffer);
+                        result.assert(format!("Expected response to match {}", file), false);
+                        result.fail(failure::FailureReason::ResponseDrift { file, differences: drift.differences,
+                         diff: drift.diff });
+                        test_passed = false;
+                    }
+                },
+                Ok(None) => log(format!("{}\n", format!("No recorded response {}, nothing to compare", file).yellow()),
+                 Some(true), &mut log_buffer),
+                Err(error) => log_error(format!("Error while reading the recorded response {}: {}\n", file, error),
+                 &mut log_buffer),
+            }
+        }
+
+        // Differences to the compare_against host are reported even if the expectations of the test still pass
+        if let (Some(secondary), Some(address)) = (&secondary, &secondary_address) {
+            let comparison_level = rest_test_config.comparison_severity.unwrap_or(severity::Level::Error);
+            let drift = comparison::compare(response_status.as_u16(), &result.response_headers, &buffer, secondary,
+             rest_test_config.comparison.as_ref());
+            let message = format!("Expected the response of {} to match", address);
+            let details: String = drift.differences.iter().chain(Some(&drift.diff).filter(|diff| !diff.is_empty()))
+                .map(|line| format!("{}\n", line)).collect();
+            log(format!("{} — {}\n{}", message, severity::verdict(drift.differences.is_empty(), comparison_level),
+             details), Some(true), &mut log_buffer);
+            result.assert(message, drift.differences.is_empty());
+            if !drift.differences.is_empty() {
+                let reason = failure::FailureReason::ResponseMismatch { url: address.clone(),
+                 differences: drift.differences, diff: drift.diff };
+                if result.violate(comparison_level, reason) {
+                    test_passed = false;
                 }
-            };
+            }
         }
 
-        let response_time_output = format!("Response time: {} ms", response_time);
+        if let Some(expected) = &test.expect_error {
+            log(format!("Expected error: {}, but received a response with status {}\n",
+             expected, response_status.as_u16()), Some(true), &mut log_buffer);
+            result.assert(format!("Expected a {} error, but received a response with status {}",
+             expected, response_status.as_u16()), false);
+            result.fail(failure::FailureReason::UnexpectedResponse {
+                expected_error: expected.to_string(),
+                status: response_status.as_u16(),
+            });
+            test_passed = false;
+        }
 
-        // Evaluate the response time
-        if response_time < time_boundaries[0] {
-            log(format!("{}\n", response_time_output.green()),
-             Some(true), &mut log_buffer);
-        } else if response_time < time_boundaries[1] {
-            log(format!("{}\n", response_time_output.yellow()),
-             Some(true), &mut log_buffer);
-        } else {
-            log(format!("{}\n", response_time_output.red()),
-             Some(true), &mut log_buffer);
+        // Violated assertions of severity warn are listed even if the test passed
+        for reason in result.warnings.iter() {
+            log(format!("{}\n", format!("Warning: {}", reason).yellow()), Some(true), &mut log_buffer);
         }
- 
-        // Check expectations
-        log(format!("Expected Status: {}\n", test.status),
-         Some(true), &mut log_buffer);
 
         // Print outcome
-        if response_status == test.status {
-            tests_passed += 1;
-            log(format!("{}", "TEST PASSED\n\n".green().bold()), 
-             Some(true), &mut log_buffer);
-        } else {
-            if is_critical {
-                println!("Test marked as 'critical' failed, cancelling all further tests.");
-                return;
+        if !test_passed {
+            log_failure_reasons(&result, &mut errors, &mut log_buffer);
+
+            // Where the rejected credentials came from, it doesnt change the outcome
+            if [hyper::StatusCode::UNAUTHORIZED, hyper::StatusCode::FORBIDDEN].contains(&response_status) {
+                let now = chrono::Local::now();
+                result.auth_diagnostics = sent_credentials.iter()
+                    .map(|sent| credential::diagnose(sent, sent.capture.as_ref()
+                        .and_then(|name| capture_producers.get(name))
+                        .map(|(index, at)| (test_reference(&rest_test_config.tests[*index], index + 1), *at)), now))
+                    .collect();
+                log_error(format!("{} status {}\n", "Auth diagnostic:".red(), response_status.as_u16()),
+                 &mut log_buffer);
+                if sent_credentials.is_empty() {
+                    log_error("  No bearer_token or session_id was sent.\n".to_string(), &mut log_buffer);
+                }
+                for line in result.auth_diagnostics.iter().flat_map(|diagnostic| diagnostic.describe()) {
+                    log_error(format!("  {}\n", line), &mut log_buffer);
+                }
             }
 
-            log(format!("{}", "TEST FAILED\n\n".red().bold()), 
-             Some(true), &mut log_buffer);
+            // The body usually tells why, verbose output printed it already
+            if !verbose && !suppress_failure_bodies && !buffer.is_empty() {
+                log_error(format!("{}\n{}\n", "Response body (failure context):".red(),
+                 utils::printable_body(&buffer, options.raw_body, max_printed_body_bytes)), &mut log_buffer);
+            }
+        }
+        if options.record_expectations.is_some() {
+            observations.entry(config_index).or_insert_with(|| record::observe(json_body.as_ref(), response.headers()));
+        }
+        let outcome = conclude(&mut result, test_passed, strict_xfail, &mut log_buffer);
+        results.push(result);
+
+        match outcome {
+            report::TestOutcome::Passed => tests_passed += 1,
+            report::TestOutcome::XFail => tests_xfailed += 1,
+            report::TestOutcome::XPass => tests_xpassed += 1,
+            _ => {
+                tests_failed += 1;
+                abort_reason = check_abort(aborts_on_failure, tests_failed, max_failures, options.fail_fast);
+                if abort_reason.is_some() {
+                    break;
+                }
+            },
+        }
+    }
+
+    log_buffer.prefix = None;
+
+    // The json report still has every failure reason that was only counted
+    for line in errors.summary() {
+        log_error(format!("{}\n", line.red()), &mut log_buffer);
+    }
+
+    if let (Some(state), Some(path)) = (&mut resume_state, &options.resume) {
+        checkpoint_resume(state, path, &schedule[checkpointed..test_index], &captures, &mut results, session,
+         &mut log_buffer);
+        if abort_reason.is_none() {
+            log(format!("All tests of the suite ran, remove {} to start over.\n", path.display()), Some(true),
+             &mut log_buffer);
+        }
+    }
+
+    // Tests that werent run due to an abort are part of the reports as well
+    if let Some(reason) = &abort_reason {
+        log(format!("{}\n", reason.red()), Some(true), &mut log_buffer);
+        log(format!("{} tests were skipped.\n", test_count - test_index),
+         Some(true), &mut log_buffer);
+
+        for (config_index, _, _) in schedule[test_index..].iter() {
+            let test = &rest_test_config.tests[*config_index];
+            let mut result = report::TestResult::new(config_index + 1,
+             test.it.clone().unwrap_or_else(|| generate_description(test)), &test.method, &test.route,
+             api_address.to_owned() + &test.route);
+            result.name = test.name.clone();
+            result.meta = test.meta.iter().flatten().map(|(key, value)| (key.clone(), value.clone())).collect();
+            result.outcome = report::TestOutcome::Skipped;
+            result.skip_reason = Some(if budget_exceeded {
+                "run time budget exceeded".to_string()
+            } else {
+                format!("Not run: {}", reason)
+            });
+            results.push(result);
+            tests_not_run += 1;
+        }
+    }
+    test_count += resumed_count;
+
+    let skipped_summary = if tests_skipped > 0 {
+        format!(", {} skipped due to their conditions or skip reasons", tests_skipped)
+    } else {
+        String::new()
+    };
+
+    let host_summary = if tests_host_skipped > 0 {
+        format!(", {}", format!("{} skipped because of their allowed_hosts", tests_host_skipped).yellow())
+    } else {
+        String::new()
+    };
+
+    // Kept apart from the failures, they only fail because of the critical test they depend on
+    let dependency_summary = if tests_dependency_skipped > 0 {
+        format!(", {} failed, {} skipped because a critical dependency failed", tests_failed, tests_dependency_skipped)
+    } else {
+        String::new()
+    };
+
+    // Tests marked xfail are neither passed nor failed, an xpass only fails the run with strict_xfail
+    let mut xfail_summary = String::new();
+    if tests_xfailed > 0 {
+        xfail_summary += &format!(", {}", format!("{} xfail", tests_xfailed).magenta());
+    }
+    if tests_xpassed > 0 {
+        let xpass = format!("{} xpass", tests_xpassed);
+        xfail_summary += &format!(", {}", if strict_xfail { xpass.red() } else { xpass.yellow() });
+    }
+
+    // Violated assertions of severity warn, they only fail the run with --warnings-as-errors
+    let assertion_warnings = results.iter().map(|result| result.warnings.len() as u32).sum::<u32>();
+    let assertion_summary = if assertion_warnings > 0 {
+        format!(", {}", format!("{} warnings", assertion_warnings).yellow())
+    } else {
+        String::new()
+    };
+
+    log(format!("{} out of {} tests passed{}{}{}{}{}.", tests_passed, test_count, dependency_summary, skipped_summary,
+     host_summary, xfail_summary, assertion_summary), Some(true), &mut log_buffer);
+
+    let count_class = |class| results.iter().filter(|result| result.time_class == Some(class)).count() as u32;
+    let (green, yellow, red) = (count_class(report::TimeClass::Green), count_class(report::TimeClass::Yellow),
+     count_class(report::TimeClass::Red));
+    let warnings = assertion_warnings + if yellow_is_warning { yellow + red } else { 0 };
+
+    if green + yellow + red > 0 {
+        let warning_summary = if yellow_is_warning { format!(", {} warnings", yellow + red) } else { String::new() };
+        log(format!("\nResponse times: {} green, {} yellow, {} red{}.", green.to_string().green(),
+         yellow.to_string().yellow(), red.to_string().red(), warning_summary), Some(true), &mut log_buffer);
+    }
+
+    // Run level limits, a violation fails the run even if every test passed
+    let budgets = rest_test_config.budgets.as_ref()
+        .map(|budgets| budget::evaluate(budgets, &results, run_start.elapsed().saturating_sub(paused).as_millis()))
+        .unwrap_or_default();
+    let exceeded_budgets: Vec<&budget::BudgetResult> = budgets.iter().filter(|budget| !budget.passed).collect();
+    if !budgets.is_empty() && exceeded_budgets.is_empty() {
+        log(format!("\nBudgets: all {} met.", budgets.len()), Some(true), &mut log_buffer);
+    } else if !budgets.is_empty() {
+        log(format!("\nBudgets: {} of {} exceeded:", exceeded_budgets.len(), budgets.len()), Some(true),
+         &mut log_buffer);
+        for budget in exceeded_budgets.iter() {
+            log(format!("  {}", budget.to_string().red()), Some(true), &mut log_buffer);
         }
     }
 
-    log(format!("{} out of {} tests passed.", 
-     tests_passed, test_count), Some(true), &mut log_buffer);
+    // The log file and the json report always get the captures, the console only on request
+    let producers: HashMap<String, usize> = capture_producers.iter()
+        .map(|(name, (index, _))| (name.clone(), *index))
+        .collect();
+    let capture_summary = capture::summarize(&captures, &producers, &rest_test_config.tests, &results);
+    if !capture_summary.is_empty() {
+        let show_captures = options.show_captures || options.verbose || rest_test_config.verbose.unwrap_or(false);
+        log(format!("\nCaptures:\n{}", capture::render_table(&capture_summary)), Some(show_captures), &mut log_buffer);
+    }
+
+    if let Some(path) = &options.record_expectations {
+        record_expectations(rest_test_config, &observations, &results, path, &mut log_buffer);
+    }
+
+    if let Some(destination) = log_destination { 
+        write_logfile(log_buffer.text, destination);
+    };
+
+    let test_run = report::TestRun {
+        api_address: api_address.clone(),
+        run_id: run_identity.run_id,
+        run_timestamp: run_identity.run_timestamp,
+        started_at: started_at.to_rfc3339(),
+        duration_ms: run_start.elapsed().as_millis(),
+        total: test_count,
+        passed: tests_passed,
+        failed: tests_failed,
+        skipped: tests_skipped + tests_dependency_skipped + tests_not_run + tests_host_skipped,
+        dependency_skipped: tests_dependency_skipped,
+        xfailed: tests_xfailed,
+        xpassed: tests_xpassed,
+        strict_xfail,
+        green,
+        yellow,
+        red,
+        warnings,
+        aborted: abort_reason.clone(),
+        shard: options.shard.map(|(shard, count)| format!("{}/{}", shard, count)),
+        budgets: budgets.clone(),
+        captures: capture_summary,
+        tests: results,
+    };
+
+    if let Some(watcher) = interrupt_watcher {
+        watcher.abort();
+    }
 
-    if let Some(directory) = rest_test_config.to_file { 
-        write_logfile(log_buffer, directory);
+    let outcome = if was_interrupted {
+        RunOutcome::Interrupted
+    } else if budget_exceeded {
+        RunOutcome::Truncated
+    } else if abort_reason.is_some() {
+        RunOutcome::Aborted
+    } else if tests_failed > 0 || (options.warnings_as_errors && warnings > 0) || (strict_xfail && tests_xpassed > 0)
+        || budgets.iter().any(|budget| !budget.passed) {
+        RunOutcome::Failed
+    } else {
+        RunOutcome::Passed
     };
+
+    return (outcome, Some(test_run));
+}
+
+// Writes the reports of a run and hands it to the configured integrations
+async fn publish(test_run: &report::TestRun, rest_test_config: &Config, options: &RunOptions) {
+    if let Some(path) = &options.report_json {
+        match report::write_json(test_run, path) {
+            Ok(()) => println!("Wrote the json report to {}", path.display()),
+            Err(error) => println!("Error while writing the json report: {}", error),
+        }
+    }
+
+    if let Some(path) = &options.report_html {
+        match report::html::write(test_run, path) {
+            Ok(()) => println!("Wrote the html report to {}", path.display()),
+            Err(error) => println!("Error while writing the html report: {}", error),
+        }
+    }
+
+    if let Some(path) = &options.metrics_file {
+        match metrics::write_file(test_run, path) {
+            Ok(()) => println!("Wrote the metrics to {}", path.display()),
+            Err(error) => println!("Error while writing the metrics: {}", error),
+        }
+    }
+
+    if let Some(metrics_config) = &rest_test_config.metrics {
+        match metrics::push(test_run, metrics_config).await {
+            Ok(()) => println!("Pushed the metrics to the pushgateway"),
+            Err(error) => println!("Error while pushing the metrics: {}", error),
+        }
+    }
+
+    if options.github {
+        github::annotate_failures(test_run, rest_test_config.source.as_deref(), &rest_test_config.test_lines);
+
+        if let Err(error) = github::write_step_summary(test_run) {
+            println!("Error while writing the GitHub step summary: {}", error);
+        }
+    }
+
+    // A failed notification doesnt change the outcome of the run
+    if let Some(notify_config) = &rest_test_config.notify {
+        match notify::notify(test_run, notify_config).await {
+            Ok(true) => println!("Sent the run summary to the webhook"),
+            Ok(false) => (),
+            Err(error) => println!("Error while sending the notification: {}", error),
+        }
+    }
+}
+
+// Runs the tests of an already validated config, with --runs the whole suite several times
+async fn run_tests(rest_test_config: Config, options: RunOptions, middleware: &[Box<dyn Middleware>]) -> RunOutcome {
+    if options.runs.unwrap_or(1) > 1 {
+        return flakiness::run_repeatedly(&rest_test_config, &options, middleware).await;
+    }
+
+    let (outcome, test_run) = run_suite(&rest_test_config, &options, middleware, None).await;
+    if let Some(test_run) = &test_run {
+        publish(test_run, &rest_test_config, &options).await;
+    }
+    return outcome;
 }
 
 fn neon_wrapper(mut ctx: FunctionContext) -> JsResult<JsBoolean> {
     let rt = Runtime::new().unwrap();
 
     // Block the main thread until the async operation completes
-    rt.block_on(async {
-        execute_tests(utils::get_cwd().join("./rest-test.yaml")).await;
+    let outcome = rt.block_on(async {
+        execute_tests(utils::get_cwd().join("./rest-test.yaml"), RunOptions::default(), &[]).await
     });
 
-    return Ok(ctx.boolean(true));
+    return Ok(ctx.boolean(outcome == RunOutcome::Passed));
 }
 
 #[neon::main]
@@ -586,3 +3545,90 @@ fn neon_main(mut ctx: ModuleContext) -> NeonResult<()> {
     ctx.export_function("execute_tests", neon_wrapper)?;
     return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(yaml: &str) -> Endpoint {
+        return serde_yaml::from_str(yaml).unwrap();
+    }
+
+    #[test]
+    fn json_body_keys_are_sent_sorted_whatever_their_order_in_the_config() {
+        let captures: interpolation::Captures = [("id".to_string(), serde_json::Value::from(7))].into_iter().collect();
+        let forward = endpoint("route: a\njson_body: { alpha: '1', mid: '{{id}}', zulu: z }");
+        let backward = endpoint("route: a\njson_body: { zulu: z, mid: '{{id}}', alpha: '1' }");
+
+        let body = construct_body(&forward, &captures);
+        assert_eq!(body, r#"{"alpha":"1","mid":"7","zulu":"z"}"#);
+        assert_eq!(construct_body(&backward, &captures), body);
+        assert!((0..20).all(|_| construct_body(&forward, &captures) == body));
+    }
+
+    #[test]
+    fn json_keys_are_sent_sorted_at_any_depth() {
+        let captures = interpolation::Captures::new();
+        let forward = endpoint("route: a\njson: { b: 1, a: { d: [2, { y: 1, x: 0 }], c: 3 } }");
+        let backward = endpoint("route: a\njson: { a: { c: 3, d: [2, { x: 0, y: 1 }] }, b: 1 }");
+
+        let body = construct_body(&forward, &captures);
+        assert_eq!(body, r#"{"a":{"c":3,"d":[2,{"x":0,"y":1}]},"b":1}"#);
+        assert_eq!(construct_body(&backward, &captures), body);
+    }
+
+    #[test]
+    fn headers_are_sent_sorted_whatever_their_order_in_the_config() {
+        let global = [("X-Zulu", "zulu"), ("X-Alpha", "alpha"), ("X-Mid", "mid"), ("Accept", "*/*")];
+        let local = [("X-Beta", Some("local")), ("X-Mid", None), ("X-Alpha", Some("own"))];
+        let expected: Vec<(String, String)> = [("Accept", "*/*"), ("X-Alpha", "own"), ("X-Beta", "local"),
+            ("X-Zulu", "zulu")].map(|(name, value)| (name.to_string(), value.to_string())).into();
+
+        // Every map has its own iteration order, like the maps of two runs
+        for reversed in [false, true] {
+            let mut global = global.to_vec();
+            let mut local = local.to_vec();
+            if reversed {
+                global.reverse();
+                local.reverse();
+            }
+            let global: HashMap<String, String> = global.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            let local: HashMap<String, Option<String>> = local.iter()
+                .map(|(name, value)| (name.to_string(), value.map(|value| value.to_string())))
+                .collect();
+
+            let headers: Vec<(String, String)> = resolve_headers(&Some(local), &Some(global)).into_iter().collect();
+            assert_eq!(headers, expected);
+        }
+    }
+
+    #[test]
+    fn captures_json_values_with_their_type() {
+        let test = endpoint("route: a\ncapture: { count: count, price: price, active: active, deleted: deleted, \
+            quote: quote, missing: missing }\ncapture_namespace: order");
+        let json_body = serde_json::json!({"count": 42, "price": 9.5, "active": false, "deleted": null,
+            "quote": "say \"hi\""});
+        let body = bytes::BytesMut::from(json_body.to_string().as_str());
+        let headers = hyper::HeaderMap::new();
+        let source = CaptureSource { json_body: Some(&json_body), body: &body, status: 200, headers: &headers,
+            cookies: &[], events: &[], response_time: 5 };
+
+        let mut captures = interpolation::Captures::new();
+        let mut stored = capture_values(&test, &mut captures, &source);
+        stored.sort();
+        assert_eq!(stored, ["order.active", "order.count", "order.deleted", "order.price", "order.quote"]);
+        assert_eq!(captures["order.count"], serde_json::json!(42));
+        assert_eq!(captures["order.price"], serde_json::json!(9.5));
+        assert_eq!(captures["order.active"], serde_json::json!(false));
+        assert_eq!(captures["order.deleted"], serde_json::Value::Null);
+        assert_eq!(captures["order.quote"], serde_json::json!("say \"hi\""));
+        assert!(!captures.contains_key("order.missing"));
+
+        let next = endpoint("route: a\njson: { count: '{{order.count}}', active: '{{order.active}}', \
+            deleted: '{{order.deleted}}', quote: '{{order.quote}}', text: '{{order.count | string}}' }");
+        assert_eq!(construct_body(&next, &captures),
+            r#"{"active":false,"count":42,"deleted":null,"quote":"say \"hi\"","text":"42"}"#);
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{Config, dependency, generate_description};
+
+// Summary of a single test as printed by --list
+#[derive(Debug, Serialize)]
+pub struct ListedTest {
+    pub index: usize,
+    pub description: String,
+    pub method: String,
+    pub route: String,
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub meta: BTreeMap<String, String>,
+    pub critical: bool,
+    pub produces: Vec<String>,
+    pub consumes: Vec<String>,
+}
+
+pub fn list_tests(config: &Config) -> Vec<ListedTest> {
+    let mut known = dependency::all_produced(&config.tests);
+    known.extend(config.variables.iter().flat_map(|variables| variables.keys().cloned()));
+
+    return config.tests.iter().enumerate()
+        .map(|(index, test)| ListedTest {
+            index: index + 1,
+            description: test.it.clone().unwrap_or_else(|| generate_description(test)),
+            method: test.method.to_uppercase(),
+            route: test.route.clone(),
+            tags: test.tags.clone().unwrap_or_default(),
+            meta: test.meta.iter().flatten().map(|(key, value)| (key.clone(), value.clone())).collect(),
+            critical: test.critical.unwrap_or(false),
+            produces: dependency::produced(test),
+            consumes: dependency::consumed(test, &known),
+        })
+        .collect();
+}
+
+// Renders the tests as a table with one row per test
+pub fn render_table(tests: &[ListedTest]) -> String {
+    let header = ["#", "Description", "Method", "Route", "Tags", "Meta", "Critical", "Produces", "Consumes"];
+    let mut rows: Vec<[String; 9]> = vec![header.map(|column| column.to_string())];
+
+    for test in tests.iter() {
+        rows.push([
+            test.index.to_string(),
+            test.description.clone(),
+            test.method.clone(),
+            test.route.clone(),
+            test.tags.join(", "),
+            test.meta.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<String>>().join(", "),
+            if test.critical { "yes".to_string() } else { String::new() },
+            test.produces.join(", "),
+            test.consumes.join(", "),
+        ]);
+    }
+
+    let mut widths = [0; 9];
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut output = String::new();
+    for row in rows.iter() {
+        let cells: Vec<String> = row.iter().zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        output += cells.join("  ").trim_end();
+        output += "\n";
+    }
+
+    return output;
+}
@@ -1,8 +1,14 @@
-use rrt::{ get_config_file, execute_tests };
+use rrt::{ get_config_file, get_run_options, execute_tests, run_subcommand };
 
 #[tokio::main]
 async fn main() {
+    if let Some(exit_code) = run_subcommand().await {
+        std::process::exit(exit_code);
+    }
+
     let test_file = get_config_file();
+    let options = get_run_options();
 
-    execute_tests(test_file).await;
+    let outcome = execute_tests(test_file, options, &[]).await;
+    std::process::exit(outcome.exit_code());
 }
@@ -0,0 +1,163 @@
+use serde::{Serialize, Deserialize};
+use strum::IntoEnumIterator;
+
+use crate::{Endpoint, HttpMethod, assertions, utils, validate_http_method};
+
+const DEFAULT_DENIED_STATUS: u16 = 405;
+
+// Sends the request with every method, the allowed ones have to succeed and all others have to be denied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MethodMatrix {
+    pub allowed: Vec<String>,
+    pub expect_allowed_status: Option<u16>, // defaults to the status of the test, otherwise anything but the denied one
+    pub expect_denied_status: Option<u16>, // defaults to 405
+}
+
+// A single method of the matrix as a test of its own
+fn expand_method(test: &Endpoint, matrix: &MethodMatrix, method: &HttpMethod, allowed: bool) -> Endpoint {
+    let denied_status = matrix.expect_denied_status.unwrap_or(DEFAULT_DENIED_STATUS);
+    let mut expanded = test.clone();
+    expanded.method_matrix = None;
+    expanded.method = method.to_string();
+    expanded.it = test.it.as_ref().map(|it| format!("{} ({})", it, method.to_string().to_uppercase()));
+    expanded.name = test.name.as_ref().map(|name| format!("{}-{}", name, method));
+
+    if allowed {
+        expanded.status = matrix.expect_allowed_status.or(test.status);
+        if expanded.status.is_none() {
+            expanded.expect_not_status = Some(utils::OneOrMany::One(assertions::StatusPattern::Code(denied_status)));
+        }
+    } else {
+        expanded.status = Some(denied_status);
+        expanded.expect_not_status = None;
+
+        // The body of a denied request has nothing to do with the one the test describes
+        expanded.capture = None;
+        expanded.require_json = None;
+        expanded.expect_valid_json = None;
+        expanded.expect_json_type = None;
+        expanded.expect_array_length = None;
+        expanded.expect_array_contains = None;
+        expanded.expect_body_equals_capture = None;
+        expanded.expect_body_file = None;
+        expanded.expected_body = None;
+        expanded.expect_body_exact = None;
+        expanded.expect_body = None;
+        expanded.expect_expr = None;
+        expanded.expect_headers = None;
+        expanded.expect_location = None;
+        expanded.expect_redirect_chain = None;
+        expanded.expect_content_disposition_filename = None;
+        expanded.expect_encoding = None;
+        expanded.min_compression_ratio = None;
+        expanded.save_response = None;
+        expanded.pagination_check = None;
+        expanded.compare = None;
+        expanded.interpolate = None;
+        expanded.expect_cookies = None;
+        expanded.snapshot = None;
+        expanded.ignore_paths = None;
+    }
+
+    return expanded;
+}
+
+// Replaces every test with a method_matrix by one test per method, the lines of the tests are expanded alongside.
+// Runs before the validation, so everything after it only sees the expanded tests.
+pub fn expand(tests: Vec<Endpoint>, lines: &[usize]) -> Result<(Vec<Endpoint>, Vec<usize>), Vec<String>> {
+    let mut expanded_tests = Vec::with_capacity(tests.len());
+    let mut expanded_lines = Vec::with_capacity(lines.len());
+    let mut errors = Vec::new();
+
+    for (index, test) in tests.into_iter().enumerate() {
+        let line = lines.get(index).copied();
+        let matrix = match &test.method_matrix {
+            Some(matrix) => matrix,
+            None => {
+                expanded_tests.push(test);
+                expanded_lines.extend(line);
+                continue;
+            },
+        };
+
+        if !test.method.is_empty() {
+            errors.push(format!("Test {} defines both 'method' and 'method_matrix', the matrix sends every method.",
+             index + 1));
+            continue;
+        }
+
+        let unknown: Vec<&String> = matrix.allowed.iter().filter(|method| validate_http_method(method).is_none()).collect();
+        if !unknown.is_empty() {
+            errors.push(format!("Test {} has unknown methods in its method_matrix: {}.", index + 1,
+             unknown.iter().map(|method| method.as_str()).collect::<Vec<&str>>().join(", ")));
+            continue;
+        }
+
+        for method in HttpMethod::iter() {
+            let allowed = matrix.allowed.iter().any(|allowed| allowed.eq_ignore_ascii_case(&method.to_string()));
+            expanded_tests.push(expand_method(&test, matrix, &method, allowed));
+            expanded_lines.extend(line);
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    return Ok((expanded_tests, expanded_lines));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST: &str = r#"
+route: /export
+status: 200
+method_matrix:
+  allowed: [get]
+capture:
+  id: id
+require_json: true
+expect_valid_json: true
+expect_json_type: object
+expect_body:
+  id: $number
+expect_expr: ["{{id}} > 0"]
+expect_headers:
+  Content-Type: json
+expect_content_disposition_filename: ^export
+expect_encoding: gzip
+min_compression_ratio: 2.0
+save_response:
+  dir: downloads
+pagination_check:
+  page_size: 10
+snapshot: true
+ignore_paths: [id]
+"#;
+
+    #[test]
+    fn drops_the_body_assertions_of_denied_methods() {
+        let test: Endpoint = serde_yaml::from_str(TEST).unwrap();
+        let (tests, lines) = expand(vec![test], &[3]).unwrap();
+        assert_eq!(tests.len(), HttpMethod::iter().count());
+        assert!(lines.iter().all(|line| *line == 3));
+
+        let (allowed, denied): (Vec<&Endpoint>, Vec<&Endpoint>) = tests.iter().partition(|test| test.method == "get");
+        assert_eq!(allowed.len(), 1);
+        assert_eq!(allowed[0].status, Some(200));
+        assert!(allowed[0].expect_body.is_some() && allowed[0].save_response.is_some());
+
+        for denied in denied {
+            assert_eq!(denied.status, Some(DEFAULT_DENIED_STATUS));
+            assert!(denied.capture.is_none());
+            assert!(denied.require_json.is_none() && denied.expect_valid_json.is_none());
+            assert!(denied.expect_json_type.is_none() && denied.expect_body.is_none());
+            assert!(denied.expect_expr.is_none() && denied.expect_headers.is_none());
+            assert!(denied.expect_content_disposition_filename.is_none() && denied.expect_encoding.is_none());
+            assert!(denied.min_compression_ratio.is_none() && denied.save_response.is_none());
+            assert!(denied.pagination_check.is_none() && denied.snapshot.is_none());
+            assert!(denied.ignore_paths.is_none());
+        }
+    }
+}
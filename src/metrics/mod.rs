@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+use std::{fs, path::Path};
+
+use serde::{Serialize, Deserialize};
+
+use crate::report::{TestOutcome, TestRun};
+use crate::{Result, notify, utils};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    pub pushgateway_url: String, // ${VAR} references are replaced with environment variables
+    pub job: Option<String>, // defaults to rest_test
+    pub instance: Option<String>,
+}
+
+fn escape_label(value: &str) -> String {
+    return value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+}
+
+fn gauge(output: &mut String, name: &str, help: &str, samples: &[(String, String)]) {
+    *output += &format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name);
+    for (labels, value) in samples.iter() {
+        *output += &format!("{}{} {}\n", name, labels, value);
+    }
+}
+
+// Renders the run in the OpenMetrics text format
+pub fn render(run: &TestRun) -> String {
+    let mut output = String::new();
+
+    gauge(&mut output, "rest_test_passed_total", "Number of passed tests of the last run.",
+        &[(String::new(), run.passed.to_string())]);
+    gauge(&mut output, "rest_test_failed_total", "Number of failed tests of the last run.",
+        &[(String::new(), run.failed.to_string())]);
+    gauge(&mut output, "rest_test_skipped_total", "Number of skipped tests of the last run.",
+        &[(String::new(), run.skipped.to_string())]);
+    gauge(&mut output, "rest_test_xfailed_total", "Number of tests of the last run that failed as their xfail expects.",
+        &[(String::new(), run.xfailed.to_string())]);
+    gauge(&mut output, "rest_test_xpassed_total", "Number of tests of the last run that passed despite their xfail.",
+        &[(String::new(), run.xpassed.to_string())]);
+    gauge(&mut output, "rest_test_duration_seconds", "Duration of the last run.",
+        &[(String::new(), format!("{:.3}", run.duration_ms as f64 / 1000.0))]);
+
+    // Series have to be unique, the slowest of several tests of the same route and method is reported
+    let mut response_times: BTreeMap<(String, String), u128> = BTreeMap::new();
+    for test in run.tests.iter().filter(|test| test.outcome != TestOutcome::Skipped) {
+        if let Some(time) = test.response_time_ms {
+            let route = format!("/{}", test.route.trim_start_matches('/'));
+            let slowest = response_times.entry((route, test.method.to_lowercase())).or_insert(time);
+            *slowest = (*slowest).max(time);
+        }
+    }
+
+    let samples: Vec<(String, String)> = response_times.iter()
+        .map(|((route, method), time)| (
+            format!("{{route=\"{}\",method=\"{}\"}}", escape_label(route), escape_label(method)),
+            time.to_string(),
+        ))
+        .collect();
+    gauge(&mut output, "rest_test_response_time_ms", "Response time of the tests of the last run.", &samples);
+
+    output += "# EOF\n";
+    return output;
+}
+
+pub fn write_file(run: &TestRun, path: &Path) -> Result<()> {
+    fs::write(path, render(run))?;
+    return Ok(());
+}
+
+// Replaces the metrics of the job and instance group on the pushgateway
+pub async fn push(run: &TestRun, config: &MetricsConfig) -> Result<()> {
+    let base = utils::substitute_env(&config.pushgateway_url)?;
+    let mut url = format!("{}/metrics/job/{}", base.trim_end_matches('/'), config.job.as_deref().unwrap_or("rest_test"));
+    if let Some(instance) = &config.instance {
+        url += &format!("/instance/{}", instance);
+    }
+
+    return notify::send_payload(hyper::Method::PUT, &url, "text/plain; version=0.0.4", render(run)).await;
+}
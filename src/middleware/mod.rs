@@ -0,0 +1,63 @@
+use std::fmt;
+
+use crate::Result;
+
+// The test a request belongs to, as seen by the hooks
+#[derive(Debug, Clone)]
+pub struct TestContext {
+    pub index: usize, // one based index of the test inside the config
+    pub description: String, // the `it` of the test or the generated description
+    pub method: String,
+    pub url: String, // the interpolated url of the request
+    pub repetition: u32,
+}
+
+// Hooks of library users around the requests of a run, e.g. to add tracing headers or to record the responses.
+// A hook that returns an error fails the test without a retry.
+pub trait Middleware: Send + Sync {
+    // Called right before every request is sent, including the second round trip of digest auth and replays
+    fn before_request(&self, _request: &mut hyper::Request<hyper::Body>, _context: &TestContext) -> Result<()> {
+        return Ok(());
+    }
+
+    // Called once the response and its body arrived
+    fn after_response(&self, _response: &hyper::Response<hyper::Body>, _body: &[u8], _context: &TestContext)
+         -> Result<()> {
+        return Ok(());
+    }
+}
+
+// A failed hook, kept apart from the transport errors of the request
+#[derive(Debug)]
+pub struct HookError {
+    pub hook: &'static str,
+    pub error: String,
+}
+
+impl fmt::Display for HookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} hook failed: {}", self.hook, self.error);
+    }
+}
+
+impl std::error::Error for HookError {}
+
+// Runs the before_request hooks in the order they were registered, the first error stops the request
+pub fn before_request(middleware: &[Box<dyn Middleware>], request: &mut hyper::Request<hyper::Body>,
+     context: &TestContext) -> Result<()> {
+    for hook in middleware.iter() {
+        hook.before_request(request, context)
+            .map_err(|error| HookError { hook: "before_request", error: error.to_string() })?;
+    }
+    return Ok(());
+}
+
+// Runs the after_response hooks in the order they were registered
+pub fn after_response(middleware: &[Box<dyn Middleware>], response: &hyper::Response<hyper::Body>, body: &[u8],
+     context: &TestContext) -> Result<()> {
+    for hook in middleware.iter() {
+        hook.after_response(response, body, context)
+            .map_err(|error| HookError { hook: "after_response", error: error.to_string() })?;
+    }
+    return Ok(());
+}
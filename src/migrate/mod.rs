@@ -0,0 +1,357 @@
+use std::collections::HashSet;
+use std::{fs, path::Path};
+
+use crate::{Config, LITERAL_PREFIX, Result, dependency, interpolation};
+use crate::import::yaml_scalar;
+
+pub const COMMAND: &str = "rust-rest-test migrate";
+
+// A deprecated form found in the config and the lines that replace it
+#[derive(Debug)]
+pub struct Change {
+    pub line: usize, // one based line of the deprecated field
+    pub deprecated: String,
+    pub replacement: String,
+    pub before: Vec<String>, // the lines from the field on as they are
+    pub after: Vec<String>, // and as they are rewritten
+}
+
+impl Change {
+    // The hint of the validation, a single line
+    pub fn hint(&self) -> String {
+        return format!("Line {}: {} is deprecated, use {} instead. `{}` rewrites it.", self.line, self.deprecated,
+            self.replacement, COMMAND);
+    }
+
+    pub fn describe(&self) -> String {
+        return format!("Line {}: {} -> {}", self.line, self.deprecated, self.replacement);
+    }
+}
+
+// A key of a mapping on a line, the dash of a sequence item belongs to the indentation
+struct KeyLine<'a> {
+    indentation: usize,
+    key: &'a str,
+    value: &'a str, // everything after the colon, including a comment
+}
+
+fn parse_key_line(line: &str) -> Option<KeyLine<'_>> {
+    let trimmed = line.trim_start();
+    let item = trimmed.strip_prefix('-').map_or(trimmed, |rest| rest.trim_start());
+    let indentation = line.len() - item.len();
+    let (key, value) = match item.split_once(':') {
+        Some((key, value)) if value.is_empty() || value.starts_with([' ', '\t']) => (key, value),
+        _ => return None,
+    };
+    if key.is_empty() || key.starts_with('#') || key.contains([' ', '"', '\'']) {
+        return None;
+    }
+    return Some(KeyLine { indentation, key, value });
+}
+
+// Splits a plain scalar from its comment, quoted scalars are returned with their quotes
+fn split_comment(value: &str) -> (&str, &str) {
+    let trimmed = value.trim_start();
+    let offset = value.len() - trimmed.len();
+    let end = match trimmed.chars().next() {
+        Some(quote @ ('"' | '\'')) => trimmed[1..].find(quote).map_or(trimmed.len(), |end| end + 2),
+        _ => trimmed.find(" #").unwrap_or(trimmed.len()),
+    };
+    let end = trimmed[..end].trim_end().len();
+    return (&value[offset..offset + end], &value[offset + end..]);
+}
+
+fn unquote(scalar: &str) -> &str {
+    return scalar.strip_prefix('"').and_then(|rest| rest.strip_suffix('"'))
+        .or_else(|| scalar.strip_prefix('\'').and_then(|rest| rest.strip_suffix('\'')))
+        .unwrap_or(scalar);
+}
+
+// A plain scalar yaml reads as a number or a bool, json_body sent it as a string
+fn is_typed_scalar(scalar: &str) -> bool {
+    if scalar.is_empty() || scalar.starts_with(['"', '\'', '{', '[', '|', '>', '&', '*']) {
+        return false;
+    }
+    return matches!(serde_yaml::from_str::<serde_yaml::Value>(scalar),
+        Ok(serde_yaml::Value::Number(_) | serde_yaml::Value::Bool(_)));
+}
+
+// A scalar inside a flow mapping, plain ones cant contain its indicators
+fn flow_scalar(text: &str) -> String {
+    if text.contains(['{', '}', '[', ']', ',']) {
+        return serde_json::Value::from(text).to_string();
+    }
+    return yaml_scalar(text);
+}
+
+// A string of json that is nothing but a placeholder would take the captured value with its type, the marked form
+// keeps sending it as a string like json_body did
+fn string_placeholder(text: &str) -> Option<String> {
+    return match interpolation::segments(text).as_slice() {
+        [interpolation::Segment::Placeholder { name, .. }] => Some(format!("{{{{{} | string}}}}", name)),
+        _ => None,
+    };
+}
+
+// The lines nested below a key, blank and comment lines in between belong to them. Yaml allows the items of a
+// sequence on the indentation of its key.
+fn nested_lines(lines: &[&str], start: usize, indentation: usize) -> usize {
+    let mut end = start;
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let line_indentation = line.len() - content.len();
+        if line_indentation < indentation || (line_indentation == indentation && !content.starts_with("- ")) {
+            break;
+        }
+        end = index + 1;
+    }
+    return end;
+}
+
+// time_boundaries: [500, 1000, 10000] becomes { ok: 500, warn: 1000, timeout: 10000 }
+fn migrate_time_boundaries(lines: &[&str], index: usize, key_line: &KeyLine) -> Option<(usize, Vec<String>)> {
+    let prefix = &lines[index][..lines[index].len() - key_line.value.len()];
+    let value = key_line.value.trim_start();
+    let named = |values: &[u64]| -> Option<Vec<String>> {
+        let names = ["ok", "warn", "timeout"];
+        return (2..=3).contains(&values.len())
+            .then(|| values.iter().zip(names.iter()).map(|(value, name)| format!("{}: {}", name, value)).collect());
+    };
+
+    // The flow form on a single line
+    if value.starts_with('[') {
+        let end = value.find(']')?;
+        let values: Vec<u64> = serde_yaml::from_str(&value[..=end]).ok()?;
+        let rest = &value[end + 1..];
+        return Some((index + 1, vec![format!("{} {{ {} }}{}", prefix, named(&values)?.join(", "), rest)]));
+    }
+
+    // The block form, one item per line
+    if !split_comment(value).0.is_empty() {
+        return None;
+    }
+    let end = nested_lines(lines, index + 1, key_line.indentation);
+    let mut values = Vec::new();
+    for line in lines[index + 1..end].iter() {
+        let content = line.trim_start();
+        if let Some(item) = content.strip_prefix("- ") {
+            values.push(split_comment(item).0.parse::<u64>().ok()?);
+        } else if !content.is_empty() && !content.starts_with('#') {
+            return None;
+        }
+    }
+    let mut names = named(&values)?.into_iter();
+    let mut rewritten = vec![lines[index].to_string()];
+    for line in lines[index + 1..end].iter() {
+        let content = line.trim_start();
+        rewritten.push(match content.strip_prefix("- ") {
+            Some(item) => format!("{}{}{}", &line[..line.len() - content.len()], names.next().unwrap_or_default(),
+                split_comment(item).1),
+            None => line.to_string(),
+        });
+    }
+    return Some((end, rewritten));
+}
+
+// json_body becomes json, values yaml reads as numbers or bools are quoted and whole placeholders marked so they are
+// still sent as strings
+fn migrate_json_body(lines: &[&str], index: usize, key_line: &KeyLine) -> Option<(usize, Vec<String>)> {
+    let line = lines[index];
+    let key_start = line.len() - key_line.value.len() - key_line.key.len() - 1;
+    let prefix = format!("{}json:", &line[..key_start]);
+    let value = key_line.value.trim_start();
+
+    // The flow form on a single line, rendered again if any value needs quotes
+    if value.starts_with('{') {
+        let end = value.rfind('}')?;
+        let entries: serde_yaml::Mapping = serde_yaml::from_str(&value[..=end]).ok()?;
+        let quoted = entries.values().any(|value| match value {
+            serde_yaml::Value::String(text) => string_placeholder(text).is_some(),
+            _ => matches!(value, serde_yaml::Value::Number(_) | serde_yaml::Value::Bool(_)),
+        });
+        if !quoted {
+            return Some((index + 1, vec![format!("{}{}", prefix, key_line.value)]));
+        }
+        let mut rendered = Vec::new();
+        for (key, value) in entries.iter() {
+            let text = match value {
+                serde_yaml::Value::String(text) => string_placeholder(text).unwrap_or(text.clone()),
+                serde_yaml::Value::Number(number) => number.to_string(),
+                serde_yaml::Value::Bool(flag) => flag.to_string(),
+                _ => return None,
+            };
+            rendered.push(format!("{}: {}", flow_scalar(key.as_str()?), flow_scalar(&text)));
+        }
+        return Some((index + 1, vec![format!("{} {{ {} }}{}", prefix, rendered.join(", "), &value[end + 1..])]));
+    }
+
+    let end = nested_lines(lines, index + 1, key_line.indentation);
+    let mut rewritten = vec![format!("{}{}", prefix, key_line.value)];
+    for line in lines[index + 1..end].iter() {
+        rewritten.push(match parse_key_line(line) {
+            Some(entry) => {
+                let (scalar, comment) = split_comment(entry.value);
+                let placeholder = serde_yaml::from_str::<String>(scalar).ok().and_then(|text| string_placeholder(&text));
+                match placeholder {
+                    Some(marked) => format!("{} {}{}", &line[..line.len() - entry.value.len()], yaml_scalar(&marked),
+                        comment),
+                    None if is_typed_scalar(scalar) => format!("{} {}{}", &line[..line.len() - entry.value.len()],
+                        yaml_scalar(scalar), comment),
+                    None => line.to_string(),
+                }
+            },
+            None => line.to_string(),
+        });
+    }
+    return Some((end, rewritten));
+}
+
+// A credential sent as a literal because no capture has its name gets the explicit $literal: prefix
+fn migrate_credential(line: &str, key_line: &KeyLine, literals: &HashSet<&str>) -> Option<String> {
+    let (scalar, comment) = split_comment(key_line.value);
+    let value = unquote(scalar);
+    if key_line.indentation == 0 || !literals.contains(value) {
+        return None;
+    }
+    let quote = if scalar.len() > value.len() { &scalar[..1] } else { "" };
+    return Some(format!("{} {}{}{}{}{}", &line[..line.len() - key_line.value.len()], quote, LITERAL_PREFIX, value,
+        quote, comment));
+}
+
+// The credentials of the tests that are sent as they are, without naming a capture or a variable
+fn implicit_literals<'a>(config: &'a Config, variables: &HashSet<String>) -> HashSet<&'a str> {
+    let produced = dependency::all_produced(&config.tests);
+    return config.tests.iter()
+        .flat_map(|test| [&test.bearer_token, &test.session_id])
+        .filter_map(|credential| credential.as_ref().and_then(|value| value.as_deref()))
+        .filter(|value| value.to_lowercase() != "none" && !value.starts_with(LITERAL_PREFIX))
+        .filter(|value| !produced.contains(*value) && !variables.contains(*value))
+        .collect();
+}
+
+// Finds the deprecated forms in the text of the config, the loaded config tells which credentials are literals.
+// Working on the lines keeps the comments and the layout of everything else.
+pub fn plan(content: &str, config: &Config, variables: &HashSet<String>) -> Vec<Change> {
+    let lines: Vec<&str> = content.lines().collect();
+    let literals = implicit_literals(config, variables);
+    let mut changes = Vec::new();
+
+    let mut index = 0;
+    while index < lines.len() {
+        let key_line = match parse_key_line(lines[index]) {
+            Some(key_line) => key_line,
+            None => {
+                index += 1;
+                continue;
+            },
+        };
+
+        let rewritten = match key_line.key {
+            "time_boundaries" => migrate_time_boundaries(&lines, index, &key_line)
+                .map(|(end, after)| ("the list form of 'time_boundaries'", "the named form { ok, warn, timeout }", end, after)),
+            "json_body" => migrate_json_body(&lines, index, &key_line)
+                .map(|(end, after)| ("the flat 'json_body'", "'json'", end, after)),
+            "bearer_token" | "session_id" => migrate_credential(lines[index], &key_line, &literals)
+                .map(|after| (if key_line.key == "bearer_token" { "a literal 'bearer_token' without a prefix" }
+                    else { "a literal 'session_id' without a prefix" }, "the '$literal:' prefix", index + 1, vec![after])),
+            _ => None,
+        };
+
+        match rewritten {
+            Some((deprecated, replacement, end, after)) => {
+                changes.push(Change {
+                    line: index + 1,
+                    deprecated: deprecated.to_string(),
+                    replacement: replacement.to_string(),
+                    before: lines[index..end].iter().map(|line| line.to_string()).collect(),
+                    after,
+                });
+                index = end;
+            },
+            None => index += 1,
+        }
+    }
+
+    return changes;
+}
+
+// The config with all changes applied
+pub fn apply(content: &str, changes: &[Change]) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    let mut index = 0;
+
+    for change in changes.iter() {
+        output.extend(lines[index..change.line - 1].iter().map(|line| line.to_string()));
+        output.extend(change.after.iter().cloned());
+        index = change.line - 1 + change.before.len();
+    }
+    output.extend(lines[index..].iter().map(|line| line.to_string()));
+
+    let mut migrated = output.join("\n");
+    if content.ends_with('\n') {
+        migrated.push('\n');
+    }
+    return migrated;
+}
+
+// The changed lines with their line numbers, like the diffs of the snapshots
+pub fn diff(changes: &[Change]) -> String {
+    let mut output = String::new();
+    for change in changes.iter() {
+        output += &format!("{}\n", change.describe());
+        for (offset, (before, after)) in change.before.iter().zip(change.after.iter()).enumerate() {
+            if before != after {
+                output += &format!("{:>5} - {}\n{:>5} + {}\n", change.line + offset, before, change.line + offset, after);
+            }
+        }
+    }
+    return output;
+}
+
+// Lists what was rewritten
+pub fn summary(changes: &[Change]) -> String {
+    return changes.iter().map(|change| format!("{}\n", change.describe())).collect();
+}
+
+pub fn write_migrated(path: &Path, migrated: &str) -> Result<()> {
+    fs::write(path, migrated)?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrate(content: &str) -> String {
+        let config: Config = serde_yaml::from_str(content).unwrap();
+        return apply(content, &plan(content, &config, &HashSet::new()));
+    }
+
+    #[test]
+    fn keeps_sending_the_values_of_a_json_body_as_strings() {
+        let content = concat!("api_address: http://localhost\ntests:\n",
+            "  - route: /a\n    json_body: { a: 1, b: \"{{id}}\", c: \"x {{id}}\" } # flow\n",
+            "  - route: /b\n    json_body:\n      a: true # flag\n      b: '{{ id | raw }}'\n      c: text\n");
+        assert_eq!(migrate(content), concat!("api_address: http://localhost\ntests:\n",
+            "  - route: /a\n    json: { a: '1', b: \"{{id | string}}\", c: \"x {{id}}\" } # flow\n",
+            "  - route: /b\n    json:\n      a: 'true' # flag\n      b: '{{id | string}}'\n      c: text\n"));
+
+        let migrated: Config = serde_yaml::from_str(&migrate(content)).unwrap();
+        let captures: interpolation::Captures = [("id".to_string(), serde_json::Value::from(1))].into();
+        let bodies: Vec<serde_json::Value> = migrated.tests.iter()
+            .map(|test| interpolation::interpolate_json(test.json.as_ref().unwrap(), &captures))
+            .collect();
+        assert_eq!(bodies, vec![serde_json::json!({"a": "1", "b": "1", "c": "x 1"}),
+            serde_json::json!({"a": "true", "b": "1", "c": "text"})]);
+    }
+
+    #[test]
+    fn leaves_a_json_body_without_typed_values_as_it_is() {
+        let content = "api_address: http://localhost\ntests:\n  - route: /a\n    json_body: { a: x, b: \"{{id}} y\" }\n";
+        assert_eq!(migrate(content), content.replace("json_body:", "json:"));
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use hyper_tls::HttpsConnector;
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+
+use crate::report::{TestOutcome, TestRun};
+use crate::{Result, interpolation, utils};
+
+// Only this many failures are listed in a notification
+const LISTED_FAILURES: usize = 5;
+
+const TIMEOUT_MS: u64 = 10000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyOn {
+    Always,
+    #[default]
+    Failure,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyFormat {
+    #[default]
+    Json,
+    Slack,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotifyConfig {
+    pub webhook_url: String, // ${VAR} references are replaced with environment variables
+    pub on: Option<NotifyOn>,
+    pub format: Option<NotifyFormat>,
+    pub template: Option<String>, // message with placeholders like {{passed}} or {{failures}}
+}
+
+// Values available to the message template
+fn template_values(run: &TestRun) -> interpolation::Captures {
+    let failures: Vec<String> = run.tests.iter()
+        .filter(|test| test.outcome == TestOutcome::Failed)
+        .take(LISTED_FAILURES)
+        .map(|test| {
+            let status = test.status.map_or("no response".to_string(), |status| status.to_string());
+            format!("- Test {}: {} {} ({}): {}", test.reference(), test.method, test.url, status, test.describe_failures())
+        })
+        .collect();
+
+    return HashMap::from([
+        ("api_address".to_string(), serde_json::Value::from(run.api_address.as_str())),
+        ("run_id".to_string(), serde_json::Value::from(run.run_id.as_str())),
+        ("run_timestamp".to_string(), serde_json::Value::from(run.run_timestamp.as_str())),
+        ("total".to_string(), serde_json::Value::from(run.total)),
+        ("passed".to_string(), serde_json::Value::from(run.passed)),
+        ("failed".to_string(), serde_json::Value::from(run.failed)),
+        ("skipped".to_string(), serde_json::Value::from(run.skipped)),
+        ("xfailed".to_string(), serde_json::Value::from(run.xfailed)),
+        ("xpassed".to_string(), serde_json::Value::from(run.xpassed)),
+        ("duration_ms".to_string(), serde_json::Value::from(run.duration_ms.to_string())),
+        ("failures".to_string(), serde_json::Value::from(failures.join("\n"))),
+    ]);
+}
+
+const DEFAULT_TEMPLATE: &str = "rust-rest-test against {{api_address}}: {{passed}} out of {{total}} tests passed, \
+    {{failed}} failed ({{duration_ms}} ms)\n{{failures}}";
+
+pub fn build_payload(run: &TestRun, config: &NotifyConfig) -> serde_json::Value {
+    let template = config.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+    let message = interpolation::interpolate(template, &template_values(run)).trim_end().to_string();
+
+    if config.format.unwrap_or_default() == NotifyFormat::Slack {
+        return json!({ "text": message });
+    }
+
+    let failures: Vec<serde_json::Value> = run.tests.iter()
+        .filter(|test| test.outcome == TestOutcome::Failed)
+        .take(LISTED_FAILURES)
+        .map(|test| json!({
+            "index": test.index,
+            "name": test.name,
+            "description": test.description,
+            "method": test.method,
+            "url": test.url,
+            "status": test.status,
+            "failures": test.failures,
+        }))
+        .collect();
+
+    return json!({
+        "message": message,
+        "api_address": run.api_address,
+        "run_id": run.run_id,
+        "run_timestamp": run.run_timestamp,
+        "total": run.total,
+        "passed": run.passed,
+        "failed": run.failed,
+        "skipped": run.skipped,
+        "xfailed": run.xfailed,
+        "xpassed": run.xpassed,
+        "duration_ms": run.duration_ms as u64,
+        "aborted": run.aborted,
+        "failures": failures,
+    });
+}
+
+// Posts the summary of the run to the webhook, if the run calls for a notification
+pub async fn notify(run: &TestRun, config: &NotifyConfig) -> Result<bool> {
+    let failed = run.failed > 0 || run.aborted.is_some() || (run.strict_xfail && run.xpassed > 0);
+    if config.on.unwrap_or_default() == NotifyOn::Failure && !failed {
+        return Ok(false);
+    }
+
+    let url = utils::substitute_env(&config.webhook_url)?;
+    send_payload(hyper::Method::POST, &url, "application/json", build_payload(run, config).to_string()).await?;
+
+    return Ok(true);
+}
+
+// Sends a payload to an endpoint outside of the tests, e.g. a webhook, anything but a 2xx response is an error
+pub async fn send_payload(method: hyper::Method, url: &str, content_type: &str, body: String) -> Result<()> {
+    let request = hyper::Request::builder()
+        .method(method)
+        .uri(url)
+        .header("Content-Type", content_type)
+        .body(hyper::Body::from(body))?;
+
+    let client = hyper::Client::builder().build::<_, hyper::Body>(HttpsConnector::new());
+    let response = match tokio::time::timeout(Duration::from_millis(TIMEOUT_MS), client.request(request)).await {
+        Ok(response) => response?,
+        Err(_) => return Err("Request timed out.".into()),
+    };
+
+    if !response.status().is_success() {
+        return Err(format!("the endpoint responded with status {}", response.status()).into());
+    }
+
+    return Ok(());
+}
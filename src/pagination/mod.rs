@@ -0,0 +1,295 @@
+use std::collections::HashSet;
+
+use hyper::Uri;
+use serde::{Serialize, Deserialize};
+
+use crate::failure::{self, FailureReason};
+use crate::utils::lookup_json_path;
+
+pub const DEFAULT_LIMIT_PARAM: &str = "limit";
+pub const DEFAULT_OFFSET_PARAM: &str = "offset";
+
+// The limit of the oversized step if the check declares no max_page_size
+pub const DEFAULT_OVERSIZED_LIMIT: u64 = 10000;
+
+// Shared ids beyond this many are only counted
+const MAX_LISTED_IDS: usize = 5;
+
+// Derived requests that check the limit/offset contract of a list endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaginationCheck {
+    pub page_size: u64,
+    pub items_path: Option<String>, // json path of the list, the body itself if not set
+    pub id_path: Option<String>, // path inside an element that identifies it, the whole element if not set
+    pub limit_param: Option<String>, // defaults to limit
+    pub offset_param: Option<String>, // defaults to offset
+    pub max_page_size: Option<u64>, // the oversized step asks for one element more and must not get more than this
+    pub total_path: Option<String>, // where the body declares the number of all elements
+    pub next_path: Option<String>, // where the body links the next page, null or missing on the last one
+}
+
+// A derived request before it is sent
+pub struct PageRequest {
+    pub step: &'static str,
+    pub url: Uri,
+}
+
+// One of the derived requests as recorded in the reports
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaginationStep {
+    pub step: String, // page 1, page 2 or oversized
+    pub url: String,
+    pub status: Option<u16>,
+    pub response_time_ms: Option<u128>,
+    pub items: Option<usize>, // the number of elements in the list
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<serde_json::Value>,
+    pub error: Option<String>, // the request failed or the body holds no list
+    #[serde(skip)]
+    ids: Vec<Option<String>>, // None for elements without the id_path
+}
+
+// Outcome of a single assertion over the steps
+pub struct PaginationOutcome {
+    pub description: String,
+    pub failure: Option<FailureReason>,
+}
+
+impl PaginationCheck {
+    fn limit_param(&self) -> &str {
+        return self.limit_param.as_deref().unwrap_or(DEFAULT_LIMIT_PARAM);
+    }
+
+    fn offset_param(&self) -> &str {
+        return self.offset_param.as_deref().unwrap_or(DEFAULT_OFFSET_PARAM);
+    }
+
+    pub fn oversized_limit(&self) -> u64 {
+        return self.max_page_size.map_or(DEFAULT_OVERSIZED_LIMIT, |max| max + 1);
+    }
+}
+
+// The url of the test with the limit and offset of a page, parameters of the same name are replaced
+fn page_url(check: &PaginationCheck, url: &Uri, limit: u64, offset: u64) -> Option<Uri> {
+    let mut pairs: Vec<String> = url.query().unwrap_or_default().split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter(|pair| {
+            let name = pair.split('=').next().unwrap_or_default();
+            name != check.limit_param() && name != check.offset_param()
+        })
+        .map(|pair| pair.to_string())
+        .collect();
+    pairs.push(format!("{}={}", check.limit_param(), limit));
+    pairs.push(format!("{}={}", check.offset_param(), offset));
+
+    let mut parts = url.clone().into_parts();
+    parts.path_and_query = Some(format!("{}?{}", url.path(), pairs.join("&")).parse().ok()?);
+    return Uri::from_parts(parts).ok();
+}
+
+// The first two pages and a request for more elements than a page may hold
+pub fn requests(check: &PaginationCheck, url: &Uri) -> Vec<PageRequest> {
+    return [
+        ("page 1", check.page_size, 0),
+        ("page 2", check.page_size, check.page_size),
+        ("oversized", check.oversized_limit(), 0),
+    ].into_iter()
+        .filter_map(|(step, limit, offset)| page_url(check, url, limit, offset).map(|url| PageRequest { step, url }))
+        .collect();
+}
+
+fn describe_id(value: &serde_json::Value) -> String {
+    return match value {
+        serde_json::Value::String(text) => text.clone(),
+        _ => value.to_string(),
+    };
+}
+
+// Records a derived request and finds the list, the ids and the declared total and next page in its body
+pub fn record(check: &PaginationCheck, request: &PageRequest, status: Option<u16>, response_time_ms: Option<u128>,
+     body: &[u8], error: Option<String>) -> PaginationStep {
+    let mut step = PaginationStep {
+        step: request.step.to_string(),
+        url: request.url.to_string(),
+        status,
+        response_time_ms,
+        items: None,
+        total: None,
+        next: None,
+        error,
+        ids: Vec::new(),
+    };
+    if step.error.is_some() || !status.is_some_and(|status| (200..300).contains(&status)) {
+        return step;
+    }
+
+    let json = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(json) => json,
+        Err(parse_error) => {
+            step.error = Some(format!("the body is no json: {}", parse_error));
+            return step;
+        },
+    };
+
+    let items_path = check.items_path.as_deref().unwrap_or_default();
+    match lookup_json_path(&json, items_path) {
+        Some(serde_json::Value::Array(items)) => {
+            step.items = Some(items.len());
+            step.ids = items.iter()
+                .map(|item| lookup_json_path(item, check.id_path.as_deref().unwrap_or_default()).map(describe_id))
+                .collect();
+        },
+        _ if items_path.is_empty() => step.error = Some("the body is no list".to_string()),
+        _ => step.error = Some(format!("the body holds no list at '{}'", items_path)),
+    }
+    step.total = check.total_path.as_deref().and_then(|path| lookup_json_path(&json, path)).cloned();
+    step.next = check.next_path.as_deref().and_then(|path| lookup_json_path(&json, path)).cloned();
+
+    return step;
+}
+
+fn outcome(description: String, step: &str, violation: Option<String>) -> PaginationOutcome {
+    let failure = violation.map(|violation| FailureReason::PaginationViolation { step: step.to_string(), violation });
+    return PaginationOutcome { description, failure };
+}
+
+// Whether the body links a next page, null and an empty text mean it is the last one
+fn links_next(step: &PaginationStep) -> bool {
+    return match &step.next {
+        None | Some(serde_json::Value::Null) => false,
+        Some(serde_json::Value::String(link)) => !link.is_empty(),
+        Some(_) => true,
+    };
+}
+
+fn describe_step(step: &PaginationStep) -> String {
+    return match (&step.error, step.status) {
+        (Some(error), Some(status)) => format!("status {}, {}", status, error),
+        (Some(error), None) => error.clone(),
+        (None, Some(status)) => format!("status {}", status),
+        (None, None) => "no response".to_string(),
+    };
+}
+
+// Checks the element counts, that the pages dont overlap and the declared total and next pages. The steps are
+// expected in the order of requests().
+pub fn check_steps(check: &PaginationCheck, steps: &[PaginationStep]) -> Vec<PaginationOutcome> {
+    let mut outcomes = Vec::new();
+    let (first, second) = match steps {
+        [first, second, ..] => (first, second),
+        _ => return outcomes,
+    };
+
+    let mut pages_listed = true;
+    for page in [first, second] {
+        let listed = page.items.is_some();
+        outcomes.push(outcome(format!("Expected {} to return a list, got {}", page.step, describe_step(page)), &page.step,
+            (!listed).then(|| describe_step(page))));
+        pages_listed &= listed;
+    }
+
+    if pages_listed {
+        let (first_items, second_items) = (first.items.unwrap_or_default() as u64, second.items.unwrap_or_default() as u64);
+
+        // A declared total decides how full both pages have to be
+        let total = match &check.total_path {
+            Some(path) => {
+                let total = first.total.as_ref().and_then(|total| total.as_u64());
+                outcomes.push(outcome(format!("Expected page 1 to declare the total at '{}'", path), &first.step,
+                    total.is_none().then(|| format!("'{}' is {}", path,
+                        first.total.as_ref().map_or("missing".to_string(), |value| failure::excerpt(&value.to_string()))))));
+                if let Some(total) = total {
+                    let same = second.total.as_ref().and_then(|total| total.as_u64()) == Some(total);
+                    outcomes.push(outcome(format!("Expected page 2 to declare the same total {}", total), &second.step,
+                        (!same).then(|| format!("the total changed from {} to {}", total,
+                            second.total.as_ref().map_or("missing".to_string(), |value| value.to_string())))));
+                }
+                total
+            },
+            None => None,
+        };
+
+        let counts = match total {
+            Some(total) => vec![
+                (first, first_items, Some(check.page_size.min(total))),
+                (second, second_items, Some(check.page_size.min(total.saturating_sub(check.page_size)))),
+            ],
+            None => vec![
+                (first, first_items, (second_items > 0).then_some(check.page_size)),
+                (second, second_items, None),
+            ],
+        };
+        for (page, items, exact) in counts {
+            let (description, holds) = match exact {
+                Some(exact) => (format!("Expected {} to hold {} elements, got {}", page.step, exact, items), items == exact),
+                None => (format!("Expected {} to hold at most {} elements, got {}", page.step, check.page_size, items),
+                    items <= check.page_size),
+            };
+            outcomes.push(outcome(description, &page.step,
+                (!holds).then(|| format!("{} elements with the limit {}", items, check.page_size))));
+        }
+
+        // Every element of page 2 has to be new
+        let id_name = check.id_path.as_deref().map_or("the elements".to_string(), |path| format!("'{}'", path));
+        let missing = first.ids.iter().chain(second.ids.iter()).filter(|id| id.is_none()).count();
+        if missing > 0 {
+            outcomes.push(outcome(format!("Expected every element to have an id at {}", id_name), &second.step,
+                Some(format!("{} elements have no id at {}", missing, id_name))));
+        } else {
+            let first_ids: HashSet<&Option<String>> = first.ids.iter().collect();
+            let mut shared: Vec<&str> = second.ids.iter().filter(|id| first_ids.contains(id)).flatten()
+                .map(|id| id.as_str()).collect();
+            let count = shared.len();
+            let hidden = count.saturating_sub(MAX_LISTED_IDS);
+            shared.truncate(MAX_LISTED_IDS);
+            let mut listed = shared.join(", ");
+            if hidden > 0 {
+                listed += &format!(" and {} more", hidden);
+            }
+            outcomes.push(outcome(format!("Expected pages 1 and 2 not to share elements by {}", id_name), &second.step,
+                (count > 0).then(|| format!("{} elements of page 1 are repeated: {}", count, listed))));
+        }
+
+        // Only pages followed by more elements link a next one
+        if let Some(path) = &check.next_path {
+            let mut expectations = vec![(first, second_items > 0)];
+            if let Some(total) = total {
+                expectations.push((second, total > 2 * check.page_size));
+            }
+            for (page, expected) in expectations {
+                let linked = links_next(page);
+                let description = if expected {
+                    format!("Expected {} to link the next page at '{}'", page.step, path)
+                } else {
+                    format!("Expected {} to link no next page at '{}'", page.step, path)
+                };
+                outcomes.push(outcome(description, &page.step, (linked != expected).then(|| match &page.next {
+                    Some(next) if linked => format!("'{}' is {} but no elements follow", path,
+                        failure::excerpt(&next.to_string())),
+                    _ => format!("'{}' is missing but more elements follow", path),
+                })));
+            }
+        }
+    }
+
+    // The oversized limit has to be capped or rejected with a client error, never answered by a server error
+    if let Some(oversized) = steps.get(2) {
+        let limit = check.oversized_limit();
+        let rejected = oversized.status.is_some_and(|status| (400..500).contains(&status));
+        let description = match check.max_page_size {
+            Some(max) => format!("Expected the oversized limit {} to be capped at {} elements or rejected", limit, max),
+            None => format!("Expected the oversized limit {} to be answered or rejected", limit),
+        };
+        let violation = match (oversized.items, rejected) {
+            (_, true) => None,
+            (Some(items), false) => check.max_page_size.filter(|max| items as u64 > *max)
+                .map(|max| format!("{} elements, more than the maximum of {}", items, max)),
+            (None, false) => Some(describe_step(oversized)),
+        };
+        outcomes.push(outcome(description, &oversized.step, violation));
+    }
+
+    return outcomes;
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use hyper::Uri;
+use tokio::net::{lookup_host, TcpStream};
+use tokio::time::timeout;
+
+// How long the lookup and the connection may take each, a reachable API answers far quicker
+pub const TIMEOUT: Duration = Duration::from_secs(3);
+
+// The host of the api_address accepted a connection
+#[derive(Debug)]
+pub struct Reachable {
+    pub host: String,
+    pub port: u16,
+    pub address: SocketAddr, // the resolved address that accepted the connection
+    pub latency_ms: u128, // lookup and connection
+}
+
+impl fmt::Display for Reachable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}:{} is reachable at {} ({} ms)", self.host, self.port, self.address.ip(), self.latency_ms);
+    }
+}
+
+// Why the host of the api_address cant be reached
+#[derive(Debug)]
+pub struct PreflightError {
+    pub host: String,
+    pub port: u16,
+    pub reason: String,
+}
+
+impl fmt::Display for PreflightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "cannot reach {}:{} ({})", self.host, self.port, self.reason);
+    }
+}
+
+fn describe(error: &std::io::Error) -> String {
+    return match error.kind() {
+        ErrorKind::ConnectionRefused => "connection refused".to_string(),
+        ErrorKind::ConnectionReset => "connection reset".to_string(),
+        _ => error.to_string(),
+    };
+}
+
+// Opens a tcp connection to the host of the api_address, trying every resolved address until one accepts
+pub async fn check(api_address: &str) -> Result<Reachable, PreflightError> {
+    let uri = api_address.parse::<Uri>().ok();
+    let host = uri.as_ref().and_then(|uri| uri.host()).unwrap_or_default().trim_matches(['[', ']']).to_string();
+    let port = uri.as_ref().and_then(|uri| uri.port_u16())
+        .unwrap_or(if uri.as_ref().and_then(|uri| uri.scheme_str()) == Some("https") { 443 } else { 80 });
+    let error = |reason: String| PreflightError { host: host.clone(), port, reason };
+
+    if host.is_empty() {
+        return Err(error("the api_address has no host".to_string()));
+    }
+
+    let start = Instant::now();
+    let addresses: Vec<SocketAddr> = match timeout(TIMEOUT, lookup_host((host.as_str(), port))).await {
+        Ok(Ok(addresses)) => addresses.collect(),
+        Ok(Err(lookup_error)) => return Err(error(format!("dns lookup failed: {}", lookup_error))),
+        Err(_) => return Err(error(format!("dns lookup timed out after {} ms", TIMEOUT.as_millis()))),
+    };
+
+    let mut reason = "the host resolved to no address".to_string();
+    for address in addresses.into_iter() {
+        match timeout(TIMEOUT, TcpStream::connect(address)).await {
+            Ok(Ok(_)) => {
+                return Ok(Reachable { host, port, address, latency_ms: start.elapsed().as_millis() });
+            },
+            Ok(Err(connect_error)) => reason = describe(&connect_error),
+            Err(_) => reason = format!("timed out after {} ms", TIMEOUT.as_millis()),
+        }
+    }
+
+    return Err(error(reason));
+}
+
+// Opens a tcp connection to the connect_to address of tests, which they connect to instead of the host
+pub async fn check_address(address: SocketAddr) -> Result<Reachable, PreflightError> {
+    let (host, port) = (address.ip().to_string(), address.port());
+    let start = Instant::now();
+    return match timeout(TIMEOUT, TcpStream::connect(address)).await {
+        Ok(Ok(_)) => Ok(Reachable { host, port, address, latency_ms: start.elapsed().as_millis() }),
+        Ok(Err(connect_error)) => Err(PreflightError { host, port, reason: describe(&connect_error) }),
+        Err(_) => Err(PreflightError { host, port, reason: format!("timed out after {} ms", TIMEOUT.as_millis()) }),
+    };
+}
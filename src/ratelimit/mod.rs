@@ -0,0 +1,65 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Serialize, Deserialize};
+
+// Caps the requests per second against the api, e.g. a shared staging cluster
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub rps: f64, // tokens added per second
+    pub burst: Option<u32>, // requests that may be sent at once after an idle phase, defaults to 1
+}
+
+struct Bucket {
+    tokens: f64, // negative while requests wait for tokens that were already handed out
+    updated: Instant,
+}
+
+// A token bucket shared by every request of the run, every request sent on the wire takes a token
+#[derive(Clone)]
+pub struct RateLimiter {
+    rps: f64,
+    burst: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.rps.is_finite() || self.rps <= 0.0 {
+            return Err(format!("rps has to be greater than 0, got {}", self.rps));
+        }
+        if self.burst == Some(0) {
+            return Err("burst has to be at least 1".to_string());
+        }
+        return Ok(());
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> RateLimiter {
+        let burst = config.burst.unwrap_or(1) as f64;
+        return RateLimiter {
+            rps: config.rps,
+            burst,
+            bucket: Arc::new(Mutex::new(Bucket { tokens: burst, updated: Instant::now() })),
+        };
+    }
+
+    // Takes a token, waits until it is due if the bucket is empty and returns how long it waited.
+    // The token is reserved before the wait, so concurrent requests queue up behind each other.
+    pub async fn acquire(&self) -> Duration {
+        let delay = {
+            let mut bucket = self.bucket.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.updated).as_secs_f64() * self.rps;
+            bucket.tokens = (bucket.tokens + refill).min(self.burst) - 1.0;
+            bucket.updated = now;
+            if bucket.tokens >= 0.0 { Duration::ZERO } else { Duration::from_secs_f64(-bucket.tokens / self.rps) }
+        };
+
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        return delay;
+    }
+}
@@ -0,0 +1,495 @@
+use std::collections::BTreeMap;
+use std::{fs, path::Path};
+
+use regex::Regex;
+
+use crate::{Config, Endpoint, Result, assertions, snapshot};
+use crate::failure::{self, FailureReason};
+use crate::import::yaml_scalar;
+use crate::report::{TestOutcome, TestResult};
+
+// Strings of an expect_body that match any value of their kind instead of themselves, $$ escapes a literal $
+pub const KEYWORDS: [&str; 7] = ["$any", "$string", "$number", "$integer", "$boolean", "$timestamp", "$uuid"];
+
+// Headers that describe the representation rather than the single response, only these are recorded
+const RECORDED_HEADERS: [&str; 7] = ["content-type", "cache-control", "vary", "content-encoding", "content-language",
+    "x-content-type-options", "access-control-allow-origin"];
+
+// Parts of key names whose values change with every run, like created_at or request_id
+const VOLATILE_KEY_PARTS: [&str; 10] = ["token", "nonce", "timestamp", "created", "updated", "expires", "request_id",
+    "requestid", "trace", "etag"];
+
+// Differences of an expect_body listed in the output
+const MAX_LISTED_MISMATCHES: usize = 10;
+
+// An ISO 8601 date with a time, e.g. 2024-05-01T12:00:00.123Z
+const TIMESTAMP_PATTERN: &str = r"^\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}(:\d{2}(\.\d+)?)?(Z|[+-]\d{2}:?\d{2})?$";
+
+// Unix timestamps in seconds or milliseconds between 2000 and 2100
+const EPOCH_SECONDS: std::ops::Range<u64> = 946_684_800..4_102_444_800;
+const EPOCH_MILLIS: std::ops::Range<u64> = 946_684_800_000..4_102_444_800_000;
+
+// What a passed test responded with, the source of its expectations
+#[derive(Debug)]
+pub struct Observation {
+    body: Option<serde_json::Value>, // already generalized
+    headers: Vec<(String, Vec<String>)>,
+}
+
+// The copy of the config and what happened to each test
+#[derive(Debug)]
+pub struct Recording {
+    pub content: String,
+    pub generated: usize, // tests that gained expectations
+    pub notes: Vec<String>, // tests left as they are and why
+}
+
+fn is_timestamp(value: &serde_json::Value) -> bool {
+    return match value {
+        serde_json::Value::String(text) => Regex::new(TIMESTAMP_PATTERN).is_ok_and(|pattern| pattern.is_match(text)),
+        serde_json::Value::Number(number) => number.as_u64()
+            .is_some_and(|number| EPOCH_SECONDS.contains(&number) || EPOCH_MILLIS.contains(&number)),
+        _ => false,
+    };
+}
+
+fn is_uuid(text: &str) -> bool {
+    return Regex::new(r"^(?i)[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$")
+        .is_ok_and(|pattern| pattern.is_match(text));
+}
+
+// Whether a value meets a keyword, None if the text is no keyword
+fn meets_keyword(keyword: &str, value: &serde_json::Value) -> Option<bool> {
+    return Some(match keyword {
+        "$any" => true,
+        "$string" => value.is_string(),
+        "$number" => value.is_number(),
+        "$integer" => value.is_i64() || value.is_u64(),
+        "$boolean" => value.is_boolean(),
+        "$timestamp" => is_timestamp(value),
+        "$uuid" => value.as_str().is_some_and(is_uuid),
+        _ => return None,
+    });
+}
+
+// The keywords of an expect_body that dont exist, e.g. a typo like $strnig
+pub fn unknown_keywords(expected: &serde_json::Value) -> Vec<String> {
+    return match expected {
+        serde_json::Value::String(text) if text.starts_with('$') && !text.starts_with("$$")
+            && !KEYWORDS.contains(&text.as_str()) => vec![text.clone()],
+        serde_json::Value::Array(array) => array.iter().flat_map(unknown_keywords).collect(),
+        serde_json::Value::Object(map) => map.values().flat_map(unknown_keywords).collect(),
+        _ => Vec::new(),
+    };
+}
+
+fn child_path(path: &str, segment: &str) -> String {
+    return if path.is_empty() { segment.to_string() } else { format!("{}.{}", path, segment) };
+}
+
+// Collects the differences as path, expected and actual
+fn compare(expected: &serde_json::Value, actual: &serde_json::Value, path: &str,
+     mismatches: &mut Vec<(String, String, String)>) {
+    match (expected, actual) {
+        (serde_json::Value::String(text), _) if meets_keyword(text, actual).is_some() => {
+            if meets_keyword(text, actual) == Some(false) {
+                mismatches.push((path.to_string(), text.clone(), failure::excerpt(&actual.to_string())));
+            }
+        },
+        (serde_json::Value::String(text), serde_json::Value::String(actual_text)) if text.starts_with("$$") => {
+            if text[1..] != *actual_text {
+                mismatches.push((path.to_string(), format!("{:?}", &text[1..]), format!("{:?}", actual_text)));
+            }
+        },
+        (serde_json::Value::Object(expected_map), serde_json::Value::Object(actual_map)) => {
+            for (key, expected_value) in expected_map.iter() {
+                match actual_map.get(key) {
+                    Some(actual_value) => compare(expected_value, actual_value, &child_path(path, key), mismatches),
+                    None => mismatches.push((child_path(path, key), failure::excerpt(&expected_value.to_string()),
+                        "missing".to_string())),
+                }
+            }
+            for (key, actual_value) in actual_map.iter().filter(|(key, _)| !expected_map.contains_key(*key)) {
+                mismatches.push((child_path(path, key), "no such key".to_string(),
+                    failure::excerpt(&actual_value.to_string())));
+            }
+        },
+        (serde_json::Value::Array(expected_array), serde_json::Value::Array(actual_array)) => {
+            if expected_array.len() != actual_array.len() {
+                mismatches.push((path.to_string(), format!("{} elements", expected_array.len()),
+                    format!("{} elements", actual_array.len())));
+            }
+            for (index, (expected_value, actual_value)) in expected_array.iter().zip(actual_array.iter()).enumerate() {
+                compare(expected_value, actual_value, &child_path(path, &index.to_string()), mismatches);
+            }
+        },
+        // 1 and 1.0 are the same number
+        (serde_json::Value::Number(expected_number), serde_json::Value::Number(actual_number)) => {
+            if expected_number.as_f64() != actual_number.as_f64() {
+                mismatches.push((path.to_string(), expected_number.to_string(), actual_number.to_string()));
+            }
+        },
+        _ => if expected != actual {
+            mismatches.push((path.to_string(), failure::excerpt(&expected.to_string()),
+                failure::excerpt(&actual.to_string())));
+        },
+    }
+}
+
+// Checks the json body against an expect_body. Objects need exactly the expected keys and arrays the expected
+// elements, keywords like $string or $timestamp stand for any value of their kind. The ignored paths are removed on
+// both sides.
+pub fn check_body(expected: &serde_json::Value, json_body: Option<&serde_json::Value>, ignore_paths: &[String])
+     -> (String, Vec<FailureReason>) {
+    let description = "Expected the body to match the expect_body".to_string();
+    let mut mismatches = Vec::new();
+    match json_body {
+        Some(actual) => {
+            let (mut expected, mut actual) = (expected.clone(), actual.clone());
+            snapshot::remove_paths(&mut expected, ignore_paths);
+            snapshot::remove_paths(&mut actual, ignore_paths);
+            compare(&expected, &actual, "", &mut mismatches);
+        },
+        None => mismatches.push((String::new(), "a json body".to_string(), "no json body".to_string())),
+    }
+    if mismatches.is_empty() {
+        return (description, Vec::new());
+    }
+
+    let mut message = format!("{}, {} differences", description, mismatches.len());
+    for (path, expected, actual) in mismatches.iter().take(MAX_LISTED_MISMATCHES) {
+        let path = if path.is_empty() { "body root" } else { path };
+        message += &format!("\n  {}: expected {}, got {}", path, expected, actual);
+    }
+    if mismatches.len() > MAX_LISTED_MISMATCHES {
+        message += &format!("\n  ... and {} more", mismatches.len() - MAX_LISTED_MISMATCHES);
+    }
+    let failures = mismatches.into_iter()
+        .take(MAX_LISTED_MISMATCHES)
+        .map(|(path, expected, actual)| FailureReason::BodyAssertion {
+            path: if path.is_empty() { "body root".to_string() } else { path },
+            expected,
+            actual,
+        })
+        .collect();
+    return (message, failures);
+}
+
+fn is_volatile_key(key: &str) -> bool {
+    let lowercase = key.to_lowercase();
+    return key.ends_with("_at") || key.ends_with("At") || VOLATILE_KEY_PARTS.iter().any(|part| lowercase.contains(part));
+}
+
+// The keyword of the type of a scalar
+fn type_keyword(value: &serde_json::Value) -> &'static str {
+    return match value {
+        _ if is_timestamp(value) => "$timestamp",
+        serde_json::Value::String(text) if is_uuid(text) => "$uuid",
+        serde_json::Value::String(_) => "$string",
+        serde_json::Value::Number(number) if number.is_i64() || number.is_u64() => "$integer",
+        serde_json::Value::Number(_) => "$number",
+        serde_json::Value::Bool(_) => "$boolean",
+        _ => "$any",
+    };
+}
+
+// Replaces what likely changes between runs with keywords: the scalars of volatile keys, timestamps and uuids.
+// Numbers only count as timestamps below a volatile key, a literal text starting with $ is escaped.
+fn generalize(value: &serde_json::Value, volatile: bool) -> serde_json::Value {
+    return match value {
+        serde_json::Value::Object(map) => serde_json::Value::Object(map.iter()
+            .map(|(key, child)| (key.clone(), generalize(child, is_volatile_key(key))))
+            .collect()),
+        serde_json::Value::Array(array) => serde_json::Value::Array(array.iter()
+            .map(|child| generalize(child, volatile))
+            .collect()),
+        serde_json::Value::Null => value.clone(),
+        _ if volatile => serde_json::Value::from(type_keyword(value)),
+        serde_json::Value::String(text) if is_timestamp(value) || is_uuid(text) =>
+            serde_json::Value::from(type_keyword(value)),
+        serde_json::Value::String(text) if text.starts_with('$') => serde_json::Value::from(format!("${}", text)),
+        _ => value.clone(),
+    };
+}
+
+pub fn observe(json_body: Option<&serde_json::Value>, headers: &hyper::HeaderMap) -> Observation {
+    let body = json_body.map(|json| generalize(json, false));
+    let headers = RECORDED_HEADERS.iter()
+        .map(|name| (name.to_string(), assertions::header_values(headers, name)))
+        .filter(|(_, values)| !values.is_empty())
+        .collect();
+    return Observation { body, headers };
+}
+
+// content-type becomes Content-Type
+fn header_name(name: &str) -> String {
+    return name.split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or(String::new(), |first| first.to_uppercase().chain(chars).collect())
+        })
+        .collect::<Vec<String>>()
+        .join("-");
+}
+
+// The lines of the generated expectations, the keys on the given indentation
+fn expectation_lines(test: &Endpoint, observation: &Observation, indentation: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let pins_body = test.expect_body.is_some() || test.expect_body_file.is_some() || test.expect_body_exact.is_some()
+        || test.snapshot == Some(true);
+    if let (Some(body), false) = (&observation.body, pins_body) {
+        let rendered = serde_yaml::to_string(body).unwrap_or_default();
+        let rendered: Vec<&str> = rendered.trim_end().lines().collect();
+        // A collection of a single entry, like id: 1, still needs its own line
+        let collection = body.as_object().is_some_and(|map| !map.is_empty())
+            || body.as_array().is_some_and(|array| !array.is_empty());
+        if rendered.len() == 1 && !collection {
+            lines.push(format!("{}expect_body: {}", indentation, rendered[0]));
+        } else {
+            lines.push(format!("{}expect_body:", indentation));
+            lines.extend(rendered.iter().map(|line| format!("{}  {}", indentation, line)));
+        }
+    }
+
+    if test.expect_headers.is_none() && !observation.headers.is_empty() {
+        lines.push(format!("{}expect_headers:", indentation));
+        for (name, values) in observation.headers.iter() {
+            match values.as_slice() {
+                [value] => lines.push(format!("{}  {}: {}", indentation, header_name(name), yaml_scalar(value))),
+                _ => {
+                    lines.push(format!("{}  {}:", indentation, header_name(name)));
+                    lines.extend(values.iter().map(|value| format!("{}    - {}", indentation, yaml_scalar(value))));
+                },
+            }
+        }
+    }
+    return lines;
+}
+
+fn indentation_of(line: &str) -> usize {
+    return line.len() - line.trim_start().len();
+}
+
+// The end of the item starting at the line, trailing blank and comment lines belong to whatever follows
+fn item_end(lines: &[&str], start: usize) -> usize {
+    let dash = indentation_of(lines[start]);
+    let mut end = start + 1;
+    for (index, line) in lines.iter().enumerate().skip(start + 1) {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        if indentation_of(line) <= dash {
+            break;
+        }
+        end = index + 1;
+    }
+    return end;
+}
+
+// The indentation of the keys of the item, None for an item in the flow style
+fn key_indentation(lines: &[&str], start: usize) -> Option<usize> {
+    let after_dash = lines[start].trim_start().strip_prefix('-').unwrap_or_default();
+    let content = after_dash.trim_start();
+    if content.is_empty() {
+        let next = lines.iter().skip(start + 1).find(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))?;
+        return (indentation_of(next) > indentation_of(lines[start])).then(|| indentation_of(next));
+    }
+    if content.starts_with(['{', '&', '!', '*']) {
+        return None;
+    }
+    return Some(lines[start].len() - content.len());
+}
+
+// Adds the expectations observed for the passed tests to the text of the config. Failed tests get a comment
+// instead, tests that didnt run, already pin their body or cant be edited line by line are listed in the notes.
+pub fn record(content: &str, config: &Config, observations: &BTreeMap<usize, Observation>, results: &[TestResult])
+     -> Recording {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut insertions: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    let mut generated = 0;
+    let mut notes = Vec::new();
+
+    for (index, test) in config.tests.iter().enumerate() {
+        let reference = test.name.clone().unwrap_or_else(|| (index + 1).to_string());
+        let outcomes: Vec<TestOutcome> = results.iter()
+            .filter(|result| result.index == index + 1)
+            .map(|result| result.outcome)
+            .collect();
+        let line = match config.test_lines.get(index) {
+            Some(line) if *line <= lines.len() => line - 1,
+            _ => {
+                notes.push(format!("Test {} wasnt found in the config file.", reference));
+                continue;
+            },
+        };
+
+        if outcomes.contains(&TestOutcome::Failed) {
+            let dash = &lines[line][..indentation_of(lines[line])];
+            let comment = format!("{}# FAILED while recording expectations, none were generated", dash);
+            let comments = insertions.entry(line).or_default();
+            if !comments.contains(&comment) {
+                comments.push(comment);
+            }
+            notes.push(format!("Test {} failed, no expectations were generated.", reference));
+            continue;
+        }
+        let observation = match observations.get(&index) {
+            Some(observation) if outcomes.contains(&TestOutcome::Passed) => observation,
+            _ => {
+                notes.push(format!("Test {} didnt run, no expectations were generated.", reference));
+                continue;
+            },
+        };
+        if config.test_lines.iter().filter(|other| **other == line + 1).count() > 1 {
+            notes.push(format!("Test {} is expanded from a method_matrix, no expectations were generated.",
+                reference));
+            continue;
+        }
+        let indentation = match key_indentation(&lines, line) {
+            Some(indentation) => " ".repeat(indentation),
+            None => {
+                notes.push(format!("Test {} is written in the flow style, no expectations were generated.",
+                    reference));
+                continue;
+            },
+        };
+
+        let expectations = expectation_lines(test, observation, &indentation);
+        if expectations.is_empty() {
+            notes.push(format!("Test {} already has its expectations.", reference));
+            continue;
+        }
+        insertions.entry(item_end(&lines, line)).or_default().extend(expectations);
+        generated += 1;
+    }
+
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        output.extend(insertions.remove(&index).into_iter().flatten());
+        output.push(line.to_string());
+    }
+    output.extend(insertions.into_values().flatten());
+
+    let mut recorded = output.join("\n");
+    if content.ends_with('\n') || content.is_empty() {
+        recorded.push('\n');
+    }
+    return Recording { content: recorded, generated, notes };
+}
+
+pub fn write(path: &Path, content: &str) -> Result<()> {
+    fs::write(path, content)?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::github;
+
+    fn config(content: &str) -> Config {
+        let mut config: Config = serde_yaml::from_str(content).unwrap();
+        config.test_lines = github::locate_tests(content);
+        return config;
+    }
+
+    fn result(index: usize, outcome: TestOutcome) -> TestResult {
+        let mut result = TestResult::new(index, String::new(), "get", "/", String::new());
+        result.outcome = outcome;
+        return result;
+    }
+
+    fn observations(bodies: &[(usize, serde_json::Value)]) -> BTreeMap<usize, Observation> {
+        return bodies.iter()
+            .map(|(index, body)| (*index, observe(Some(body), &hyper::HeaderMap::new())))
+            .collect();
+    }
+
+    #[test]
+    fn finds_the_end_and_the_key_indentation_of_items() {
+        let lines = vec!["tests:", "  - route: /a", "    # about b", "", "    status: 200", "  # next one", "",
+            "  -", "    route: /b", "  - {route: /c}", "  - &anchor", "    route: /d"];
+        assert_eq!(item_end(&lines, 1), 5);
+        assert_eq!(item_end(&lines, 7), 9);
+        assert_eq!(item_end(&lines, 9), 10);
+        assert_eq!(key_indentation(&lines, 1), Some(4));
+        assert_eq!(key_indentation(&lines, 7), Some(4));
+        assert_eq!(key_indentation(&lines, 9), None);
+        assert_eq!(key_indentation(&lines, 10), None);
+    }
+
+    #[test]
+    fn appends_the_expectations_behind_the_last_key_of_block_items() {
+        let content = concat!("api_address: http://localhost\ntests:\n",
+            "  - route: /a\n    status: 200\n    # the b test\n\n",
+            "  -\n    route: /b\n",
+            "  - {route: /c}\n");
+        let results = [result(1, TestOutcome::Passed), result(2, TestOutcome::Passed), result(3, TestOutcome::Passed)];
+        let observations = observations(&[(0, json!({"id": 1})), (1, json!([1, 2])), (2, json!({"id": 3}))]);
+        let recording = record(content, &config(content), &observations, &results);
+
+        assert_eq!(recording.content, concat!("api_address: http://localhost\ntests:\n",
+            "  - route: /a\n    status: 200\n    expect_body:\n      id: 1\n    # the b test\n\n",
+            "  -\n    route: /b\n    expect_body:\n      - 1\n      - 2\n",
+            "  - {route: /c}\n"));
+        assert_eq!(recording.generated, 2);
+        assert_eq!(recording.notes, vec!["Test 3 is written in the flow style, no expectations were generated."]);
+    }
+
+    #[test]
+    fn comments_failed_tests_once() {
+        let content = "api_address: http://localhost\ntests:\n  - name: login\n    route: /login\n  - route: /me\n";
+        let results = [result(1, TestOutcome::Passed), result(1, TestOutcome::Failed), result(1, TestOutcome::Failed)];
+        let observations = observations(&[(0, json!({"id": 1}))]);
+        let recording = record(content, &config(content), &observations, &results);
+
+        assert_eq!(recording.content, concat!("api_address: http://localhost\ntests:\n",
+            "  # FAILED while recording expectations, none were generated\n",
+            "  - name: login\n    route: /login\n  - route: /me\n"));
+        assert_eq!(recording.generated, 0);
+        assert_eq!(recording.notes, vec!["Test login failed, no expectations were generated.",
+            "Test 2 didnt run, no expectations were generated."]);
+    }
+
+    #[test]
+    fn generalizes_volatile_values_and_escapes_dollars() {
+        let body = json!({
+            "id": "0b6f3a52-7d4e-4c1a-9f0e-3c2b1a0d9e8f",
+            "price": "$5",
+            "count": 3,
+            "created_at": 1700000000,
+            "updatedAt": "yesterday",
+            "session": {"token": "abc", "expires": null, "ttl": 1.5},
+            "events": [{"request_id": 7, "at": "2024-05-01T12:00:00Z"}],
+        });
+        assert_eq!(generalize(&body, false), json!({
+            "id": "$uuid",
+            "price": "$$5",
+            "count": 3,
+            "created_at": "$timestamp",
+            "updatedAt": "$string",
+            "session": {"token": "$string", "expires": null, "ttl": 1.5},
+            "events": [{"request_id": "$integer", "at": "$timestamp"}],
+        }));
+
+        let (_, failures) = check_body(&generalize(&body, false), Some(&body), &[]);
+        assert!(failures.is_empty());
+    }
+
+    #[test]
+    fn ignores_the_paths_on_both_sides() {
+        let expected = json!({"id": 1, "items": [{"id": 1, "name": "a"}]});
+        let actual = json!({"id": 2, "items": [{"id": 5, "name": "a"}], "version": 3});
+        let ignore_paths = vec!["id".to_string(), "items.*.id".to_string(), "version".to_string()];
+        assert!(check_body(&expected, Some(&actual), &ignore_paths).1.is_empty());
+
+        let (message, failures) = check_body(&expected, Some(&actual), &ignore_paths[..1]);
+        assert_eq!(failures.len(), 2);
+        assert!(message.contains("items.0.id: expected 1, got 5"), "{}", message);
+        assert!(message.contains("version: expected no such key, got 3"), "{}", message);
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+
+use hyper::{Method, StatusCode, Uri};
+
+use crate::Endpoint;
+use crate::failure::FailureReason;
+
+pub const DEFAULT_MAX_REDIRECTS: u32 = 5;
+
+// A redirect chain the runner gave up on, the chain starts with the url of the test
+#[derive(Debug)]
+pub struct RedirectError {
+    pub chain: Vec<String>,
+    pub limit: Option<u32>, // the exceeded max_redirects, None if a url repeated
+}
+
+impl fmt::Display for RedirectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self.limit {
+            Some(limit) => write!(f, "more than {} redirects: {}", limit, self.chain.join(" -> ")),
+            None => write!(f, "redirect loop: {}", self.chain.join(" -> ")),
+        };
+    }
+}
+
+impl std::error::Error for RedirectError {}
+
+// Whether a test follows redirects, asking for their limit or chain implies it
+pub fn follows(test: &Endpoint) -> bool {
+    return test.follow_redirects
+        .unwrap_or(test.max_redirects.is_some() || test.expect_redirect_chain.is_some());
+}
+
+// Resolves a Location header against the url that was redirected, fragments are dropped
+pub fn resolve(location: &str, current: &Uri) -> Result<Uri, String> {
+    let location = location.trim().split('#').next().unwrap_or_default();
+    let scheme = current.scheme_str().unwrap_or("http");
+    let authority = current.authority().map_or("", |authority| authority.as_str());
+
+    let absolute = if location.contains("://") {
+        location.to_string()
+    } else if let Some(rest) = location.strip_prefix("//") {
+        format!("{}://{}", scheme, rest)
+    } else if location.starts_with('/') {
+        format!("{}://{}{}", scheme, authority, location)
+    } else if location.starts_with('?') {
+        format!("{}://{}{}{}", scheme, authority, current.path(), location)
+    } else {
+        let path = current.path();
+        let directory = &path[..path.rfind('/').map_or(0, |slash| slash + 1)];
+        format!("{}://{}{}{}", scheme, authority, if directory.is_empty() { "/" } else { directory }, location)
+    };
+
+    return absolute.parse::<Uri>().map_err(|error| format!("invalid Location '{}': {}", location, error));
+}
+
+// The method of the next hop and whether it still carries the body. 307 and 308 repeat the request as it was,
+// 303 always switches to GET and so do 301 and 302 for a POST, like browsers do.
+pub fn next_method(status: StatusCode, method: &Method) -> (Method, bool) {
+    return match status {
+        StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => (method.clone(), true),
+        StatusCode::SEE_OTHER if method != Method::HEAD => (Method::GET, false),
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND if method == Method::POST => (Method::GET, false),
+        _ => (method.clone(), true),
+    };
+}
+
+// A hop matches an expected absolute url or, if the expectation starts with a slash, its path and query
+fn hop_matches(expected: &str, hop: &str) -> bool {
+    if !expected.starts_with('/') {
+        return expected == hop;
+    }
+    return hop.parse::<Uri>().ok()
+        .and_then(|uri| uri.path_and_query().map(|path| path.as_str() == expected))
+        .unwrap_or(false);
+}
+
+// The redirects have to lead exactly through the expected urls, in this order
+pub fn check_chain(expected: &[String], chain: &[String]) -> (String, Option<FailureReason>) {
+    let description = format!("Expected the redirect chain {}", expected.join(" -> "));
+    let matches = expected.len() == chain.len()
+        && expected.iter().zip(chain.iter()).all(|(expected, hop)| hop_matches(expected, hop));
+
+    if matches {
+        return (description, None);
+    }
+    let actual = if chain.is_empty() { "no redirect".to_string() } else { chain.join(" -> ") };
+    return (format!("{}, got {}", description, actual), Some(FailureReason::RedirectChainMismatch {
+        expected: expected.to_vec(),
+        actual: chain.to_vec(),
+    }));
+}
@@ -0,0 +1,207 @@
+use std::{fs, path::Path};
+
+use crate::Result;
+use crate::report::{TestOutcome, TestResult, TestRun};
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h1 { margin-bottom: 0.2em; }
+.summary span { margin-right: 1.5em; }
+.controls { margin: 1em 0; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.4em 0.6em; border-bottom: 1px solid #ddd; vertical-align: top; }
+tr.test { cursor: pointer; }
+tr.passed > td:first-child { border-left: 6px solid #2e7d32; }
+tr.failed > td:first-child { border-left: 6px solid #c62828; }
+tr.skipped > td:first-child { border-left: 6px solid #f9a825; }
+tr.xfail > td:first-child { border-left: 6px solid #8e24aa; }
+tr.xpass > td:first-child { border-left: 6px solid #ef6c00; }
+tr.failed { background: #fdecea; }
+tr.details > td { background: #fafafa; }
+.outcome-passed { color: #2e7d32; font-weight: bold; }
+.outcome-failed { color: #c62828; font-weight: bold; }
+.outcome-skipped { color: #f9a825; font-weight: bold; }
+.outcome-xfail { color: #8e24aa; font-weight: bold; }
+.outcome-xpass { color: #ef6c00; font-weight: bold; }
+pre { background: #f0f0f0; padding: 0.6em; overflow-x: auto; white-space: pre-wrap; }
+.aborted { color: #c62828; font-weight: bold; }
+.time-green { color: #2e7d32; }
+.time-yellow { color: #f9a825; }
+.time-red { color: #c62828; }
+";
+
+// Shows the details below a row on click and filters the rows by text and outcome
+const SCRIPT: &str = "
+document.querySelectorAll('tr.test').forEach(function (row) {
+  row.addEventListener('click', function () {
+    var details = row.nextElementSibling;
+    details.hidden = !details.hidden;
+  });
+});
+function filterTests() {
+  var text = document.getElementById('filter').value.toLowerCase();
+  var outcome = document.getElementById('outcome').value;
+  document.querySelectorAll('tr.test').forEach(function (row) {
+    var visible = row.textContent.toLowerCase().indexOf(text) !== -1
+      && (outcome === 'all' || row.classList.contains(outcome));
+    row.hidden = !visible;
+    if (!visible) { row.nextElementSibling.hidden = true; }
+  });
+}
+document.getElementById('filter').addEventListener('input', filterTests);
+document.getElementById('outcome').addEventListener('change', filterTests);
+";
+
+fn escape(text: &str) -> String {
+    return text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+}
+
+fn outcome_name(outcome: TestOutcome) -> &'static str {
+    return match outcome {
+        TestOutcome::Passed => "passed",
+        TestOutcome::Failed => "failed",
+        TestOutcome::Skipped => "skipped",
+        TestOutcome::XFail => "xfail",
+        TestOutcome::XPass => "xpass",
+    };
+}
+
+fn render_headers(headers: &[(String, String)]) -> String {
+    let lines: Vec<String> = headers.iter().map(|(name, value)| format!("{}: {}", name, value)).collect();
+    return escape(&lines.join("\n"));
+}
+
+fn render_details(test: &TestResult) -> String {
+    let mut details = String::new();
+
+    if !test.meta.is_empty() {
+        details += "<h4>Meta</h4><ul>";
+        for (key, value) in test.meta.iter() {
+            details += &format!("<li>{}: {}</li>", escape(key), escape(value));
+        }
+        details += "</ul>";
+    }
+
+    if let Some(reason) = &test.skip_reason {
+        details += &format!("<h4>Skipped</h4><p>{}</p>", escape(reason));
+    }
+
+    if !test.failures.is_empty() {
+        details += "<h4>Failure reasons</h4><ul>";
+        for reason in test.failures.iter() {
+            details += &format!("<li>{}</li>", escape(&reason.to_string()));
+        }
+        details += "</ul>";
+    }
+
+    if !test.warnings.is_empty() {
+        details += "<h4>Warnings</h4><ul>";
+        for reason in test.warnings.iter() {
+            details += &format!("<li>{}</li>", escape(&reason.to_string()));
+        }
+        details += "</ul>";
+    }
+
+    if let Some(error) = &test.error {
+        details += &format!("<h4>Error</h4><pre>{}</pre>", escape(error));
+    }
+
+    if !test.assertions.is_empty() {
+        details += "<h4>Assertions</h4><ul>";
+        for assertion in test.assertions.iter() {
+            let outcome = if assertion.passed { TestOutcome::Passed } else { TestOutcome::Failed };
+            details += &format!("<li><span class=\"outcome-{}\">{}</span> {}</li>", outcome_name(outcome),
+                outcome_name(outcome).to_uppercase(), escape(&assertion.description));
+        }
+        details += "</ul>";
+    }
+
+    if !test.pagination.is_empty() {
+        details += "<h4>Pagination check</h4><ul>";
+        for step in test.pagination.iter() {
+            let status = step.status.map_or("no response".to_string(), |status| format!("status {}", status));
+            let items = step.items.map_or(String::new(), |items| format!(", {} elements", items));
+            let error = step.error.as_ref().map_or(String::new(), |error| format!(", {}", error));
+            details += &format!("<li>{}: {} {}{}{}</li>", escape(&step.step), escape(&step.url), status, items,
+                escape(&error));
+        }
+        details += "</ul>";
+    }
+
+    details += &format!("<h4>Request</h4><pre>{} {}\n{}</pre>", escape(&test.method), escape(&test.url),
+        render_headers(&test.request_headers));
+    if let Some(body) = &test.request_body {
+        details += &format!("<pre>{}</pre>", escape(body));
+    }
+
+    if let Some(status) = test.status {
+        details += &format!("<h4>Response</h4><pre>Status: {}\n{}</pre>", status, render_headers(&test.response_headers));
+        if let Some(body) = &test.response_body {
+            details += &format!("<pre>{}</pre>", escape(body));
+        }
+    }
+
+    return details;
+}
+
+fn render_row(test: &TestResult) -> String {
+    let outcome = outcome_name(test.outcome);
+    let status = test.status.map(|status| status.to_string()).unwrap_or_default();
+    let time = match (test.response_time_ms, test.time_class) {
+        (Some(time), Some(class)) => format!("<span class=\"time-{}\">{} ms</span>", class.name(), time),
+        _ => String::new(),
+    };
+
+    return format!(
+        "<tr class=\"test {outcome}\"><td>{}</td><td class=\"outcome-{outcome}\">{}</td><td>{}</td><td>{}</td>\
+         <td>{}</td><td>{}</td><td>{}</td></tr>\n<tr class=\"details\" hidden><td colspan=\"7\">{}</td></tr>\n",
+        escape(&test.reference()), outcome.to_uppercase(), escape(&test.description), escape(&test.method), escape(&test.url),
+        status, time, render_details(test), outcome = outcome);
+}
+
+// Renders the run as a single html file without any external assets
+pub fn render(run: &TestRun) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html += "<title>rust-rest-test report</title>\n";
+    html += &format!("<style>{}</style>\n</head>\n<body>\n", STYLE);
+
+    html += "<h1>rust-rest-test report</h1>\n<p class=\"summary\">";
+    html += &format!("<span>API: <b>{}</b></span>", escape(&run.api_address));
+    html += &format!("<span>Started: {}</span>", escape(&run.started_at));
+    html += &format!("<span>Run id: <b>{}</b></span>", escape(&run.run_id));
+    html += &format!("<span>Duration: {} ms</span></p>\n<p class=\"summary\">", run.duration_ms);
+    html += &format!("<span>Total: <b>{}</b></span>", run.total);
+    html += &format!("<span class=\"outcome-passed\">Passed: {}</span>", run.passed);
+    html += &format!("<span class=\"outcome-failed\">Failed: {}</span>", run.failed);
+    html += &format!("<span class=\"outcome-skipped\">Skipped: {}</span>", run.skipped);
+    html += &format!("<span class=\"outcome-xfail\">XFail: {}</span>", run.xfailed);
+    html += &format!("<span class=\"outcome-xpass\">XPass: {}</span></p>\n", run.xpassed);
+    html += &format!("<p class=\"summary\"><span>Response times: <span class=\"outcome-passed\">{} green</span>\
+        <span class=\"outcome-skipped\">{} yellow</span><span class=\"outcome-failed\">{} red</span></span>",
+        run.green, run.yellow, run.red);
+    html += &format!("<span>Warnings: {}</span></p>\n", run.warnings);
+
+    if let Some(reason) = &run.aborted {
+        html += &format!("<p class=\"aborted\">{}</p>\n", escape(reason));
+    }
+
+    html += "<div class=\"controls\"><input id=\"filter\" type=\"search\" placeholder=\"Filter tests\"> \
+             <select id=\"outcome\"><option value=\"all\">All outcomes</option><option value=\"passed\">Passed</option>\
+             <option value=\"failed\">Failed</option><option value=\"skipped\">Skipped</option>\
+             <option value=\"xfail\">XFail</option><option value=\"xpass\">XPass</option></select></div>\n";
+
+    html += "<table>\n<thead><tr><th>#</th><th>Outcome</th><th>Description</th><th>Method</th><th>Url</th>\
+             <th>Status</th><th>Time</th></tr></thead>\n<tbody>\n";
+    for test in run.tests.iter() {
+        html += &render_row(test);
+    }
+    html += "</tbody>\n</table>\n";
+
+    html += &format!("<script>{}</script>\n</body>\n</html>\n", SCRIPT);
+    return html;
+}
+
+pub fn write(run: &TestRun, path: &Path) -> Result<()> {
+    fs::write(path, render(run))?;
+    return Ok(());
+}
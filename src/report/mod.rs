@@ -0,0 +1,288 @@
+use std::collections::BTreeMap;
+use std::{fs, path::Path};
+
+use serde::{Serialize, Deserialize};
+
+use crate::Result;
+use crate::boundaries::TimeBoundaries;
+use crate::budget::BudgetResult;
+use crate::capture::CaptureSummary;
+use crate::comparison::Comparison;
+use crate::compression::BodySize;
+use crate::connect::PhaseTimings;
+use crate::credential::AuthDiagnostic;
+use crate::failure::FailureReason;
+use crate::idempotency::Replay;
+use crate::pagination::PaginationStep;
+use crate::severity::Level;
+
+pub mod html;
+
+// Response bodies are cut off after this many characters
+const BODY_EXCERPT_LENGTH: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    Skipped,
+    XFail, // failed like its xfail expects, doesnt fail the run
+    XPass, // passed although its xfail expects it to fail
+}
+
+// Classification of a response time by the first two time boundaries
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeClass {
+    Green,
+    Yellow,
+    Red,
+}
+
+impl TimeClass {
+    pub fn classify(response_time: u128, time_boundaries: &TimeBoundaries) -> TimeClass {
+        if response_time < time_boundaries.ok {
+            return TimeClass::Green;
+        } else if response_time < time_boundaries.warn {
+            return TimeClass::Yellow;
+        }
+        return TimeClass::Red;
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            TimeClass::Green => "green",
+            TimeClass::Yellow => "yellow",
+            TimeClass::Red => "red",
+        };
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssertionResult {
+    pub description: String,
+    pub passed: bool,
+}
+
+// A single try of sending the request of a test with retries
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Attempt {
+    pub attempt: u32,
+    pub status: Option<u16>,
+    pub response_time_ms: Option<u128>,
+    pub failure: Option<FailureReason>,
+    pub retried: bool, // whether the failure qualified for another attempt
+}
+
+// Everything that is known about a single test after it ran
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestResult {
+    pub index: usize, // one based position inside the config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meta: BTreeMap<String, String>, // the annotations of the test
+    pub method: String,
+    pub route: String, // route of the config before interpolation
+    pub url: String,
+    pub outcome: TestOutcome,
+    #[serde(serialize_with = "serialize_headers", deserialize_with = "deserialize_headers")]
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: Option<String>,
+    pub status: Option<u16>,
+    #[serde(serialize_with = "serialize_headers", deserialize_with = "deserialize_headers")]
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: Option<String>, // excerpt of the body
+    pub response_time_ms: Option<u128>,
+    pub time_class: Option<TimeClass>,
+    pub time_boundaries: Option<TimeBoundaries>, // the boundaries in effect for the classification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings: Option<PhaseTimings>, // the phases of the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_size: Option<BodySize>, // the size on the wire and decoded, for compressed bodies
+    pub assertions: Vec<AssertionResult>,
+    pub failures: Vec<FailureReason>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<FailureReason>, // violated assertions of severity warn, they dont fail the test
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<Attempt>, // only recorded for tests with retries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replay: Option<Replay>, // the second request of an idempotency check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<Comparison>, // the request sent to the compare_against host
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pagination: Vec<PaginationStep>, // the derived requests of a pagination_check
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirect_chain: Vec<String>, // the urls the followed redirects lead to, in this order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saved_file: Option<String>, // where save_response wrote the body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>, // the trace or correlation id that was sent
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auth_diagnostics: Vec<AuthDiagnostic>, // where the credentials of a failed 401 or 403 came from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<u32>, // the session of --resume that ran the test
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ran_at: Option<String>,
+    pub error: Option<String>,
+    pub skip_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xfail: Option<String>, // the ticket of the bug the test is expected to fail because of
+}
+
+// Writes headers as an object of arrays in the order they were sent, repeated headers keep all of their values
+fn serialize_headers<S: serde::Serializer>(headers: &[(String, String)], serializer: S)
+     -> std::result::Result<S::Ok, S::Error> {
+    let mut grouped: Vec<(&str, Vec<&str>)> = Vec::new();
+    for (name, value) in headers.iter() {
+        match grouped.iter_mut().find(|(grouped_name, _)| grouped_name.eq_ignore_ascii_case(name)) {
+            Some((_, values)) => values.push(value),
+            None => grouped.push((name, vec![value])),
+        }
+    }
+    return serializer.collect_map(grouped);
+}
+
+// Reads the object of arrays back in the order of the file, e.g. from the state of --resume
+fn deserialize_headers<'de, D: serde::Deserializer<'de>>(deserializer: D)
+     -> std::result::Result<Vec<(String, String)>, D::Error> {
+    struct HeadersVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for HeadersVisitor {
+        type Value = Vec<(String, String)>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            return formatter.write_str("an object of header names and their values");
+        }
+
+        fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+            let mut headers = Vec::new();
+            while let Some((name, values)) = map.next_entry::<String, Vec<String>>()? {
+                headers.extend(values.into_iter().map(|value| (name.clone(), value)));
+            }
+            return Ok(headers);
+        }
+    }
+
+    return deserializer.deserialize_map(HeadersVisitor);
+}
+
+// The results of a whole run, the source of all reports
+#[derive(Debug, Serialize)]
+pub struct TestRun {
+    pub api_address: String,
+    pub run_id: String, // the {{run_id}} of the tests, e.g. to find the requests inside the server logs
+    pub run_timestamp: String,
+    pub started_at: String,
+    pub duration_ms: u128,
+    pub total: usize,
+    pub passed: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    pub dependency_skipped: u32, // part of skipped, tests whose critical dependency failed
+    pub xfailed: u32, // tests marked xfail that failed, they are neither passed nor failed
+    pub xpassed: u32, // tests marked xfail that passed
+    pub strict_xfail: bool, // whether the xpassed tests fail the run
+    pub green: u32, // number of responses per time class
+    pub yellow: u32,
+    pub red: u32,
+    pub warnings: u32, // violated assertions of severity warn and slow responses if yellow_is_warning is set
+    pub aborted: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shard: Option<String>, // like 2/4, lets an aggregator merge the reports of all shards
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub budgets: Vec<BudgetResult>, // the evaluation of the budgets of the config
+    pub captures: Vec<CaptureSummary>, // every capture at the end of the run
+    pub tests: Vec<TestResult>,
+}
+
+impl TestResult {
+    // Refers to the test by its index and its name, e.g. "3" or "3 (login)"
+    pub fn reference(&self) -> String {
+        return match &self.name {
+            Some(name) => format!("{} ({})", self.index, name),
+            None => self.index.to_string(),
+        };
+    }
+
+    pub fn new(index: usize, description: String, method: &str, route: &str, url: String) -> TestResult {
+        return TestResult {
+            index,
+            name: None,
+            description,
+            meta: BTreeMap::new(),
+            method: method.to_uppercase(),
+            route: route.to_string(),
+            url,
+            outcome: TestOutcome::Failed,
+            request_headers: Vec::new(),
+            request_body: None,
+            status: None,
+            response_headers: Vec::new(),
+            response_body: None,
+            response_time_ms: None,
+            time_class: None,
+            time_boundaries: None,
+            timings: None,
+            body_size: None,
+            assertions: Vec::new(),
+            failures: Vec::new(),
+            warnings: Vec::new(),
+            attempts: Vec::new(),
+            replay: None,
+            comparison: None,
+            pagination: Vec::new(),
+            redirect_chain: Vec::new(),
+            saved_file: None,
+            trace_id: None,
+            auth_diagnostics: Vec::new(),
+            session: None,
+            ran_at: None,
+            error: None,
+            skip_reason: None,
+            xfail: None,
+        };
+    }
+
+    pub fn assert(&mut self, description: String, passed: bool) {
+        self.assertions.push(AssertionResult { description, passed });
+    }
+
+    pub fn fail(&mut self, reason: FailureReason) {
+        self.failures.push(reason);
+    }
+
+    // Records a violated assertion, returns whether it fails the test or only counts as a warning
+    pub fn violate(&mut self, level: Level, reason: FailureReason) -> bool {
+        if level == Level::Warn {
+            self.warnings.push(reason);
+            return false;
+        }
+        self.failures.push(reason);
+        return true;
+    }
+
+    // The failure reasons as a single line, e.g. for annotations or notifications
+    pub fn describe_failures(&self) -> String {
+        return self.failures.iter().map(|reason| reason.to_string()).collect::<Vec<String>>().join("; ");
+    }
+}
+
+pub fn body_excerpt(body: &[u8]) -> Option<String> {
+    if body.is_empty() {
+        return None;
+    }
+
+    let body = String::from_utf8_lossy(body);
+    return match body.char_indices().nth(BODY_EXCERPT_LENGTH) {
+        Some((cut, _)) => Some(format!("{}… ({} more bytes)", &body[..cut], body.len() - cut)),
+        None => Some(body.to_string()),
+    };
+}
+
+pub fn write_json(run: &TestRun, path: &Path) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(run)? + "\n")?;
+    return Ok(());
+}
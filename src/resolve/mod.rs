@@ -0,0 +1,383 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::{Config, Endpoint, RunOptions, generate_description, trace, utils};
+use crate::boundaries::{TimeBoundaries, TimeBoundariesConfig};
+
+// Replaces the values of secret headers, variables and credentials
+const REDACTED: &str = "<redacted>";
+
+// Where the log of a run is written to
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogDestination {
+    Directory(PathBuf), // a new file named after the current date inside the directory
+    File { path: PathBuf, append: bool },
+}
+
+// How much of the output ends up in the log file, independent of what the console shows
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error, // errors and failure reasons
+    Info, // everything printed without verbose
+    #[default]
+    Debug, // also the requests and responses verbose output shows
+}
+
+pub const LOG_LEVEL_VALUES: [&str; 3] = ["error", "info", "debug"];
+
+impl LogLevel {
+    pub fn parse(value: &str) -> Option<LogLevel> {
+        return match value {
+            "error" => Some(LogLevel::Error),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            _ => None,
+        };
+    }
+}
+
+// The settings of a run after the command line, the config and the tests were merged, what the runner works with
+#[derive(Debug, Serialize)]
+pub struct ResolvedConfig {
+    pub api_address: String,
+    pub source: Option<PathBuf>,
+    pub overrides: Vec<String>, // the command line settings that beat the config
+    pub time_boundaries: TimeBoundaries, // of the tests without their own ones
+    pub boundaries_level: &'static str, // global if the config sets them, otherwise default
+    pub timeout_ms: u128, // of the tests without their own one
+    pub max_failures: Option<u32>,
+    pub max_duration_s: Option<u64>,
+    pub yellow_is_warning: bool,
+    pub prefix_test_output: bool,
+    pub max_printed_body_bytes: usize,
+    pub suppress_failure_bodies: bool,
+    pub log_destination: Option<LogDestination>,
+    pub log_level: LogLevel, // of the log file, defaults to debug
+    pub transcripts: Option<PathBuf>,
+    pub trace_header: Option<trace::TraceHeader>,
+    pub variables: BTreeMap<String, String>, // the config variables and the defines of the command line
+    pub headers: BTreeMap<String, String>,
+    pub bearer_token: Option<String>,
+    pub session_id: Option<String>,
+    pub tests: Vec<ResolvedTest>, // in config order
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResolvedTest {
+    pub index: usize, // one based
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub description: String,
+    pub method: String,
+    pub route: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>, // the expected status, the test's own one or the default
+    pub verbose: bool,
+    pub critical: bool,
+    pub repeat: u32,
+    pub time_boundaries: TimeBoundaries,
+    pub boundaries_level: &'static str, // local, global or default
+    pub timeout_ms: u128, // cancels the request, the timeout of the time_boundaries if no timeout_ms is set
+    pub headers: BTreeMap<String, String>, // the global headers merged with the ones of the test
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bearer_token: Option<String>, // only shows whether one is sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+fn redact(name: &str, value: &str) -> String {
+    return if utils::is_secret_name(name) { REDACTED.to_string() } else { value.to_string() };
+}
+
+// Describes the settings of the command line that override the config, secret defines are redacted
+fn describe_overrides(options: &RunOptions) -> Vec<String> {
+    let mut overrides = Vec::new();
+
+    if options.verbose {
+        overrides.push("verbose".to_string());
+    }
+    if let Some(timeout) = options.timeout_ms {
+        overrides.push(format!("timeout {} ms", timeout));
+    }
+    if let Some(repeat) = options.repeat {
+        overrides.push(format!("repeat {}", repeat));
+    }
+    if let Some(max_failures) = options.max_failures {
+        overrides.push(format!("max failures {}", max_failures));
+    }
+    if let Some(max_duration) = options.max_duration_s {
+        overrides.push(format!("max duration {} s", max_duration));
+    }
+    if options.fail_fast {
+        overrides.push("fail fast".to_string());
+    }
+    if options.no_critical {
+        overrides.push("no critical".to_string());
+    }
+    if options.no_preflight {
+        overrides.push("no preflight".to_string());
+    }
+    if options.continue_on_critical {
+        overrides.push("continue on critical".to_string());
+    }
+    for (name, value) in options.defines.iter() {
+        overrides.push(format!("{} = {}", name, redact(name, value)));
+    }
+
+    return overrides;
+}
+
+// The boundaries got validated beforehand, invalid ones only reach this point from a config that wasnt validated
+fn resolve_boundaries(boundaries: &TimeBoundariesConfig) -> TimeBoundaries {
+    return boundaries.resolve().unwrap_or(TimeBoundaries::DEFAULT);
+}
+
+// Whether a credential is sent, a null or none of the test suppresses the global one
+fn resolve_credential(local: &Option<Option<String>>, global: &Option<String>) -> Option<String> {
+    let sent = match local {
+        Some(Some(value)) => value.to_lowercase() != "none",
+        Some(None) => false,
+        None => global.is_some(),
+    };
+    return sent.then(|| REDACTED.to_string());
+}
+
+// A timeout_ms beats the timeout of the time_boundaries on the same level, the ones of the test beat the global ones
+fn resolve_timeout(test: Option<&Endpoint>, config: &Config) -> u128 {
+    let local = test.and_then(|test| test.timeout_ms
+        .or(test.time_boundaries.as_ref().and_then(|boundaries| boundaries.timeout())));
+    let global = config.timeout_ms.or(config.time_boundaries.as_ref().and_then(|boundaries| boundaries.timeout()));
+    return local.or(global).map_or(TimeBoundaries::DEFAULT.timeout, |timeout| timeout as u128);
+}
+
+fn resolve_test(config: &Config, options: &RunOptions, index: usize, test: &Endpoint) -> ResolvedTest {
+    // Local time boundaries are of higher precedence, a command line timeout beats both
+    let (mut time_boundaries, boundaries_level) = match (&test.time_boundaries, &config.time_boundaries) {
+        (Some(boundaries), _) => (resolve_boundaries(boundaries), "local"),
+        (None, Some(boundaries)) => (resolve_boundaries(boundaries), "global"),
+        (None, None) => (TimeBoundaries::DEFAULT, "default"),
+    };
+    let timeout_ms = options.timeout_ms.unwrap_or_else(|| resolve_timeout(Some(test), config));
+    time_boundaries.timeout = timeout_ms;
+
+    let headers = crate::resolve_headers(&test.headers, &config.headers).into_iter()
+        .map(|(name, value)| (name.clone(), redact(&name, &value)))
+        .collect();
+
+    return ResolvedTest {
+        index: index + 1,
+        name: test.name.clone(),
+        description: test.it.clone().unwrap_or_else(|| generate_description(test)),
+        method: test.method.to_uppercase(),
+        route: test.route.clone(),
+        status: test.status,
+        // Local verbosity is of higher precedence, the command line beats both
+        verbose: options.verbose || test.verbose.or(config.verbose).unwrap_or(false),
+        // Criticalness can be disabled via the command line
+        critical: !options.no_critical && test.critical.unwrap_or(false),
+        repeat: options.repeat.or(test.repeat).unwrap_or(1).max(1),
+        time_boundaries,
+        boundaries_level,
+        timeout_ms,
+        headers,
+        bearer_token: resolve_credential(&test.bearer_token, &config.bearer_token),
+        session_id: resolve_credential(&test.session_id, &config.session_id),
+    };
+}
+
+// Merges the command line, the config and the tests, the command line takes precedence over the config file
+pub fn resolve(config: &Config, options: &RunOptions) -> ResolvedConfig {
+    // An exact log file path beats the directory, the command line beats the config
+    let log_destination = match options.log_file.clone().or(config.log_file.clone()) {
+        Some(path) => Some(LogDestination::File { path, append: config.append.unwrap_or(false) }),
+        None => config.to_file.clone().map(LogDestination::Directory),
+    };
+
+    let mut variables: BTreeMap<String, String> = config.variables.iter().flatten()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    variables.extend(options.defines.iter().cloned());
+
+    let mut time_boundaries = config.time_boundaries.as_ref().map_or(TimeBoundaries::DEFAULT, resolve_boundaries);
+    let timeout_ms = options.timeout_ms.unwrap_or_else(|| resolve_timeout(None, config));
+    time_boundaries.timeout = timeout_ms;
+
+    return ResolvedConfig {
+        api_address: config.api_address.clone(),
+        source: config.source.clone(),
+        overrides: describe_overrides(options),
+        time_boundaries,
+        boundaries_level: if config.time_boundaries.is_some() { "global" } else { "default" },
+        timeout_ms,
+        max_failures: options.max_failures.or(config.max_failures),
+        max_duration_s: options.max_duration_s.or(config.max_duration_s),
+        yellow_is_warning: config.yellow_is_warning.unwrap_or(false),
+        prefix_test_output: config.prefix_test_output.unwrap_or(false),
+        max_printed_body_bytes: config.max_printed_body_bytes.unwrap_or(utils::DEFAULT_MAX_PRINTED_BODY_BYTES),
+        suppress_failure_bodies: config.suppress_failure_bodies.unwrap_or(false),
+        log_destination,
+        log_level: config.log_level.as_deref().and_then(LogLevel::parse).unwrap_or_default(),
+        transcripts: options.transcripts.clone().or(config.transcripts.clone()),
+        trace_header: config.tracing.as_ref()
+            .filter(|tracing| tracing.enabled.unwrap_or(true))
+            .map(|tracing| tracing.header.unwrap_or_default()),
+        variables: variables.into_iter().map(|(name, value)| (name.clone(), redact(&name, &value))).collect(),
+        headers: config.headers.iter().flatten()
+            .map(|(name, value)| (name.clone(), redact(name, value)))
+            .collect(),
+        bearer_token: config.bearer_token.as_ref().map(|_| REDACTED.to_string()),
+        session_id: config.session_id.as_ref().map(|_| REDACTED.to_string()),
+        tests: config.tests.iter().enumerate().map(|(index, test)| resolve_test(config, options, index, test)).collect(),
+    };
+}
+
+// The condensed settings at the top of every log file, the run logs the command line overrides right after them
+pub fn summary(resolved: &ResolvedConfig) -> String {
+    let mut summary = format!("Base url: {}\n", resolved.api_address);
+    if let Some(source) = &resolved.source {
+        summary += &format!("Config: {}\n", source.display());
+    }
+    summary += &format!("Time boundaries: ok {} ms, warn {} ms ({})\n", resolved.time_boundaries.ok,
+        resolved.time_boundaries.warn, resolved.boundaries_level);
+    summary += &format!("Timeout: {} ms\n", resolved.timeout_ms);
+    return summary + "\n";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(yaml: &str) -> Config {
+        return serde_yaml::from_str(yaml).unwrap();
+    }
+
+    const PRECEDENCE: &str = r#"
+api_address: http://localhost:8080/
+verbose: true
+timeout_ms: 4000
+time_boundaries: [100, 200, 3000]
+max_failures: 5
+tests:
+  - route: own
+    method: GET
+    status: 200
+    verbose: false
+    repeat: 3
+    timeout_ms: 900
+    time_boundaries: { ok: 10, warn: 20, timeout: 800 }
+  - route: inherited
+    method: GET
+    status: 200
+"#;
+
+    #[test]
+    fn the_test_beats_the_global_settings() {
+        let resolved = resolve(&config(PRECEDENCE), &RunOptions::default());
+
+        let own = &resolved.tests[0];
+        assert!(!own.verbose);
+        assert_eq!(own.repeat, 3);
+        assert_eq!((own.time_boundaries.ok, own.time_boundaries.warn, own.boundaries_level), (10, 20, "local"));
+
+        let inherited = &resolved.tests[1];
+        assert!(inherited.verbose);
+        assert_eq!(inherited.repeat, 1);
+        assert_eq!((inherited.time_boundaries.ok, inherited.boundaries_level), (100, "global"));
+        assert_eq!(resolved.max_failures, Some(5));
+    }
+
+    #[test]
+    fn the_command_line_beats_the_test_and_the_config() {
+        let options = RunOptions { verbose: true, repeat: Some(2), timeout_ms: Some(50), max_failures: Some(1),
+            ..RunOptions::default() };
+        let mut config = config(PRECEDENCE);
+        config.verbose = Some(false);
+        let resolved = resolve(&config, &options);
+
+        assert!(resolved.tests.iter().all(|test| test.verbose && test.repeat == 2 && test.timeout_ms == 50));
+        assert!(resolved.tests.iter().all(|test| test.time_boundaries.timeout == 50));
+        assert_eq!((resolved.timeout_ms, resolved.max_failures), (50, Some(1)));
+        assert_eq!(resolved.overrides, ["verbose", "timeout 50 ms", "repeat 2", "max failures 1"]);
+    }
+
+    #[test]
+    fn defaults_apply_without_any_setting() {
+        let config = config("api_address: http://localhost:8080/\ntests:\n  - { route: a, method: GET, status: 200 }");
+        let resolved = resolve(&config, &RunOptions::default());
+
+        let test = &resolved.tests[0];
+        assert!(!test.verbose && !test.critical);
+        assert_eq!(test.repeat, 1);
+        assert_eq!(test.boundaries_level, "default");
+        assert_eq!(test.timeout_ms, TimeBoundaries::DEFAULT.timeout);
+        assert_eq!(resolved.max_failures, None);
+        assert!(resolved.overrides.is_empty());
+    }
+
+    #[test]
+    fn a_timeout_ms_beats_the_timeout_of_the_time_boundaries() {
+        let resolved = resolve(&config(PRECEDENCE), &RunOptions::default());
+
+        // The test's own timeout_ms beats its boundaries, the global timeout_ms the global boundaries
+        assert_eq!(resolved.tests[0].timeout_ms, 900);
+        assert_eq!(resolved.tests[0].time_boundaries.timeout, 900);
+        assert_eq!(resolved.tests[1].timeout_ms, 4000);
+        assert_eq!(resolved.timeout_ms, 4000);
+
+        let mut config = config(PRECEDENCE);
+        config.timeout_ms = None;
+        config.tests[0].timeout_ms = None;
+        let resolved = resolve(&config, &RunOptions::default());
+        assert_eq!((resolved.tests[0].timeout_ms, resolved.tests[1].timeout_ms), (800, 3000));
+    }
+
+    #[test]
+    fn the_command_line_can_disable_critical_tests() {
+        let mut config = config(PRECEDENCE);
+        config.tests[0].critical = Some(true);
+
+        assert!(resolve(&config, &RunOptions::default()).tests[0].critical);
+        assert!(!resolve(&config, &RunOptions { no_critical: true, ..RunOptions::default() }).tests[0].critical);
+    }
+
+    #[test]
+    fn secret_defines_and_variables_are_redacted() {
+        let mut config = config(PRECEDENCE);
+        config.variables = Some([("password".to_string(), "hunter2".to_string()),
+            ("user".to_string(), "alice".to_string())].into_iter().collect());
+        let options = RunOptions { defines: vec![("api_token".to_string(), "abc".to_string()),
+            ("region".to_string(), "eu".to_string())], ..RunOptions::default() };
+        let resolved = resolve(&config, &options);
+
+        assert_eq!(resolved.overrides, ["api_token = <redacted>", "region = eu"]);
+        assert_eq!(resolved.variables["api_token"], REDACTED);
+        assert_eq!(resolved.variables["password"], REDACTED);
+        assert_eq!(resolved.variables["user"], "alice");
+        assert_eq!(resolved.variables["region"], "eu");
+    }
+
+    #[test]
+    fn a_define_beats_the_variable_of_the_config() {
+        let mut config = config(PRECEDENCE);
+        config.variables = Some([("region".to_string(), "us".to_string())].into_iter().collect());
+        let options = RunOptions { defines: vec![("region".to_string(), "eu".to_string())], ..RunOptions::default() };
+
+        assert_eq!(resolve(&config, &options).variables["region"], "eu");
+    }
+
+    #[test]
+    fn credentials_only_show_whether_they_are_sent() {
+        let mut config = config(PRECEDENCE);
+        config.bearer_token = Some("global-token".to_string());
+        config.tests[0].bearer_token = Some(Some("none".to_string()));
+        let resolved = resolve(&config, &RunOptions::default());
+
+        assert_eq!(resolved.bearer_token.as_deref(), Some(REDACTED));
+        assert_eq!(resolved.tests[0].bearer_token, None);
+        assert_eq!(resolved.tests[1].bearer_token.as_deref(), Some(REDACTED));
+    }
+}
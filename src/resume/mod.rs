@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+
+use crate::{Config, Result};
+use crate::interpolation::Captures;
+use crate::report::TestResult;
+
+// One invocation with --resume
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub session: u32,
+    pub started_at: String,
+}
+
+// The progress of a suite that runs across several invocations, written after every test
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResumeState {
+    pub config_hash: String, // a state only continues the config it was started with
+    pub sessions: Vec<Session>,
+    pub completed: Vec<(usize, u32)>, // one based index and repetition of every test with a result
+    pub captures: BTreeMap<String, serde_json::Value>, // including the credentials the tests captured
+    pub results: Vec<TestResult>,
+}
+
+// Hash of the config file, or of the config itself if it wasnt read from a file
+pub fn config_hash(config: &Config) -> String {
+    let content = match config.source.as_ref().and_then(|source| fs::read(source).ok()) {
+        Some(content) => content,
+        None => serde_json::to_vec(&serde_json::to_value(config).unwrap_or_default()).unwrap_or_default(),
+    };
+    return Sha256::digest(content).iter().map(|byte| format!("{:02x}", byte)).collect();
+}
+
+impl ResumeState {
+    pub fn new(config_hash: String) -> ResumeState {
+        return ResumeState {
+            config_hash,
+            sessions: Vec::new(),
+            completed: Vec::new(),
+            captures: BTreeMap::new(),
+            results: Vec::new(),
+        };
+    }
+
+    // Starts the next session and returns its number
+    pub fn start_session(&mut self, started_at: String) -> u32 {
+        let session = self.sessions.last().map_or(1, |last| last.session + 1);
+        self.sessions.push(Session { session, started_at });
+        return session;
+    }
+
+    pub fn is_completed(&self, index: usize, repetition: u32) -> bool {
+        return self.completed.contains(&(index, repetition));
+    }
+
+    // Takes over the progress since the last checkpoint, new results are stamped with the session
+    pub fn update(&mut self, completed: impl Iterator<Item = (usize, u32)>, captures: &Captures,
+         results: &mut [TestResult], session: u32) {
+        self.completed.extend(completed);
+        self.captures = captures.iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+        let now = chrono::Local::now().to_rfc3339();
+        for result in results.iter_mut().filter(|result| result.session.is_none()) {
+            result.session = Some(session);
+            result.ran_at = Some(now.clone());
+        }
+    }
+}
+
+// Reads the state of earlier sessions, a missing file starts the first one
+pub fn load(path: &Path, config_hash: &str) -> std::result::Result<ResumeState, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(ResumeState::new(config_hash.to_string())),
+        Err(error) => return Err(format!("Cant read the resume state {}: {}", path.display(), error)),
+    };
+    let state: ResumeState = serde_json::from_str(&content)
+        .map_err(|error| format!("The resume state {} is invalid: {}", path.display(), error))?;
+    if state.config_hash != config_hash {
+        return Err(format!("The resume state {} belongs to a different config, remove it to start over.",
+            path.display()));
+    }
+    return Ok(state);
+}
+
+// The state as it is written, the results belong to the running session until then
+#[derive(Serialize)]
+struct StateFile<'a> {
+    config_hash: &'a str,
+    sessions: &'a [Session],
+    completed: &'a [(usize, u32)],
+    captures: &'a BTreeMap<String, serde_json::Value>,
+    results: &'a [TestResult],
+}
+
+// Writes the state into a temporary file first, so a run killed while writing keeps the previous state
+pub fn save(path: &Path, state: &ResumeState, results: &[TestResult]) -> Result<()> {
+    let content = StateFile {
+        config_hash: &state.config_hash,
+        sessions: &state.sessions,
+        completed: &state.completed,
+        captures: &state.captures,
+        results,
+    };
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, serde_json::to_string_pretty(&content)?)?;
+    fs::rename(&temporary, path)?;
+    return Ok(());
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+
+use crate::Endpoint;
+use crate::failure::FailureReason;
+
+// Failure categories that may trigger another attempt of a request, see retry_on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetryCondition {
+    Timeout,
+    Connection, // any other transport error, e.g. a refused or reset connection
+    StatusClass(u16), // a response of this class, e.g. 5 for 5xx
+    StatusMismatch,
+}
+
+// Per default only requests that didnt get a response are retried
+pub const DEFAULT_RETRY_ON: [RetryCondition; 2] = [RetryCondition::Timeout, RetryCondition::Connection];
+
+impl RetryCondition {
+    pub fn parse(value: &str) -> Result<RetryCondition, String> {
+        let value = value.to_lowercase();
+        return match value.as_str() {
+            "timeout" => Ok(RetryCondition::Timeout),
+            "connection" => Ok(RetryCondition::Connection),
+            "status_mismatch" => Ok(RetryCondition::StatusMismatch),
+            _ => match value.strip_suffix("xx").and_then(|class| class.parse::<u16>().ok()) {
+                Some(class) if (1..=5).contains(&class) => Ok(RetryCondition::StatusClass(class)),
+                _ => Err(format!("expected timeout, connection, status_mismatch or a status class like 5xx, got '{}'",
+                    value)),
+            },
+        };
+    }
+
+    pub fn matches(&self, reason: &FailureReason) -> bool {
+        return match (self, reason) {
+            (RetryCondition::Timeout, FailureReason::Timeout { .. }) => true,
+            (RetryCondition::Connection, FailureReason::Transport { .. }) => true,
+            (RetryCondition::StatusMismatch, FailureReason::StatusMismatch { .. }) => true,
+            (RetryCondition::StatusClass(class), FailureReason::StatusMismatch { actual, .. })
+                | (RetryCondition::StatusClass(class), FailureReason::StatusExcluded { actual, .. }) => actual / 100 == *class,
+            _ => false,
+        };
+    }
+}
+
+impl fmt::Display for RetryCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            RetryCondition::Timeout => write!(f, "timeout"),
+            RetryCondition::Connection => write!(f, "connection"),
+            RetryCondition::StatusClass(class) => write!(f, "{}xx", class),
+            RetryCondition::StatusMismatch => write!(f, "status_mismatch"),
+        };
+    }
+}
+
+// The categories a failed attempt falls into, as named in retry_on
+pub fn categories(reason: &FailureReason) -> Vec<String> {
+    return match reason {
+        FailureReason::Timeout { .. } => vec!["timeout".to_string()],
+        FailureReason::Transport { .. } => vec!["connection".to_string()],
+        FailureReason::StatusMismatch { actual, .. } => vec!["status_mismatch".to_string(), format!("{}xx", actual / 100)],
+        FailureReason::StatusExcluded { actual, .. } => vec![format!("{}xx", actual / 100)],
+        _ => Vec::new(),
+    };
+}
+
+// Whether the status of a response already fails the test, before any body is looked at
+pub fn status_failure(test: &Endpoint, status: u16) -> Option<FailureReason> {
+    if let Some(expected) = test.status {
+        if expected != status {
+            return Some(FailureReason::StatusMismatch { expected, actual: status });
+        }
+    }
+
+    if let Some(not_status) = &test.expect_not_status {
+        if not_status.as_slice().iter().any(|pattern| pattern.matches(status)) {
+            return Some(FailureReason::StatusExcluded {
+                excluded: crate::assertions::describe_patterns(not_status.as_slice()),
+                actual: status,
+            });
+        }
+    }
+
+    return None;
+}
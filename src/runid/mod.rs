@@ -0,0 +1,54 @@
+use serde::Serialize;
+
+use crate::interpolation::{self, Captures};
+use crate::utils::random_u64;
+
+// The variables every run provides, like the ones of the config
+pub const NAMES: [&str; 2] = ["run_id", "run_timestamp"];
+
+// Characters of a run id, short ids stay readable inside emails and names of fixtures
+const ID_CHARACTERS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+const ID_LENGTH: usize = 8;
+
+// The identifiers of a run, the same for all of its tests
+#[derive(Debug, Clone, Serialize)]
+pub struct RunIdentity {
+    pub run_id: String,
+    pub run_timestamp: String, // the start of the run in UTC like 20261015T142233Z, safe inside routes and names
+}
+
+impl RunIdentity {
+    pub fn generate() -> RunIdentity {
+        let mut random = random_u64();
+        let run_id = (0..ID_LENGTH)
+            .map(|_| {
+                let character = ID_CHARACTERS[(random % ID_CHARACTERS.len() as u64) as usize] as char;
+                random /= ID_CHARACTERS.len() as u64;
+                character
+            })
+            .collect();
+        return RunIdentity {
+            run_id,
+            run_timestamp: chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string(),
+        };
+    }
+
+    // Takes over the values of variables, defines or resumed captures of the same names, e.g. to reuse the fixtures
+    // of an earlier run
+    pub fn pin(&mut self, captures: &Captures) {
+        if let Some(run_id) = captures.get(NAMES[0]) {
+            self.run_id = interpolation::text(run_id).to_string();
+        }
+        if let Some(run_timestamp) = captures.get(NAMES[1]) {
+            self.run_timestamp = interpolation::text(run_timestamp).to_string();
+        }
+    }
+
+    // The identifiers as the variables the tests interpolate
+    pub fn variables(&self) -> [(String, serde_json::Value); 2] {
+        return [
+            (NAMES[0].to_string(), serde_json::Value::from(self.run_id.as_str())),
+            (NAMES[1].to_string(), serde_json::Value::from(self.run_timestamp.as_str())),
+        ];
+    }
+}
@@ -0,0 +1,620 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use hyper::header::HeaderMap;
+use sha2::{Digest, Sha256};
+
+use crate::condition::{self, Token, Tokens};
+use crate::interpolation::{self, Captures};
+use crate::utils;
+
+// Tiny scripting language for the pre_script and post_script of a test, one statement per line or separated by ';'
+//
+//   signature = hmac_sha256(secret, user_id + ':' + nonce + ':' + request.body)
+//   if response.status != 201 { fail('nothing got created: ' + response.body) }
+//
+// Every value is a string like the captures, names refer to captures and assigning one sets the capture.
+// The request and response are read only under request.* and response.*.
+
+// The functions a script can call and their number of arguments
+const FUNCTIONS: [(&str, usize); 10] = [
+    ("sha256", 1), ("hmac_sha256", 2), ("upper", 1), ("lower", 1), ("trim", 1), ("len", 1), ("contains", 2),
+    ("add", 2), ("now", 1), ("json", 2),
+];
+
+// Fields of the request and response, headers are read as request.headers.<name> in lower case
+const REQUEST_FIELDS: [&str; 3] = ["request.method", "request.url", "request.body"];
+const RESPONSE_FIELDS: [&str; 3] = ["response.status", "response.body", "response.time_ms"];
+
+// When a script runs, a pre_script cant read the response yet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hook {
+    Pre,
+    Post,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operator {
+    Concat,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug)]
+enum Expression {
+    Literal(String),
+    Variable(String),
+    Exists(String),
+    Call(String, Vec<Expression>),
+    Not(Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+}
+
+#[derive(Debug)]
+enum StatementKind {
+    Assign(String, Expression),
+    Fail(Expression),
+    If(Expression, Vec<Statement>, Vec<Statement>),
+}
+
+#[derive(Debug)]
+struct Statement {
+    line: usize,
+    kind: StatementKind,
+}
+
+// Why a script stopped, either it called fail() or a statement couldnt be evaluated
+#[derive(Debug)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+// Recursive descent parser over the token list
+struct Parser {
+    tokens: Tokens,
+    hook: Hook,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        return self.tokens.peek();
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        return self.tokens.next();
+    }
+
+    fn error(&self, message: String) -> String {
+        return format!("line {}: {}", self.tokens.line(), message);
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        return self.tokens.expect(expected).map_err(|error| self.error(error));
+    }
+
+    // Statements end at a line break or ';'
+    fn at_end(&self) -> bool {
+        return matches!(self.peek(), Some(Token::Semicolon | Token::LineBreak));
+    }
+
+    fn skip_ends(&mut self) {
+        while self.at_end() {
+            self.next();
+        }
+    }
+
+    // Statements up to the end of the script or, inside a block, up to the closing brace
+    fn parse_statements(&mut self, in_block: bool) -> Result<Vec<Statement>, String> {
+        let mut statements = Vec::new();
+
+        loop {
+            self.skip_ends();
+            match self.peek() {
+                None if in_block => return Err(self.error("expected '}', found the end of the script".to_string())),
+                None => return Ok(statements),
+                Some(Token::CloseBrace) if in_block => return Ok(statements),
+                _ => statements.push(self.parse_statement()?),
+            }
+
+            match self.peek() {
+                _ if self.at_end() => (),
+                None => (),
+                Some(Token::CloseBrace) if in_block => (),
+                Some(token) => {
+                    let error = format!("unexpected {} after the statement", token);
+                    return Err(self.error(error));
+                },
+            }
+        }
+    }
+
+    fn parse_block(&mut self) -> Result<Vec<Statement>, String> {
+        self.expect(Token::OpenBrace)?;
+        let statements = self.parse_statements(true)?;
+        self.expect(Token::CloseBrace)?;
+        return Ok(statements);
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, String> {
+        let line = self.tokens.line();
+        let name = match self.peek() {
+            Some(Token::Identifier(name)) => name.clone(),
+            Some(token) => {
+                let error = format!("expected a statement, found {}", token);
+                return Err(self.error(error));
+            },
+            None => return Err(self.error("expected a statement, found the end of the script".to_string())),
+        };
+        self.next();
+
+        if name == "if" {
+            let condition = self.parse_or()?;
+            let then = self.parse_block()?;
+            let otherwise = match self.peek() {
+                Some(Token::Identifier(word)) if word == "else" => {
+                    self.next();
+                    if matches!(self.peek(), Some(Token::Identifier(word)) if word == "if") {
+                        vec![self.parse_statement()?]
+                    } else {
+                        self.parse_block()?
+                    }
+                },
+                _ => Vec::new(),
+            };
+            return Ok(Statement { line, kind: StatementKind::If(condition, then, otherwise) });
+        }
+
+        if name == "fail" && self.peek() == Some(&Token::OpenParen) {
+            self.next();
+            let message = self.parse_or()?;
+            self.expect(Token::CloseParen)?;
+            return Ok(Statement { line, kind: StatementKind::Fail(message) });
+        }
+
+        self.expect(Token::Assign)?;
+        if name.starts_with("request.") || name.starts_with("response.") {
+            return Err(format!("line {}: the {} cant be changed by a script", line, name));
+        }
+        return Ok(Statement { line, kind: StatementKind::Assign(name, self.parse_or()?) });
+    }
+
+    fn parse_binary(&mut self, operators: &[(Token, Operator)], operand: fn(&mut Parser) -> Result<Expression, String>,
+     chained: bool) -> Result<Expression, String> {
+        let mut expression = operand(self)?;
+        while let Some(operator) = operators.iter().find(|(token, _)| self.peek() == Some(token)).map(|(_, operator)| *operator) {
+            self.next();
+            expression = Expression::Binary(operator, Box::new(expression), Box::new(operand(self)?));
+            if !chained {
+                break;
+            }
+        }
+
+        return Ok(expression);
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, String> {
+        return self.parse_binary(&[(Token::Or, Operator::Or)], Parser::parse_and, true);
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, String> {
+        return self.parse_binary(&[(Token::And, Operator::And)], Parser::parse_comparison, true);
+    }
+
+    // Comparisons dont chain, 'a == b == c' is an error
+    fn parse_comparison(&mut self) -> Result<Expression, String> {
+        return self.parse_binary(&[
+            (Token::Equal, Operator::Equal), (Token::NotEqual, Operator::NotEqual),
+            (Token::Less, Operator::Less), (Token::LessEqual, Operator::LessEqual),
+            (Token::Greater, Operator::Greater), (Token::GreaterEqual, Operator::GreaterEqual),
+        ], Parser::parse_concat, false);
+    }
+
+    fn parse_concat(&mut self) -> Result<Expression, String> {
+        return self.parse_binary(&[(Token::Plus, Operator::Concat)], Parser::parse_unary, true);
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(Expression::Not(Box::new(self.parse_unary()?)));
+        }
+
+        return self.parse_primary();
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, String> {
+        let name = match (self.peek(), self.tokens.peek_nth(1)) {
+            (Some(Token::OpenParen), _) => {
+                self.next();
+                let expression = self.parse_or()?;
+                self.expect(Token::CloseParen)?;
+                return Ok(expression);
+            },
+            (Some(Token::Literal(value)), _) => {
+                let value = value.clone();
+                self.next();
+                return Ok(Expression::Literal(value));
+            },
+            // A negative number like -1
+            (Some(Token::Minus), Some(Token::Literal(value))) if value.parse::<f64>().is_ok() => {
+                let value = format!("-{}", value);
+                self.next();
+                self.next();
+                return Ok(Expression::Literal(value));
+            },
+            (Some(Token::Identifier(name)), _) => name.clone(),
+            _ => return Err(self.error(format!("expected a name or a value, found {}", self.tokens.found()))),
+        };
+        self.next();
+
+        if self.peek() != Some(&Token::OpenParen) {
+            self.check_variable(&name)?;
+            return Ok(Expression::Variable(name));
+        }
+        self.next();
+
+        // exists(name) checks whether a value has been captured or a header was sent
+        if name == "exists" {
+            let argument = match self.next() {
+                Some(Token::Identifier(argument)) => argument.clone(),
+                _ => return Err(self.error("exists() expects a name".to_string())),
+            };
+            self.check_variable(&argument)?;
+            self.expect(Token::CloseParen)?;
+            return Ok(Expression::Exists(argument));
+        }
+
+        let mut arguments = Vec::new();
+        if self.peek() != Some(&Token::CloseParen) {
+            arguments.push(self.parse_or()?);
+            while self.peek() == Some(&Token::Comma) {
+                self.next();
+                arguments.push(self.parse_or()?);
+            }
+        }
+        self.expect(Token::CloseParen)?;
+
+        return match FUNCTIONS.iter().find(|(function, _)| *function == name) {
+            Some((_, count)) if *count == arguments.len() => Ok(Expression::Call(name, arguments)),
+            Some((_, count)) => Err(self.error(format!("{}() expects {} arguments, got {}", name, count, arguments.len()))),
+            None => Err(self.error(format!("unknown function {}(), expected one of: {}", name,
+             FUNCTIONS.iter().map(|(function, _)| *function).collect::<Vec<&str>>().join(", ")))),
+        };
+    }
+
+    // The request and response only have the documented fields, the response isnt there before the request
+    fn check_variable(&self, name: &str) -> Result<(), String> {
+        let response = name.starts_with("response.");
+        let known = if response {
+            RESPONSE_FIELDS.contains(&name) || name.strip_prefix("response.headers.").is_some_and(|header| !header.is_empty())
+        } else if name.starts_with("request.") {
+            REQUEST_FIELDS.contains(&name) || name.strip_prefix("request.headers.").is_some_and(|header| !header.is_empty())
+        } else {
+            true
+        };
+
+        if !known {
+            return Err(self.error(format!("unknown field {}", name)));
+        }
+        if response && self.hook == Hook::Pre {
+            return Err(self.error(format!("a pre_script runs before the request is sent and cant read {}", name)));
+        }
+        return Ok(());
+    }
+}
+
+// Numbers compare by their value, other strings alphabetically
+fn compare(left: &str, right: &str) -> std::cmp::Ordering {
+    return match (left.parse::<f64>(), right.parse::<f64>()) {
+        (Ok(left), Ok(right)) => left.partial_cmp(&right).unwrap_or(std::cmp::Ordering::Equal),
+        _ => left.cmp(right),
+    };
+}
+
+fn hex(bytes: &[u8]) -> String {
+    return bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> String {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let inner: Vec<u8> = block.iter().map(|byte| byte ^ 0x36).chain(message.iter().copied()).collect();
+    let outer: Vec<u8> = block.iter().map(|byte| byte ^ 0x5c).chain(Sha256::digest(&inner)).collect();
+    return hex(&Sha256::digest(&outer));
+}
+
+fn call(name: &str, arguments: &[String]) -> Result<String, String> {
+    return match (name, arguments) {
+        ("sha256", [text]) => Ok(hex(&Sha256::digest(text.as_bytes()))),
+        ("hmac_sha256", [key, text]) => Ok(hmac_sha256(key.as_bytes(), text.as_bytes())),
+        ("upper", [text]) => Ok(text.to_uppercase()),
+        ("lower", [text]) => Ok(text.to_lowercase()),
+        ("trim", [text]) => Ok(text.trim().to_string()),
+        ("len", [text]) => Ok(text.chars().count().to_string()),
+        ("contains", [text, part]) => Ok(text.contains(part.as_str()).to_string()),
+        ("add", [left, right]) => match (left.parse::<i64>(), right.parse::<i64>()) {
+            (Ok(left), Ok(right)) => Ok((left + right).to_string()),
+            _ => match (left.parse::<f64>(), right.parse::<f64>()) {
+                (Ok(left), Ok(right)) => Ok((left + right).to_string()),
+                _ => Err(format!("add() expects two numbers, got '{}' and '{}'", left, right)),
+            },
+        },
+        ("now", [format]) => Ok(chrono::Utc::now().format(format).to_string()),
+        ("json", [text, path]) => {
+            let value: serde_json::Value = serde_json::from_str(text)
+                .map_err(|error| format!("json() got a text that isnt json: {}", error))?;
+            match utils::lookup_json_path(&value, path) {
+                Some(serde_json::Value::String(text)) => Ok(text.clone()),
+                Some(value) => Ok(value.to_string()),
+                None => Err(format!("json() found nothing at the path '{}'", path)),
+            }
+        },
+        _ => Err(format!("unknown function {}()", name)),
+    };
+}
+
+// What a script can read besides the captures, request.* and response.*
+struct Scope<'a> {
+    captures: &'a mut Captures,
+    context: &'a HashMap<String, String>,
+}
+
+impl Scope<'_> {
+    // Scripts work on text, captures of other types are read in their json notation
+    fn lookup(&self, name: &str) -> Option<Cow<'_, str>> {
+        if name.starts_with("request.") || name.starts_with("response.") {
+            return self.context.get(name).map(|value| Cow::Borrowed(value.as_str()));
+        }
+        return self.captures.get(name).map(interpolation::text);
+    }
+
+    fn evaluate(&self, expression: &Expression) -> Result<String, String> {
+        return match expression {
+            Expression::Literal(value) => Ok(value.clone()),
+            Expression::Variable(name) => self.lookup(name).map(|value| value.into_owned())
+                .ok_or_else(|| format!("'{}' is not set", name)),
+            Expression::Exists(name) => Ok(self.lookup(name).is_some().to_string()),
+            Expression::Call(name, arguments) => {
+                let values = arguments.iter().map(|argument| self.evaluate(argument)).collect::<Result<Vec<String>, String>>()?;
+                call(name, &values)
+            },
+            Expression::Not(expression) => Ok((!condition::truthy(&self.evaluate(expression)?)).to_string()),
+            // The right side of && and || is only evaluated if it decides the result
+            Expression::Binary(Operator::And, left, right) =>
+                Ok((condition::truthy(&self.evaluate(left)?) && condition::truthy(&self.evaluate(right)?)).to_string()),
+            Expression::Binary(Operator::Or, left, right) =>
+                Ok((condition::truthy(&self.evaluate(left)?) || condition::truthy(&self.evaluate(right)?)).to_string()),
+            Expression::Binary(operator, left, right) => {
+                let (left, right) = (self.evaluate(left)?, self.evaluate(right)?);
+                Ok(match operator {
+                    Operator::Concat => left + &right,
+                    Operator::Equal => (left == right).to_string(),
+                    Operator::NotEqual => (left != right).to_string(),
+                    Operator::Less => compare(&left, &right).is_lt().to_string(),
+                    Operator::LessEqual => compare(&left, &right).is_le().to_string(),
+                    Operator::Greater => compare(&left, &right).is_gt().to_string(),
+                    Operator::GreaterEqual => compare(&left, &right).is_ge().to_string(),
+                    Operator::And | Operator::Or => unreachable!(),
+                })
+            },
+        };
+    }
+
+    fn execute(&mut self, statements: &[Statement]) -> Result<(), ScriptError> {
+        for statement in statements.iter() {
+            let error = |message: String| ScriptError { line: statement.line, message };
+            match &statement.kind {
+                StatementKind::Assign(name, value) => {
+                    let value = self.evaluate(value).map_err(error)?;
+                    self.captures.insert(name.clone(), serde_json::Value::String(value));
+                },
+                StatementKind::Fail(message) => return Err(error(self.evaluate(message).map_err(error)?)),
+                StatementKind::If(condition, then, otherwise) => {
+                    if condition::truthy(&self.evaluate(condition).map_err(error)?) {
+                        self.execute(then)?;
+                    } else {
+                        self.execute(otherwise)?;
+                    }
+                },
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+#[derive(Debug)]
+pub struct Script {
+    statements: Vec<Statement>,
+}
+
+fn collect_names(statements: &[Statement], assigned: &mut Vec<String>, read: &mut Vec<String>) {
+    fn reads(expression: &Expression, assigned: &[String], read: &mut Vec<String>) {
+        match expression {
+            Expression::Literal(_) => (),
+            Expression::Variable(name) | Expression::Exists(name) => {
+                if !name.starts_with("request.") && !name.starts_with("response.") && !assigned.contains(name) {
+                    read.push(name.clone());
+                }
+            },
+            Expression::Call(_, arguments) => arguments.iter().for_each(|argument| reads(argument, assigned, read)),
+            Expression::Not(expression) => reads(expression, assigned, read),
+            Expression::Binary(_, left, right) => {
+                reads(left, assigned, read);
+                reads(right, assigned, read);
+            },
+        }
+    }
+
+    for statement in statements.iter() {
+        match &statement.kind {
+            StatementKind::Assign(name, value) => {
+                reads(value, assigned, read);
+                assigned.push(name.clone());
+            },
+            StatementKind::Fail(message) => reads(message, assigned, read),
+            StatementKind::If(condition, then, otherwise) => {
+                reads(condition, assigned, read);
+                collect_names(then, assigned, read);
+                collect_names(otherwise, assigned, read);
+            },
+        }
+    }
+}
+
+impl Script {
+    pub fn parse(source: &str, hook: Hook) -> Result<Script, String> {
+        let tokens = condition::tokenize(source, true).map_err(|(line, message)| format!("line {}: {}", line, message))?;
+        let mut parser = Parser { tokens: Tokens::new(tokens, "the end of the script"), hook };
+        return Ok(Script { statements: parser.parse_statements(false)? });
+    }
+
+    // Runs the statements in order, the captures assigned before an error stay set
+    pub fn run(&self, captures: &mut Captures, context: &HashMap<String, String>) -> Result<(), ScriptError> {
+        return Scope { captures, context }.execute(&self.statements);
+    }
+
+    // Names of the captures the script sets
+    pub fn assigned(&self) -> Vec<String> {
+        let (mut assigned, mut read) = (Vec::new(), Vec::new());
+        collect_names(&self.statements, &mut assigned, &mut read);
+        return assigned;
+    }
+
+    // Names of the captures the script reads before it sets them itself
+    pub fn captures(&self) -> Vec<String> {
+        let (mut assigned, mut read) = (Vec::new(), Vec::new());
+        collect_names(&self.statements, &mut assigned, &mut read);
+        return read;
+    }
+}
+
+// The request.* fields of a script, header names are lower case
+pub fn request_context(method: &str, url: &str, headers: &[(String, String)], body: &str) -> HashMap<String, String> {
+    let mut context: HashMap<String, String> = headers.iter()
+        .map(|(name, value)| (format!("request.headers.{}", name.to_lowercase()), value.clone()))
+        .collect();
+    context.insert("request.method".to_string(), method.to_uppercase());
+    context.insert("request.url".to_string(), url.to_string());
+    context.insert("request.body".to_string(), body.to_string());
+    return context;
+}
+
+// Adds the response.* fields for a post_script, repeated headers are joined with ', '
+pub fn add_response(context: &mut HashMap<String, String>, status: u16, headers: &HeaderMap, body: &[u8], time_ms: u128) {
+    for name in headers.keys() {
+        let values: Vec<&str> = headers.get_all(name).iter().filter_map(|value| value.to_str().ok()).collect();
+        context.insert(format!("response.headers.{}", name.as_str()), values.join(", "));
+    }
+    context.insert("response.status".to_string(), status.to_string());
+    context.insert("response.body".to_string(), String::from_utf8_lossy(body).into_owned());
+    context.insert("response.time_ms".to_string(), time_ms.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> HashMap<String, String> {
+        let headers = vec![("X-Nonce".to_string(), "n1".to_string())];
+        let mut context = request_context("post", "http://localhost/users", &headers, "{\"name\":\"ada\"}");
+        add_response(&mut context, 201, &HeaderMap::new(), b"{\"id\":7}", 12);
+        return context;
+    }
+
+    fn run(source: &str, captures: &mut Captures) -> Result<(), ScriptError> {
+        return Script::parse(source, Hook::Post).unwrap().run(captures, &context());
+    }
+
+    fn value(captures: &Captures, name: &str) -> String {
+        return interpolation::text(&captures[name]).into_owned();
+    }
+
+    #[test]
+    fn assigns_captures_from_the_request_and_response() {
+        let mut captures = Captures::new();
+        run("method = lower(request.method); nonce = request.headers.x-nonce\n\
+            id = json(response.body, 'id'); took = add(response.time_ms, -2)", &mut captures).unwrap();
+        assert_eq!(value(&captures, "method"), "post");
+        assert_eq!(value(&captures, "nonce"), "n1");
+        assert_eq!(value(&captures, "id"), "7");
+        assert_eq!(value(&captures, "took"), "10");
+    }
+
+    #[test]
+    fn concatenates_and_compares() {
+        let mut captures = Captures::new();
+        run("a = 'x' + 1 + true\nb = 9 < 10\nc = 'b' < 'a'\nd = !(b && c) || missing", &mut captures).unwrap();
+        assert_eq!(value(&captures, "a"), "x1true");
+        assert_eq!(value(&captures, "b"), "true");
+        assert_eq!(value(&captures, "c"), "false");
+        assert_eq!(value(&captures, "d"), "true");
+    }
+
+    #[test]
+    fn runs_the_branch_of_the_condition() {
+        let mut captures = Captures::new();
+        let source = "if response.status == 200 {\n  kind = 'ok'\n} else if response.status == 201 { kind = 'created' } \
+            else { kind = 'other' }";
+        run(source, &mut captures).unwrap();
+        assert_eq!(value(&captures, "kind"), "created");
+    }
+
+    #[test]
+    fn computes_the_hmac_of_a_message() {
+        let mut captures = Captures::new();
+        run("mac = hmac_sha256('key', 'The quick brown fox jumps over the lazy dog')", &mut captures).unwrap();
+        assert_eq!(value(&captures, "mac"), "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8");
+    }
+
+    #[test]
+    fn stops_at_fail_with_its_line() {
+        let mut captures = Captures::new();
+        let error = run("a = 1\n\nif exists(a) { fail('got ' + a) }\nb = 2", &mut captures).unwrap_err();
+        assert_eq!((error.line, error.message.as_str()), (3, "got 1"));
+        assert!(captures.contains_key("a") && !captures.contains_key("b"));
+    }
+
+    #[test]
+    fn reports_values_that_are_not_set() {
+        let error = run("a = missing", &mut Captures::new()).unwrap_err();
+        assert_eq!((error.line, error.message.as_str()), (1, "'missing' is not set"));
+    }
+
+    #[test]
+    fn lists_the_captures_it_reads_and_sets() {
+        let script = Script::parse("a = b\nif exists(c) { d = a + request.body }", Hook::Pre).unwrap();
+        assert_eq!(script.assigned(), vec!["a", "d"]);
+        assert_eq!(script.captures(), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn rejects_invalid_scripts() {
+        let error = |source: &str| Script::parse(source, Hook::Pre).unwrap_err();
+        assert_eq!(error("a = 1 b = 2"), "line 1: unexpected 'b' after the statement");
+        assert_eq!(error("a = (1 +\n2"), "line 2: expected ')', found the end of the script");
+        assert_eq!(error("if a { b = 1"), "line 1: expected '}', found the end of the script");
+        assert_eq!(error("a = upper()"), "line 1: upper() expects 1 arguments, got 0");
+        assert_eq!(error("a = b + c()"), "line 1: unknown function c(), expected one of: sha256, hmac_sha256, upper, \
+            lower, trim, len, contains, add, now, json");
+        assert_eq!(error("a = 1\nb = 'open"), "line 2: unterminated string starting at position 4");
+        assert_eq!(error("a = response.status"),
+            "line 1: a pre_script runs before the request is sent and cant read response.status");
+        assert_eq!(error("request.body = 'x'"), "line 1: the request.body cant be changed by a script");
+        assert_eq!(error("a = request.size"), "line 1: unknown field request.size");
+        assert_eq!(error("a == 1"), "line 1: expected '=', found '=='");
+        assert_eq!(error("a = 1 == 1 == 1"), "line 1: unexpected '==' after the statement");
+    }
+}
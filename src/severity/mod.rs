@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+use colored::Colorize;
+use serde::{Serialize, Deserialize};
+
+// The assertions of a test that can be downgraded to warnings, named like their fields
+pub const ASSERTIONS: [&str; 34] = ["status", "expect_not_status", "require_json", "expect_valid_json",
+    "expect_json_type", "post_script", "expect_expr", "fail_on", "min_time_ms", "max_dns_ms", "max_connect_ms",
+    "max_tls_ms", "max_ttfb_ms", "max_download_ms", "expect_array_length", "expect_array_contains", "expect_body",
+    "expect_cookies", "expect_headers", "expect_location", "expect_content_disposition_filename",
+    "expect_redirect_chain", "expect_headers_absent", "cors", "websocket", "idempotency_check", "pagination_check",
+    "expect_events", "expect_body_equals_capture", "expect_body_file", "expect_body_exact", "snapshot",
+    "expect_encoding", "min_compression_ratio"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Error,
+    Warn, // the violation is reported but doesnt fail the test
+}
+
+// Either one level for every assertion of a test or the level per assertion, the rest stay errors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Severity {
+    All(Level),
+    PerAssertion(HashMap<String, Level>),
+}
+
+// The level of an assertion of a test, errors if the test doesnt say otherwise
+pub fn level(severity: Option<&Severity>, assertion: &str) -> Level {
+    return match severity {
+        Some(Severity::All(level)) => *level,
+        Some(Severity::PerAssertion(levels)) => levels.get(assertion).copied().unwrap_or(Level::Error),
+        None => Level::Error,
+    };
+}
+
+// The verdict behind the message of an assertion, a violated warning is yellow instead of failing
+pub fn verdict(passed: bool, level: Level) -> String {
+    return match (passed, level) {
+        (true, _) => "PASSED".to_string(),
+        (false, Level::Error) => "FAILED".to_string(),
+        (false, Level::Warn) => "WARNING".yellow().to_string(),
+    };
+}
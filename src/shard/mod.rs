@@ -0,0 +1,54 @@
+use crate::dependency::Graph;
+
+fn root(parents: &mut [usize], index: usize) -> usize {
+    let mut root = index;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[index] = root;
+    return root;
+}
+
+// Groups the tests that share captures, a group is always run by a single shard
+fn groups(graph: &Graph) -> Vec<Vec<usize>> {
+    // Union find over the capture edges, every group is rooted at its smallest index
+    let mut parents: Vec<usize> = (0..graph.producers.len()).collect();
+    for (index, producers) in graph.producers.iter().enumerate() {
+        for (producer, _) in producers.iter() {
+            let (a, b) = (root(&mut parents, index), root(&mut parents, *producer));
+            parents[a.max(b)] = a.min(b);
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of_root: Vec<Option<usize>> = vec![None; parents.len()];
+    for index in 0..parents.len() {
+        let root = root(&mut parents, index);
+        match group_of_root[root] {
+            Some(group) => groups[group].push(index),
+            None => {
+                group_of_root[root] = Some(groups.len());
+                groups.push(vec![index]);
+            },
+        }
+    }
+    return groups;
+}
+
+// The zero based tests of a one based shard out of count. The groups are handed out in config order, each to the
+// shard with the fewest tests so far, so every job of the CI computes the same assignment.
+pub fn tests_of_shard(graph: &Graph, shard: usize, count: usize) -> Vec<usize> {
+    let mut sizes = vec![0; count];
+    let mut tests = Vec::new();
+
+    for group in groups(graph).into_iter() {
+        let smallest = (0..count).min_by_key(|candidate| sizes[*candidate]).unwrap_or(0);
+        sizes[smallest] += group.len();
+        if smallest + 1 == shard {
+            tests.extend(group);
+        }
+    }
+
+    tests.sort();
+    return tests;
+}
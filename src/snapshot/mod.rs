@@ -0,0 +1,278 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::{Endpoint, Result};
+
+pub const DIRECTORY_NAME: &str = "snapshots";
+
+// Replaces the values of ignore_paths inside the stored snapshots
+const IGNORED_VALUE: &str = "<ignored>";
+
+// Stands in for the values of ignore_paths until the keys and elements holding it are removed
+const REMOVED_VALUE: &str = "\u{0}<removed>";
+
+// Slugs of the file names are cut off after this many characters
+const MAX_SLUG_LENGTH: usize = 60;
+
+// Bodies with more lines are only compared up to their first difference
+const MAX_DIFF_LINES: usize = 2000;
+
+// At most this many changed lines are printed
+const MAX_LISTED_LINES: usize = 20;
+
+// Bytes shown on either side of the first difference of an exact comparison
+const CONTEXT_BYTES: usize = 8;
+
+#[derive(Debug)]
+pub enum SnapshotOutcome {
+    Recorded, // there was no snapshot yet
+    Updated, // rewritten because of --update-snapshots
+    Matched,
+    Mismatch { diff: String, changed: usize },
+}
+
+fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.truncate(MAX_SLUG_LENGTH);
+    return slug.trim_end_matches('-').to_string();
+}
+
+// The snapshot file of a test, derived from its one based index and its name or method and route
+pub fn snapshot_path(directory: &Path, index: usize, test: &Endpoint) -> PathBuf {
+    let name = match &test.it {
+        Some(description) => slug(description),
+        None => slug(&format!("{} {}", test.method, test.route)),
+    };
+
+    return directory.join(format!("{:03}-{}.snap", index, name));
+}
+
+fn ignore_path(value: &mut serde_json::Value, segments: &[&str], replacement: &str) {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => {
+            *value = serde_json::Value::from(replacement);
+            return;
+        },
+    };
+
+    match value {
+        serde_json::Value::Object(map) if *segment == "*" => map.values_mut()
+            .for_each(|child| ignore_path(child, rest, replacement)),
+        serde_json::Value::Array(array) if *segment == "*" => array.iter_mut()
+            .for_each(|child| ignore_path(child, rest, replacement)),
+        serde_json::Value::Object(map) => if let Some(child) = map.get_mut(*segment) {
+            ignore_path(child, rest, replacement);
+        },
+        serde_json::Value::Array(array) => if let Some(child) = segment.parse::<usize>().ok()
+            .and_then(|index| array.get_mut(index)) {
+            ignore_path(child, rest, replacement);
+        },
+        _ => (),
+    }
+}
+
+fn ignore_paths_of(json: &mut serde_json::Value, ignore_paths: &[String], replacement: &str) {
+    for path in ignore_paths.iter() {
+        let segments: Vec<&str> = path.split('.').filter(|segment| !segment.is_empty()).collect();
+        ignore_path(json, &segments, replacement);
+    }
+}
+
+// Drops the keys and elements the ignored paths marked, the indices of later paths still refer to the original arrays
+fn drop_removed(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, child| child.as_str() != Some(REMOVED_VALUE));
+            map.values_mut().for_each(drop_removed);
+        },
+        serde_json::Value::Array(array) => {
+            array.retain(|child| child.as_str() != Some(REMOVED_VALUE));
+            array.iter_mut().for_each(drop_removed);
+        },
+        _ => (),
+    }
+}
+
+// Removes the keys and array elements of the ignored paths, e.g. before comparing with an expected document that
+// leaves them out
+pub fn remove_paths(json: &mut serde_json::Value, ignore_paths: &[String]) {
+    ignore_paths_of(json, ignore_paths, REMOVED_VALUE);
+    drop_removed(json);
+}
+
+// Json bodies are stored pretty-printed with sorted keys and the ignored paths masked, everything else as it is
+pub fn normalize(body: &[u8], ignore_paths: &[String]) -> String {
+    let mut json = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(json) => json,
+        Err(_) => return String::from_utf8_lossy(body).to_string(),
+    };
+
+    ignore_paths_of(&mut json, ignore_paths, IGNORED_VALUE);
+    return serde_json::to_string_pretty(&json).unwrap_or_default() + "\n";
+}
+
+// Like normalize, but the ignored paths are removed instead of masked
+fn normalize_without(body: &[u8], ignore_paths: &[String]) -> String {
+    let mut json = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(json) => json,
+        Err(_) => return String::from_utf8_lossy(body).to_string(),
+    };
+
+    remove_paths(&mut json, ignore_paths);
+    return serde_json::to_string_pretty(&json).unwrap_or_default() + "\n";
+}
+
+// A line diff of the snapshot and the new body, returns the listed lines and the number of changed lines
+pub fn diff(expected: &str, actual: &str) -> (String, usize) {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    if expected.len() > MAX_DIFF_LINES || actual.len() > MAX_DIFF_LINES {
+        let line = expected.iter().zip(actual.iter()).position(|(a, b)| a != b)
+            .unwrap_or(expected.len().min(actual.len()));
+        return (format!("  the bodies differ from line {} on\n- {}\n+ {}", line + 1,
+            expected.get(line).unwrap_or(&""), actual.get(line).unwrap_or(&"")), 1);
+    }
+
+    // Longest common subsequence of the lines, the table holds the lengths of the suffixes
+    let mut lengths = vec![vec![0u32; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lengths[i][j] = if expected[i] == actual[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            i += 1;
+            j += 1;
+        } else if i < expected.len() && (j == actual.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            changes.push(format!("{:>5} - {}", i + 1, expected[i]));
+            i += 1;
+        } else {
+            changes.push(format!("{:>5} + {}", j + 1, actual[j]));
+            j += 1;
+        }
+    }
+
+    let changed = changes.len();
+    if changed > MAX_LISTED_LINES {
+        changes.truncate(MAX_LISTED_LINES);
+        changes.push(format!("      ... {} more changed lines", changed - MAX_LISTED_LINES));
+    }
+    return (changes.join("\n"), changed);
+}
+
+// Compares the body with the stored snapshot, a missing snapshot is recorded instead
+pub fn check_snapshot(path: &Path, body: &[u8], ignore_paths: &[String], update: bool) -> Result<SnapshotOutcome> {
+    let normalized = normalize(body, ignore_paths);
+
+    let stored = fs::read_to_string(path).ok();
+    if stored.as_ref() == Some(&normalized) {
+        return Ok(SnapshotOutcome::Matched);
+    }
+
+    let stored = match stored {
+        Some(stored) if !update => stored,
+        previous => {
+            if let Some(directory) = path.parent() {
+                fs::create_dir_all(directory)?;
+            }
+            fs::write(path, &normalized)?;
+            return Ok(if previous.is_some() { SnapshotOutcome::Updated } else { SnapshotOutcome::Recorded });
+        },
+    };
+
+    let (diff, changed) = diff(&stored, &normalized);
+    return Ok(SnapshotOutcome::Mismatch { diff, changed });
+}
+
+// Compares the body with an expected document, json ignores the key order, the formatting and the ignored paths,
+// which are removed on both sides. Text has to be identical apart from the trailing newline of the file.
+// Returns the diff and the number of changed lines if they differ.
+pub fn compare_expected(expected: &str, body: &[u8], as_text: bool, ignore_paths: &[String]) -> Option<(String, usize)> {
+    let (expected, actual) = if as_text {
+        (expected.strip_suffix('\n').unwrap_or(expected).to_string(), String::from_utf8_lossy(body).to_string())
+    } else {
+        (normalize_without(expected.as_bytes(), ignore_paths), normalize_without(body, ignore_paths))
+    };
+
+    if expected == actual {
+        return None;
+    }
+    return Some(diff(&expected, &actual));
+}
+
+// One side of the context window as hex and as characters, unprintable ones shown as dots
+fn context_line(label: &str, bytes: &[u8], start: usize, end: usize) -> String {
+    let window = &bytes[start.min(bytes.len())..end.min(bytes.len())];
+    let hex: Vec<String> = window.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let chars: String = window.iter()
+        .map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' })
+        .collect();
+    return format!("{:<9} {:<width$} |{}|", format!("{}:", label), hex.join(" "), chars,
+        width = (2 * CONTEXT_BYTES + 1) * 3);
+}
+
+// Compares the body byte by byte with the expected one, returns the offset of the first difference and the bytes
+// around it. A body that is a prefix of the other differs at the end of the shorter one.
+pub fn compare_exact(expected: &[u8], body: &[u8]) -> Option<(usize, String)> {
+    let offset = expected.iter().zip(body.iter()).position(|(a, b)| a != b)
+        .or_else(|| (expected.len() != body.len()).then(|| expected.len().min(body.len())))?;
+
+    let (start, end) = (offset.saturating_sub(CONTEXT_BYTES), offset + CONTEXT_BYTES + 1);
+    let marker = format!("{:<9} {}^^", "", " ".repeat((offset - start) * 3));
+    let context = format!("{}\n{}\n{}\n  expected {} bytes, got {}", context_line("expected", expected, start, end),
+        context_line("actual", body, start, end), marker, expected.len(), body.len());
+    return Some((offset, context));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn removed(json: serde_json::Value, ignore_paths: &[&str]) -> serde_json::Value {
+        let mut json = json;
+        remove_paths(&mut json, &ignore_paths.iter().map(|path| path.to_string()).collect::<Vec<String>>());
+        return json;
+    }
+
+    #[test]
+    fn removes_the_keys_and_elements_of_ignored_paths() {
+        let body = serde_json::json!({"id": 1, "created_at": "now", "items": [{"id": 2, "name": "a"}, {"id": 3}]});
+        assert_eq!(removed(body.clone(), &["created_at", "items.*.id"]),
+            serde_json::json!({"id": 1, "items": [{"name": "a"}, {}]}));
+        assert_eq!(removed(body.clone(), &["items.0", "items.1", "missing.path"]),
+            serde_json::json!({"id": 1, "created_at": "now", "items": []}));
+        assert_eq!(removed(body, &["items.*"]), serde_json::json!({"id": 1, "created_at": "now", "items": []}));
+    }
+
+    #[test]
+    fn compares_expected_documents_without_the_ignored_paths() {
+        let ignore_paths = vec!["created_at".to_string(), "items.*.id".to_string()];
+        let body = br#"{"name": "a", "created_at": "now", "items": [{"id": 7, "n": 1}]}"#;
+        assert!(compare_expected(r#"{"name": "a", "items": [{"n": 1}]}"#, body, false, &ignore_paths).is_none());
+        assert!(compare_expected(r#"{"name": "b", "items": [{"n": 1}]}"#, body, false, &ignore_paths).is_some());
+        assert!(compare_expected(r#"{"name": "a", "items": [{"n": 1}]}"#, body, false, &[]).is_some());
+    }
+
+    #[test]
+    fn masks_the_ignored_paths_of_snapshots() {
+        assert_eq!(normalize(br#"{"id": 1, "at": "now"}"#, &["at".to_string()]),
+            "{\n  \"at\": \"<ignored>\",\n  \"id\": 1\n}\n");
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::time::Duration;
+
+use bytes::BufMut;
+use hyper::body::HttpBody as _;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::Result;
+use crate::failure::FailureReason;
+
+// How a test reads a text/event-stream response
+#[derive(Debug, Clone, Copy)]
+pub struct SseOptions {
+    pub expected_events: Option<u32>, // stop once this many events arrived
+    pub max_wait: Duration, // stop reading after this long, counted from the response headers
+}
+
+// A dispatched event, the data lines are joined with newlines
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub event: String,
+    pub data: String,
+    pub id: Option<String>,
+}
+
+// Why reading the stream stopped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamEnd {
+    Complete, // the expected number of events arrived
+    Closed,
+    WaitLimit,
+}
+
+#[derive(Debug)]
+pub struct EventStream {
+    pub events: Vec<Event>,
+    pub end: StreamEnd,
+    pub max_wait_ms: u128,
+}
+
+impl fmt::Display for EventStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self.end {
+            StreamEnd::Complete => write!(f, "the expected number of events arrived"),
+            StreamEnd::Closed => write!(f, "the connection was closed"),
+            StreamEnd::WaitLimit => write!(f, "the wait limit of {} ms was reached", self.max_wait_ms),
+        };
+    }
+}
+
+// Incremental parser of the event stream format, chunks may end in the middle of a line
+#[derive(Default)]
+struct EventParser {
+    pending: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+}
+
+impl EventParser {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<Event> {
+        self.pending.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(end) = self.pending.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            // A blank line dispatches the event, one without data is dropped
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(Event {
+                        event: self.event.take().unwrap_or("message".to_string()),
+                        data: self.data.join("\n"),
+                        id: self.id.clone(),
+                    });
+                }
+                self.event = None;
+                self.data.clear();
+                continue;
+            }
+
+            // Lines starting with a colon are comments, e.g. keep-alives
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push(value.to_string()),
+                "id" => self.id = Some(value.to_string()),
+                _ => (),
+            }
+        }
+
+        return events;
+    }
+}
+
+// Reads the events of the response until enough arrived, the connection closes or the wait limit is reached.
+// The raw stream is kept in the buffer, so it is the body of the test.
+pub async fn read_events(response: &mut hyper::Response<hyper::Body>, buffer: &mut bytes::BytesMut,
+     options: SseOptions) -> Result<EventStream> {
+    let deadline = Instant::now() + options.max_wait;
+    let mut parser = EventParser::default();
+    let mut stream = EventStream {
+        events: Vec::new(),
+        end: StreamEnd::Closed,
+        max_wait_ms: options.max_wait.as_millis(),
+    };
+
+    loop {
+        if options.expected_events.is_some_and(|expected| stream.events.len() >= expected as usize) {
+            stream.end = StreamEnd::Complete;
+            break;
+        }
+
+        let chunk = match tokio::time::timeout_at(deadline, response.data()).await {
+            Ok(Some(chunk)) => chunk?,
+            Ok(None) => break,
+            Err(_) => {
+                stream.end = StreamEnd::WaitLimit;
+                break;
+            },
+        };
+
+        buffer.put(chunk.clone());
+        stream.events.extend(parser.feed(&chunk));
+    }
+
+    return Ok(stream);
+}
+
+// Checks the number of received events, returns the log message and the failure if there are too few
+pub fn check_event_count(stream: &EventStream, expected: Option<u32>) -> (String, Option<FailureReason>) {
+    let received = stream.events.len() as u32;
+
+    let expected = match expected {
+        Some(expected) => expected,
+        None => return (format!("Received {} events ({})", received, stream), None),
+    };
+
+    let message = format!("Expected {} events, received {} ({})", expected, received, stream);
+    if received >= expected {
+        return (message, None);
+    }
+
+    return (message, Some(FailureReason::EventsMissing { expected, received, end: stream.to_string() }));
+}
+
+// Looks up a capture source like "0" (the data of the first event) or "0.user.id" (a json value of its data)
+pub fn capture_event(events: &[Event], source: &str) -> Option<serde_json::Value> {
+    let (index, path) = source.split_once('.').unwrap_or((source, ""));
+    let event = events.get(index.parse::<usize>().ok()?)?;
+
+    if path.is_empty() {
+        return Some(serde_json::Value::from(event.data.as_str()));
+    }
+
+    let json: serde_json::Value = serde_json::from_str(&event.data).ok()?;
+    let value = crate::utils::lookup_json_path(&json, path)?;
+
+    // Typed just like the captures of the body
+    return Some(value.clone());
+}
+
+// Whether a capture source after the "$event:" prefix is well formed
+pub fn is_valid_capture(source: &str) -> bool {
+    let index = source.split_once('.').map_or(source, |(index, _)| index);
+    return index.parse::<usize>().is_ok();
+}
@@ -0,0 +1,73 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use crate::{interpolation, utils};
+
+// What to do after a pause of --step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepAction {
+    Continue, // run the next test
+    Skip, // skip the next test
+    Rerun, // run the previous test again
+    Quit, // stop the run, the reports are still written
+}
+
+impl StepAction {
+    fn parse(input: &str) -> Option<StepAction> {
+        return match input.trim().to_lowercase().as_str() {
+            "" => Some(StepAction::Continue),
+            "s" => Some(StepAction::Skip),
+            "r" => Some(StepAction::Rerun),
+            "q" => Some(StepAction::Quit),
+            _ => None,
+        };
+    }
+}
+
+// Pausing only makes sense if someone can answer
+pub fn is_interactive() -> bool {
+    return io::stdin().is_terminal();
+}
+
+// The captures as they stand, sorted by name and with secret values redacted
+fn describe_captures(captures: &interpolation::Captures) -> String {
+    if captures.is_empty() {
+        return "Captures: none\n".to_string();
+    }
+
+    let mut names: Vec<&String> = captures.keys().collect();
+    names.sort();
+
+    let mut description = "Captures:\n".to_string();
+    for name in names {
+        let value = if utils::is_secret_name(name) {
+            "<redacted>".into()
+        } else {
+            interpolation::text(&captures[name])
+        };
+        description += &format!("  {} = {}\n", name, value);
+    }
+    return description;
+}
+
+// Asks until the answer is known, a closed stdin continues the run without further questions
+fn read_action() -> StepAction {
+    let stdin = io::stdin();
+    loop {
+        print!("[Enter] next test, s skip it, r re-run the previous one, q quit: ");
+        let _ = io::stdout().flush();
+
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => return StepAction::Continue,
+            Ok(_) => if let Some(action) = StepAction::parse(&line) {
+                return action;
+            },
+        }
+    }
+}
+
+// Prints the captures and waits for the user, the stdin is read off the runtime's worker threads
+pub async fn pause(captures: &interpolation::Captures) -> StepAction {
+    print!("{}", describe_captures(captures));
+    return tokio::task::spawn_blocking(read_action).await.unwrap_or(StepAction::Continue);
+}
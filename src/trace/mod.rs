@@ -0,0 +1,83 @@
+use serde::{Serialize, Deserialize};
+
+use crate::idempotency;
+use crate::utils::random_u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TraceHeader {
+    #[default]
+    Traceparent, // the W3C trace context
+    XCorrelationId, // a plain uuid
+}
+
+// Sends a fresh trace id with every test, so failures can be looked up in the server logs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TracingConfig {
+    pub header: Option<TraceHeader>,
+    pub enabled: Option<bool>, // defaults to true
+}
+
+// The header of a single test, the id is what gets logged and reported
+pub struct TraceContext {
+    pub header: &'static str,
+    pub value: String,
+    pub id: String,
+}
+
+impl TraceHeader {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            TraceHeader::Traceparent => "traceparent",
+            TraceHeader::XCorrelationId => "X-Correlation-Id",
+        };
+    }
+}
+
+fn is_hex(text: &str, length: usize) -> bool {
+    return text.len() == length && text.bytes().all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte));
+}
+
+// Extracts the trace id of a bare id or a whole traceparent value
+fn parse_trace_id(value: &str) -> Option<String> {
+    let value = value.trim().to_lowercase();
+    if is_hex(&value, 32) {
+        return Some(value);
+    }
+
+    let parts: Vec<&str> = value.split('-').collect();
+    return match parts.as_slice() {
+        [version, trace_id, parent_id, flags] if is_hex(version, 2) && is_hex(trace_id, 32) && is_hex(parent_id, 16)
+            && is_hex(flags, 2) => Some(trace_id.to_string()),
+        _ => None,
+    };
+}
+
+fn context(header: TraceHeader, id: String) -> TraceContext {
+    // Every request is a span of its own, sampled so the backend keeps it
+    let value = match header {
+        TraceHeader::Traceparent => format!("00-{}-{:016x}-01", id, random_u64()),
+        TraceHeader::XCorrelationId => id.clone(),
+    };
+
+    return TraceContext { header: header.name(), value, id };
+}
+
+// Starts a new trace for a test
+pub fn start(header: TraceHeader) -> TraceContext {
+    let id = match header {
+        TraceHeader::Traceparent => format!("{:016x}{:016x}", random_u64(), random_u64()),
+        TraceHeader::XCorrelationId => idempotency::generate_key(),
+    };
+    return context(header, id);
+}
+
+// Continues the trace of an earlier request, e.g. a captured id, fails if it isnt a valid trace id
+pub fn resume(header: TraceHeader, reused: &str) -> Result<TraceContext, String> {
+    let id = match header {
+        TraceHeader::Traceparent => parse_trace_id(reused)
+            .ok_or(format!("'{}' is neither a trace id of 32 hex digits nor a traceparent", reused))?,
+        TraceHeader::XCorrelationId => reused.to_string(),
+    };
+    return Ok(context(header, id));
+}
@@ -0,0 +1,113 @@
+use std::fs;
+use std::path::Path;
+
+use crate::{Result, utils};
+
+// Files of earlier runs with these extensions are removed
+const EXTENSIONS: [&str; 2] = ["http", "bin"];
+
+// One side of an exchange as it went over the wire
+pub struct Message<'a> {
+    pub start_line: String, // the request or status line
+    pub headers: Vec<(String, String)>,
+    pub body: &'a [u8],
+}
+
+// A response read back from the transcripts of an earlier run
+pub struct Recorded {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+// Creates the directory, the transcripts of earlier runs are removed unless they are kept
+pub fn prepare(directory: &Path, keep: bool) -> Result<()> {
+    fs::create_dir_all(directory)?;
+    if keep {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().and_then(|extension| extension.to_str())
+            .is_some_and(|extension| EXTENSIONS.contains(&extension)) {
+            fs::remove_file(path)?;
+        }
+    }
+    return Ok(());
+}
+
+// The common part of the file names of a test, e.g. 003 or 003-2 for the second repetition
+pub fn base_name(index: usize, repetition: u32, repeat: u32) -> String {
+    if repeat > 1 {
+        return format!("{:03}-{}", index, repetition);
+    }
+    return format!("{:03}", index);
+}
+
+fn is_binary(body: &[u8]) -> bool {
+    return std::str::from_utf8(body).is_err() || body.contains(&0);
+}
+
+// Writes <name>.http with secret headers redacted, a binary body is written to <name>.bin and referenced instead
+fn write_message(directory: &Path, name: &str, message: &Message) -> Result<()> {
+    let mut text = format!("{}\n", message.start_line);
+    for (header, value) in message.headers.iter() {
+        let value = if utils::is_secret_name(header) { "<redacted>" } else { value.as_str() };
+        text += &format!("{}: {}\n", header, value);
+    }
+
+    if !message.body.is_empty() {
+        text += "\n";
+        if is_binary(message.body) {
+            let binary_file = format!("{}.bin", name);
+            fs::write(directory.join(&binary_file), message.body)?;
+            text += &format!("<binary body of {} bytes, see {}>\n", message.body.len(), binary_file);
+        } else {
+            text += &String::from_utf8_lossy(message.body);
+        }
+    }
+
+    fs::write(directory.join(format!("{}.http", name)), text)?;
+    return Ok(());
+}
+
+// Writes the transcripts of a test, the response is missing if the request failed
+pub fn record(directory: &Path, base_name: &str, request: &Message, response: Option<&Message>) -> Result<()> {
+    write_message(directory, &format!("{}-request", base_name), request)?;
+    if let Some(response) = response {
+        write_message(directory, &format!("{}-response", base_name), response)?;
+    }
+    return Ok(());
+}
+
+// Reads the response transcript of a test, None if the earlier run didnt record one
+pub fn read_response(directory: &Path, base_name: &str) -> Result<Option<Recorded>> {
+    let path = directory.join(format!("{}-response.http", base_name));
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let text = fs::read_to_string(&path)?;
+    let (head, body) = text.split_once("\n\n").unwrap_or((text.as_str(), ""));
+    let mut lines = head.lines();
+    let status = lines.next()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or(format!("{} doesnt start with a status line", path.display()))?;
+    let headers = lines
+        .filter_map(|line| line.split_once(": "))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+    // A binary body is only referenced inside the transcript
+    let binary_file = body.strip_prefix("<binary body of ")
+        .and_then(|reference| reference.split_once(" bytes, see "))
+        .and_then(|(_, file)| file.trim_end().strip_suffix('>'));
+    let body = match binary_file {
+        Some(file) => fs::read(directory.join(file))?,
+        None => body.as_bytes().to_vec(),
+    };
+
+    return Ok(Some(Recorded { status, headers, body }));
+}
@@ -1,4 +1,9 @@
 use std::{env, path::PathBuf};
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Serialize, Deserialize};
 
 pub fn get_cwd() -> PathBuf {
     return env::current_dir().unwrap();
@@ -8,11 +13,12 @@ pub fn get_cwd() -> PathBuf {
 pub fn get_num_digits<T: std::ops::Div<T, Output = T> + std::cmp::PartialOrd + std::marker::Copy>
     (number: T, base: T) -> u8 {
 
-    let iterations = 0;
+    let mut iterations = 1;
     let mut test_number = number;
 
     while test_number >= base {
         test_number = test_number / base;
+        iterations += 1;
     }
 
     return iterations;
@@ -37,3 +43,212 @@ macro_rules! printif {
         }
     }
 }
+
+// A config value that can either be given as a single value or as a list
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    pub fn as_slice(&self) -> &[T] {
+        return match self {
+            OneOrMany::One(value) => std::slice::from_ref(value),
+            OneOrMany::Many(values) => values,
+        };
+    }
+}
+
+// Deserializes a field so that an explicit null (Some(None)) can be told apart from an absent one (None),
+// needs to be combined with #[serde(default)]
+pub fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where D: serde::Deserializer<'de>, T: Deserialize<'de> {
+
+    return Ok(Some(Option::deserialize(deserializer)?));
+}
+
+// Deserializes a map of free-form text, numbers and booleans are kept as they were written
+pub fn deserialize_text_map<'de, D>(deserializer: D) -> Result<Option<HashMap<String, String>>, D::Error>
+    where D: serde::Deserializer<'de> {
+
+    let map = match Option::<HashMap<String, serde_yaml::Value>>::deserialize(deserializer)? {
+        Some(map) => map,
+        None => return Ok(None),
+    };
+
+    let mut texts = HashMap::new();
+    for (key, value) in map.into_iter() {
+        let text = match value {
+            serde_yaml::Value::String(text) => text,
+            serde_yaml::Value::Number(number) => number.to_string(),
+            serde_yaml::Value::Bool(value) => value.to_string(),
+            serde_yaml::Value::Null => String::new(),
+            _ => return Err(serde::de::Error::custom(format!("the value of '{}' has to be text", key))),
+        };
+        texts.insert(key, text);
+    }
+    return Ok(Some(texts));
+}
+
+// Looks up a dotted path like "data.items.0.id" inside a json value, an empty path yields the root
+pub fn lookup_json_path<'a>(value: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    let mut current = value;
+
+    for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+        current = match current {
+            serde_json::Value::Object(map) => map.get(segment)?,
+            serde_json::Value::Array(array) => array.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+
+    return Some(current);
+}
+
+// Matches a text against a case insensitive pattern where '*' matches any sequence of characters
+pub fn matches_wildcard(text: &str, pattern: &str) -> bool {
+    let text = text.to_lowercase();
+    let pattern = pattern.to_lowercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+
+    // Without wildcards the pattern has to match exactly
+    if parts.len() == 1 {
+        return text == pattern;
+    }
+
+    let mut rest = text.as_str();
+    for (index, part) in parts.iter().enumerate() {
+        if index == 0 {
+            match rest.strip_prefix(part) {
+                Some(stripped) => rest = stripped,
+                None => return false,
+            }
+        } else if index == parts.len() - 1 {
+            return rest.ends_with(part);
+        } else {
+            match rest.find(part) {
+                Some(position) => rest = &rest[position + part.len()..],
+                None => return false,
+            }
+        }
+    }
+
+    return true;
+}
+
+// Bodies printed to the log are cut off after this many bytes per default
+pub const DEFAULT_MAX_PRINTED_BODY_BYTES: usize = 4096;
+
+// Json with sorted keys, compact or indented by two spaces, None if the text isnt json
+pub fn normalize_json(body: &[u8], pretty: bool) -> Option<String> {
+    let json = serde_json::from_slice::<serde_json::Value>(body).ok()?;
+    if pretty {
+        return serde_json::to_string_pretty(&json).ok();
+    }
+    return Some(json.to_string());
+}
+
+// A body on a single line for failure reasons, json with sorted keys
+pub fn compact_body(body: &[u8]) -> String {
+    return normalize_json(body, false).unwrap_or_else(|| String::from_utf8_lossy(body).to_string());
+}
+
+// Formats a body for the log, json is pretty-printed unless the raw bytes are requested
+pub fn printable_body(body: &[u8], raw: bool, max_bytes: usize) -> String {
+    let normalized = if raw { None } else { normalize_json(body, true) };
+    let text = normalized.unwrap_or_else(|| String::from_utf8_lossy(body).to_string());
+
+    if text.len() <= max_bytes {
+        return text;
+    }
+
+    let mut cut = max_bytes;
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    return format!("{}\n... ({} more bytes omitted)", &text[..cut], text.len() - cut);
+}
+
+// Headers and variables whose lowercase name contains one of these are treated as secrets
+const SECRET_NAME_PARTS: [&str; 6] = ["authorization", "cookie", "token", "secret", "password", "key"];
+
+pub fn is_secret_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    return SECRET_NAME_PARTS.iter().any(|part| name.contains(part));
+}
+
+// A json body with the values of secret fields like "password" replaced by <redacted>, for the reports and the
+// transcripts. Other bodies and json without a secret field are returned as they are.
+pub fn redact_body(body: &str) -> String {
+    let mut json = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(json) => json,
+        Err(_) => return body.to_string(),
+    };
+    if !redact_json(&mut json) {
+        return body.to_string();
+    }
+    return json.to_string();
+}
+
+fn redact_json(value: &mut serde_json::Value) -> bool {
+    let mut redacted = false;
+    match value {
+        serde_json::Value::Object(fields) => for (name, field) in fields.iter_mut() {
+            if is_secret_name(name) {
+                *field = serde_json::Value::from("<redacted>");
+                redacted = true;
+            } else {
+                redacted |= redact_json(field);
+            }
+        },
+        serde_json::Value::Array(items) => for item in items.iter_mut() {
+            redacted |= redact_json(item);
+        },
+        _ => (),
+    }
+    return redacted;
+}
+
+// Replaces ${NAME} references with the value of the environment variable
+pub fn substitute_env(text: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        let name = &rest[start + 2..end];
+        let value = std::env::var(name).map_err(|_| format!("the environment variable {} is not set", name))?;
+        result += &rest[..start];
+        result += &value;
+        rest = &rest[end + 1..];
+    }
+
+    result += rest;
+    return Ok(result);
+}
+
+// Random enough for ids that only have to be unique, e.g. idempotency keys and trace ids
+pub fn random_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos()));
+    return hasher.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_the_secret_fields_of_json_bodies() {
+        assert_eq!(redact_body(r#"{"user": "admin", "Password": "hunter2", "nested": [{"api_key": 7}]}"#),
+            r#"{"Password":"<redacted>","nested":[{"api_key":"<redacted>"}],"user":"admin"}"#);
+        assert_eq!(redact_body("{ \"user\": \"admin\" }"), "{ \"user\": \"admin\" }");
+        assert_eq!(redact_body("password=hunter2"), "password=hunter2");
+    }
+}
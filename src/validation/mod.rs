@@ -0,0 +1,513 @@
+use std::collections::HashSet;
+
+use crate::{Config, RunOptions, assertions, compression, condition, conditional, connect, cookies, dependency,
+    expression, forwarded, hosts, idempotency, record, redirect, resolve, retry, runid, severity, sse, test_reference,
+    validate_http_method};
+#[cfg(feature = "scripting")]
+use crate::script;
+
+const BODY_COMPARE_VALUES: [&str; 3] = ["json", "text", "exact"];
+const FAIL_ON_VALUES: [&str; 2] = ["yellow", "red"];
+const SCHEME_VALUES: [&str; 2] = ["http", "https"];
+const TRANSFER_ENCODING_VALUES: [&str; 2] = ["chunked", "length"];
+
+// Checks the config for errors before any request is sent, collects all found errors. The findings that dont stop
+// a run are returned as warnings, the options add the defines and can turn these warnings into errors.
+pub fn validate_config(config: &Config, options: &RunOptions) -> Result<Vec<String>, Vec<String>> {
+    let mut errors: Vec<String> = Vec::new();
+    let captured = dependency::all_produced(&config.tests);
+
+    if config.tests.is_empty() && config.allow_empty != Some(true) {
+        errors.push("The config has no tests, set 'allow_empty: true' if that is intended.".to_string());
+    }
+
+    for (index, test) in config.tests.iter().enumerate() {
+        let test_number = index + 1;
+
+        if let Some(name) = &test.name {
+            if let Some(other) = config.tests[..index].iter().position(|other| other.name.as_ref() == Some(name)) {
+                errors.push(format!("Test {} has the name '{}' of test {}, names have to be unique.",
+                 test_number, name, other + 1));
+            }
+        }
+
+        if test.method.is_empty() {
+            errors.push(format!("Test {} needs a 'method', either its own or one of the defaults.", test_number));
+        } else if validate_http_method(&test.method).is_none() {
+            errors.push(format!("Test {} has an unknown or unsupported method '{}'.", test_number, test.method));
+        }
+
+        if test.status.is_none() && test.expect_not_status.is_none() && test.expect_error.is_none() && test.cors.is_none()
+         && test.websocket.is_none() {
+            errors.push(format!("Test {} needs a 'status', 'expect_not_status', 'expect_error', 'cors' or 'websocket', \
+             the status can also come from the defaults.", test_number));
+        }
+
+        if test.websocket.is_some() {
+            if !test.method.is_empty() && !test.method.eq_ignore_ascii_case("get") {
+                errors.push(format!("Test {} defines 'websocket' with the method '{}', the handshake is always a GET.",
+                 test_number, test.method));
+            }
+
+            if test.cors.is_some() || test.sse == Some(true) {
+                errors.push(format!("Test {} defines 'websocket' together with 'cors' or 'sse', only one of them can be used.",
+                 test_number));
+            }
+        }
+
+        if let Some(preflight) = &test.cors {
+            if !test.method.is_empty() && !test.method.eq_ignore_ascii_case("options") {
+                errors.push(format!("Test {} defines 'cors' with the method '{}', a preflight is always sent via OPTIONS.",
+                 test_number, test.method));
+            }
+
+            if preflight.origin.is_empty() {
+                errors.push(format!("Test {} needs an 'origin' for its cors preflight.", test_number));
+            }
+
+            if validate_http_method(&preflight.request_method).is_none() {
+                errors.push(format!("Test {} has an unknown or unsupported cors request_method '{}'.",
+                 test_number, preflight.request_method));
+            }
+        }
+
+        if let Some(expected) = &test.expect_error {
+            if !assertions::EXPECTED_ERROR_VALUES.contains(&expected.as_str()) {
+                errors.push(format!("Test {} has an invalid expect_error '{}', expected one of: {}.",
+                 test_number, expected, assertions::EXPECTED_ERROR_VALUES.join(", ")));
+            }
+        }
+
+        if let Some(not_status) = &test.expect_not_status {
+            if let Some(pattern) = not_status.as_slice().iter().find(|pattern| !pattern.is_valid()) {
+                errors.push(format!("Test {} has an invalid status pattern '{}', expected a code like 404 or a class like 4xx.",
+                 test_number, pattern));
+            }
+        }
+
+        let bodies: Vec<&str> = [
+            ("json", test.json.is_some()),
+            ("json_body", test.json_body.is_some()),
+            ("body", test.body.is_some()),
+            ("body_template", test.body_template.is_some()),
+        ].into_iter().filter(|(_, set)| *set).map(|(name, _)| name).collect();
+        if bodies.len() > 1 {
+            errors.push(format!("Test {} defines {}, only one of them can be sent.", test_number,
+             bodies.iter().map(|name| format!("'{}'", name)).collect::<Vec<String>>().join(" and ")));
+        }
+
+        if let Some(fail_on) = &test.fail_on {
+            if !FAIL_ON_VALUES.contains(&fail_on.as_str()) {
+                errors.push(format!("Test {} has an invalid fail_on '{}', expected one of: {}.",
+                 test_number, fail_on, FAIL_ON_VALUES.join(", ")));
+            }
+        }
+
+        if (test.compare.is_some() || test.interpolate.is_some()) && test.expect_body_file.is_none() {
+            errors.push(format!("Test {} defines 'compare' or 'interpolate', which need an 'expect_body_file'.", test_number));
+        }
+
+        if let Some(compare) = &test.compare {
+            if !BODY_COMPARE_VALUES.contains(&compare.as_str()) {
+                errors.push(format!("Test {} has an invalid compare '{}', expected one of: {}.",
+                 test_number, compare, BODY_COMPARE_VALUES.join(", ")));
+            }
+        }
+
+        // Json fixtures are checked right away, unless placeholders may only turn them into json later on
+        if let Some(expected) = &test.expected_body {
+            if matches!(test.compare.as_deref(), None | Some("json")) && test.interpolate != Some(true)
+             && serde_json::from_str::<serde_json::Value>(expected).is_err() {
+                errors.push(format!("Test {} has an expect_body_file that isnt valid json, use 'compare: text' or 'compare: exact' for other bodies.",
+                 test_number));
+            }
+        }
+
+        if test.template_values.is_some() && test.body_template.is_none() {
+            errors.push(format!("Test {} defines 'template_values', which needs a 'body_template'.", test_number));
+        }
+
+        // Every placeholder of a template has to be a template value, a variable, an identifier of the run or a capture
+        if let Some(template) = &test.template {
+            let mut unresolved = Vec::new();
+            dependency::placeholders(template, &mut unresolved);
+            unresolved.retain(|name| !test.template_values.as_ref().is_some_and(|values| values.contains_key(name))
+                && !config.variables.as_ref().is_some_and(|variables| variables.contains_key(name))
+                && !runid::NAMES.contains(&name.as_str())
+                && !captured.contains(name));
+            unresolved.sort();
+            unresolved.dedup();
+
+            if !unresolved.is_empty() {
+                errors.push(format!("Test {} has unresolved placeholders in its body_template: {}.", test_number,
+                 unresolved.join(", ")));
+            }
+        }
+
+        for (field, constraint) in test.expect_array_contains.iter().flat_map(|expected| expected.fields.iter()) {
+            if let assertions::FieldConstraint::Matches { matches } = constraint {
+                if let Err(error) = regex::Regex::new(matches) {
+                    errors.push(format!("Test {} has an invalid pattern for the field '{}' of expect_array_contains: {}.",
+                     test_number, field, error));
+                }
+            }
+        }
+        if test.expect_array_contains.as_ref().is_some_and(|expected| expected.fields.is_empty()) {
+            errors.push(format!("Test {} has no fields in expect_array_contains, every element would match.", test_number));
+        }
+
+        let unknown_keywords = test.expect_body.as_ref().map(record::unknown_keywords).unwrap_or_default();
+        if !unknown_keywords.is_empty() {
+            errors.push(format!("Test {} has unknown keywords in its expect_body: {}. Known are {}, $$ starts a \
+             literal text with $.", test_number, unknown_keywords.join(", "), record::KEYWORDS.join(", ")));
+        }
+
+        if let Some(encoding) = &test.expect_encoding {
+            if !compression::ENCODING_VALUES.contains(&encoding.as_str()) {
+                errors.push(format!("Test {} has an invalid expect_encoding '{}', expected one of: {}.",
+                 test_number, encoding, compression::ENCODING_VALUES.join(", ")));
+            }
+        }
+        if test.min_compression_ratio.is_some_and(|ratio| !ratio.is_finite() || ratio <= 0.0) {
+            errors.push(format!("Test {} has an invalid min_compression_ratio, expected a number above 0.",
+             test_number));
+        }
+        let asserts_compression = test.expect_encoding.is_some() || test.min_compression_ratio.is_some();
+        if asserts_compression && (test.sse == Some(true) || test.websocket.is_some()) {
+            errors.push(format!("Test {} defines 'expect_encoding' or 'min_compression_ratio' together with 'sse' or \
+             'websocket', only plain response bodies are decoded.", test_number));
+        }
+
+        if let Some(severity::Severity::PerAssertion(levels)) = &test.severity {
+            let mut unknown: Vec<&String> = levels.keys()
+                .filter(|assertion| !severity::ASSERTIONS.contains(&assertion.as_str()))
+                .collect();
+            unknown.sort();
+            if !unknown.is_empty() {
+                errors.push(format!("Test {} has a severity for unknown assertions: {}. Known are {}.", test_number,
+                 unknown.iter().map(|assertion| assertion.as_str()).collect::<Vec<&str>>().join(", "),
+                 severity::ASSERTIONS.join(", ")));
+            }
+        }
+
+        let filename_pattern = test.expect_content_disposition_filename.as_ref().map(|pattern| regex::Regex::new(pattern));
+        if let Some(Err(error)) = filename_pattern {
+            errors.push(format!("Test {} has an invalid expect_content_disposition_filename: {}.", test_number, error));
+        }
+        if test.save_response.as_ref().is_some_and(|save| save.dir.as_os_str().is_empty()) {
+            errors.push(format!("Test {} has no dir to save the response to.", test_number));
+        }
+
+        for (name, expected) in test.expect_cookies.iter().flatten() {
+            if let Some(Err(error)) = expected.matches.as_ref().map(|pattern| regex::Regex::new(pattern)) {
+                errors.push(format!("Test {} has an invalid pattern for the cookie '{}': {}.", test_number, name, error));
+            }
+
+            if let Some(same_site) = &expected.same_site {
+                if !cookies::SAME_SITE_VALUES.contains(&same_site.to_lowercase().as_str()) {
+                    errors.push(format!("Test {} has an invalid same_site '{}' for the cookie '{}', expected one of: {}.",
+                     test_number, same_site, name, cookies::SAME_SITE_VALUES.join(", ")));
+                }
+            }
+        }
+
+        let is_sse = test.sse == Some(true);
+        if !is_sse && (test.expect_events.is_some() || test.max_wait_ms.is_some()) {
+            errors.push(format!("Test {} defines 'expect_events' or 'max_wait_ms', which need 'sse: true'.", test_number));
+        }
+
+        if let Some(namespace) = &test.capture_namespace {
+            if namespace.is_empty() || !namespace.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                errors.push(format!("Test {} has an invalid capture_namespace '{}', use letters, digits, '_' and '-'.",
+                 test_number, namespace));
+            } else if namespace == "request" || namespace == "response" {
+                errors.push(format!("Test {} has the capture_namespace '{}', which scripts use for the exchange.",
+                 test_number, namespace));
+            }
+        }
+
+        for (name, source) in test.capture.iter().flatten() {
+            if let Some(event_source) = source.strip_prefix("$event:") {
+                if !is_sse {
+                    errors.push(format!("Test {} captures '{}' from an event, which needs 'sse: true'.", test_number, name));
+                } else if !sse::is_valid_capture(event_source) {
+                    errors.push(format!("Test {} has an invalid event capture '{}', expected e.g. $event:0 or $event:0.user.id.",
+                     test_number, source));
+                }
+            }
+        }
+
+        if let Some(compare) = &test.idempotency_compare {
+            if test.idempotency_check != Some(true) {
+                errors.push(format!("Test {} defines 'idempotency_compare', which needs 'idempotency_check: true'.",
+                 test_number));
+            } else if !idempotency::COMPARE_VALUES.contains(&compare.as_str()) {
+                errors.push(format!("Test {} has an invalid idempotency_compare '{}', expected one of: {}.",
+                 test_number, compare, idempotency::COMPARE_VALUES.join(", ")));
+            }
+        }
+
+        if let Some(check) = &test.pagination_check {
+            if check.page_size == 0 {
+                errors.push(format!("Test {} has a pagination_check with a page_size of 0.", test_number));
+            }
+            if check.max_page_size.is_some_and(|max| max < check.page_size) {
+                errors.push(format!("Test {} has a pagination_check whose max_page_size is less than its page_size.",
+                 test_number));
+            }
+            for parameter in [&check.limit_param, &check.offset_param].into_iter().flatten() {
+                if parameter.is_empty() || !parameter.chars().all(|c| c.is_ascii_alphanumeric() || "_-.[]".contains(c)) {
+                    errors.push(format!("Test {} has a pagination_check with the invalid query parameter '{}'.",
+                     test_number, parameter));
+                }
+            }
+            if test.sse == Some(true) || test.websocket.is_some() {
+                errors.push(format!("Test {} defines 'pagination_check', which cant be combined with 'sse' or 'websocket'.",
+                 test_number));
+            }
+        }
+
+        if test.trace_id.is_some() && !config.tracing.as_ref().is_some_and(|tracing| tracing.enabled.unwrap_or(true)) {
+            errors.push(format!("Test {} defines 'trace_id', which needs an enabled 'tracing' section.", test_number));
+        }
+
+        // Only json comparisons know the paths, text and exact ones compare every byte
+        let compares_json = test.expect_body_file.is_some()
+            && test.compare.as_deref().is_none_or(|compare| compare == "json");
+        if test.ignore_paths.is_some() && test.snapshot != Some(true) && !compares_json {
+            errors.push(format!("Test {} defines 'ignore_paths', which needs 'snapshot: true' or an 'expect_body_file' \
+             compared as json.", test_number));
+        }
+
+        if let Some(scheme) = &test.scheme {
+            if !SCHEME_VALUES.contains(&scheme.as_str()) {
+                errors.push(format!("Test {} has an invalid scheme '{}', expected one of: {}.", test_number, scheme,
+                 SCHEME_VALUES.join(", ")));
+            }
+        }
+
+        if test.follow_redirects == Some(false) && (test.max_redirects.is_some() || test.expect_redirect_chain.is_some()) {
+            errors.push(format!("Test {} sets 'follow_redirects: false' together with 'max_redirects' or \
+             'expect_redirect_chain', which need the redirects to be followed.", test_number));
+        }
+        if redirect::follows(test) && (test.expect_location.is_some() || test.websocket.is_some()) {
+            errors.push(format!("Test {} follows redirects, which cant be combined with 'expect_location' or 'websocket'.",
+             test_number));
+        }
+
+        if test.conditional.as_ref().is_some_and(|conditional| conditional.headers().is_empty()) {
+            errors.push(format!("Test {} has a 'conditional' without 'if_none_match' or 'if_modified_since'.", test_number));
+        }
+        let body_assertions = conditional::body_assertions(test);
+        if test.status == Some(304) && !body_assertions.is_empty() {
+            errors.push(format!("Test {} expects the status 304, which has no body, but asserts on it with {}.",
+             test_number, body_assertions.join(", ")));
+        }
+
+        if let Some(transfer_encoding) = &test.transfer_encoding {
+            if !TRANSFER_ENCODING_VALUES.contains(&transfer_encoding.as_str()) {
+                errors.push(format!("Test {} has an invalid transfer_encoding '{}', expected one of: {}.", test_number,
+                 transfer_encoding, TRANSFER_ENCODING_VALUES.join(", ")));
+            }
+            if test.websocket.is_some() {
+                errors.push(format!("Test {} defines 'transfer_encoding' together with 'websocket', the handshake has no body.",
+                 test_number));
+            }
+        }
+
+        if let Some(target) = &test.connect_to {
+            if connect::parse_target(target, &hyper::Uri::from_static("http://localhost")).is_none() {
+                errors.push(format!("Test {} has an invalid connect_to '{}', expected an ip like 10.0.3.7 or 10.0.3.7:443.",
+                 test_number, target));
+            }
+        }
+
+        if let Some(Err(error)) = test.forwarded_for.iter().flatten().next().map(|ip| forwarded::validate(ip)) {
+            errors.push(format!("Test {} has an invalid forwarded_for, {}.", test_number, error));
+        }
+
+        if test.allowed_hosts.as_ref()
+            .is_some_and(|patterns| patterns.is_empty() || patterns.iter().any(|pattern| pattern.is_empty())) {
+            errors.push(format!("Test {} has empty allowed_hosts, it would never run.", test_number));
+        }
+
+        if test.skip.as_ref().is_some_and(|reason| reason.trim().is_empty()) {
+            errors.push(format!("Test {} has an empty skip, it needs the reason the test is skipped for.", test_number));
+        }
+
+        if test.xfail.as_ref().is_some_and(|ticket| ticket.trim().is_empty()) {
+            errors.push(format!("Test {} has an empty xfail, it needs the ticket of the bug it fails because of.",
+             test_number));
+        }
+
+        if test.skip.is_some() && test.xfail.is_some() {
+            errors.push(format!("Test {} defines 'skip' together with 'xfail', a skipped test neither fails nor passes.",
+             test_number));
+        }
+
+        for entry in test.expect_headers_absent.iter().flatten().flatten() {
+            let (name, _) = assertions::parse_absent_header(entry);
+            if hyper::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                errors.push(format!("Test {} has an invalid header name '{}' in expect_headers_absent.", test_number, name));
+            }
+        }
+
+        for name in test.expect_headers.iter().flat_map(|headers| headers.keys()) {
+            if hyper::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                errors.push(format!("Test {} has an invalid header name '{}' in expect_headers.", test_number, name));
+            }
+        }
+
+        if let Some(retry_on) = &test.retry_on {
+            for value in retry_on.iter() {
+                if let Err(error) = retry::RetryCondition::parse(value) {
+                    errors.push(format!("Test {} has an invalid retry_on value: {}.", test_number, error));
+                }
+            }
+        }
+
+        if test.retries.is_some_and(|retries| retries > 0) && test.expect_error.is_some() {
+            errors.push(format!("Test {} defines both 'retries' and 'expect_error', an expected error cannot be retried.",
+             test_number));
+        }
+
+        if let Some(expression) = &test.when {
+            if let Err(error) = condition::Condition::parse(expression) {
+                errors.push(format!("Test {} has an invalid 'when' condition '{}': {}.",
+                 test_number, expression, error));
+            }
+        }
+
+        for expression in test.expect_expr.iter().flatten() {
+            if let Err(error) = expression::Expectation::parse(expression) {
+                errors.push(format!("Test {} has an invalid expect_expr '{}': {}.", test_number, expression, error));
+            }
+        }
+
+        for (field, source) in [("pre_script", &test.pre_script), ("post_script", &test.post_script)] {
+            if let Some(source) = source {
+                if let Err(error) = parse_script(field, source) {
+                    errors.push(format!("Test {} has an invalid {}, {}.", test_number, field, error));
+                }
+            }
+        }
+
+        if test.timeout_ms == Some(0) {
+            errors.push(format!("Test {} has a timeout_ms of 0, it has to be greater than 0.", test_number));
+        }
+        if let (Some(min_time), Some(timeout)) = (test.min_time_ms, test.timeout_ms) {
+            if min_time >= timeout as u128 {
+                errors.push(format!("Test {} has a min_time_ms of {} that isnt below its timeout_ms of {}, it cant pass.",
+                 test_number, min_time, timeout));
+            }
+        }
+        if let Some(Err(error)) = test.time_boundaries.as_ref().map(|boundaries| boundaries.resolve()) {
+            errors.push(format!("Test {} has invalid time_boundaries: {}.", test_number, error));
+        }
+    }
+
+    if let Some(level) = &config.log_level {
+        if !resolve::LOG_LEVEL_VALUES.contains(&level.as_str()) {
+            errors.push(format!("The log_level '{}' is invalid, expected one of: {}.", level,
+             resolve::LOG_LEVEL_VALUES.join(", ")));
+        }
+    }
+
+    if config.timeout_ms == Some(0) {
+        errors.push("The global timeout_ms has to be greater than 0.".to_string());
+    }
+    if let Some(Err(error)) = config.time_boundaries.as_ref().map(|boundaries| boundaries.resolve()) {
+        errors.push(format!("The global time_boundaries are invalid: {}.", error));
+    }
+    if let Some(Err(error)) = config.rate_limit.as_ref().map(|rate_limit| rate_limit.validate()) {
+        errors.push(format!("The rate_limit is invalid: {}.", error));
+    }
+
+    if let Some(compare_against) = &config.compare_against {
+        if !compare_against.parse::<hyper::Uri>().is_ok_and(|uri| uri.scheme().is_some() && uri.authority().is_some()) {
+            errors.push(format!("The compare_against '{}' is no absolute url like the api_address.", compare_against));
+        }
+    } else if config.comparison.is_some() || config.comparison_severity.is_some() {
+        errors.push("The comparison and comparison_severity only apply together with a compare_against.".to_string());
+    }
+
+    if config.allowed_hosts.as_ref()
+        .is_some_and(|patterns| patterns.is_empty() || patterns.iter().any(|pattern| pattern.is_empty())) {
+        errors.push("The global allowed_hosts are empty, no test would run.".to_string());
+    }
+    if let Some(enforce) = &config.enforce_allowed_hosts {
+        if !hosts::ENFORCE_VALUES.contains(&enforce.as_str()) {
+            errors.push(format!("The enforce_allowed_hosts '{}' is invalid, expected one of: {}.", enforce,
+             hosts::ENFORCE_VALUES.join(", ")));
+        }
+    }
+    if let Some(Err(error)) = config.forwarded_for.as_deref().map(forwarded::validate) {
+        errors.push(format!("The global forwarded_for is invalid, {}.", error));
+    }
+
+    for entry in config.expect_headers_absent.iter().flatten() {
+        let (name, _) = assertions::parse_absent_header(entry);
+        if hyper::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+            errors.push(format!("The global expect_headers_absent has an invalid header name '{}'.", name));
+        }
+    }
+
+    let warnings = validate_captures(config, options, &mut errors);
+
+    if errors.is_empty() {
+        return Ok(warnings);
+    }
+
+    return Err(errors);
+}
+
+// Names the tests can read without a test capturing them: the config variables, the defines of the command line and
+// the identifiers of the run
+pub fn variable_names(config: &Config, defines: &[(String, String)]) -> HashSet<String> {
+    return config.variables.iter()
+        .flat_map(|variables| variables.keys().cloned())
+        .chain(defines.iter().map(|(name, _)| name.clone()))
+        .chain(runid::NAMES.iter().map(|name| name.to_string()))
+        .collect();
+}
+
+// Every test runs after the tests whose captures it reads, unknown names and cycles cant be scheduled. Captures nobody
+// reads and credentials naming captures nobody produces are usually leftovers of a refactoring, so they are warnings.
+fn validate_captures(config: &Config, options: &RunOptions, errors: &mut Vec<String>) -> Vec<String> {
+    let variables = variable_names(config, &options.defines);
+
+    let graph = dependency::graph(&config.tests, &variables);
+    for (index, name) in graph.unknown.iter() {
+        errors.push(format!("Test {} reads '{}', which is neither a variable nor captured by any test.",
+         test_reference(&config.tests[*index], index + 1), name));
+    }
+    for cycle in dependency::cycles(&graph).iter() {
+        errors.push(format!("Tests {} read each other's captures, none of them can run first.",
+         cycle.iter().map(|index| (index + 1).to_string()).collect::<Vec<String>>().join(", ")));
+    }
+
+    let mut warnings: Vec<String> = dependency::unused_captures(config, &variables).iter()
+        .map(|(index, name)| format!("The capture '{}' of test {} is never used.", name,
+         test_reference(&config.tests[index - 1], *index)))
+        .collect();
+    warnings.extend(dependency::dangling_credentials(&config.tests, &variables).iter()
+        .map(|(index, credential, name)| format!("The {} '{}' of test {} names no capture, it is sent as it is.",
+         credential, name, test_reference(&config.tests[index - 1], *index))));
+
+    if options.strict_captures {
+        errors.append(&mut warnings);
+    }
+    return warnings;
+}
+
+// Compiles a script of a test, without the scripting feature every script is an error
+#[cfg(feature = "scripting")]
+fn parse_script(field: &str, source: &str) -> Result<(), String> {
+    let hook = if field == "pre_script" { script::Hook::Pre } else { script::Hook::Post };
+    return script::Script::parse(source, hook).map(|_| ());
+}
+
+#[cfg(not(feature = "scripting"))]
+fn parse_script(_field: &str, _source: &str) -> Result<(), String> {
+    return Err("rust-rest-test was built without the scripting feature".to_string());
+}
@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::{Serialize, Deserialize};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::handshake::{client::generate_key, derive_accept_key};
+use tokio_tungstenite::tungstenite::protocol::Role;
+
+use crate::Result;
+use crate::failure::{self, FailureReason};
+
+// Upgrades the route to a websocket, optionally sends a message and waits for the first one of the server
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct WebSocketExchange {
+    pub send: Option<String>, // text message sent after the handshake, supports interpolation
+    pub expect_message_contains: Option<String>,
+    pub timeout_ms: Option<u64>, // how long to wait for the message, defaults to the timeout of the test
+}
+
+// The prepared exchange of a single request
+#[derive(Debug, Clone)]
+pub struct WebSocketRequest {
+    pub key: String, // the Sec-WebSocket-Key of the handshake
+    pub send: Option<String>,
+    pub wait: Duration,
+}
+
+// What happened after the handshake response arrived
+#[derive(Debug)]
+pub enum Outcome {
+    Rejected(String), // the server didnt switch protocols, the reason is what was wrong with the handshake
+    Message(String),
+    Closed, // the server closed the socket before sending a message
+    Timeout,
+}
+
+// Outcome of a single assertion on the exchange
+pub struct WebSocketCheck {
+    pub description: String,
+    pub failure: Option<FailureReason>,
+}
+
+impl WebSocketRequest {
+    pub fn new(send: Option<String>, wait: Duration) -> WebSocketRequest {
+        return WebSocketRequest { key: generate_key(), send, wait };
+    }
+
+    // The headers that ask the server to upgrade the connection
+    pub fn upgrade_headers(&self) -> Vec<(String, String)> {
+        return vec![
+            ("Connection".to_string(), "Upgrade".to_string()),
+            ("Upgrade".to_string(), "websocket".to_string()),
+            ("Sec-WebSocket-Version".to_string(), "13".to_string()),
+            ("Sec-WebSocket-Key".to_string(), self.key.clone()),
+        ];
+    }
+}
+
+fn header_value(headers: &hyper::HeaderMap, name: &str) -> Option<String> {
+    return headers.get(name).map(|value| value.to_str().unwrap_or_default().to_string());
+}
+
+// Checks that the server accepted the upgrade, returns what was wrong otherwise
+pub fn verify_handshake(response: &hyper::Response<hyper::Body>, key: &str) -> std::result::Result<(), String> {
+    if response.status() != hyper::StatusCode::SWITCHING_PROTOCOLS {
+        return Err(format!("expected status 101, got {}", response.status().as_u16()));
+    }
+
+    let upgrade = header_value(response.headers(), "upgrade");
+    if !upgrade.as_ref().is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket")) {
+        return Err(format!("expected the Upgrade header 'websocket', got {}",
+            upgrade.map_or("none".to_string(), |upgrade| format!("'{}'", upgrade))));
+    }
+
+    let expected = derive_accept_key(key.as_bytes());
+    return match header_value(response.headers(), "sec-websocket-accept") {
+        Some(accept) if accept == expected => Ok(()),
+        Some(accept) => Err(format!("expected the Sec-WebSocket-Accept '{}', got '{}'", expected, accept)),
+        None => Err("the Sec-WebSocket-Accept header is missing".to_string()),
+    };
+}
+
+// Sends the message of the test and waits for the first text or binary message, pings are answered on the way
+pub async fn exchange(response: &mut hyper::Response<hyper::Body>, request: &WebSocketRequest) -> Result<Outcome> {
+    let upgraded = hyper::upgrade::on(response).await?;
+    let mut socket = WebSocketStream::from_raw_socket(upgraded, Role::Client, None).await;
+
+    if let Some(message) = &request.send {
+        socket.send(Message::Text(message.clone())).await?;
+    }
+
+    let received = tokio::time::timeout(request.wait, async {
+        while let Some(message) = socket.next().await {
+            match message? {
+                Message::Text(text) => return Ok(Outcome::Message(text)),
+                Message::Binary(data) => return Ok(Outcome::Message(String::from_utf8_lossy(&data).to_string())),
+                Message::Close(_) => return Ok(Outcome::Closed),
+                _ => (),
+            }
+        }
+        return Ok::<Outcome, tokio_tungstenite::tungstenite::Error>(Outcome::Closed);
+    }).await;
+
+    let outcome = match received {
+        Ok(outcome) => outcome?,
+        Err(_) => Outcome::Timeout,
+    };
+
+    // A failed close doesnt change what the server answered
+    let _ = socket.close(None).await;
+
+    return Ok(outcome);
+}
+
+fn check(description: String, failure: Option<FailureReason>) -> WebSocketCheck {
+    return WebSocketCheck { description, failure };
+}
+
+// Evaluates the exchange, a rejected handshake or a missing message make the other assertions pointless
+pub fn check_exchange(expected: &WebSocketExchange, outcome: &Outcome, status: u16, wait_ms: u128)
+     -> Vec<WebSocketCheck> {
+    let message = match outcome {
+        Outcome::Rejected(reason) => return vec![check(format!("Expected the websocket handshake to succeed, {}", reason),
+            Some(FailureReason::HandshakeRejected { status, reason: reason.clone() }))],
+        Outcome::Timeout => return vec![check(format!("Expected a websocket message within {} ms, got none", wait_ms),
+            Some(FailureReason::MessageTimeout { limit_ms: wait_ms }))],
+        Outcome::Closed => return vec![check("Expected a websocket message, the server closed the socket".to_string(),
+            Some(FailureReason::SocketClosed))],
+        Outcome::Message(message) => message,
+    };
+
+    let mut checks = vec![check("Expected a websocket message, got one".to_string(), None)];
+
+    if let Some(part) = &expected.expect_message_contains {
+        let contained = message.contains(part.as_str());
+        checks.push(check(format!("Expected the websocket message to contain '{}'", part),
+            (!contained).then(|| FailureReason::BodyAssertion {
+                path: "$message".to_string(),
+                expected: format!("to contain '{}'", part),
+                actual: failure::excerpt(message),
+            })));
+    }
+
+    return checks;
+}
@@ -0,0 +1,75 @@
+use crate::utils;
+
+// Bodies up to this size are dumped as hex next to their text, larger ones only as text
+const HEX_DUMP_BYTES: usize = 512;
+
+// Bytes per line of the hex dump
+const HEX_LINE_BYTES: usize = 16;
+
+// Text of larger bodies shown before the rest is omitted
+const MAX_TEXT_BYTES: usize = 16384;
+
+// One line per header behind the marker of the direction, secret values are redacted
+fn header_lines(marker: char, headers: &hyper::HeaderMap) -> String {
+    let mut lines = String::new();
+    for (name, value) in headers.iter() {
+        let value = if utils::is_secret_name(name.as_str()) {
+            "<redacted>".to_string()
+        } else {
+            String::from_utf8_lossy(value.as_bytes()).to_string()
+        };
+        lines += &format!("{} {}: {}\n", marker, name, value);
+    }
+    return lines;
+}
+
+// Offset, hex and printable characters, like xxd
+fn hex_dump(body: &[u8]) -> String {
+    let mut dump = String::new();
+    for (line, chunk) in body.chunks(HEX_LINE_BYTES).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let text: String = chunk.iter()
+            .map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' })
+            .collect();
+        dump += &format!("{:08x}  {:<width$}  {}\n", line * HEX_LINE_BYTES, hex.join(" "),
+            text, width = HEX_LINE_BYTES * 3 - 1);
+    }
+    return dump;
+}
+
+fn body_lines(body: &[u8]) -> String {
+    if body.is_empty() {
+        return String::new();
+    }
+    if body.len() <= HEX_DUMP_BYTES {
+        return format!("\n{}", hex_dump(body));
+    }
+    return format!("\n<{} bytes>\n{}\n", body.len(), utils::printable_body(body, true, MAX_TEXT_BYTES));
+}
+
+// The request as it goes over the wire, including the Host and Content-Length hyper adds on its own
+pub fn request(parts: &hyper::http::request::Parts, body: &[u8]) -> String {
+    let target = parts.uri.path_and_query().map_or("/", |target| target.as_str());
+    let mut text = format!("> {} {} {:?}\n", parts.method, target, parts.version);
+    text += &header_lines('>', &parts.headers);
+    if let (None, Some(authority)) = (parts.headers.get(hyper::header::HOST), parts.uri.authority()) {
+        text += &format!("> host: {}\n", authority);
+    }
+    let framed = parts.headers.contains_key(hyper::header::CONTENT_LENGTH)
+        || parts.headers.contains_key(hyper::header::TRANSFER_ENCODING);
+    if !framed && !body.is_empty() {
+        text += &format!("> content-length: {}\n", body.len());
+    }
+    return text + &body_lines(body);
+}
+
+// The status line and the headers of a response as they were received, the body is read afterwards. hyper only
+// keeps the reason phrase if it differs from the standard one.
+pub fn response(parts: &hyper::http::response::Parts) -> String {
+    let reason = match parts.extensions.get::<hyper::ext::ReasonPhrase>() {
+        Some(reason) => String::from_utf8_lossy(reason.as_bytes()).to_string(),
+        None => parts.status.canonical_reason().unwrap_or_default().to_string(),
+    };
+    return format!("< {:?} {} {}\n{}", parts.version, parts.status.as_u16(), reason,
+        header_lines('<', &parts.headers));
+}